@@ -7,13 +7,23 @@ use clap::Parser;
 use ipa_core::{
     error::Error,
     ff::Fp32BitPrime,
-    helpers::{query::IpaQueryConfig, GatewayConfig},
+    helpers::{
+        query::{
+            DpParameter, FeatureLabelDotProductQueryConfig, InvalidReportPolicy, IpaQueryConfig,
+        },
+        GatewayConfig,
+    },
+    report::ReportVersion,
     test_fixture::{
+        feature_label_dot_product::{
+            feature_label_dot_product_in_the_clear, test_feature_label_dot_product,
+            TestFeatureLabelRecord,
+        },
         ipa::{ipa_in_the_clear, test_ipa, test_oprf_ipa, CappingOrder, IpaSecurityModel},
         EventGenerator, EventGeneratorConfig, TestWorld, TestWorldConfig,
     },
 };
-use rand::{random, rngs::StdRng, SeedableRng};
+use rand::{random, rngs::StdRng, Rng, SeedableRng};
 use tokio::runtime::Builder;
 
 #[cfg(all(not(target_env = "msvc"), not(feature = "dhat-heap")))]
@@ -72,6 +82,19 @@ struct Args {
     bench: bool,
     #[arg(short = 'o', long)]
     oprf: bool,
+    /// Privacy budget for the dummy records that OPRF IPA adds to its input. No dummy records
+    /// are added if not set.
+    #[arg(long)]
+    dummy_records_epsilon: Option<DpParameter>,
+    /// Privacy budget for the noise added to every breakdown total. No noise is added if not set.
+    #[arg(long)]
+    output_noise_epsilon: Option<DpParameter>,
+    /// Run the feature-label dot product instead of IPA. The per-user cap must be a power of two.
+    #[arg(short = 'f', long)]
+    feature_label_dot_product: bool,
+    /// The number of features in every source report, for the feature-label dot product.
+    #[arg(long, default_value = "32")]
+    feature_vector_bits: u32,
 }
 
 impl Args {
@@ -92,6 +115,25 @@ impl Args {
             attribution_window_seconds: self.attribution_window(),
             num_multi_bits: self.num_multi_bits,
             plaintext_match_keys: true,
+            dummy_records_epsilon: self.dummy_records_epsilon,
+            dummy_records_delta: DpParameter::DEFAULT_DELTA,
+            output_noise_epsilon: self.output_noise_epsilon,
+            output_noise_delta: DpParameter::DEFAULT_DELTA,
+            epoch: 0,
+            min_report_epoch: None,
+            max_report_epoch: None,
+            breakdown_key_bits: 8,
+            trigger_value_bits: 3,
+            timestamp_bits: 20,
+            invalid_report_policy: InvalidReportPolicy::Fail,
+            report_version: ReportVersion::MatchKeyOnly,
+        }
+    }
+
+    fn feature_label_config(&self) -> FeatureLabelDotProductQueryConfig {
+        FeatureLabelDotProductQueryConfig {
+            feature_vector_bits: self.feature_vector_bits,
+            per_user_credit_cap: self.per_user_cap,
         }
     }
 }
@@ -112,9 +154,10 @@ async fn run(args: Args) -> Result<(), Error> {
     );
     let rng = StdRng::seed_from_u64(seed);
     let (user_count, min_events_per_user, max_events_per_user, query_size) =
-        if args.oprf && cfg!(feature = "step-trace") {
-            // For the steps collection, OPRF mode requires a single user with the same number
-            // of dynamic steps as defined for `UserNthRowStep::Row`.
+        if (args.oprf || args.feature_label_dot_product) && cfg!(feature = "step-trace") {
+            // For the steps collection, OPRF mode and the feature-label dot product require a
+            // single user with the same number of dynamic steps as defined for
+            // `UserNthRowStep::Row` and `UserNthRowFeatureLabelStep::Row`.
             (
                 NonZeroU64::new(1).unwrap(),
                 NonZeroU32::new(64).unwrap(),
@@ -146,6 +189,42 @@ async fn run(args: Args) -> Result<(), Error> {
     // timestamp.
     raw_data.sort_by_key(|e| e.timestamp);
 
+    if args.feature_label_dot_product {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let records = raw_data
+            .into_iter()
+            .map(|e| TestFeatureLabelRecord {
+                timestamp: e.timestamp,
+                user_id: e.user_id,
+                is_trigger_report: e.is_trigger_report,
+                feature_vector: rng.gen::<u32>() >> (u32::BITS - args.feature_vector_bits),
+            })
+            .collect::<Vec<_>>();
+        let expected_results = feature_label_dot_product_in_the_clear(
+            &records,
+            args.feature_vector_bits,
+            args.per_user_cap,
+        );
+
+        let world = TestWorld::new_with(config);
+        tracing::trace!("Preparation complete in {:?}", _prep_time.elapsed());
+
+        let _protocol_time = Instant::now();
+        test_feature_label_dot_product::<BenchField>(
+            &world,
+            records,
+            &expected_results,
+            args.feature_label_config(),
+        )
+        .await;
+        tracing::trace!(
+            "Feature-label dot product for {q} records took {t:?}",
+            q = query_size,
+            t = _protocol_time.elapsed()
+        );
+        return Ok(());
+    }
+
     let order = if args.oprf {
         CappingOrder::CapMostRecentFirst
     } else {
@@ -1,13 +1,13 @@
 use crate::{
     helpers::{
-        query::{QueryConfig, QueryInput},
+        query::{QueryConfig, QueryInput, QueryInputChunk, ReceivedInputChunks},
         Transport, TransportCallbacks, TransportImpl,
     },
     hpke::{KeyPair, KeyRegistry},
     protocol::QueryId,
     query::{
-        NewQueryError, QueryCompletionError, QueryInputError, QueryProcessor, QueryStatus,
-        QueryStatusError,
+        NewQueryError, PrivacyBudgetLedger, QueryCompletionError, QueryInputError, QueryKillError,
+        QueryProcessor, QueryStatus, QueryStatusError, QueryTimeouts,
     },
     sync::Arc,
 };
@@ -33,7 +33,26 @@ impl Setup {
     pub fn with_key_registry(
         key_registry: KeyRegistry<KeyPair>,
     ) -> (Self, TransportCallbacks<TransportImpl>) {
-        let query_processor = Arc::new(QueryProcessor::new(key_registry));
+        Self::with_query_processor(QueryProcessor::new(key_registry))
+    }
+
+    /// Same as [`Self::with_key_registry`], but the helper also rejects queries from report
+    /// collectors that ran out of privacy budget according to `ledger`.
+    #[must_use]
+    pub fn with_privacy_budget(
+        key_registry: KeyRegistry<KeyPair>,
+        ledger: PrivacyBudgetLedger,
+    ) -> (Self, TransportCallbacks<TransportImpl>) {
+        Self::with_query_processor(QueryProcessor::new(key_registry).with_privacy_budget(ledger))
+    }
+
+    /// Sets up a helper that uses the provided query processor. Use it when the processor needs
+    /// more configuration than other constructors offer.
+    #[must_use]
+    pub fn with_query_processor(
+        query_processor: QueryProcessor,
+    ) -> (Self, TransportCallbacks<TransportImpl>) {
+        let query_processor = Arc::new(query_processor);
         let this = Self {
             query_processor: Arc::clone(&query_processor),
         };
@@ -51,9 +70,16 @@ impl Setup {
     fn callbacks(query_processor: &Arc<QueryProcessor>) -> TransportCallbacks<TransportImpl> {
         let rqp = Arc::clone(query_processor);
         let pqp = Arc::clone(query_processor);
+        let aqqp = Arc::clone(query_processor);
         let iqp = Arc::clone(query_processor);
+        let icqp = Arc::clone(query_processor);
+        let rcqp = Arc::clone(query_processor);
         let sqp = Arc::clone(query_processor);
         let cqp = Arc::clone(query_processor);
+        let kqp = Arc::clone(query_processor);
+        let aqp = Arc::clone(query_processor);
+        let kdqp = Arc::clone(query_processor);
+        let pkqp = Arc::clone(query_processor);
 
         TransportCallbacks {
             receive_query: Box::new(move |transport: TransportImpl, receive_query| {
@@ -68,24 +94,65 @@ impl Setup {
                 let processor = Arc::clone(&pqp);
                 Box::pin(async move { processor.prepare(&transport, prepare_query) })
             }),
+            authorize_query: Box::new(
+                move |_transport: TransportImpl, query_id, report_collector| {
+                    let processor = Arc::clone(&aqqp);
+                    Box::pin(async move { processor.authorize(query_id, report_collector) })
+                },
+            ),
             query_input: Box::new(move |transport: TransportImpl, query_input| {
                 let processor = Arc::clone(&iqp);
                 Box::pin(async move { processor.receive_inputs(transport, query_input) })
             }),
+            query_input_chunk: Box::new(move |transport: TransportImpl, chunk| {
+                let processor = Arc::clone(&icqp);
+                Box::pin(async move { processor.receive_input_chunk(transport, chunk) })
+            }),
+            input_chunks: Box::new(move |_transport: TransportImpl, query_id| {
+                let processor = Arc::clone(&rcqp);
+                Box::pin(async move { processor.received_input_chunks(query_id) })
+            }),
             query_status: Box::new(move |_transport: TransportImpl, query_id| {
                 let processor = Arc::clone(&sqp);
-                Box::pin(async move { processor.query_status(query_id) })
+                Box::pin(async move { processor.query_status_report(query_id) })
             }),
             complete_query: Box::new(move |_transport: TransportImpl, query_id| {
                 let processor = Arc::clone(&cqp);
                 Box::pin(async move { processor.complete(query_id).await })
             }),
+            kill_query: Box::new(move |transport: TransportImpl, query_id| {
+                let processor = Arc::clone(&kqp);
+                Box::pin(async move { processor.kill(transport, query_id).await })
+            }),
+            abort_query: Box::new(move |_transport: TransportImpl, query_id| {
+                let processor = Arc::clone(&aqp);
+                Box::pin(async move { processor.abort(query_id) })
+            }),
+            reload_keys: Box::new(move |_transport: TransportImpl| {
+                let processor = Arc::clone(&kdqp);
+                Box::pin(async move { processor.reload_keys() })
+            }),
+            public_keys: Box::new(move |_transport: TransportImpl| {
+                let processor = Arc::clone(&pkqp);
+                Box::pin(async move { processor.public_keys() })
+            }),
         }
     }
 }
 
 impl HelperApp {
+    /// Creates a new helper. This also starts a background task that enforces the
+    /// [`QueryTimeouts`] of the query processor and forgets old queries, so it must be called
+    /// within Tokio runtime.
     pub fn new(transport: TransportImpl, query_processor: Arc<QueryProcessor>) -> Self {
+        // Shuttle tests do not have Tokio timers to run the reaper with
+        #[cfg(not(all(test, feature = "shuttle")))]
+        tokio::spawn(QueryProcessor::run_reaper(
+            Arc::downgrade(&query_processor),
+            Transport::clone_ref(&transport),
+            QueryTimeouts::REAPER_PERIOD,
+        ));
+
         Self {
             query_processor,
             transport,
@@ -115,6 +182,18 @@ impl HelperApp {
         Ok(())
     }
 
+    /// Sends one chunk of the query input to a helper.
+    ///
+    /// ## Errors
+    /// Propagates errors from the helper.
+    pub fn execute_query_chunk(
+        &self,
+        chunk: QueryInputChunk,
+    ) -> Result<ReceivedInputChunks, Error> {
+        let transport = <TransportImpl as Clone>::clone(&self.transport);
+        Ok(self.query_processor.receive_input_chunk(transport, chunk)?)
+    }
+
     /// Retrieves the status of a query.
     ///
     /// ## Errors
@@ -130,6 +209,17 @@ impl HelperApp {
     pub async fn complete_query(&self, query_id: QueryId) -> Result<Vec<u8>, Error> {
         Ok(self.query_processor.complete(query_id).await?.into_bytes())
     }
+
+    /// Cancels a query on all helpers.
+    ///
+    /// ## Errors
+    /// Propagates errors from the helper.
+    pub async fn kill_query(&self, query_id: QueryId) -> Result<(), Error> {
+        self.query_processor
+            .kill(Transport::clone_ref(&self.transport), query_id)
+            .await?;
+        Ok(())
+    }
 }
 
 /// Union of error types returned by API operations.
@@ -143,4 +233,6 @@ pub enum Error {
     QueryCompletion(#[from] QueryCompletionError),
     #[error(transparent)]
     QueryStatus(#[from] QueryStatusError),
+    #[error(transparent)]
+    QueryKill(#[from] QueryKillError),
 }
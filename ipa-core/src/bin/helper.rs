@@ -4,6 +4,7 @@ use std::{
     os::fd::{FromRawFd, RawFd},
     path::{Path, PathBuf},
     process,
+    time::Duration,
 };
 
 use clap::{self, Parser, Subcommand};
@@ -12,10 +13,14 @@ use ipa_core::{
     cli::{
         client_config_setup, keygen, test_setup, ConfGenArgs, KeygenArgs, TestSetupArgs, Verbosity,
     },
-    config::{hpke_registry, HpkeServerConfig, NetworkConfig, ServerConfig, TlsConfig},
+    config::{
+        hpke_registry, HpkeServerConfig, HttpServerConfig, NetworkConfig, ServerConfig, TlsConfig,
+    },
     error::BoxError,
     helpers::HelperIdentity,
+    hpke::KeyDirectory,
     net::{ClientIdentity, HttpTransport, MpcHelperClient},
+    query::{PrivacyBudgetLedger, QueryProcessor, QueryTimeouts, ResultStore},
     AppSetup,
 };
 use tracing::{error, info};
@@ -88,6 +93,76 @@ struct ServerArgs {
     /// Private key for decrypting match keys
     #[arg(long, requires = "mk_public_key")]
     mk_private_key: Option<PathBuf>,
+
+    /// Directory with key pairs for decrypting match keys, created by `keygen --mk-keys-dir`.
+    /// Reports encrypted with any of them are accepted. Keys added to or removed from the
+    /// directory take effect after a `POST /keys/reload` request.
+    #[arg(long, conflicts_with = "mk_public_key")]
+    mk_keys_dir: Option<PathBuf>,
+
+    /// Privacy budget that every report collector may spend on every epoch. If not set, queries
+    /// are accepted regardless of the privacy budget they spend.
+    #[arg(long)]
+    privacy_budget: Option<f64>,
+
+    /// File to keep track of the privacy budget that report collectors spent in, so that it is
+    /// not reset when the helper restarts
+    #[arg(long, requires = "privacy_budget")]
+    privacy_budget_file: Option<PathBuf>,
+
+    /// Time out new queries if other helpers do not accept them within this many seconds
+    #[arg(long)]
+    prepare_timeout: Option<u64>,
+
+    /// Time out queries that do not receive their inputs within this many seconds after being
+    /// accepted by all helpers
+    #[arg(long)]
+    input_timeout: Option<u64>,
+
+    /// Discard query results if the report collector does not ask for them within this many
+    /// seconds after the query completes
+    #[arg(long)]
+    result_retention: Option<u64>,
+
+    /// Directory to keep the results of completed queries in, so that report collectors can
+    /// fetch them again, including after the helper restarts
+    #[arg(long)]
+    result_store: Option<PathBuf>,
+
+    /// File containing HTTP settings of the helper web service. Settings passed as arguments
+    /// take precedence over the ones in the file.
+    #[arg(long)]
+    http_config: Option<PathBuf>,
+
+    /// Initial HTTP/2 flow control window of a stream, in bytes
+    #[arg(long)]
+    stream_window_size: Option<u32>,
+
+    /// Initial HTTP/2 flow control window of a connection, in bytes
+    #[arg(long)]
+    connection_window_size: Option<u32>,
+
+    /// Maximum number of concurrent HTTP/2 streams of a connection
+    #[arg(long)]
+    max_concurrent_streams: Option<u32>,
+
+    /// Send HTTP/2 pings every this many seconds to keep connections alive
+    #[arg(long)]
+    keep_alive_interval: Option<u64>,
+
+    /// Close connections if HTTP/2 pings are not acknowledged within this many seconds
+    #[arg(long, requires = "keep_alive_interval")]
+    keep_alive_timeout: Option<u64>,
+
+    /// Reject requests with bodies larger than this many bytes. Query inputs sent in one piece and
+    /// data exchanged between helpers are not limited.
+    #[arg(long)]
+    max_request_body_bytes: Option<u64>,
+
+    /// Reject query input chunks larger than this many bytes. Defaults to
+    /// `--max-request-body-bytes`.
+    #[arg(long)]
+    max_input_chunk_bytes: Option<u64>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -103,8 +178,33 @@ fn read_utf8_bytes(path: &Path) -> Result<Vec<u8>, BoxError> {
         .into_bytes())
 }
 
+/// Reads HTTP settings from the file given in `args`, if any, and overrides them with the ones
+/// passed as arguments.
+fn http_config(args: &ServerArgs) -> Result<HttpServerConfig, BoxError> {
+    let mut config = match &args.http_config {
+        Some(path) => HttpServerConfig::from_toml_str(&fs::read_to_string(path)?)?,
+        None => HttpServerConfig::default(),
+    };
+    let secs = |secs: Option<u64>| secs.map(Duration::from_secs);
+    config.stream_window_size = args.stream_window_size.or(config.stream_window_size);
+    config.connection_window_size = args
+        .connection_window_size
+        .or(config.connection_window_size);
+    config.max_concurrent_streams = args
+        .max_concurrent_streams
+        .or(config.max_concurrent_streams);
+    config.keep_alive_interval = secs(args.keep_alive_interval).or(config.keep_alive_interval);
+    config.keep_alive_timeout = secs(args.keep_alive_timeout).or(config.keep_alive_timeout);
+    config.max_request_body_bytes = args
+        .max_request_body_bytes
+        .or(config.max_request_body_bytes);
+    config.max_input_chunk_bytes = args.max_input_chunk_bytes.or(config.max_input_chunk_bytes);
+    Ok(config)
+}
+
 async fn server(args: ServerArgs) -> Result<(), BoxError> {
     let my_identity = HelperIdentity::try_from(args.identity.expect("enforced by clap")).unwrap();
+    let http_config = http_config(&args)?;
 
     let (identity, server_tls) = match (args.tls_cert, args.tls_key) {
         (Some(cert), Some(key_file)) => {
@@ -122,32 +222,63 @@ async fn server(args: ServerArgs) -> Result<(), BoxError> {
         _ => panic!("should have been rejected by clap"),
     };
 
-    let mk_encryption = args
-        .mk_public_key
-        .zip(args.mk_private_key)
-        .map(|(pk_path, sk_path)| HpkeServerConfig::File {
+    let mk_encryption = match (args.mk_public_key, args.mk_private_key, args.mk_keys_dir) {
+        (_, _, Some(path)) => Some(HpkeServerConfig::Directory { path }),
+        (Some(pk_path), Some(sk_path), None) => Some(HpkeServerConfig::File {
             public_key_file: pk_path,
             private_key_file: sk_path,
-        });
+        }),
+        _ => None,
+    };
+
+    let scheme = if args.disable_https {
+        Scheme::HTTP
+    } else {
+        Scheme::HTTPS
+    };
+    let network_config_path = args.network.as_deref().unwrap();
+    let network_config = NetworkConfig::from_toml_str(&fs::read_to_string(network_config_path)?)?
+        .override_scheme(&scheme);
 
     let key_registry = hpke_registry(mk_encryption.as_ref()).await?;
-    let (setup, callbacks) = AppSetup::with_key_registry(key_registry);
+    let query_processor = QueryProcessor::new(key_registry)
+        .with_helper_origin(&network_config.helper_origin)
+        .with_timeouts(QueryTimeouts {
+            prepare: args.prepare_timeout.map(Duration::from_secs),
+            inputs: args.input_timeout.map(Duration::from_secs),
+            result_retention: args.result_retention.map(Duration::from_secs),
+        });
+    let query_processor = match &mk_encryption {
+        Some(HpkeServerConfig::Directory { path }) => {
+            query_processor.with_key_directory(KeyDirectory::open(path)?)
+        }
+        _ => query_processor,
+    };
+    let query_processor = match (args.privacy_budget, args.privacy_budget_file) {
+        (Some(limit), Some(path)) => {
+            query_processor.with_privacy_budget(PrivacyBudgetLedger::open(path, limit)?)
+        }
+        (Some(limit), None) => {
+            query_processor.with_privacy_budget(PrivacyBudgetLedger::in_memory(limit))
+        }
+        (None, _) => query_processor,
+    };
 
     let server_config = ServerConfig {
         port: args.port,
         disable_https: args.disable_https,
         tls: server_tls,
         hpke_config: mk_encryption,
+        result_store: args.result_store,
+        http: http_config,
     };
 
-    let scheme = if args.disable_https {
-        Scheme::HTTP
-    } else {
-        Scheme::HTTPS
+    let query_processor = match &server_config.result_store {
+        Some(dir) => query_processor.with_result_store(ResultStore::open(dir)?),
+        None => query_processor,
     };
-    let network_config_path = args.network.as_deref().unwrap();
-    let network_config = NetworkConfig::from_toml_str(&fs::read_to_string(network_config_path)?)?
-        .override_scheme(&scheme);
+    let (setup, callbacks) = AppSetup::with_query_processor(query_processor);
+
     let clients = MpcHelperClient::from_conf(&network_config, identity);
 
     let (transport, server) = HttpTransport::new(
@@ -15,17 +15,26 @@ use hyper::http::uri::Scheme;
 use ipa_core::{
     cli::{
         noise::{apply, ApplyDpArgs},
-        playbook::{make_clients, playbook_ipa, playbook_oprf_ipa, validate, InputSource},
+        playbook::{
+            make_clients, playbook_feature_label_dot_product, playbook_ipa, playbook_oprf_ipa,
+            validate, InputSource,
+        },
         CsvSerializer, IpaQueryResult, Verbosity,
     },
-    config::NetworkConfig,
+    config::{HpkeClientConfig, NetworkConfig},
     ff::{FieldType, Fp32BitPrime},
-    helpers::query::{IpaQueryConfig, QueryConfig, QuerySize, QueryType},
+    helpers::query::{
+        FeatureLabelDotProductQueryConfig, IpaQueryConfig, QueryConfig, QuerySize, QueryType,
+        ReportCollectorId,
+    },
     hpke::{KeyRegistry, PublicKeyOnly},
-    net::MpcHelperClient,
-    protocol::{BreakdownKey, MatchKey},
-    report::{KeyIdentifier, DEFAULT_KEY_ID},
+    net::{ClientIdentity, MpcHelperClient},
+    protocol::{BreakdownKey, MatchKey, QueryId},
+    report::KeyIdentifier,
     test_fixture::{
+        feature_label_dot_product::{
+            feature_label_dot_product_in_the_clear, TestFeatureLabelRecord,
+        },
         ipa::{ipa_in_the_clear, CappingOrder, IpaQueryStyle, IpaSecurityModel, TestRawDataRecord},
         EventGenerator, EventGeneratorConfig,
     },
@@ -52,6 +61,18 @@ struct Args {
     #[arg(short, long, default_value_t = 0)]
     wait: usize,
 
+    /// TLS certificate to authenticate with, if helpers restrict access to the query APIs
+    #[arg(long, visible_alias("cert"), requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// TLS key for the certificate above
+    #[arg(long, visible_alias("key"), requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// File that contains a bearer token to authenticate with, instead of a TLS certificate
+    #[arg(long, conflicts_with = "tls_cert")]
+    token_file: Option<PathBuf>,
+
     #[clap(flatten)]
     input: CommandInput,
 
@@ -105,6 +126,14 @@ enum ReportCollectorCommand {
     ApplyDpNoise(ApplyDpArgs),
     /// Execute OPRF IPA in a semi-honest majority setting
     OprfIpa(IpaQueryConfig),
+    /// Execute feature-label dot product in a semi-honest majority setting
+    FeatureLabelDotProduct(FeatureLabelDotProductQueryConfig),
+    /// Cancel a query on all helpers
+    Kill {
+        /// Id of the query to cancel
+        #[clap(long)]
+        query_id: u64,
+    },
 }
 
 #[derive(Debug, clap::Args)]
@@ -116,6 +145,20 @@ struct GenInputArgs {
     breakdowns: u32,
 }
 
+/// Picks the credentials that the report collector presents to helpers.
+fn client_identity(args: &Args) -> Result<ClientIdentity, Box<dyn Error>> {
+    let read = |path: &Path| {
+        std::fs::read(path).map_err(|e| format!("failed to open file {}: {e:?}", path.display()))
+    };
+    Ok(match (&args.tls_cert, &args.tls_key, &args.token_file) {
+        (Some(cert), Some(key), _) => ClientIdentity::from_pks8(&read(cert)?, &read(key)?)?,
+        (_, _, Some(token)) => {
+            ClientIdentity::Token(String::from_utf8(read(token)?)?.trim().to_owned())
+        }
+        _ => ClientIdentity::None,
+    })
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
@@ -127,7 +170,9 @@ async fn main() -> Result<(), Box<dyn Error>> {
         Scheme::HTTPS
     };
 
-    let (clients, network) = make_clients(args.network.as_deref(), scheme, args.wait).await;
+    let identity = client_identity(&args)?;
+    let (clients, network) =
+        make_clients(args.network.as_deref(), scheme, identity, args.wait).await;
     match args.action {
         ReportCollectorCommand::SemiHonestIpa(config) => {
             ipa(
@@ -168,6 +213,12 @@ async fn main() -> Result<(), Box<dyn Error>> {
             )
             .await?
         }
+        ReportCollectorCommand::FeatureLabelDotProduct(config) => {
+            feature_label_dot_product(&args, config, &clients).await?
+        }
+        ReportCollectorCommand::Kill { query_id } => {
+            clients[0].kill_query(QueryId::from(query_id)).await?;
+        }
     };
 
     Ok(())
@@ -220,14 +271,25 @@ impl KeyRegistries {
             return None;
         };
 
+        // Encrypt with the most recent key that all helpers accept
+        let key_id = configs
+            .iter()
+            .map(|hpke| hpke.latest_key_id())
+            .min()
+            .filter(|key_id| {
+                configs
+                    .iter()
+                    .all(|hpke| hpke.public_keys.contains_key(key_id))
+            })?;
+
         // Create key registries
         self.0 = configs
             .into_iter()
-            .map(|hpke| KeyRegistry::from_keys([PublicKeyOnly(hpke.public_key.clone())]))
+            .map(HpkeClientConfig::key_registry)
             .collect::<Vec<KeyRegistry<PublicKeyOnly>>>();
 
         Some((
-            DEFAULT_KEY_ID,
+            key_id,
             self.0.iter().collect::<Vec<_>>().try_into().ok().unwrap(),
         ))
     }
@@ -263,6 +325,7 @@ async fn ipa(
         size: QuerySize::try_from(input_rows.len()).unwrap(),
         field_type: FieldType::Fp32BitPrime,
         query_type,
+        report_collector: ReportCollectorId::default(),
     };
     let query_id = helper_clients[0].create_query(query_config).await.unwrap();
 
@@ -290,11 +353,13 @@ async fn ipa(
     let mut key_registries = KeyRegistries::default();
     let actual = match query_style {
         IpaQueryStyle::Oprf => {
-            playbook_oprf_ipa::<Fp32BitPrime>(
+            playbook_oprf_ipa::<Fp32BitPrime, _>(
                 input_rows,
                 &helper_clients,
                 query_id,
                 ipa_query_config,
+                key_registries.init_from(network),
+                &network.helper_origin,
             )
             .await
         }
@@ -305,6 +370,7 @@ async fn ipa(
                 query_id,
                 ipa_query_config,
                 key_registries.init_from(network),
+                &network.helper_origin,
             )
             .await
         }
@@ -352,6 +418,51 @@ async fn ipa(
     Ok(())
 }
 
+async fn feature_label_dot_product(
+    args: &Args,
+    config: FeatureLabelDotProductQueryConfig,
+    helper_clients: &[MpcHelperClient; 3],
+) -> Result<(), Box<dyn Error>> {
+    let input = InputSource::from(&args.input);
+    let input_rows = input.iter::<TestFeatureLabelRecord>().collect::<Vec<_>>();
+    let query_config = QueryConfig {
+        size: QuerySize::try_from(input_rows.len()).unwrap(),
+        field_type: FieldType::Fp32BitPrime,
+        query_type: QueryType::FeatureLabelDotProduct(config),
+        report_collector: ReportCollectorId::default(),
+    };
+    let query_id = helper_clients[0].create_query(query_config).await.unwrap();
+
+    let expected = feature_label_dot_product_in_the_clear(
+        &input_rows,
+        config.feature_vector_bits,
+        config.per_user_credit_cap,
+    );
+    let actual = playbook_feature_label_dot_product::<Fp32BitPrime>(
+        &input_rows,
+        helper_clients,
+        query_id,
+        config,
+    )
+    .await;
+
+    if let Some(ref path) = args.output_file {
+        let mut file = File::options()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|e| format!("Failed to create output file {}: {e}", path.display()))?;
+
+        write!(file, "{}", serde_json::to_string_pretty(&actual)?)?;
+    }
+
+    tracing::info!("{m:?}", m = config);
+
+    validate(&expected, &actual);
+
+    Ok(())
+}
+
 fn apply_dp_noise(args: &Args, dp_args: &ApplyDpArgs) -> Result<(), Box<dyn Error>> {
     let IpaQueryResult { breakdowns, .. } =
         serde_json::from_slice(&InputSource::from(&args.input).to_vec()?)?;
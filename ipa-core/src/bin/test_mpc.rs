@@ -10,7 +10,7 @@ use ipa_core::{
     },
     ff::{Field, FieldType, Fp31, Fp32BitPrime, Serializable},
     helpers::query::{QueryConfig, QueryType::TestMultiply},
-    net::MpcHelperClient,
+    net::{ClientIdentity, MpcHelperClient},
     secret_sharing::{replicated::semi_honest::AdditiveShare, IntoShares},
 };
 
@@ -91,7 +91,13 @@ async fn main() -> Result<(), Box<dyn Error>> {
         Scheme::HTTPS
     };
 
-    let (clients, _) = make_clients(args.network.as_deref(), scheme, args.wait).await;
+    let (clients, _) = make_clients(
+        args.network.as_deref(),
+        scheme,
+        ClientIdentity::None,
+        args.wait,
+    )
+    .await;
     match args.action {
         TestAction::Multiply => multiply(&args, &clients).await,
     };
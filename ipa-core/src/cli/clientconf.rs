@@ -9,6 +9,7 @@ use crate::{
     cli::paths::PathExt,
     config::{ClientConfig, HpkeClientConfig, NetworkConfig, PeerConfig},
     error::BoxError,
+    report::DEFAULT_KEY_ID,
 };
 
 #[derive(Debug, Args)]
@@ -215,6 +216,8 @@ fn assert_hpke_config(expected: &Value, actual: Option<&HpkeClientConfig>) {
             .get("public_key")
             .and_then(toml::Value::as_str)
             .map(ToOwned::to_owned),
-        actual.map(|v| hex::encode(v.public_key.to_bytes()))
+        actual
+            .and_then(|v| v.public_keys.get(&DEFAULT_KEY_ID))
+            .map(|pk| hex::encode(pk.to_bytes()))
     );
 }
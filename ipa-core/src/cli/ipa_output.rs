@@ -1,6 +1,9 @@
 use std::time::Duration;
 
-use crate::helpers::query::{IpaQueryConfig, QuerySize};
+use crate::{
+    helpers::query::{IpaQueryConfig, QuerySize},
+    query::RejectedReports,
+};
 
 #[derive(Debug)]
 #[cfg_attr(feature = "enable-serde", derive(serde::Serialize, serde::Deserialize))]
@@ -13,4 +16,8 @@ pub struct QueryResult {
     )]
     pub latency: Duration,
     pub breakdowns: Vec<u32>,
+    /// Input reports that helpers left out of the query, see
+    /// [`InvalidReportPolicy`](crate::helpers::query::InvalidReportPolicy).
+    #[cfg_attr(feature = "enable-serde", serde(default))]
+    pub rejected_reports: RejectedReports,
 }
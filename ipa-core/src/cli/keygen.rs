@@ -1,5 +1,5 @@
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{self, Write},
     path::{Path, PathBuf},
 };
@@ -12,8 +12,13 @@ use rcgen::{
     KeyUsagePurpose, SanType, SerialNumber, PKCS_ECDSA_P256_SHA256,
 };
 use time::{Duration, OffsetDateTime};
+use toml::{Table, Value};
 
-use crate::{error::BoxError, hpke::KeyPair};
+use crate::{
+    error::BoxError,
+    hpke::{KeyDirectory, KeyPair},
+    report::{KeyIdentifier, DEFAULT_KEY_ID},
+};
 
 #[derive(Debug, Args)]
 #[clap(
@@ -24,24 +29,52 @@ use crate::{error::BoxError, hpke::KeyPair};
 
 pub struct KeygenArgs {
     /// DNS name to use for the TLS certificate
-    #[arg(short, long)]
-    pub(crate) name: String,
+    #[arg(
+        short,
+        long,
+        required_unless_present = "mk_keys_dir",
+        requires = "tls_cert"
+    )]
+    pub(crate) name: Option<String>,
 
     /// Writes the generated TLS certificate to the file
-    #[arg(long, visible_alias("cert"), visible_alias("tls-certificate"))]
-    pub(crate) tls_cert: PathBuf,
+    #[arg(
+        long,
+        visible_alias("cert"),
+        visible_alias("tls-certificate"),
+        requires_all = ["name", "tls_key"]
+    )]
+    pub(crate) tls_cert: Option<PathBuf>,
 
     /// Writes the generated TLS private key to the file
-    #[arg(long, visible_alias("key"))]
-    pub(crate) tls_key: PathBuf,
+    #[arg(long, visible_alias("key"), requires = "tls_cert")]
+    pub(crate) tls_key: Option<PathBuf>,
 
     /// Writes the generated report public key to the file
-    #[arg(long)]
-    pub(crate) mk_public_key: PathBuf,
+    #[arg(
+        long,
+        required_unless_present = "mk_keys_dir",
+        requires = "mk_private_key"
+    )]
+    pub(crate) mk_public_key: Option<PathBuf>,
 
     /// Writes the generated report private key to the file
+    #[arg(long, requires = "mk_public_key", conflicts_with = "mk_keys_dir")]
+    pub(crate) mk_private_key: Option<PathBuf>,
+
+    /// Adds the generated report key pair to the key directory under the next key identifier,
+    /// instead of writing it to `--mk-public-key` and `--mk-private-key`
     #[arg(long)]
-    pub(crate) mk_private_key: PathBuf,
+    pub(crate) mk_keys_dir: Option<PathBuf>,
+
+    /// Adds the public key generated into `--mk-keys-dir` to the keys of `--peer` in this
+    /// network config file, so report collectors can start using it
+    #[arg(long, requires_all = ["mk_keys_dir", "peer"])]
+    pub(crate) network: Option<PathBuf>,
+
+    /// The helper (1, 2 or 3) whose keys are updated in the `--network` config file
+    #[arg(long, requires = "network", value_parser = clap::value_parser!(u8).range(1..=3))]
+    pub(crate) peer: Option<u8>,
 }
 
 fn create_new<P: AsRef<Path>>(path: P) -> io::Result<File> {
@@ -52,14 +85,19 @@ fn create_new<P: AsRef<Path>>(path: P) -> io::Result<File> {
         .open(path)
 }
 
-/// Generate keys necessary for running a helper service.
+/// Generate the TLS certificate and key used by a helper service.
 ///
 /// # Errors
 /// If a problem is encountered during key generation.
 ///
 /// # Panics
 /// If something that shouldn't happen goes wrong during key generation.
-pub fn keygen_tls<R: Rng + CryptoRng>(args: &KeygenArgs, rng: &mut R) -> Result<(), BoxError> {
+pub fn keygen_tls<R: Rng + CryptoRng>(
+    name: &str,
+    tls_cert: &Path,
+    tls_key: &Path,
+    rng: &mut R,
+) -> Result<(), BoxError> {
     let mut params = CertificateParams::default();
     params.alg = &PKCS_ECDSA_P256_SHA256;
 
@@ -79,18 +117,16 @@ pub fn keygen_tls<R: Rng + CryptoRng>(args: &KeygenArgs, rng: &mut R) -> Result<
         rng.gen_range(0..=i64::MAX.try_into().unwrap()),
     ));
 
-    let mut name = DistinguishedName::new();
-    name.push(rcgen::DnType::CommonName, args.name.clone());
-    params.distinguished_name = name;
+    let mut distinguished_name = DistinguishedName::new();
+    distinguished_name.push(rcgen::DnType::CommonName, name);
+    params.distinguished_name = distinguished_name;
 
-    params.subject_alt_names = vec![SanType::DnsName(args.name.clone())];
+    params.subject_alt_names = vec![SanType::DnsName(name.to_owned())];
 
     let gen = Certificate::from_params(params)?;
 
-    create_new(&args.tls_cert)?
-        .write_all(gen.serialize_pem().unwrap().replace('\r', "").as_bytes())?;
-    create_new(&args.tls_key)?
-        .write_all(gen.serialize_private_key_pem().replace('\r', "").as_bytes())?;
+    create_new(tls_cert)?.write_all(gen.serialize_pem().unwrap().replace('\r', "").as_bytes())?;
+    create_new(tls_key)?.write_all(gen.serialize_private_key_pem().replace('\r', "").as_bytes())?;
 
     Ok(())
 }
@@ -99,12 +135,77 @@ pub fn keygen_tls<R: Rng + CryptoRng>(args: &KeygenArgs, rng: &mut R) -> Result<
 fn keygen_matchkey<R: Rng + CryptoRng>(args: &KeygenArgs, mut rng: &mut R) -> Result<(), BoxError> {
     let keypair = KeyPair::gen(&mut rng);
 
-    create_new(&args.mk_public_key)?.write_all(hex::encode(keypair.pk_bytes()).as_bytes())?;
-    create_new(&args.mk_private_key)?.write_all(hex::encode(keypair.sk_bytes()).as_bytes())?;
+    if let Some(dir) = &args.mk_keys_dir {
+        let keys = KeyDirectory::open(dir)?;
+        let key_id = keys.next_key_id()?;
+        let network = match (&args.network, args.peer) {
+            (Some(path), Some(peer)) => {
+                let config = add_public_key(&fs::read_to_string(path)?, peer, key_id, &keypair)
+                    .map_err(|e| format!("cannot update {}: {e}", path.display()))?;
+                Some((path, config))
+            }
+            _ => None,
+        };
+        keys.add(key_id, &keypair)?;
+        if let Some((path, config)) = network {
+            fs::write(path, config)?;
+        }
+        return Ok(());
+    }
+
+    if let (Some(pk_path), Some(sk_path)) = (&args.mk_public_key, &args.mk_private_key) {
+        create_new(pk_path)?.write_all(hex::encode(keypair.pk_bytes()).as_bytes())?;
+        create_new(sk_path)?.write_all(hex::encode(keypair.sk_bytes()).as_bytes())?;
+    }
 
     Ok(())
 }
 
+/// Appends the public key of `keypair` to the `hpke.keys` of the `peer` helper in the network
+/// config and returns the updated config. Comments and formatting are not preserved.
+fn add_public_key(
+    network: &str,
+    peer: u8,
+    key_id: KeyIdentifier,
+    keypair: &KeyPair,
+) -> Result<String, BoxError> {
+    let mut config = network.parse::<Table>()?;
+    let hpke = config
+        .get_mut("peers")
+        .and_then(Value::as_array_mut)
+        .and_then(|peers| peers.get_mut(usize::from(peer) - 1))
+        .and_then(Value::as_table_mut)
+        .ok_or_else(|| format!("peer {peer} is missing"))?
+        .entry("hpke")
+        .or_insert_with(|| Value::Table(Table::new()))
+        .as_table_mut()
+        .ok_or("hpke must be a table")?;
+    if key_id == DEFAULT_KEY_ID && hpke.contains_key("public_key") {
+        return Err(format!("peer {peer} already has public key {key_id}").into());
+    }
+    let keys = hpke
+        .entry("keys")
+        .or_insert_with(|| Value::Array(Vec::new()))
+        .as_array_mut()
+        .ok_or("hpke.keys must be an array")?;
+    let listed = keys
+        .iter()
+        .any(|key| key.get("key_id").and_then(Value::as_integer) == Some(i64::from(key_id)));
+    if listed {
+        return Err(format!("peer {peer} already has public key {key_id}").into());
+    }
+
+    let mut key = Table::new();
+    key.insert("key_id".to_owned(), Value::Integer(key_id.into()));
+    key.insert(
+        "public_key".to_owned(),
+        Value::String(hex::encode(keypair.pk_bytes())),
+    );
+    keys.push(Value::Table(key));
+
+    Ok(toml::to_string_pretty(&config)?)
+}
+
 /// Generate keys necessary for running a helper service.
 ///
 /// # Errors
@@ -114,7 +215,84 @@ fn keygen_matchkey<R: Rng + CryptoRng>(args: &KeygenArgs, mut rng: &mut R) -> Re
 /// If something that shouldn't happen goes wrong during key generation.
 pub fn keygen(args: &KeygenArgs) -> Result<(), BoxError> {
     let mut rng = thread_rng();
-    keygen_tls(args, &mut rng)?;
+    if let (Some(name), Some(tls_cert), Some(tls_key)) = (&args.name, &args.tls_cert, &args.tls_key)
+    {
+        keygen_tls(name, tls_cert, tls_key, &mut rng)?;
+    }
     keygen_matchkey(args, &mut rng)?;
     Ok(())
 }
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::{
+        config::NetworkConfig,
+        hpke::{PublicKeyRegistry, Serializable},
+    };
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        keygen: KeygenArgs,
+    }
+
+    #[test]
+    fn rotate_match_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys_dir = dir.path().join("keys");
+        let network = dir.path().join("network.toml");
+        fs::write(
+            &network,
+            r#"
+[[peers]]
+url = "localhost:3000"
+
+[[peers]]
+url = "localhost:3001"
+hpke = { keys = [] }
+
+[[peers]]
+url = "localhost:3002"
+"#,
+        )
+        .unwrap();
+        let args = Cli::parse_from([
+            "keygen".as_ref(),
+            "--mk-keys-dir".as_ref(),
+            keys_dir.as_os_str(),
+            "--network".as_ref(),
+            network.as_os_str(),
+            "--peer".as_ref(),
+            "2".as_ref(),
+        ])
+        .keygen;
+
+        keygen(&args).unwrap();
+        keygen(&args).unwrap();
+
+        let registry = KeyDirectory::open(&keys_dir).unwrap().load().unwrap();
+        assert_eq!(vec![0, 1], registry.key_ids().collect::<Vec<_>>());
+        let config = NetworkConfig::from_toml_str(&fs::read_to_string(&network).unwrap()).unwrap();
+        let hpke = config.peers()[1].hpke_config.as_ref().unwrap();
+        assert_eq!(
+            vec![0, 1],
+            hpke.public_keys.keys().copied().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            registry.public_key(1).unwrap().to_bytes(),
+            hpke.public_keys[&1].to_bytes()
+        );
+    }
+
+    #[test]
+    fn public_key_conflict() {
+        let keypair = KeyPair::gen(&mut thread_rng());
+        let network = r#"peers = [{ hpke = { public_key = "00" } }]"#;
+        assert!(add_public_key(network, 1, DEFAULT_KEY_ID, &keypair).is_err());
+        assert!(add_public_key(network, 1, 1, &keypair).is_ok());
+        assert!(add_public_key(network, 2, 1, &keypair).is_err());
+    }
+}
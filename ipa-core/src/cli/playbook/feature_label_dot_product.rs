@@ -0,0 +1,124 @@
+#![cfg(all(feature = "web-app", feature = "cli"))]
+use std::{
+    cmp::min,
+    iter::zip,
+    time::{Duration, Instant},
+};
+
+use futures_util::{future::try_join_all, TryFutureExt};
+use generic_array::GenericArray;
+use tokio::time::sleep;
+use typenum::Unsigned;
+
+use crate::{
+    ff::{
+        boolean_array::{BA16, BA20, BA32, BA8},
+        Field, PrimeField, Serializable,
+    },
+    helpers::{
+        query::{FeatureLabelDotProductQueryConfig, QueryInput},
+        BodyStream,
+    },
+    net::{MpcHelperClient, QueryResults},
+    protocol::QueryId,
+    query::QueryStatus,
+    report::FeatureLabelDotProductReport,
+    secret_sharing::{replicated::semi_honest::AdditiveShare, IntoShares, WeakSharedValue},
+    test_fixture::{feature_label_dot_product::TestFeatureLabelRecord, Reconstruct},
+};
+
+/// Feature-label dot product protocol.
+/// Returns, for every feature, the number of labelled source events that have it.
+///
+/// ## Panics
+/// If the feature vector width in `query_config` is not supported, or if any of the helpers
+/// fails to run the query.
+pub async fn playbook_feature_label_dot_product<F>(
+    records: &[TestFeatureLabelRecord],
+    clients: &[MpcHelperClient; 3],
+    query_id: QueryId,
+    query_config: FeatureLabelDotProductQueryConfig,
+) -> Vec<u32>
+where
+    F: PrimeField,
+    AdditiveShare<F>: Serializable,
+{
+    let query_size = records.len();
+    let buffers = match query_config.feature_vector_bits {
+        8 => feature_label_inputs::<BA8>(records),
+        16 => feature_label_inputs::<BA16>(records),
+        32 => feature_label_inputs::<BA32>(records),
+        fv => panic!("feature-label dot product does not support {fv}-bit feature vectors"),
+    };
+
+    let mpc_time = Instant::now();
+    try_join_all(buffers.map(BodyStream::from).into_iter().zip(clients).map(
+        |(input_stream, client)| {
+            client.query_input(QueryInput {
+                query_id,
+                input_stream,
+            })
+        },
+    ))
+    .await
+    .unwrap();
+
+    let mut delay = Duration::from_millis(125);
+    loop {
+        if try_join_all(clients.iter().map(|client| client.query_status(query_id)))
+            .await
+            .unwrap()
+            .into_iter()
+            .all(|report| report.status == QueryStatus::Completed)
+        {
+            break;
+        }
+
+        sleep(delay).await;
+        delay = min(Duration::from_secs(5), delay * 2);
+    }
+
+    let results: [_; 3] = try_join_all(clients.iter().map(|client| {
+        client
+            .query_results(query_id)
+            .and_then(QueryResults::into_bytes)
+    }))
+    .await
+    .unwrap()
+    .try_into()
+    .unwrap();
+
+    let results: Vec<F> = results
+        .map(|bytes| AdditiveShare::<F>::from_byte_slice(&bytes).collect::<Vec<_>>())
+        .reconstruct();
+
+    tracing::info!(
+        "Running feature-label dot product for {query_size:?} records took {t:?}",
+        t = mpc_time.elapsed()
+    );
+
+    results
+        .into_iter()
+        .map(|v| u32::try_from(v.as_u128()).unwrap())
+        .collect()
+}
+
+/// Secret-shares `records` as feature-label dot product input reports with `FV`-bit feature
+/// vectors.
+fn feature_label_inputs<FV>(records: &[TestFeatureLabelRecord]) -> [Vec<u8>; 3]
+where
+    FV: WeakSharedValue + Field + IntoShares<AdditiveShare<FV>>,
+    FeatureLabelDotProductReport<FV, BA20>: Serializable,
+{
+    let sz = <FeatureLabelDotProductReport<FV, BA20> as Serializable>::Size::USIZE;
+    let mut buffers: [_; 3] = std::array::from_fn(|_| vec![0u8; records.len() * sz]);
+
+    let shares: [Vec<FeatureLabelDotProductReport<FV, BA20>>; 3] = records.iter().cloned().share();
+    zip(&mut buffers, shares).for_each(|(buf, shares)| {
+        for (share, chunk) in zip(shares, buf.chunks_mut(sz)) {
+            share.serialize(GenericArray::from_mut_slice(chunk));
+        }
+    });
+
+    buffers
+}
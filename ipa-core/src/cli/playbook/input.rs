@@ -9,7 +9,10 @@ use std::{
 use crate::{
     ff::{Field, GaloisField},
     ipa_test_input,
-    test_fixture::{input::GenericReportTestInput, ipa::TestRawDataRecord},
+    test_fixture::{
+        feature_label_dot_product::TestFeatureLabelRecord, input::GenericReportTestInput,
+        ipa::TestRawDataRecord,
+    },
 };
 
 pub trait InputItem {
@@ -77,6 +80,23 @@ impl InputItem for TestRawDataRecord {
     }
 }
 
+impl InputItem for TestFeatureLabelRecord {
+    fn from_str(s: &str) -> Self {
+        if let [ts, match_key, is_trigger_bit, feature_vector] =
+            s.splitn(4, ',').collect::<Vec<_>>()[..]
+        {
+            TestFeatureLabelRecord {
+                user_id: match_key.parse().unwrap(),
+                timestamp: ts.parse().unwrap(),
+                is_trigger_report: is_trigger_bit.parse::<u8>().unwrap() == 1,
+                feature_vector: feature_vector.parse().unwrap(),
+            }
+        } else {
+            panic!("{s} is not a valid {}", type_name::<Self>())
+        }
+    }
+}
+
 pub struct InputSource {
     inner: Box<dyn BufRead>,
 }
@@ -5,7 +5,7 @@ use std::{
     time::{Duration, Instant},
 };
 
-use futures_util::future::try_join_all;
+use futures_util::{future::try_join_all, TryFutureExt};
 use generic_array::GenericArray;
 use rand::{distributions::Standard, prelude::Distribution, rngs::StdRng};
 use rand_core::SeedableRng;
@@ -14,7 +14,7 @@ use typenum::Unsigned;
 
 use crate::{
     cli::IpaQueryResult,
-    ff::{PrimeField, Serializable},
+    ff::{Field, PrimeField, Serializable},
     helpers::{
         query::{IpaQueryConfig, QueryInput, QuerySize},
         BodyStream,
@@ -22,10 +22,13 @@ use crate::{
     hpke::PublicKeyRegistry,
     ipa_test_input,
     net::MpcHelperClient,
-    protocol::{ipa::IPAInputRow, BreakdownKey, MatchKey, QueryId, Timestamp, TriggerValue},
+    protocol::{ipa::IPAInputRow, BreakdownKey, MatchKey, QueryId},
     query::QueryStatus,
-    report::{KeyIdentifier, OprfReport, Report},
-    secret_sharing::{replicated::semi_honest::AdditiveShare, IntoShares},
+    report::{
+        with_oprf_report_types, DecryptedOprfReport, KeyIdentifier, OprfReport, Report,
+        ESTIMATED_AVERAGE_REPORT_SIZE,
+    },
+    secret_sharing::{replicated::semi_honest::AdditiveShare, IntoShares, WeakSharedValue},
     test_fixture::{input::GenericReportTestInput, ipa::TestRawDataRecord, Reconstruct},
 };
 
@@ -38,6 +41,7 @@ pub async fn playbook_ipa<F, MK, BK, KR>(
     query_id: QueryId,
     query_config: IpaQueryConfig,
     encryption: Option<(KeyIdentifier, [&KR; 3])>,
+    helper_origin: &str,
 ) -> IpaQueryResult
 where
     F: PrimeField + IntoShares<AdditiveShare<F>>,
@@ -52,7 +56,6 @@ where
 
     if !query_config.plaintext_match_keys {
         if let Some((key_id, key_registries)) = encryption {
-            const ESTIMATED_AVERAGE_REPORT_SIZE: usize = 80; // TODO: confirm/adjust
             for buffer in &mut buffers {
                 buffer.reserve(query_size * ESTIMATED_AVERAGE_REPORT_SIZE);
             }
@@ -63,7 +66,14 @@ where
                 |((buf, shares), key_registry)| {
                     for share in shares {
                         share
-                            .delimited_encrypt_to(key_id, key_registry, &mut rng, buf)
+                            .delimited_encrypt_to(
+                                query_config.report_version,
+                                key_id,
+                                key_registry,
+                                helper_origin,
+                                &mut rng,
+                                buf,
+                            )
                             .unwrap();
                     }
                 },
@@ -103,40 +113,105 @@ where
     run_query_and_validate::<F>(inputs, query_size, clients, query_id, query_config).await
 }
 
-pub async fn playbook_oprf_ipa<F>(
-    mut records: Vec<TestRawDataRecord>,
+pub async fn playbook_oprf_ipa<F, KR>(
+    records: Vec<TestRawDataRecord>,
     clients: &[MpcHelperClient; 3],
     query_id: QueryId,
     query_config: IpaQueryConfig,
+    encryption: Option<(KeyIdentifier, [&KR; 3])>,
+    helper_origin: &str,
 ) -> IpaQueryResult
 where
     F: PrimeField,
     AdditiveShare<F>: Serializable,
+    KR: PublicKeyRegistry,
 {
-    let mut buffers: [_; 3] = std::array::from_fn(|_| Vec::new());
     let query_size = records.len();
+    let buffers = with_oprf_report_types!(
+        query_config,
+        |BK, TV, TS| oprf_ipa_inputs::<BK, TV, TS, KR>(
+            &records,
+            &query_config,
+            encryption,
+            helper_origin,
+        ),
+        _ => panic!(
+            "OPRF IPA does not support {}-bit breakdown keys, {}-bit trigger values \
+             and {}-bit timestamps",
+            query_config.breakdown_key_bits,
+            query_config.trigger_value_bits,
+            query_config.timestamp_bits,
+        ),
+    );
 
-    let sz = <OprfReport<BreakdownKey, TriggerValue, Timestamp> as Serializable>::Size::USIZE;
-    for buffer in &mut buffers {
-        buffer.resize(query_size * sz, 0u8);
-    }
+    let inputs = buffers.map(BodyStream::from);
+    tracing::info!("Starting query for OPRF");
 
-    //TODO(richaj) This manual sorting will be removed once we have the PRF sharding in place.
-    //This does a stable sort. It also expects the inputs to be sorted by timestamp
-    records.sort_by(|a, b| b.user_id.cmp(&a.user_id));
+    run_query_and_validate::<F>(inputs, query_size, clients, query_id, query_config).await
+}
 
-    let shares: [Vec<OprfReport<BreakdownKey, TriggerValue, Timestamp>>; 3] =
-        records.iter().cloned().share();
-    zip(&mut buffers, shares).for_each(|(buf, shares)| {
-        for (share, chunk) in zip(shares, buf.chunks_mut(sz)) {
-            share.serialize(GenericArray::from_mut_slice(chunk));
+/// Secret-shares `records` as OPRF IPA input reports with the given field types, encrypting them
+/// unless `query_config` asks for plaintext match keys.
+fn oprf_ipa_inputs<BK, TV, TS, KR>(
+    records: &[TestRawDataRecord],
+    query_config: &IpaQueryConfig,
+    encryption: Option<(KeyIdentifier, [&KR; 3])>,
+    helper_origin: &str,
+) -> [Vec<u8>; 3]
+where
+    BK: WeakSharedValue + Field + IntoShares<AdditiveShare<BK>>,
+    TV: WeakSharedValue + Field + IntoShares<AdditiveShare<TV>>,
+    TS: WeakSharedValue + Field + IntoShares<AdditiveShare<TS>>,
+    AdditiveShare<BK>: Serializable,
+    AdditiveShare<TV>: Serializable,
+    AdditiveShare<TS>: Serializable,
+    OprfReport<BK, TV, TS>: Serializable,
+    KR: PublicKeyRegistry,
+{
+    let mut buffers: [_; 3] = std::array::from_fn(|_| Vec::new());
+    let query_size = records.len();
+
+    if !query_config.plaintext_match_keys {
+        if let Some((key_id, key_registries)) = encryption {
+            for buffer in &mut buffers {
+                buffer.reserve(query_size * ESTIMATED_AVERAGE_REPORT_SIZE);
+            }
+
+            let mut rng = StdRng::from_entropy();
+            let shares: [Vec<DecryptedOprfReport<BK, TV, TS>>; 3] = records.iter().cloned().share();
+            zip(&mut buffers, shares).zip(key_registries).for_each(
+                |((buf, shares), key_registry)| {
+                    for share in shares {
+                        share
+                            .delimited_encrypt_to(
+                                key_id,
+                                key_registry,
+                                helper_origin,
+                                &mut rng,
+                                buf,
+                            )
+                            .unwrap();
+                    }
+                },
+            );
+        } else {
+            panic!("match key encryption was requested, but one or more helpers is missing a public key")
+        }
+    } else {
+        let sz = <OprfReport<BK, TV, TS> as Serializable>::Size::USIZE;
+        for buffer in &mut buffers {
+            buffer.resize(query_size * sz, 0u8);
         }
-    });
 
-    let inputs = buffers.map(BodyStream::from);
-    tracing::info!("Starting query for OPRF");
+        let shares: [Vec<OprfReport<BK, TV, TS>>; 3] = records.iter().cloned().share();
+        zip(&mut buffers, shares).for_each(|(buf, shares)| {
+            for (share, chunk) in zip(shares, buf.chunks_mut(sz)) {
+                share.serialize(GenericArray::from_mut_slice(chunk));
+            }
+        });
+    }
 
-    run_query_and_validate::<F>(inputs, query_size, clients, query_id, query_config).await
+    buffers
 }
 
 pub async fn run_query_and_validate<F>(
@@ -171,7 +246,7 @@ where
             .await
             .unwrap()
             .into_iter()
-            .all(|status| status == QueryStatus::Completed)
+            .all(|report| report.status == QueryStatus::Completed)
         {
             break;
         }
@@ -183,14 +258,26 @@ where
     }
 
     // wait until helpers have processed the query and get the results from them
-    let results: [_; 3] = try_join_all(clients.iter().map(|client| client.query_results(query_id)))
-        .await
-        .unwrap()
-        .try_into()
-        .unwrap();
+    let results: [_; 3] = try_join_all(clients.iter().map(|client| {
+        client.query_results(query_id).and_then(|results| {
+            let rejected_reports = results.header.rejected_reports.clone();
+            results
+                .into_bytes()
+                .map_ok(|bytes| (rejected_reports, bytes))
+        })
+    }))
+    .await
+    .unwrap()
+    .try_into()
+    .unwrap();
 
+    // helpers agree on the reports they reject
+    let rejected_reports = results[0].0.clone();
+    if !rejected_reports.is_empty() {
+        tracing::warn!("Helpers rejected invalid reports: {rejected_reports:?}");
+    }
     let results: Vec<F> = results
-        .map(|bytes| AdditiveShare::<F>::from_byte_slice(&bytes).collect::<Vec<_>>())
+        .map(|(_, bytes)| AdditiveShare::<F>::from_byte_slice(&bytes).collect::<Vec<_>>())
         .reconstruct();
 
     let lat = mpc_time.elapsed();
@@ -215,5 +302,6 @@ where
         config: query_config,
         latency: lat,
         breakdowns,
+        rejected_reports,
     }
 }
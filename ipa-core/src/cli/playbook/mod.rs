@@ -1,3 +1,4 @@
+mod feature_label_dot_product;
 mod input;
 mod ipa;
 mod multiply;
@@ -11,9 +12,12 @@ pub use input::InputSource;
 pub use multiply::secure_mul;
 use tokio::time::sleep;
 
-pub use self::ipa::{playbook_ipa, playbook_oprf_ipa};
+pub use self::{
+    feature_label_dot_product::playbook_feature_label_dot_product,
+    ipa::{playbook_ipa, playbook_oprf_ipa},
+};
 use crate::{
-    config::{ClientConfig, NetworkConfig, PeerConfig},
+    config::{ClientConfig, HpkeClientConfig, NetworkConfig, PeerConfig},
     net::{ClientIdentity, MpcHelperClient},
 };
 
@@ -64,9 +68,19 @@ where
     );
 }
 
+/// Creates clients for the helpers listed in the network config at `network_path`, waiting up to
+/// `wait` seconds for them to come up.
+///
+/// Helpers that do not have public keys listed in the network config are asked for their
+/// public keys, which are then added to the returned config. If the config has a certificate of
+/// the helper, the keys must be signed with its private key.
+///
+/// # Panics
+/// If the network config cannot be read, or a helper's public keys cannot be fetched or verified.
 pub async fn make_clients(
     network_path: Option<&Path>,
     scheme: Scheme,
+    identity: ClientIdentity,
     wait: usize,
 ) -> ([MpcHelperClient; 3], NetworkConfig) {
     let mut wait = wait;
@@ -80,18 +94,34 @@ pub async fn make_clients(
                 PeerConfig::new("localhost:3002".parse().unwrap(), None),
             ],
             client: ClientConfig::default(),
+            report_collectors: Vec::new(),
+            helper_origin: NetworkConfig::default_helper_origin(),
         }
     };
-    let network = network.override_scheme(&scheme);
+    let mut network = network.override_scheme(&scheme);
 
     // Note: This closure is only called when the selected action uses clients.
 
-    let clients = MpcHelperClient::from_conf(&network, ClientIdentity::None);
+    let clients = MpcHelperClient::from_conf(&network, identity);
     while wait > 0 && !clients_ready(&clients).await {
         tracing::debug!("waiting for servers to come up");
         sleep(Duration::from_secs(1)).await;
         wait -= 1;
     }
+
+    for (peer, client) in network.peers.iter_mut().zip(&clients) {
+        if peer.hpke_config.is_some() {
+            continue;
+        }
+        let public_keys = client
+            .public_keys(peer.certificate.as_ref())
+            .await
+            .unwrap_or_else(|e| panic!("failed to get public keys from {}: {e}", peer.url));
+        if !public_keys.is_empty() {
+            peer.hpke_config = Some(HpkeClientConfig { public_keys });
+        }
+    }
+
     (clients, network)
 }
 
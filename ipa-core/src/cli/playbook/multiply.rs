@@ -2,14 +2,14 @@
 
 use std::ops::Add;
 
-use futures::future::try_join_all;
+use futures::{future::try_join_all, TryFutureExt};
 use generic_array::{ArrayLength, GenericArray};
 use typenum::Unsigned;
 
 use crate::{
     ff::{Field, Serializable},
     helpers::{query::QueryInput, BodyStream},
-    net::MpcHelperClient,
+    net::{MpcHelperClient, QueryResults},
     protocol::QueryId,
     secret_sharing::{replicated::semi_honest::AdditiveShare as Replicated, IntoShares},
     test_fixture::Reconstruct,
@@ -65,11 +65,15 @@ where
     .unwrap();
 
     // wait until helpers have processed the query and get the results from them
-    let results: [_; 3] = try_join_all(clients.iter().map(|client| client.query_results(query_id)))
-        .await
-        .unwrap()
-        .try_into()
-        .unwrap();
+    let results: [_; 3] = try_join_all(clients.iter().map(|client| {
+        client
+            .query_results(query_id)
+            .and_then(QueryResults::into_bytes)
+    }))
+    .await
+    .unwrap()
+    .try_into()
+    .unwrap();
 
     // expect replicated shares to be sent back
     results
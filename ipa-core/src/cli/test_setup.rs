@@ -59,11 +59,14 @@ pub fn test_setup(args: TestSetupArgs) -> Result<(), BoxError> {
     let clients_config: [_; 3] = zip([1, 2, 3], args.ports)
         .map(|(id, port)| {
             let keygen_args = KeygenArgs {
-                name: localhost.clone(),
-                tls_cert: args.output_dir.helper_tls_cert(id),
-                tls_key: args.output_dir.helper_tls_key(id),
-                mk_public_key: args.output_dir.helper_mk_public_key(id),
-                mk_private_key: args.output_dir.helper_mk_private_key(id),
+                name: Some(localhost.clone()),
+                tls_cert: Some(args.output_dir.helper_tls_cert(id)),
+                tls_key: Some(args.output_dir.helper_tls_key(id)),
+                mk_public_key: Some(args.output_dir.helper_mk_public_key(id)),
+                mk_private_key: Some(args.output_dir.helper_mk_private_key(id)),
+                mk_keys_dir: None,
+                network: None,
+                peer: None,
             };
 
             keygen(&keygen_args)?;
@@ -71,8 +74,8 @@ pub fn test_setup(args: TestSetupArgs) -> Result<(), BoxError> {
             Ok(HelperClientConf {
                 host: &localhost,
                 port,
-                tls_cert_file: keygen_args.tls_cert,
-                mk_public_key_file: keygen_args.mk_public_key,
+                tls_cert_file: args.output_dir.helper_tls_cert(id),
+                mk_public_key_file: args.output_dir.helper_mk_public_key(id),
             })
         })
         .collect::<Result<Vec<_>, BoxError>>()?
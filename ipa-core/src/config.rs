@@ -1,6 +1,7 @@
 use std::{
     array,
     borrow::{Borrow, Cow},
+    collections::{BTreeMap, HashSet},
     fmt::{Debug, Formatter},
     iter::Zip,
     path::PathBuf,
@@ -16,10 +17,12 @@ use tokio::fs;
 
 use crate::{
     error::BoxError,
-    helpers::HelperIdentity,
+    helpers::{query::ReportCollectorId, HelperIdentity},
     hpke::{
-        Deserializable as _, IpaPrivateKey, IpaPublicKey, KeyPair, KeyRegistry, Serializable as _,
+        Deserializable as _, IpaPrivateKey, IpaPublicKey, KeyDirectory, KeyPair, KeyRegistry,
+        PublicKeyOnly, Serializable as _,
     },
+    report::{KeyIdentifier, DEFAULT_HELPER_ORIGIN, DEFAULT_KEY_ID},
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -46,6 +49,16 @@ pub struct NetworkConfig {
     /// HTTP client configuration.
     #[serde(default)]
     pub client: ClientConfig,
+
+    /// Report collectors that may use the query APIs. If none are listed, the query APIs are open
+    /// to anyone, and report collectors that do not authenticate share the same identity.
+    #[serde(default)]
+    pub report_collectors: Vec<ReportCollectorConfig>,
+
+    /// Origin of the helper network. Report collectors bind reports to it when encrypting them,
+    /// and helpers reject reports that were encrypted for a different origin.
+    #[serde(default = "NetworkConfig::default_helper_origin")]
+    pub helper_origin: String,
 }
 
 impl NetworkConfig {
@@ -66,13 +79,38 @@ impl NetworkConfig {
     }
 
     pub fn new(peers: [PeerConfig; 3], client: ClientConfig) -> Self {
-        Self { peers, client }
+        Self {
+            peers,
+            client,
+            report_collectors: Vec::new(),
+            helper_origin: Self::default_helper_origin(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_report_collectors(mut self, report_collectors: Vec<ReportCollectorConfig>) -> Self {
+        self.report_collectors = report_collectors;
+        self
+    }
+
+    /// Identities of the report collectors that may use the query APIs, empty if anyone may.
+    #[must_use]
+    pub fn report_collector_ids(&self) -> HashSet<ReportCollectorId> {
+        self.report_collectors
+            .iter()
+            .map(ReportCollectorConfig::id)
+            .collect()
     }
 
     pub fn peers(&self) -> &[PeerConfig; 3] {
         &self.peers
     }
 
+    #[must_use]
+    pub fn default_helper_origin() -> String {
+        DEFAULT_HELPER_ORIGIN.to_owned()
+    }
+
     // Can maybe be replaced with array::zip when stable?
     pub fn enumerate_peers(
         &self,
@@ -136,28 +174,136 @@ impl PeerConfig {
 }
 
 /// Match key encryption client configuration. To encrypt match keys towards a helper node, clients
-/// need to know helper's public key.
+/// need to know helper's public keys.
+///
+/// Helpers may accept reports encrypted with any of several keys, which are listed in
+/// `network.toml` as `keys = [{ key_id = 1, public_key = "..." }, ...]`. A single `public_key`
+/// is the key with identifier [`DEFAULT_KEY_ID`].
 #[derive(Clone, Deserialize)]
+#[serde(try_from = "HpkeClientConfigToml")]
 pub struct HpkeClientConfig {
-    #[serde(deserialize_with = "pk_from_str")]
-    pub public_key: IpaPublicKey,
+    pub public_keys: BTreeMap<KeyIdentifier, IpaPublicKey>,
 }
 
 impl Debug for HpkeClientConfig {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("HpkeClientConfig")
-            .field("public_key", &pk_to_str(&self.public_key))
+            .field(
+                "public_keys",
+                &self
+                    .public_keys
+                    .iter()
+                    .map(|(key_id, pk)| (key_id, pk_to_str(pk)))
+                    .collect::<BTreeMap<_, _>>(),
+            )
             .finish()
     }
 }
 
 impl HpkeClientConfig {
+    /// Creates a config with a single public key that has the [`DEFAULT_KEY_ID`] identifier.
     #[must_use]
     pub fn new(public_key: IpaPublicKey) -> Self {
-        Self { public_key }
+        Self {
+            public_keys: BTreeMap::from([(DEFAULT_KEY_ID, public_key)]),
+        }
+    }
+
+    /// Returns the most recent key, which clients should use to encrypt new reports.
+    ///
+    /// ## Panics
+    /// If there are no keys, which the config does not allow.
+    #[must_use]
+    pub fn latest_key_id(&self) -> KeyIdentifier {
+        *self.public_keys.keys().next_back().unwrap()
+    }
+
+    #[must_use]
+    pub fn key_registry(&self) -> KeyRegistry<PublicKeyOnly> {
+        KeyRegistry::from_key_ids(
+            self.public_keys
+                .iter()
+                .map(|(&key_id, pk)| (key_id, PublicKeyOnly(pk.clone()))),
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct HpkeClientConfigToml {
+    #[serde(default, deserialize_with = "optional_pk_from_str")]
+    public_key: Option<IpaPublicKey>,
+    #[serde(default)]
+    keys: Vec<HpkePublicKeyToml>,
+}
+
+#[derive(Deserialize)]
+struct HpkePublicKeyToml {
+    key_id: KeyIdentifier,
+    #[serde(deserialize_with = "pk_from_str")]
+    public_key: IpaPublicKey,
+}
+
+impl TryFrom<HpkeClientConfigToml> for HpkeClientConfig {
+    type Error = String;
+
+    fn try_from(value: HpkeClientConfigToml) -> Result<Self, Self::Error> {
+        let mut public_keys = BTreeMap::new();
+        let keys = value
+            .public_key
+            .map(|public_key| HpkePublicKeyToml {
+                key_id: DEFAULT_KEY_ID,
+                public_key,
+            })
+            .into_iter()
+            .chain(value.keys);
+        for HpkePublicKeyToml { key_id, public_key } in keys {
+            if public_keys.insert(key_id, public_key).is_some() {
+                return Err(format!("public key {key_id} is listed more than once"));
+            }
+        }
+        if public_keys.is_empty() {
+            return Err("at least one public key is required".to_owned());
+        }
+
+        Ok(Self { public_keys })
+    }
+}
+
+/// A report collector that may use the query APIs. It authenticates either with a TLS client
+/// certificate, or with a bearer token. Helpers only know the SHA-256 digest of the token.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ReportCollectorConfig {
+    /// Authenticates with a TLS client certificate, which must be in PEM format in
+    /// `network.toml`.
+    Certificate {
+        #[serde(deserialize_with = "required_certificate_from_pem")]
+        certificate: Certificate,
+    },
+    /// Authenticates with a bearer token. `token_sha256` is the hex-encoded digest of the token.
+    Token { token_sha256: ReportCollectorId },
+}
+
+impl ReportCollectorConfig {
+    #[must_use]
+    pub fn id(&self) -> ReportCollectorId {
+        match self {
+            Self::Certificate { certificate } => {
+                ReportCollectorId::from_certificate(&certificate.0)
+            }
+            Self::Token { token_sha256 } => *token_sha256,
+        }
     }
 }
 
+fn required_certificate_from_pem<'de, D>(deserializer: D) -> Result<Certificate, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    certificate_from_pem(deserializer)?
+        .ok_or_else(|| serde::de::Error::custom("certificate is missing"))
+}
+
 fn certificate_from_pem<'de, D>(deserializer: D) -> Result<Option<Certificate>, D::Error>
 where
     D: Deserializer<'de>,
@@ -185,6 +331,13 @@ where
     IpaPublicKey::from_bytes(&buf).map_err(<D::Error as serde::de::Error>::custom)
 }
 
+fn optional_pk_from_str<'de, D>(deserializer: D) -> Result<Option<IpaPublicKey>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    pk_from_str(deserializer).map(Some)
+}
+
 fn pk_to_str(pk: &IpaPublicKey) -> String {
     hex::encode(pk.to_bytes().as_slice())
 }
@@ -223,6 +376,12 @@ pub enum HpkeServerConfig {
         // Private key in hex format
         private_key: String,
     },
+    /// Any number of key pairs kept in a [`KeyDirectory`], which the helper can reload without
+    /// restarting.
+    Directory {
+        /// Path to the key directory
+        path: PathBuf,
+    },
 }
 
 /// # Errors
@@ -232,6 +391,7 @@ pub async fn hpke_registry(
 ) -> Result<KeyRegistry<KeyPair>, BoxError> {
     let (pk_str, sk_str) = match config {
         None => return Ok(KeyRegistry::empty()),
+        Some(HpkeServerConfig::Directory { path }) => return Ok(KeyDirectory::open(path)?.load()?),
         Some(HpkeServerConfig::Inline {
             public_key,
             private_key,
@@ -271,6 +431,126 @@ pub struct ServerConfig {
 
     /// Configuration needed for encrypting and decrypting match keys
     pub hpke_config: Option<HpkeServerConfig>,
+
+    /// Directory to keep the results of completed queries in. If not specified, results are
+    /// kept in memory only and are gone once the report collector receives them.
+    pub result_store: Option<PathBuf>,
+
+    /// HTTP settings of the web service.
+    pub http: HttpServerConfig,
+}
+
+/// HTTP settings of the helper web service. Settings that are not specified keep the defaults of
+/// [`hyper`]. HTTP/2 settings apply to connections from other helpers as well as from report
+/// collectors.
+///
+/// The settings can be read from a TOML file, for example:
+/// ```toml
+/// stream_window_size = 4194304
+/// max_concurrent_streams = 1000
+/// keep_alive_interval_secs = 30
+/// max_request_body_bytes = 1048576
+///
+/// [timeouts]
+/// prepare = 10
+/// status = 5
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpServerConfig {
+    /// HTTP/2 flow control window of every stream, in bytes. Larger windows keep links with
+    /// high latency busy.
+    pub stream_window_size: Option<u32>,
+
+    /// HTTP/2 flow control window of every connection, in bytes.
+    pub connection_window_size: Option<u32>,
+
+    /// The number of HTTP/2 streams that a client may have open on one connection at the same
+    /// time. Every query needs one stream per step for every other helper.
+    pub max_concurrent_streams: Option<u32>,
+
+    /// Send HTTP/2 [`PING`] frames this often to keep connections alive. Disabled if not set.
+    ///
+    /// [`PING`]: https://datatracker.ietf.org/doc/html/rfc9113#name-ping
+    #[serde(
+        rename = "keep_alive_interval_secs",
+        serialize_with = "crate::serde::duration::to_secs",
+        deserialize_with = "crate::serde::duration::from_secs_optional",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub keep_alive_interval: Option<Duration>,
+
+    /// Close the connection if a [`PING`] frame is not acknowledged within this time.
+    ///
+    /// [`PING`]: https://datatracker.ietf.org/doc/html/rfc9113#name-ping
+    #[serde(
+        rename = "keep_alive_timeout_secs",
+        serialize_with = "crate::serde::duration::to_secs",
+        deserialize_with = "crate::serde::duration::from_secs_optional",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub keep_alive_timeout: Option<Duration>,
+
+    /// The largest request body accepted, in bytes. It does not apply to query inputs sent in one
+    /// piece and step data, which are streamed and can be arbitrarily large.
+    pub max_request_body_bytes: Option<u64>,
+
+    /// The largest query input chunk accepted, in bytes. Helpers keep the chunks of a query input
+    /// in memory until all of them arrive. If not set, `max_request_body_bytes` applies.
+    pub max_input_chunk_bytes: Option<u64>,
+
+    /// Requests that take longer than this are cancelled, in seconds. Routes that are not listed
+    /// here have no time limit. Note that requests that send query inputs or step data last as
+    /// long as it takes to stream them.
+    #[serde(
+        serialize_with = "crate::serde::duration::to_secs_map",
+        deserialize_with = "crate::serde::duration::from_secs_map"
+    )]
+    pub timeouts: BTreeMap<HttpRoute, Duration>,
+}
+
+impl HttpServerConfig {
+    /// Reads config from string. Expects config to be toml format.
+    /// To read file, use `fs::read_to_string`
+    ///
+    /// # Errors
+    /// if `input` is in an invalid format
+    pub fn from_toml_str(input: &str) -> Result<Self, Error> {
+        use config::{Config, File, FileFormat};
+
+        let conf: Self = Config::builder()
+            .add_source(File::from_str(input, FileFormat::Toml))
+            .build()?
+            .try_deserialize()?;
+
+        Ok(conf)
+    }
+}
+
+/// Routes of the helper web service.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpRoute {
+    Echo,
+    Create,
+    Prepare,
+    Input,
+    #[serde(rename = "input_chunk")]
+    InputChunk,
+    Step,
+    Status,
+    Results,
+    Kill,
+    Abort,
+    Keys,
+}
+
+impl HttpRoute {
+    /// Returns `true` if requests to this route stream data of any size.
+    #[must_use]
+    pub fn is_streaming(self) -> bool {
+        matches!(self, Self::Input | Self::Step)
+    }
 }
 
 pub trait HyperClientConfigurator {
@@ -280,6 +560,10 @@ pub trait HyperClientConfigurator {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClientConfig {
     pub http_config: HttpClientConfigurator,
+
+    /// How requests to other helpers are retried if they fail because of a transient error.
+    #[serde(default)]
+    pub retry: RetryConfig,
 }
 
 impl Default for ClientConfig {
@@ -298,6 +582,7 @@ impl ClientConfig {
     pub fn configure_http2(conf: Http2Configurator) -> Self {
         Self {
             http_config: HttpClientConfigurator::Http2(conf),
+            retry: RetryConfig::default(),
         }
     }
 
@@ -305,8 +590,72 @@ impl ClientConfig {
     pub fn use_http1() -> Self {
         Self {
             http_config: HttpClientConfigurator::http1(),
+            retry: RetryConfig::default(),
         }
     }
+
+    #[must_use]
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+}
+
+/// Retry policy for requests to other helpers. Only requests that can be safely repeated are
+/// retried: control-plane requests like prepare query or query status, and step data, which is
+/// resumed from the last byte that the other helper received.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// The maximum number of attempts for a request, including the first one. Set it to 1 to
+    /// disable retries.
+    pub max_attempts: u32,
+
+    /// Delay before the first retry. Every following retry waits twice as long as the previous
+    /// one, up to `max_backoff`.
+    #[serde(
+        rename = "initial_backoff_secs",
+        serialize_with = "crate::serde::duration::to_secs",
+        deserialize_with = "crate::serde::duration::from_secs"
+    )]
+    pub initial_backoff: Duration,
+
+    #[serde(
+        rename = "max_backoff_secs",
+        serialize_with = "crate::serde::duration::to_secs",
+        deserialize_with = "crate::serde::duration::from_secs"
+    )]
+    pub max_backoff: Duration,
+
+    /// How many of the most recently sent bytes of step data are kept to send them again after
+    /// reconnecting. It must cover the data in flight, which is bounded by the
+    /// `stream_window_size` of the other helper (1 MiB unless configured), plus what the HTTP/2
+    /// client buffers before sending it.
+    ///
+    /// Every step that sends data to another helper keeps its own buffer, so a query can hold up
+    /// to this many bytes for each step it runs in parallel, times two peers.
+    pub resume_buffer_bytes: usize,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            resume_buffer_bytes: 2 * 1024 * 1024,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Returns the delay before the attempt that follows `attempt`, counting from 1.
+    #[must_use]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
 }
 
 impl<B: Borrow<ClientConfig>> HyperClientConfigurator for B {
@@ -398,21 +747,68 @@ pub struct Http2Configurator {
         skip_serializing_if = "Option::is_none"
     )]
     ping_interval: Option<Duration>,
+
+    /// Close the connection if a [`PING`] frame is not acknowledged within this time. Hyper
+    /// default (20 seconds) is used if not set.
+    ///
+    /// [`PING`]: https://datatracker.ietf.org/doc/html/rfc9113#name-ping
+    #[serde(
+        rename = "ping_timeout_secs",
+        default,
+        serialize_with = "crate::serde::duration::to_secs",
+        deserialize_with = "crate::serde::duration::from_secs_optional",
+        skip_serializing_if = "Option::is_none"
+    )]
+    ping_timeout: Option<Duration>,
+
+    /// HTTP/2 flow control window of every stream, in bytes. Hyper default is used if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stream_window_size: Option<u32>,
+
+    /// HTTP/2 flow control window of every connection, in bytes. Hyper default is used if not
+    /// set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    connection_window_size: Option<u32>,
 }
 
 impl Default for Http2Configurator {
     fn default() -> Self {
         Self {
             ping_interval: Some(Duration::from_secs(90)),
+            ping_timeout: None,
+            stream_window_size: None,
+            connection_window_size: None,
         }
     }
 }
 
+impl Http2Configurator {
+    #[must_use]
+    pub fn with_ping(mut self, interval: Option<Duration>, timeout: Option<Duration>) -> Self {
+        self.ping_interval = interval;
+        self.ping_timeout = timeout;
+        self
+    }
+
+    #[must_use]
+    pub fn with_window_sizes(mut self, stream: Option<u32>, connection: Option<u32>) -> Self {
+        self.stream_window_size = stream;
+        self.connection_window_size = connection;
+        self
+    }
+}
+
 impl HyperClientConfigurator for Http2Configurator {
     fn configure<'a>(&self, client_builder: &'a mut Builder) -> &'a mut Builder {
         client_builder
             .http2_only(true)
             .http2_keep_alive_interval(self.ping_interval)
+            .http2_initial_stream_window_size(self.stream_window_size)
+            .http2_initial_connection_window_size(self.connection_window_size);
+        if let Some(timeout) = self.ping_timeout {
+            client_builder.http2_keep_alive_timeout(timeout);
+        }
+        client_builder
     }
 }
 
@@ -420,6 +816,9 @@ impl Debug for Http2Configurator {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Http2Configurator")
             .field("PING_interval", &self.ping_interval)
+            .field("PING_timeout", &self.ping_timeout)
+            .field("stream_window_size", &self.stream_window_size)
+            .field("connection_window_size", &self.connection_window_size)
             .finish()
     }
 }
@@ -432,7 +831,11 @@ mod tests {
     use rand_core::SeedableRng;
 
     use super::*;
-    use crate::{config::HpkeClientConfig, helpers::HelperIdentity, net::test::TestConfigBuilder};
+    use crate::{
+        config::HpkeClientConfig,
+        helpers::HelperIdentity,
+        net::test::{TestConfigBuilder, TEST_CERTS, TEST_CERTS_DER},
+    };
 
     const URI_1: &str = "http://localhost:3000";
     const URI_2: &str = "http://localhost:3001";
@@ -458,12 +861,107 @@ mod tests {
         assert_eq!(value3.url, uri3);
     }
 
+    #[test]
+    fn parse_report_collectors() {
+        let certificate = std::str::from_utf8(TEST_CERTS[0]).unwrap();
+        let token_sha256 = ReportCollectorId::from_token("token");
+        let peers = format!(
+            r#"peers = [{{ url = "{URI_1}" }}, {{ url = "{URI_2}" }}, {{ url = "{URI_3}" }}]"#
+        );
+        let conf = NetworkConfig::from_toml_str(&format!(
+            r#"
+{peers}
+
+[[report_collectors]]
+certificate = """
+{certificate}"""
+
+[[report_collectors]]
+token_sha256 = "{token_sha256}"
+"#
+        ))
+        .unwrap();
+        assert_eq!(
+            HashSet::from([
+                ReportCollectorId::from_certificate(&TEST_CERTS_DER[0]),
+                token_sha256,
+            ]),
+            conf.report_collector_ids()
+        );
+
+        assert!(NetworkConfig::from_toml_str(&format!(
+            "{peers}\n[[report_collectors]]\ntoken_sha256 = \"not a digest\"\n"
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn parse_helper_origin() {
+        let peers = format!(
+            r#"peers = [{{ url = "{URI_1}" }}, {{ url = "{URI_2}" }}, {{ url = "{URI_3}" }}]"#
+        );
+        let conf = NetworkConfig::from_toml_str(&peers).unwrap();
+        assert_eq!(DEFAULT_HELPER_ORIGIN, conf.helper_origin);
+
+        let conf =
+            NetworkConfig::from_toml_str(&format!("helper_origin = \"helpers.example\"\n{peers}"))
+                .unwrap();
+        assert_eq!("helpers.example", conf.helper_origin);
+    }
+
     #[test]
     fn debug_hpke_client_config() {
         let mut rng = StdRng::seed_from_u64(1);
         let (_, public_key) = X25519HkdfSha256::gen_keypair(&mut rng);
-        let config = HpkeClientConfig { public_key };
-        assert_eq!(format!("{config:?}"), "HpkeClientConfig { public_key: \"2bd9da78f01d8bc6948bbcbe44ec1e7163d05083e267d110cdb2e75d847e3b6f\" }");
+        let config = HpkeClientConfig::new(public_key);
+        assert_eq!(format!("{config:?}"), "HpkeClientConfig { public_keys: {0: \"2bd9da78f01d8bc6948bbcbe44ec1e7163d05083e267d110cdb2e75d847e3b6f\"} }");
+    }
+
+    #[test]
+    fn parse_hpke_public_keys() {
+        const PK: &str = "2bd9da78f01d8bc6948bbcbe44ec1e7163d05083e267d110cdb2e75d847e3b6f";
+        let parse = |hpke: &str| {
+            NetworkConfig::from_toml_str(&format!(
+                r#"
+[[peers]]
+url = "{URI_1}"
+hpke = {hpke}
+
+[[peers]]
+url = "{URI_2}"
+
+[[peers]]
+url = "{URI_3}"
+"#
+            ))
+            .map(|conf| {
+                conf.peers()[0]
+                    .hpke_config
+                    .as_ref()
+                    .unwrap()
+                    .public_keys
+                    .keys()
+                    .copied()
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(
+            vec![0],
+            parse(&format!(r#"{{ public_key = "{PK}" }}"#)).unwrap()
+        );
+        assert_eq!(
+            vec![0, 1, 2],
+            parse(&format!(
+                r#"{{ public_key = "{PK}", keys = [{{ key_id = 2, public_key = "{PK}" }}, {{ key_id = 1, public_key = "{PK}" }}] }}"#
+            ))
+            .unwrap()
+        );
+        assert!(parse(&format!(
+            r#"{{ public_key = "{PK}", keys = [{{ key_id = 0, public_key = "{PK}" }}] }}"#
+        ))
+        .is_err());
+        assert!(parse("{ keys = [] }").is_err());
     }
 
     #[test]
@@ -491,9 +989,7 @@ mod tests {
 
         assert_config_eq(
             r#"{ "http_config": { "version": "http2" } }"#,
-            &ClientConfig::configure_http2(Http2Configurator {
-                ping_interval: None,
-            }),
+            &ClientConfig::configure_http2(Http2Configurator::default().with_ping(None, None)),
         );
         assert_config_eq(
             r#"{ "http_config": { "version": "http1" } }"#,
@@ -501,9 +997,95 @@ mod tests {
         );
         assert_config_eq(
             r#"{ "http_config": { "version": "http2", "ping_interval_secs": 132 } }"#,
-            &ClientConfig::configure_http2(Http2Configurator {
-                ping_interval: Some(Duration::from_secs(132)),
-            }),
+            &ClientConfig::configure_http2(
+                Http2Configurator::default().with_ping(Some(Duration::from_secs(132)), None),
+            ),
+        );
+        assert_config_eq(
+            r#"{ "http_config": { "version": "http2", "ping_timeout_secs": 5, "stream_window_size": 1048576 } }"#,
+            &ClientConfig::configure_http2(
+                Http2Configurator::default()
+                    .with_ping(None, Some(Duration::from_secs(5)))
+                    .with_window_sizes(Some(1_048_576), None),
+            ),
+        );
+    }
+
+    #[test]
+    fn http_server_config_toml() {
+        let config = HttpServerConfig::from_toml_str(
+            r"
+            stream_window_size = 4194304
+            max_concurrent_streams = 1000
+            keep_alive_interval_secs = 30
+            max_request_body_bytes = 1048576
+            max_input_chunk_bytes = 16777216
+
+            [timeouts]
+            prepare = 10
+            status = 0.5
+            input_chunk = 60
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            HttpServerConfig {
+                stream_window_size: Some(4_194_304),
+                max_concurrent_streams: Some(1000),
+                keep_alive_interval: Some(Duration::from_secs(30)),
+                max_request_body_bytes: Some(1_048_576),
+                max_input_chunk_bytes: Some(16_777_216),
+                timeouts: [
+                    (HttpRoute::Prepare, Duration::from_secs(10)),
+                    (HttpRoute::Status, Duration::from_millis(500)),
+                    (HttpRoute::InputChunk, Duration::from_secs(60)),
+                ]
+                .into_iter()
+                .collect(),
+                ..HttpServerConfig::default()
+            },
+            config
+        );
+
+        assert_eq!(
+            HttpServerConfig::default(),
+            HttpServerConfig::from_toml_str("").unwrap()
+        );
+        assert!(HttpServerConfig::from_toml_str("[timeouts]\nfoo = 1").is_err());
+    }
+
+    #[test]
+    fn retry_config_serde() {
+        let config: ClientConfig =
+            serde_json::from_str(r#"{ "http_config": { "version": "http2" } }"#).unwrap();
+        assert_eq!(RetryConfig::default(), config.retry);
+
+        let config: ClientConfig = serde_json::from_str(
+            r#"{ "http_config": { "version": "http2" }, "retry": { "max_attempts": 3, "initial_backoff_secs": 0.5 } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            RetryConfig {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(500),
+                ..RetryConfig::default()
+            },
+            config.retry
+        );
+    }
+
+    #[test]
+    fn retry_backoff() {
+        let retry = RetryConfig {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+            ..RetryConfig::default()
+        };
+        assert_eq!(
+            [1, 2, 4, 5, 5].map(Duration::from_secs).to_vec(),
+            (1..=5)
+                .map(|attempt| retry.backoff(attempt))
+                .collect::<Vec<_>>()
         );
     }
 }
@@ -56,6 +56,8 @@ pub enum Error {
     InvalidQueryParameter(BoxError),
     #[error("invalid report: {0}")]
     InvalidReport(#[from] InvalidReportError),
+    #[error(transparent)]
+    QueryInput(#[from] crate::query::QueryInputError),
     #[error("unsupported: {0}")]
     Unsupported(String),
     #[error("Decompressing invalid elliptic curve point: {0}")]
@@ -3,7 +3,7 @@ use bitvec::{
     slice::Iter,
 };
 use generic_array::GenericArray;
-use typenum::{U32, U8};
+use typenum::{U14, U16, U32, U8};
 
 use crate::{ff::boolean::Boolean, secret_sharing::Block};
 
@@ -94,10 +94,31 @@ macro_rules! boolean_array_impl {
                 }
             }
 
+            impl<'a, 'b> std::ops::Add<&'b $name> for &'a $name {
+                type Output = $name;
+                fn add(self, rhs: &'b $name) -> Self::Output {
+                    $name(self.0 ^ rhs.0)
+                }
+            }
+
+            impl std::ops::Add<&$name> for $name {
+                type Output = Self;
+                fn add(self, rhs: &$name) -> Self::Output {
+                    std::ops::Add::add(&self, rhs)
+                }
+            }
+
+            impl std::ops::Add<$name> for &$name {
+                type Output = $name;
+                fn add(self, rhs: $name) -> Self::Output {
+                    std::ops::Add::add(self, &rhs)
+                }
+            }
+
             impl std::ops::Add for $name {
                 type Output = Self;
                 fn add(self, rhs: Self) -> Self::Output {
-                    Self(self.0 ^ rhs.0)
+                    std::ops::Add::add(&self, &rhs)
                 }
             }
 
@@ -303,6 +324,12 @@ macro_rules! boolean_array_impl {
 //impl store for U8
 store_impl!(U8, 64);
 
+//impl store for U14
+store_impl!(U14, 112);
+
+//impl store for U16
+store_impl!(U16, 128);
+
 //impl store for U32
 store_impl!(U32, 256);
 
@@ -324,6 +351,27 @@ boolean_array_impl!(boolean_array_7, BA7, 7, 1, [1, 0, 0, 0, 0, 0, 0]);
 //impl BA8
 boolean_array_impl!(boolean_array_8, BA8, 8, 1, [1, 0, 0, 0, 0, 0, 0, 0]);
 
+//impl BA9
+boolean_array_impl!(boolean_array_9, BA9, 9, 2, [1, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+//impl BA12
+boolean_array_impl!(
+    boolean_array_12,
+    BA12,
+    12,
+    2,
+    [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+);
+
+//impl BA16
+boolean_array_impl!(
+    boolean_array_16,
+    BA16,
+    16,
+    2,
+    [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+);
+
 //impl BA20
 boolean_array_impl!(
     boolean_array_20,
@@ -358,6 +406,37 @@ boolean_array_impl!(
     ]
 );
 
+//impl BA112
+// used to pack all fields of an OPRF report into a single value for shuffling
+boolean_array_impl!(
+    boolean_array_112,
+    BA112,
+    112,
+    14,
+    [
+        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
+    ]
+);
+
+//impl BA128
+// used to pack all fields of a feature-label dot product report into a single value for shuffling
+boolean_array_impl!(
+    boolean_array_128,
+    BA128,
+    128,
+    16,
+    [
+        1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0
+    ]
+);
+
 // impl BA256
 // used to convert into Fp25519
 boolean_array_impl!(
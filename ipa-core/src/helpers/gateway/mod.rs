@@ -1,3 +1,4 @@
+mod progress;
 mod receive;
 mod send;
 #[cfg(feature = "stall-detection")]
@@ -6,6 +7,7 @@ mod transport;
 
 use std::num::NonZeroUsize;
 
+pub use progress::{ChannelProgress, GatewayProgress, QueryProgress};
 pub(super) use receive::ReceivingEnd;
 pub(super) use send::SendingEnd;
 #[cfg(all(test, feature = "shuttle"))]
@@ -43,6 +45,7 @@ pub struct Gateway {
     inner: crate::sync::Arc<State>,
     #[cfg(not(feature = "stall-detection"))]
     inner: State,
+    progress: crate::sync::Arc<GatewayProgress>,
 }
 
 #[derive(Default)]
@@ -82,9 +85,15 @@ impl Gateway {
                 config,
             },
             inner: State::default().into(),
+            progress: crate::sync::Arc::default(),
         }
     }
 
+    #[must_use]
+    pub fn query_id(&self) -> QueryId {
+        self.transport.query_id
+    }
+
     #[must_use]
     pub fn role(&self) -> Role {
         self.transport.role()
@@ -95,6 +104,12 @@ impl Gateway {
         &self.config
     }
 
+    /// Returns the tracker of the data this gateway has sent and received so far.
+    #[must_use]
+    pub fn progress(&self) -> crate::sync::Arc<GatewayProgress> {
+        crate::sync::Arc::clone(&self.progress)
+    }
+
     ///
     /// ## Panics
     /// If there is a failure connecting via HTTP
@@ -104,17 +119,19 @@ impl Gateway {
         channel_id: &ChannelId,
         total_records: TotalRecords,
     ) -> send::SendingEnd<M> {
-        let (tx, maybe_stream) = self.inner.senders.get_or_create::<M>(
+        let (tx, maybe_stream) = self.inner.senders.get_or_create::<M, _>(
             channel_id,
             self.config.active_work(),
             total_records,
+            || self.progress.channel(channel_id, Some(total_records)),
         );
         if let Some(stream) = maybe_stream {
             tokio::spawn({
                 let channel_id = channel_id.clone();
                 let transport = self.transport.clone();
                 async move {
-                    // TODO(651): In the HTTP case we probably need more robust error handling here.
+                    // The HTTP transport retries sending if the connection breaks, so this only
+                    // fails if the other helper is unreachable for a long time.
                     transport
                         .send(&channel_id, stream)
                         .await
@@ -128,12 +145,13 @@ impl Gateway {
 
     #[must_use]
     pub fn get_receiver<M: Message>(&self, channel_id: &ChannelId) -> receive::ReceivingEnd<M> {
-        receive::ReceivingEnd::new(
-            channel_id.clone(),
-            self.inner
-                .receivers
-                .get_or_create(channel_id, || self.transport.receive(channel_id)),
-        )
+        let (rx, progress) = self.inner.receivers.get_or_create(channel_id, || {
+            (
+                self.transport.receive(channel_id),
+                self.progress.channel(channel_id, None),
+            )
+        });
+        receive::ReceivingEnd::new(channel_id.clone(), rx, progress)
     }
 }
 
@@ -264,6 +282,51 @@ mod tests {
         let _world = unsafe { Box::from_raw(world_ptr) };
     }
 
+    #[tokio::test]
+    async fn tracks_progress() {
+        let world = TestWorld::default();
+        let contexts = world.contexts();
+        let sender_ctx = contexts[0].narrow("progress-test").set_total_records(3);
+        let recv_ctx = contexts[1].narrow("progress-test").set_total_records(3);
+
+        let send_channel = sender_ctx.send_channel(Role::H2);
+        for i in 0..3 {
+            send_channel
+                .send(RecordId::from(i), Fp31::truncate_from(1_u128))
+                .await
+                .unwrap();
+        }
+        let recv_channel = recv_ctx.recv_channel::<Fp31>(Role::H1);
+        for i in 0..2 {
+            recv_channel.receive(RecordId::from(i)).await.unwrap();
+        }
+
+        let sender = world.gateway(Role::H1).progress().snapshot();
+        assert_eq!(3, sender.records_sent);
+        assert_eq!(3, sender.bytes_sent);
+        assert_eq!(0, sender.records_received);
+        let [channel] = sender.channels.as_slice() else {
+            panic!("expected one channel, got {:?}", sender.channels);
+        };
+        assert!(channel.step.ends_with("progress-test"));
+        assert_eq!(
+            (Role::H2, Some(3), 3, 0),
+            (
+                channel.peer,
+                channel.total_records,
+                channel.records_sent,
+                channel.records_received
+            )
+        );
+
+        let receiver = world.gateway(Role::H2).progress().snapshot();
+        assert_eq!(2, receiver.records_received);
+        assert_eq!(2, receiver.bytes_received);
+        assert_eq!(0, receiver.records_sent);
+        assert_eq!(None, receiver.channels[0].total_records);
+        assert!(receiver.current_step.is_some());
+    }
+
     /// this test requires quite a few threads to simulate send contention and will panic if
     /// there is more than one sender channel created per step.
     #[tokio::test(flavor = "multi_thread", worker_threads = 20)]
@@ -0,0 +1,145 @@
+use std::time::Instant;
+
+use dashmap::DashMap;
+
+use crate::{
+    helpers::{ChannelId, Role, TotalRecords},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Keeps track of the amount of data that gateway has sent and received since the query
+/// started. Counters are updated by [`SendingEnd`] and [`ReceivingEnd`] on every message.
+///
+/// [`SendingEnd`]: super::SendingEnd
+/// [`ReceivingEnd`]: super::ReceivingEnd
+pub struct GatewayProgress {
+    started: Instant,
+    channels: DashMap<ChannelId, Arc<ChannelCounters>>,
+    current_step: Mutex<Option<String>>,
+}
+
+#[derive(Default)]
+pub(super) struct ChannelCounters {
+    total_records: AtomicUsize,
+    records_sent: AtomicUsize,
+    records_received: AtomicUsize,
+    bytes_sent: AtomicUsize,
+    bytes_received: AtomicUsize,
+}
+
+/// Snapshot of the query progress, as seen by one helper.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QueryProgress {
+    /// Top-level protocol step that the query most recently opened a channel for.
+    pub current_step: Option<String>,
+    /// Time since the query started executing, in milliseconds.
+    pub elapsed_ms: u64,
+    pub records_sent: usize,
+    pub records_received: usize,
+    pub bytes_sent: usize,
+    pub bytes_received: usize,
+    pub channels: Vec<ChannelProgress>,
+}
+
+/// The number of records exchanged with another helper on one step.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelProgress {
+    pub step: String,
+    pub peer: Role,
+    /// The number of records this helper is going to send on this channel, if it sends anything.
+    pub total_records: Option<usize>,
+    pub records_sent: usize,
+    pub records_received: usize,
+}
+
+impl Default for GatewayProgress {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            channels: DashMap::default(),
+            current_step: Mutex::default(),
+        }
+    }
+}
+
+impl GatewayProgress {
+    /// Returns the counters for the given channel, creating them if this channel has not been
+    /// used before. Gateway keeps the counters along with the sending and receiving ends of the
+    /// channel, so it only calls this when it creates them.
+    pub(super) fn channel(
+        &self,
+        channel_id: &ChannelId,
+        total_records: Option<TotalRecords>,
+    ) -> Arc<ChannelCounters> {
+        let counters = Arc::clone(
+            self.channels
+                .entry(channel_id.clone())
+                .or_insert_with(|| {
+                    let step = channel_id.gate.as_ref();
+                    let top_level = step.split('/').nth(1).unwrap_or(step);
+                    *self.current_step.lock().unwrap() = Some(top_level.to_string());
+                    Arc::default()
+                })
+                .value(),
+        );
+        if let Some(TotalRecords::Specified(count)) = total_records {
+            counters.total_records.store(count.get(), Ordering::Relaxed);
+        }
+
+        counters
+    }
+
+    /// ## Panics
+    /// If the mutex guarding the current step is poisoned.
+    #[must_use]
+    pub fn snapshot(&self) -> QueryProgress {
+        let mut progress = QueryProgress {
+            current_step: self.current_step.lock().unwrap().clone(),
+            elapsed_ms: u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX),
+            records_sent: 0,
+            records_received: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+            channels: Vec::with_capacity(self.channels.len()),
+        };
+
+        for entry in &self.channels {
+            let counters = entry.value();
+            let channel = ChannelProgress {
+                step: entry.key().gate.as_ref().to_string(),
+                peer: entry.key().role,
+                total_records: Some(counters.total_records.load(Ordering::Relaxed))
+                    .filter(|&v| v > 0),
+                records_sent: counters.records_sent.load(Ordering::Relaxed),
+                records_received: counters.records_received.load(Ordering::Relaxed),
+            };
+            progress.records_sent += channel.records_sent;
+            progress.records_received += channel.records_received;
+            progress.bytes_sent += counters.bytes_sent.load(Ordering::Relaxed);
+            progress.bytes_received += counters.bytes_received.load(Ordering::Relaxed);
+            progress.channels.push(channel);
+        }
+        progress
+            .channels
+            .sort_by(|a, b| (&a.step, a.peer).cmp(&(&b.step, b.peer)));
+
+        progress
+    }
+}
+
+impl ChannelCounters {
+    pub(super) fn record_sent(&self, bytes: usize) {
+        self.records_sent.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_received(&self, bytes: usize) {
+        self.records_received.fetch_add(1, Ordering::Relaxed);
+        self.bytes_received.fetch_add(bytes, Ordering::Relaxed);
+    }
+}
@@ -2,23 +2,29 @@ use std::marker::PhantomData;
 
 use dashmap::{mapref::entry::Entry, DashMap};
 use futures::Stream;
+use typenum::Unsigned;
 
 use crate::{
-    helpers::{buffers::UnorderedReceiver, ChannelId, Error, Message, Transport, TransportImpl},
+    helpers::{
+        buffers::UnorderedReceiver, gateway::progress::ChannelCounters, ChannelId, Error, Message,
+        Transport, TransportImpl,
+    },
     protocol::RecordId,
+    sync::Arc,
 };
 
 /// Receiving end end of the gateway channel.
 pub struct ReceivingEnd<M: Message> {
     channel_id: ChannelId,
     unordered_rx: UR,
+    progress: Arc<ChannelCounters>,
     _phantom: PhantomData<M>,
 }
 
-/// Receiving channels, indexed by (role, step).
+/// Receiving channels and their progress counters, indexed by (role, step).
 #[derive(Default)]
 pub(super) struct GatewayReceivers {
-    pub(super) inner: DashMap<ChannelId, UR>,
+    pub(super) inner: DashMap<ChannelId, (UR, Arc<ChannelCounters>)>,
 }
 
 pub(super) type UR = UnorderedReceiver<
@@ -27,10 +33,11 @@ pub(super) type UR = UnorderedReceiver<
 >;
 
 impl<M: Message> ReceivingEnd<M> {
-    pub(super) fn new(channel_id: ChannelId, rx: UR) -> Self {
+    pub(super) fn new(channel_id: ChannelId, rx: UR, progress: Arc<ChannelCounters>) -> Self {
         Self {
             channel_id,
             unordered_rx: rx,
+            progress,
             _phantom: PhantomData,
         }
     }
@@ -46,27 +53,40 @@ impl<M: Message> ReceivingEnd<M> {
     /// and sent to this helper.
     #[tracing::instrument(level = "trace", "receive", skip_all, fields(i = %record_id, from = ?self.channel_id.role, gate = ?self.channel_id.gate.as_ref()))]
     pub async fn receive(&self, record_id: RecordId) -> Result<M, Error> {
-        self.unordered_rx
+        let r = self
+            .unordered_rx
             .recv::<M, _>(record_id)
             .await
             .map_err(|e| Error::ReceiveError {
                 source: self.channel_id.role,
                 step: self.channel_id.gate.to_string(),
                 inner: Box::new(e),
-            })
+            });
+        if r.is_ok() {
+            self.progress.record_received(M::Size::USIZE);
+        }
+
+        r
     }
 }
 
 impl GatewayReceivers {
-    pub fn get_or_create<F: FnOnce() -> UR>(&self, channel_id: &ChannelId, ctr: F) -> UR {
+    pub fn get_or_create<F: FnOnce() -> (UR, Arc<ChannelCounters>)>(
+        &self,
+        channel_id: &ChannelId,
+        ctr: F,
+    ) -> (UR, Arc<ChannelCounters>) {
         // TODO: raw entry API if it becomes available to avoid cloning the key
         match self.inner.entry(channel_id.clone()) {
-            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Occupied(entry) => {
+                let (stream, progress) = entry.get();
+                (stream.clone(), Arc::clone(progress))
+            }
             Entry::Vacant(entry) => {
-                let stream = ctr();
-                entry.insert(stream.clone());
+                let (stream, progress) = ctr();
+                entry.insert((stream.clone(), Arc::clone(&progress)));
 
-                stream
+                (stream, progress)
             }
         }
     }
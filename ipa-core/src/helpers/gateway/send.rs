@@ -10,7 +10,10 @@ use futures::Stream;
 use typenum::Unsigned;
 
 use crate::{
-    helpers::{buffers::OrderingSender, ChannelId, Error, Message, Role, TotalRecords},
+    helpers::{
+        buffers::OrderingSender, gateway::progress::ChannelCounters, ChannelId, Error, Message,
+        Role, TotalRecords,
+    },
     protocol::RecordId,
     sync::Arc,
     telemetry::{
@@ -37,6 +40,7 @@ pub(super) struct GatewaySender {
     channel_id: ChannelId,
     ordering_tx: OrderingSender,
     total_records: TotalRecords,
+    progress: Arc<ChannelCounters>,
 }
 
 pub(super) struct GatewaySendStream {
@@ -44,11 +48,17 @@ pub(super) struct GatewaySendStream {
 }
 
 impl GatewaySender {
-    fn new(channel_id: ChannelId, tx: OrderingSender, total_records: TotalRecords) -> Self {
+    fn new(
+        channel_id: ChannelId,
+        tx: OrderingSender,
+        total_records: TotalRecords,
+        progress: Arc<ChannelCounters>,
+    ) -> Self {
         Self {
             channel_id,
             ordering_tx: tx,
             total_records,
+            progress,
         }
     }
 
@@ -111,6 +121,7 @@ impl<M: Message> SendingEnd<M> {
     #[tracing::instrument(level = "trace", "send", skip_all, fields(i = %record_id, total = %self.inner.total_records, to = ?self.channel_id.role, gate = ?self.channel_id.gate.as_ref()))]
     pub async fn send(&self, record_id: RecordId, msg: M) -> Result<(), Error> {
         let r = self.inner.send(record_id, msg).await;
+        self.inner.progress.record_sent(M::Size::USIZE);
         metrics::increment_counter!(RECORDS_SENT,
             STEP => self.channel_id.gate.as_ref().to_string(),
             ROLE => self.sender_role.as_static_str()
@@ -127,12 +138,13 @@ impl<M: Message> SendingEnd<M> {
 impl GatewaySenders {
     /// Returns or creates a new communication channel. In case if channel is newly created,
     /// returns the receiving end of it as well. It must be send over to the receiver in order for
-    /// messages to get through.
-    pub(crate) fn get_or_create<M: Message>(
+    /// messages to get through. `progress` is only called to create the channel.
+    pub(crate) fn get_or_create<M: Message, F: FnOnce() -> Arc<ChannelCounters>>(
         &self,
         channel_id: &ChannelId,
         capacity: NonZeroUsize,
         total_records: TotalRecords, // TODO track children for indeterminate senders
+        progress: F,
     ) -> (Arc<GatewaySender>, Option<GatewaySendStream>) {
         assert!(
             total_records.is_specified(),
@@ -164,6 +176,7 @@ impl GatewaySenders {
                     channel_id.clone(),
                     OrderingSender::new(write_size, SPARE.unwrap()),
                     total_records,
+                    progress(),
                 ));
                 entry.insert(Arc::clone(&sender));
 
@@ -73,7 +73,7 @@ mod gateway {
     use super::{receive, send, AtomicUsize, Debug, Formatter, ObserveState, Observed, Weak};
     use crate::{
         helpers::{
-            gateway::{Gateway, State},
+            gateway::{Gateway, GatewayProgress, State},
             ChannelId, GatewayConfig, Message, ReceivingEnd, Role, RoleAssignment, SendingEnd,
             TotalRecords, TransportImpl,
         },
@@ -92,11 +92,17 @@ mod gateway {
         delegate! {
             to self.inner().gateway {
 
+                #[inline]
+                pub fn query_id(&self) -> QueryId;
+
                 #[inline]
                 pub fn role(&self) -> Role;
 
                 #[inline]
                 pub fn config(&self) -> &GatewayConfig;
+
+                #[inline]
+                pub fn progress(&self) -> Arc<GatewayProgress>;
             }
         }
 
@@ -258,7 +264,7 @@ mod receive {
             let mut map = BTreeMap::default();
             for entry in &self.inner {
                 let channel = entry.key();
-                if let Some(waiting) = super::to_ranges(entry.value().waiting()).get_state() {
+                if let Some(waiting) = super::to_ranges(entry.value().0.waiting()).get_state() {
                     map.insert(channel.clone(), waiting);
                 }
             }
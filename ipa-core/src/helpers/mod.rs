@@ -39,7 +39,7 @@ mod gateway_exports {
     pub type ReceivingEnd<M> = gateway::ReceivingEnd<M>;
 }
 
-pub use gateway::GatewayConfig;
+pub use gateway::{ChannelProgress, GatewayConfig, GatewayProgress, QueryProgress};
 // TODO: this type should only be available within infra. Right now several infra modules
 // are exposed at the root level. That makes it impossible to have a proper hierarchy here.
 pub use gateway::{TransportError, TransportImpl};
@@ -48,7 +48,7 @@ pub use prss_protocol::negotiate as negotiate_prss;
 #[cfg(feature = "web-app")]
 pub use transport::WrappedAxumBodyStream;
 pub use transport::{
-    callbacks::*, query, BodyStream, BytesStream, LengthDelimitedStream, LogErrors,
+    callbacks::*, query, BodyStream, BoxBytesStream, BytesStream, LengthDelimitedStream, LogErrors,
     NoResourceIdentifier, QueryIdBinding, ReceiveRecords, RecordsStream, RouteId, RouteParams,
     StepBinding, StreamCollection, StreamKey, Transport, WrappedBoxBodyStream,
 };
@@ -232,8 +232,7 @@ pub enum Role {
     H3 = 2,
 }
 
-#[derive(Clone, Debug)]
-#[cfg_attr(test, derive(PartialEq, Eq))]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(
     feature = "enable-serde",
     derive(serde::Serialize, serde::Deserialize),
@@ -1,12 +1,17 @@
 use std::{future::Future, pin::Pin};
 
 use crate::{
-    helpers::query::{PrepareQuery, QueryConfig, QueryInput},
+    helpers::query::{
+        PrepareQuery, QueryConfig, QueryInput, QueryInputChunk, ReceivedInputChunks,
+        ReportCollectorId,
+    },
+    hpke::{KeyRegistry, PublicKeyOnly},
     protocol::QueryId,
     query::{
-        NewQueryError, PrepareQueryError, ProtocolResult, QueryCompletionError, QueryInputError,
-        QueryStatus, QueryStatusError,
+        KeyReloadError, NewQueryError, PrepareQueryError, ProtocolResult, QueryAccessError,
+        QueryCompletionError, QueryInputError, QueryKillError, QueryStatusError, QueryStatusReport,
     },
+    report::KeyIdentifier,
 };
 
 /// Macro for defining transport callbacks.
@@ -52,25 +57,60 @@ callbacks! {
     (PrepareQueryCallback, PrepareQueryResult):
         async fn(T, PrepareQuery) -> Result<(), PrepareQueryError>;
 
+    /// Called to check that an authenticated report collector is the one that created the query.
+    (AuthorizeQueryCallback, AuthorizeQueryResult):
+        async fn(T, QueryId, ReportCollectorId) -> Result<(), QueryAccessError>;
+
     /// Called by clients to deliver query input data.
     (QueryInputCallback, QueryInputResult):
         async fn(T, QueryInput) -> Result<(), QueryInputError>;
 
-    /// Called by clients to retrieve query status.
+    /// Called by clients to deliver one chunk of query input data.
+    (QueryInputChunkCallback, QueryInputChunkResult):
+        async fn(T, QueryInputChunk) -> Result<ReceivedInputChunks, QueryInputError>;
+
+    /// Called by clients to find out which chunks of query input data need to be sent.
+    (InputChunksCallback, InputChunksResult):
+        async fn(T, QueryId) -> Result<ReceivedInputChunks, QueryInputError>;
+
+    /// Called by clients to retrieve query status and progress.
     (QueryStatusCallback, QueryStatusResult):
-        async fn(T, QueryId) -> Result<QueryStatus, QueryStatusError>;
+        async fn(T, QueryId) -> Result<QueryStatusReport, QueryStatusError>;
 
     /// Called by clients to drive query to completion and retrieve results.
     (CompleteQueryCallback, CompleteQueryResult):
         async fn(T, QueryId) -> Result<Box<dyn ProtocolResult>, QueryCompletionError>;
+
+    /// Called by clients to cancel a query on all helpers.
+    (KillQueryCallback, KillQueryResult):
+        async fn(T, QueryId) -> Result<(), QueryKillError>;
+
+    /// Called by the helper that received a kill request to cancel the query on its peers.
+    (AbortQueryCallback, AbortQueryResult):
+        async fn(T, QueryId) -> Result<(), QueryKillError>;
+
+    /// Called by helper operators to reload the HPKE keys used to decrypt reports.
+    (ReloadKeysCallback, ReloadKeysResult):
+        async fn(T) -> Result<Vec<KeyIdentifier>, KeyReloadError>;
+
+    /// Called by report collectors to get the public keys to encrypt reports with.
+    (PublicKeysCallback, PublicKeysResult):
+        async fn(T) -> KeyRegistry<PublicKeyOnly>;
 }
 
 pub struct TransportCallbacks<T> {
     pub receive_query: Box<dyn ReceiveQueryCallback<T>>,
     pub prepare_query: Box<dyn PrepareQueryCallback<T>>,
+    pub authorize_query: Box<dyn AuthorizeQueryCallback<T>>,
     pub query_input: Box<dyn QueryInputCallback<T>>,
+    pub query_input_chunk: Box<dyn QueryInputChunkCallback<T>>,
+    pub input_chunks: Box<dyn InputChunksCallback<T>>,
     pub query_status: Box<dyn QueryStatusCallback<T>>,
     pub complete_query: Box<dyn CompleteQueryCallback<T>>,
+    pub kill_query: Box<dyn KillQueryCallback<T>>,
+    pub abort_query: Box<dyn AbortQueryCallback<T>>,
+    pub reload_keys: Box<dyn ReloadKeysCallback<T>>,
+    pub public_keys: Box<dyn PublicKeysCallback<T>>,
 }
 
 #[cfg(any(test, feature = "in-memory-infra"))]
@@ -87,15 +127,36 @@ impl<T> Default for TransportCallbacks<T> {
             prepare_query: Box::new(move |_, _| {
                 Box::pin(async { panic!("unexpected call to prepare_query") })
             }),
+            authorize_query: Box::new(move |_, _, _| {
+                Box::pin(async { panic!("unexpected call to authorize_query") })
+            }),
             query_input: Box::new(move |_, _| {
                 Box::pin(async { panic!("unexpected call to query_input") })
             }),
+            query_input_chunk: Box::new(move |_, _| {
+                Box::pin(async { panic!("unexpected call to query_input_chunk") })
+            }),
+            input_chunks: Box::new(move |_, _| {
+                Box::pin(async { panic!("unexpected call to input_chunks") })
+            }),
             query_status: Box::new(move |_, _| {
                 Box::pin(async { panic!("unexpected call to query_status") })
             }),
             complete_query: Box::new(move |_, _| {
                 Box::pin(async { panic!("unexpected call to complete_query") })
             }),
+            kill_query: Box::new(move |_, _| {
+                Box::pin(async { panic!("unexpected call to kill_query") })
+            }),
+            abort_query: Box::new(move |_, _| {
+                Box::pin(async { panic!("unexpected call to abort_query") })
+            }),
+            reload_keys: Box::new(move |_| {
+                Box::pin(async { panic!("unexpected call to reload_keys") })
+            }),
+            public_keys: Box::new(move |_| {
+                Box::pin(async { panic!("unexpected call to public_keys") })
+            }),
         }
     }
 }
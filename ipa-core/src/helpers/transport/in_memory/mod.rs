@@ -4,6 +4,7 @@ pub use transport::Setup;
 
 use crate::{
     helpers::{HelperIdentity, TransportCallbacks},
+    protocol::QueryId,
     sync::{Arc, Weak},
 };
 
@@ -86,4 +87,11 @@ impl InMemoryNetwork {
             t.reset();
         }
     }
+
+    /// Reset the state associated with the given query on all transports.
+    pub fn reset_query(&self, query_id: QueryId) {
+        for t in &self.transports {
+            t.reset_query(query_id);
+        }
+    }
 }
@@ -122,6 +122,15 @@ impl InMemoryTransport {
                                         inner: Box::new(e),
                                     })
                             }
+                            RouteId::AbortQuery => {
+                                let query_id = addr.query_id.unwrap();
+                                (callbacks.abort_query)(Transport::clone_ref(&this), query_id)
+                                    .await
+                                    .map_err(|e| Error::Rejected {
+                                        dest,
+                                        inner: Box::new(e),
+                                    })
+                            }
                         };
 
                         ack.send(result).unwrap();
@@ -148,6 +157,12 @@ impl InMemoryTransport {
     pub fn reset(&self) {
         self.record_streams.clear();
     }
+
+    /// Makes this transport forget all the streams associated with the given query, leaving
+    /// other queries unaffected.
+    pub fn reset_query(&self, query_id: QueryId) {
+        self.record_streams.clear_query(query_id);
+    }
 }
 
 #[async_trait]
@@ -413,7 +428,7 @@ mod tests {
                             .expect("query callback invoked more than once")
                             .send(query_config)
                             .unwrap();
-                        Ok(QueryId)
+                        Ok(QueryId::from(1))
                     })
                 }),
                 ..Default::default()
@@ -437,7 +452,8 @@ mod tests {
         let transport = Arc::downgrade(&transport);
         let expected = vec![vec![1], vec![2]];
 
-        let mut stream = transport.receive(HelperIdentity::TWO, (QueryId, Gate::from(STEP)));
+        let mut stream =
+            transport.receive(HelperIdentity::TWO, (QueryId::from(1), Gate::from(STEP)));
 
         // make sure it is not ready as it hasn't received the records stream yet.
         assert!(matches!(
@@ -446,7 +462,7 @@ mod tests {
         ));
         send_and_ack(
             &tx,
-            Addr::records(HelperIdentity::TWO, QueryId, Gate::from(STEP)),
+            Addr::records(HelperIdentity::TWO, QueryId::from(1), Gate::from(STEP)),
             InMemoryStream::from_iter(expected.clone()),
         )
         .await;
@@ -462,13 +478,13 @@ mod tests {
 
         send_and_ack(
             &tx,
-            Addr::records(HelperIdentity::TWO, QueryId, Gate::from(STEP)),
+            Addr::records(HelperIdentity::TWO, QueryId::from(1), Gate::from(STEP)),
             InMemoryStream::from_iter(expected.clone()),
         )
         .await;
 
-        let stream =
-            Arc::downgrade(&transport).receive(HelperIdentity::TWO, (QueryId, Gate::from(STEP)));
+        let stream = Arc::downgrade(&transport)
+            .receive(HelperIdentity::TWO, (QueryId::from(1), Gate::from(STEP)));
 
         assert_eq!(expected, stream.collect::<Vec<_>>().await);
     }
@@ -487,14 +503,18 @@ mod tests {
             let to_transport = transports.get(&to).unwrap();
             let gate = Gate::from(STEP);
 
-            let mut recv = to_transport.receive(from, (QueryId, gate.clone()));
+            let mut recv = to_transport.receive(from, (QueryId::from(1), gate.clone()));
             assert!(matches!(
                 poll_immediate(&mut recv).next().await,
                 Some(Poll::Pending)
             ));
 
             from_transport
-                .send(to, (RouteId::Records, QueryId, gate.clone()), stream)
+                .send(
+                    to,
+                    (RouteId::Records, QueryId::from(1), gate.clone()),
+                    stream,
+                )
                 .await
                 .unwrap();
             stream_tx.send(vec![1, 2, 3]).await.unwrap();
@@ -540,10 +560,11 @@ mod tests {
         let stream = InMemoryStream::from(stream_rx);
         let transport = Arc::downgrade(&owned_transport);
 
-        let mut recv_stream = transport.receive(HelperIdentity::TWO, (QueryId, gate.clone()));
+        let mut recv_stream =
+            transport.receive(HelperIdentity::TWO, (QueryId::from(1), gate.clone()));
         send_and_ack(
             &tx,
-            Addr::records(HelperIdentity::TWO, QueryId, gate.clone()),
+            Addr::records(HelperIdentity::TWO, QueryId::from(1), gate.clone()),
             stream,
         )
         .await;
@@ -552,7 +573,7 @@ mod tests {
         assert_eq!(vec![4, 5, 6], recv_stream.next().await.unwrap());
 
         // the same stream cannot be received again
-        let mut err_recv = transport.receive(HelperIdentity::TWO, (QueryId, gate.clone()));
+        let mut err_recv = transport.receive(HelperIdentity::TWO, (QueryId::from(1), gate.clone()));
         let err = AssertUnwindSafe(err_recv.next()).catch_unwind().await;
         assert_eq!(
             Some(true),
@@ -563,7 +584,7 @@ mod tests {
 
         // even after the input stream is closed
         drop(stream_tx);
-        let mut err_recv = transport.receive(HelperIdentity::TWO, (QueryId, gate.clone()));
+        let mut err_recv = transport.receive(HelperIdentity::TWO, (QueryId::from(1), gate.clone()));
         let err = AssertUnwindSafe(err_recv.next()).catch_unwind().await;
         assert_eq!(
             Some(true),
@@ -588,12 +609,12 @@ mod tests {
         transport1
             .send(
                 HelperIdentity::TWO,
-                (RouteId::Records, QueryId, gate.clone()),
+                (RouteId::Records, QueryId::from(1), gate.clone()),
                 rx,
             )
             .await
             .unwrap();
-        let mut recv = transport2.receive(HelperIdentity::ONE, (QueryId, gate));
+        let mut recv = transport2.receive(HelperIdentity::ONE, (QueryId::from(1), gate));
 
         tx.send(0, Fp31::try_from(0_u128).unwrap()).await;
         // can't receive the value at index 0 because of buffering inside the sender
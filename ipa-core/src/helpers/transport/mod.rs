@@ -21,8 +21,8 @@ pub use receive::{LogErrors, ReceiveRecords};
 #[cfg(feature = "web-app")]
 pub use stream::WrappedAxumBodyStream;
 pub use stream::{
-    BodyStream, BytesStream, LengthDelimitedStream, RecordsStream, StreamCollection, StreamKey,
-    WrappedBoxBodyStream,
+    BodyStream, BoxBytesStream, BytesStream, LengthDelimitedStream, RecordsStream,
+    StreamCollection, StreamKey, WrappedBoxBodyStream,
 };
 
 pub trait ResourceIdentifier: Sized {}
@@ -46,6 +46,7 @@ pub enum RouteId {
     Records,
     ReceiveQuery,
     PrepareQuery,
+    AbortQuery,
 }
 
 impl ResourceIdentifier for NoResourceIdentifier {}
@@ -123,6 +124,26 @@ impl RouteParams<RouteId, QueryId, Gate> for (RouteId, QueryId, Gate) {
     }
 }
 
+impl RouteParams<RouteId, QueryId, NoStep> for (RouteId, QueryId) {
+    type Params = &'static str;
+
+    fn resource_identifier(&self) -> RouteId {
+        self.0
+    }
+
+    fn query_id(&self) -> QueryId {
+        self.1
+    }
+
+    fn gate(&self) -> NoStep {
+        NoStep
+    }
+
+    fn extra(&self) -> Self::Params {
+        ""
+    }
+}
+
 /// Transport that supports per-query,per-step channels
 #[async_trait]
 pub trait Transport: Clone + Send + Sync + 'static {
@@ -3,9 +3,13 @@ pub mod oprf_shuffle;
 use std::{
     fmt::{Debug, Display, Formatter},
     num::NonZeroU32,
+    ops::RangeInclusive,
+    str::FromStr,
 };
 
+use bytes::Bytes;
 use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::{
     ff::FieldType,
@@ -14,6 +18,7 @@ use crate::{
         GatewayConfig, RoleAssignment, RouteId, RouteParams,
     },
     protocol::{step::Step, QueryId},
+    report::{Epoch, ReportVersion},
 };
 
 #[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
@@ -84,19 +89,77 @@ impl From<QuerySize> for usize {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
-#[cfg_attr(test, derive(PartialEq, Eq))]
+/// Identifies the report collector that requested a query by the SHA-256 digest of the TLS
+/// certificate or the bearer token it authenticated with. All report collectors that do not
+/// authenticate share the default identity.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "enable-serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct ReportCollectorId([u8; 32]);
+
+impl ReportCollectorId {
+    #[must_use]
+    pub fn from_certificate(certificate: &[u8]) -> Self {
+        Self(Sha256::digest(certificate).into())
+    }
+
+    #[must_use]
+    pub fn from_token(token: &str) -> Self {
+        Self(Sha256::digest(token.as_bytes()).into())
+    }
+}
+
+impl Display for ReportCollectorId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+impl FromStr for ReportCollectorId {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut id = [0; 32];
+        hex::decode_to_slice(s, &mut id)?;
+        Ok(Self(id))
+    }
+}
+
+impl TryFrom<String> for ReportCollectorId {
+    type Error = hex::FromHexError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ReportCollectorId> for String {
+    fn from(id: ReportCollectorId) -> Self {
+        id.to_string()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub struct QueryConfig {
     pub size: QuerySize,
     pub field_type: FieldType,
     pub query_type: QueryType,
+    /// Set by the helper that receives the request from the report collector, and passed on to
+    /// the other helpers, so that all of them serve the query to the same report collector only.
+    #[cfg_attr(feature = "enable-serde", serde(default))]
+    pub report_collector: ReportCollectorId,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum QueryConfigError {
     #[error(transparent)]
     BadQuerySize(#[from] BadQuerySizeError),
+    #[error("report epochs {min}..={max} are empty, the earliest epoch is after the latest")]
+    EmptyReportEpochs { min: Epoch, max: Epoch },
 }
 
 #[derive(Clone, Debug)]
@@ -145,7 +208,7 @@ impl QueryConfig {
     /// Initialize new query configuration.
     ///
     /// ## Errors
-    /// If query size is too large or 0.
+    /// If query size is too large or 0, or the query accepts reports from no epochs.
     pub fn new<S>(
         query_type: QueryType,
         field_type: FieldType,
@@ -154,11 +217,43 @@ impl QueryConfig {
     where
         S: TryInto<QuerySize, Error = BadQuerySizeError>,
     {
-        Ok(Self {
+        let config = Self {
             size: size.try_into()?,
             field_type,
             query_type,
-        })
+            report_collector: ReportCollectorId::default(),
+        };
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Checks the parameters that cannot be validated one by one. Queries that accept reports from
+    /// no epochs are rejected, because they would not be charged any privacy budget.
+    ///
+    /// ## Errors
+    /// If the earliest report epoch is after the latest one.
+    pub fn validate(&self) -> Result<(), QueryConfigError> {
+        match self.query_type {
+            QueryType::SemiHonestIpa(config)
+            | QueryType::MaliciousIpa(config)
+            | QueryType::OprfIpa(config) => {
+                let epochs = config.report_epochs();
+                if epochs.is_empty() {
+                    return Err(QueryConfigError::EmptyReportEpochs {
+                        min: *epochs.start(),
+                        max: *epochs.end(),
+                    });
+                }
+            }
+            #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
+            QueryType::TestMultiply => {}
+            QueryType::SemiHonestSparseAggregate(_)
+            | QueryType::MaliciousSparseAggregate(_)
+            | QueryType::FeatureLabelDotProduct(_) => {}
+        }
+
+        Ok(())
     }
 }
 
@@ -199,7 +294,102 @@ impl Debug for QueryInput {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+/// A numbered segment of the query input. Large inputs can be uploaded in chunks, so that an
+/// interrupted upload can be resumed by sending only the chunks that helper has not received yet.
+pub struct QueryInputChunk {
+    pub query_id: QueryId,
+    /// Position of this chunk in the input, starting from 0. Chunks do not have to be numbered
+    /// contiguously, they are concatenated in the order of their indices.
+    pub index: u32,
+    /// The number of input records in this chunk. Records must not span chunks.
+    pub records: u32,
+    /// Digest of `data`, as computed by the sender.
+    pub checksum: InputChecksum,
+    pub data: Bytes,
+}
+
+impl QueryInputChunk {
+    #[must_use]
+    pub fn new(query_id: QueryId, index: u32, records: u32, data: Bytes) -> Self {
+        Self {
+            query_id,
+            index,
+            records,
+            checksum: InputChecksum::of(&data),
+            data,
+        }
+    }
+
+    /// Returns `true` if the data in this chunk matches its checksum.
+    #[must_use]
+    pub fn is_intact(&self) -> bool {
+        InputChecksum::of(&self.data) == self.checksum
+    }
+}
+
+impl Debug for QueryInputChunk {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "query_input_chunk[{:?}, {}, records={}]",
+            self.query_id, self.index, self.records
+        )
+    }
+}
+
+/// SHA-256 digest of a [`QueryInputChunk`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InputChecksum([u8; 32]);
+
+impl InputChecksum {
+    #[must_use]
+    pub fn of(data: &[u8]) -> Self {
+        Self(Sha256::digest(data).into())
+    }
+}
+
+impl Display for InputChecksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(self.0))
+    }
+}
+
+impl FromStr for InputChecksum {
+    type Err = hex::FromHexError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut checksum = [0; 32];
+        hex::decode_to_slice(s, &mut checksum)?;
+        Ok(Self(checksum))
+    }
+}
+
+/// Chunks of the query input that helper has received so far.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub struct ReceivedInputChunks {
+    /// Indices of the received chunks, in ascending order.
+    pub chunks: Vec<u32>,
+    /// The number of records in the received chunks.
+    pub records: u32,
+    /// The number of records the query expects. Once all of them are received, the query starts
+    /// running.
+    pub expected_records: u32,
+}
+
+impl ReceivedInputChunks {
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.records == self.expected_records
+    }
+
+    #[must_use]
+    pub fn contains(&self, index: u32) -> bool {
+        self.chunks.binary_search(&index).is_ok()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
 pub enum QueryType {
     #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
@@ -209,6 +399,7 @@ pub enum QueryType {
     SemiHonestSparseAggregate(SparseAggregateQueryConfig),
     MaliciousSparseAggregate(SparseAggregateQueryConfig),
     OprfIpa(IpaQueryConfig),
+    FeatureLabelDotProduct(FeatureLabelDotProductQueryConfig),
 }
 
 impl QueryType {
@@ -218,6 +409,7 @@ impl QueryType {
     pub const SEMIHONEST_AGGREGATE_STR: &'static str = "semihonest-sparse-aggregate";
     pub const MALICIOUS_AGGREGATE_STR: &'static str = "malicious-sparse-aggregate";
     pub const OPRF_IPA_STR: &'static str = "oprf_ipa";
+    pub const FEATURE_LABEL_DOT_PRODUCT_STR: &'static str = "feature-label-dot-product";
 }
 
 /// TODO: should this `AsRef` impl (used for `Substep`) take into account config of IPA?
@@ -231,6 +423,7 @@ impl AsRef<str> for QueryType {
             QueryType::SemiHonestSparseAggregate(_) => Self::SEMIHONEST_AGGREGATE_STR,
             QueryType::MaliciousSparseAggregate(_) => Self::MALICIOUS_AGGREGATE_STR,
             QueryType::OprfIpa(_) => Self::OPRF_IPA_STR,
+            QueryType::FeatureLabelDotProduct(_) => Self::FEATURE_LABEL_DOT_PRODUCT_STR,
         }
     }
 }
@@ -257,6 +450,87 @@ pub struct IpaQueryConfig {
     #[cfg_attr(feature = "clap", arg(long))]
     #[serde(default)]
     pub plaintext_match_keys: bool,
+
+    /// Privacy budget spent on the dummy records that helpers add to the input of OPRF IPA to
+    /// hide how many users have a given number of reports. If not set, no dummy records are
+    /// added and that histogram is revealed as is.
+    #[cfg_attr(feature = "clap", arg(long))]
+    #[serde(default)]
+    pub dummy_records_epsilon: Option<DpParameter>,
+
+    /// Probability of the dummy records failing to provide the `dummy_records_epsilon` guarantee.
+    /// Ignored if `dummy_records_epsilon` is not set.
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, default_value_t = IpaQueryConfig::DEFAULT_DELTA)
+    )]
+    #[serde(default = "IpaQueryConfig::default_delta")]
+    pub dummy_records_delta: DpParameter,
+
+    /// Privacy budget spent on the noise that helpers add to every breakdown total before
+    /// returning it to the report collector. `per_user_credit_cap` is the sensitivity of the
+    /// totals. If not set, the totals are returned as is.
+    #[cfg_attr(feature = "clap", arg(long))]
+    #[serde(default)]
+    pub output_noise_epsilon: Option<DpParameter>,
+
+    /// Probability of the noise failing to provide the `output_noise_epsilon` guarantee. Ignored
+    /// if `output_noise_epsilon` is not set.
+    #[cfg_attr(
+        feature = "clap",
+        arg(long, default_value_t = IpaQueryConfig::DEFAULT_DELTA)
+    )]
+    #[serde(default = "IpaQueryConfig::default_delta")]
+    pub output_noise_delta: DpParameter,
+
+    /// Epoch of the reports this query runs on, unless `min_report_epoch` or `max_report_epoch`
+    /// say otherwise. Helpers account for the privacy budget spent by every report collector
+    /// separately for each epoch.
+    #[cfg_attr(feature = "clap", arg(long, default_value = "0"))]
+    #[serde(default)]
+    pub epoch: Epoch,
+
+    /// Earliest epoch of the encrypted reports this query accepts. Reports from earlier epochs are
+    /// rejected, so that old reports cannot be replayed into new queries. If not set, defaults
+    /// to `epoch`.
+    #[cfg_attr(feature = "clap", arg(long))]
+    #[serde(default)]
+    pub min_report_epoch: Option<Epoch>,
+
+    /// Latest epoch of the encrypted reports this query accepts. If not set, defaults to `epoch`.
+    #[cfg_attr(feature = "clap", arg(long))]
+    #[serde(default)]
+    pub max_report_epoch: Option<Epoch>,
+
+    /// Number of bits in the breakdown keys of OPRF IPA input reports. The query computes
+    /// `max_breakdown_key` breakdown totals, so this must be large enough for
+    /// `max_breakdown_key - 1` to fit.
+    #[cfg_attr(feature = "clap", arg(long, default_value = "8"))]
+    #[serde(default = "IpaQueryConfig::default_breakdown_key_bits")]
+    pub breakdown_key_bits: u32,
+
+    /// Number of bits in the trigger values of OPRF IPA input reports. Trigger values are capped
+    /// at `per_user_credit_cap`, so this must not exceed `log2(per_user_credit_cap)`.
+    #[cfg_attr(feature = "clap", arg(long, default_value = "3"))]
+    #[serde(default = "IpaQueryConfig::default_trigger_value_bits")]
+    pub trigger_value_bits: u32,
+
+    /// Number of bits in the timestamps of OPRF IPA input reports.
+    #[cfg_attr(feature = "clap", arg(long, default_value = "20"))]
+    #[serde(default = "IpaQueryConfig::default_timestamp_bits")]
+    pub timestamp_bits: u32,
+
+    /// What to do with encrypted reports that cannot be decrypted or are malformed. OPRF IPA only
+    /// supports [`InvalidReportPolicy::Fail`].
+    #[cfg_attr(feature = "clap", arg(long, value_enum, default_value_t))]
+    #[serde(default)]
+    pub invalid_report_policy: InvalidReportPolicy,
+
+    /// Version of the encrypted input reports. Reports of any other version are rejected. OPRF
+    /// IPA only supports [`ReportVersion::MatchKeyOnly`].
+    #[cfg_attr(feature = "clap", arg(long, value_enum, default_value_t))]
+    #[serde(default)]
+    pub report_version: ReportVersion,
 }
 
 impl Default for IpaQueryConfig {
@@ -267,11 +541,28 @@ impl Default for IpaQueryConfig {
             attribution_window_seconds: None,
             num_multi_bits: 3,
             plaintext_match_keys: false,
+            dummy_records_epsilon: None,
+            dummy_records_delta: Self::default_delta(),
+            output_noise_epsilon: None,
+            output_noise_delta: Self::default_delta(),
+            epoch: 0,
+            min_report_epoch: None,
+            max_report_epoch: None,
+            breakdown_key_bits: Self::DEFAULT_BREAKDOWN_KEY_BITS,
+            trigger_value_bits: Self::DEFAULT_TRIGGER_VALUE_BITS,
+            timestamp_bits: Self::DEFAULT_TIMESTAMP_BITS,
+            invalid_report_policy: InvalidReportPolicy::Fail,
+            report_version: ReportVersion::MatchKeyOnly,
         }
     }
 }
 
 impl IpaQueryConfig {
+    const DEFAULT_DELTA: DpParameter = DpParameter::DEFAULT_DELTA;
+    const DEFAULT_BREAKDOWN_KEY_BITS: u32 = 8;
+    const DEFAULT_TRIGGER_VALUE_BITS: u32 = 3;
+    const DEFAULT_TIMESTAMP_BITS: u32 = 20;
+
     /// ## Panics
     /// If attribution window is 0
     #[must_use]
@@ -290,6 +581,18 @@ impl IpaQueryConfig {
             ),
             num_multi_bits,
             plaintext_match_keys: false,
+            dummy_records_epsilon: None,
+            dummy_records_delta: Self::default_delta(),
+            output_noise_epsilon: None,
+            output_noise_delta: Self::default_delta(),
+            epoch: 0,
+            min_report_epoch: None,
+            max_report_epoch: None,
+            breakdown_key_bits: Self::DEFAULT_BREAKDOWN_KEY_BITS,
+            trigger_value_bits: Self::DEFAULT_TRIGGER_VALUE_BITS,
+            timestamp_bits: Self::DEFAULT_TIMESTAMP_BITS,
+            invalid_report_policy: InvalidReportPolicy::Fail,
+            report_version: ReportVersion::MatchKeyOnly,
         }
     }
 
@@ -309,6 +612,238 @@ impl IpaQueryConfig {
             attribution_window_seconds: None,
             num_multi_bits,
             plaintext_match_keys: false,
+            dummy_records_epsilon: None,
+            dummy_records_delta: Self::default_delta(),
+            output_noise_epsilon: None,
+            output_noise_delta: Self::default_delta(),
+            epoch: 0,
+            min_report_epoch: None,
+            max_report_epoch: None,
+            breakdown_key_bits: Self::DEFAULT_BREAKDOWN_KEY_BITS,
+            trigger_value_bits: Self::DEFAULT_TRIGGER_VALUE_BITS,
+            timestamp_bits: Self::DEFAULT_TIMESTAMP_BITS,
+            invalid_report_policy: InvalidReportPolicy::Fail,
+            report_version: ReportVersion::MatchKeyOnly,
+        }
+    }
+
+    /// Makes helpers add dummy records to the input, so that the number of users having a given
+    /// number of reports is revealed with (`epsilon`, `delta`) differential privacy.
+    ///
+    /// ## Panics
+    /// If `epsilon` or `delta` is negative or not finite.
+    #[must_use]
+    pub fn with_dummy_records(mut self, epsilon: f64, delta: f64) -> Self {
+        self.dummy_records_epsilon = Some(epsilon.try_into().unwrap());
+        self.dummy_records_delta = delta.try_into().unwrap();
+        self
+    }
+
+    /// Makes helpers add noise to the breakdown totals, so that they are (`epsilon`, `delta`)
+    /// differentially private.
+    ///
+    /// ## Panics
+    /// If `epsilon` or `delta` is negative or not finite.
+    #[must_use]
+    pub fn with_output_noise(mut self, epsilon: f64, delta: f64) -> Self {
+        self.output_noise_epsilon = Some(epsilon.try_into().unwrap());
+        self.output_noise_delta = delta.try_into().unwrap();
+        self
+    }
+
+    /// Sets the number of bits in the breakdown keys, trigger values and timestamps of OPRF IPA
+    /// input reports.
+    #[must_use]
+    pub fn with_report_field_bits(
+        mut self,
+        breakdown_key_bits: u32,
+        trigger_value_bits: u32,
+        timestamp_bits: u32,
+    ) -> Self {
+        self.breakdown_key_bits = breakdown_key_bits;
+        self.trigger_value_bits = trigger_value_bits;
+        self.timestamp_bits = timestamp_bits;
+        self
+    }
+
+    /// Makes helpers reject encrypted reports from epochs outside of `epochs`.
+    #[must_use]
+    pub fn with_report_epochs(mut self, epochs: RangeInclusive<Epoch>) -> Self {
+        self.min_report_epoch = Some(*epochs.start());
+        self.max_report_epoch = Some(*epochs.end());
+        self
+    }
+
+    /// Sets what helpers do with encrypted reports that they fail to decrypt or parse.
+    #[must_use]
+    pub fn with_invalid_report_policy(mut self, policy: InvalidReportPolicy) -> Self {
+        self.invalid_report_policy = policy;
+        self
+    }
+
+    /// Sets the version of the encrypted reports that helpers accept.
+    #[must_use]
+    pub fn with_report_version(mut self, version: ReportVersion) -> Self {
+        self.report_version = version;
+        self
+    }
+
+    /// Epochs of the encrypted reports this query accepts. The query spends privacy budget on
+    /// every one of them.
+    #[must_use]
+    pub fn report_epochs(&self) -> RangeInclusive<Epoch> {
+        self.min_report_epoch.unwrap_or(self.epoch)..=self.max_report_epoch.unwrap_or(self.epoch)
+    }
+
+    /// Total privacy budget that OPRF IPA spends on each of the epochs it runs on. Sort-based IPA
+    /// does not add dummy records, so it spends only `output_noise_epsilon`.
+    #[must_use]
+    pub fn epsilon(&self) -> f64 {
+        self.dummy_records_epsilon.map_or(0.0, f64::from)
+            + self.output_noise_epsilon.map_or(0.0, f64::from)
+    }
+
+    fn default_delta() -> DpParameter {
+        Self::DEFAULT_DELTA
+    }
+
+    fn default_breakdown_key_bits() -> u32 {
+        Self::DEFAULT_BREAKDOWN_KEY_BITS
+    }
+
+    fn default_trigger_value_bits() -> u32 {
+        Self::DEFAULT_TRIGGER_VALUE_BITS
+    }
+
+    fn default_timestamp_bits() -> u32 {
+        Self::DEFAULT_TIMESTAMP_BITS
+    }
+}
+
+/// Epsilon or delta of a differential privacy guarantee. Unlike a bare `f64`, it is always finite
+/// and non-negative, so query configs that carry it can be compared for equality.
+#[derive(Copy, Clone, Debug)]
+#[cfg_attr(
+    feature = "enable-serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "f64", into = "f64")
+)]
+pub struct DpParameter(f64);
+
+#[derive(Debug, thiserror::Error)]
+pub enum DpParameterError {
+    #[error("differential privacy parameter must be finite and non-negative, got {0}")]
+    OutOfRange(f64),
+    #[error(transparent)]
+    Parse(#[from] std::num::ParseFloatError),
+}
+
+impl DpParameter {
+    /// Delta that query configs use unless told otherwise.
+    pub const DEFAULT_DELTA: Self = Self(1e-6);
+}
+
+impl TryFrom<f64> for DpParameter {
+    type Error = DpParameterError;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if value.is_finite() && value >= 0.0 {
+            // Adding zero turns -0.0 into 0.0, so that equal values have equal bit patterns.
+            Ok(Self(value + 0.0))
+        } else {
+            Err(DpParameterError::OutOfRange(value))
+        }
+    }
+}
+
+impl From<DpParameter> for f64 {
+    fn from(value: DpParameter) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for DpParameter {
+    type Err = DpParameterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s.parse::<f64>()?)
+    }
+}
+
+impl Display for DpParameter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl PartialEq for DpParameter {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for DpParameter {}
+
+/// Policy for encrypted reports that a helper fails to decrypt or parse. Helpers receive different
+/// copies of every report, so a report can be valid on some of them and invalid on the others.
+/// Unless the query fails, helpers agree on the reports that at least one of them rejected and
+/// count them by [`InvalidReportKind`], so that the rows they hold stay aligned.
+///
+/// [`InvalidReportKind`]: crate::report::InvalidReportKind
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "enable-serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum InvalidReportPolicy {
+    /// The first invalid report fails the whole query.
+    #[default]
+    Fail,
+    /// Invalid reports are left out of the query. The input is padded back to the query size
+    /// with dummy rows that contribute nothing to the result.
+    Drop,
+    /// Every invalid report is replaced by a null event: a trigger event with zero value and a
+    /// random match key, which contributes nothing to the result.
+    ReplaceWithNullEvent,
+}
+
+impl AsRef<str> for InvalidReportPolicy {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Fail => "fail",
+            Self::Drop => "drop",
+            Self::ReplaceWithNullEvent => "replace-with-null-event",
+        }
+    }
+}
+
+impl Display for InvalidReportPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct FeatureLabelDotProductQueryConfig {
+    /// Number of features in the feature vector of every source report, one bit each.
+    #[cfg_attr(feature = "clap", arg(long, default_value = "32"))]
+    pub feature_vector_bits: u32,
+
+    /// Maximum number of source reports of a single user that can receive a label. Must be a
+    /// power of two.
+    #[cfg_attr(feature = "clap", arg(long, default_value = "1"))]
+    pub per_user_credit_cap: u32,
+}
+
+impl Default for FeatureLabelDotProductQueryConfig {
+    fn default() -> Self {
+        Self {
+            feature_vector_bits: 32,
+            per_user_credit_cap: 1,
         }
     }
 }
@@ -358,3 +893,21 @@ impl Default for SparseAggregateQueryConfig {
         }
     }
 }
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_empty_report_epochs() {
+        let query_type =
+            |min, max| QueryType::OprfIpa(IpaQueryConfig::default().with_report_epochs(min..=max));
+
+        assert!(QueryConfig::new(query_type(3, 3), FieldType::Fp31, 1).is_ok());
+        assert!(QueryConfig::new(query_type(3, 5), FieldType::Fp31, 1).is_ok());
+        assert!(matches!(
+            QueryConfig::new(query_type(5, 3), FieldType::Fp31, 1),
+            Err(QueryConfigError::EmptyReportEpochs { min: 5, max: 3 })
+        ));
+    }
+}
@@ -5,7 +5,7 @@ use std::{
 
 use futures::Stream;
 
-use crate::helpers::transport::stream::BoxBytesStream;
+use crate::helpers::transport::stream::{BoxBytesStream, BytesStream};
 
 pub struct WrappedBoxBodyStream(BoxBytesStream);
 
@@ -18,6 +18,14 @@ impl WrappedBoxBodyStream {
     }
 }
 
+impl WrappedBoxBodyStream {
+    /// Returns a body stream that yields the items of `stream`.
+    #[must_use]
+    pub fn from_bytes_stream<S: BytesStream + 'static>(stream: S) -> Self {
+        Self(Box::pin(stream))
+    }
+}
+
 impl Stream for WrappedBoxBodyStream {
     type Item = <BoxBytesStream as Stream>::Item;
 
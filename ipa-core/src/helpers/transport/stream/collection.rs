@@ -119,6 +119,16 @@ impl<S: Stream> StreamCollection<S> {
         let mut streams = self.inner.lock().unwrap();
         streams.clear();
     }
+
+    /// Removes all streams that belong to the given query, leaving streams of other queries
+    /// intact.
+    ///
+    /// ## Panics
+    /// if mutex is poisoned.
+    pub fn clear_query(&self, query_id: QueryId) {
+        let mut streams = self.inner.lock().unwrap();
+        streams.retain(|(stream_query_id, _, _), _| *stream_query_id != query_id);
+    }
 }
 
 /// Describes the lifecycle of records stream inside [`StreamCollection`]
@@ -18,7 +18,9 @@ use generic_array::GenericArray;
 use pin_project::pin_project;
 use typenum::{Unsigned, U2};
 
-use crate::{error::BoxError, ff::Serializable, helpers::BytesStream};
+use crate::{
+    error::BoxError, ff::Serializable, helpers::BytesStream, report::ESTIMATED_AVERAGE_REPORT_SIZE,
+};
 
 #[derive(Debug)]
 pub struct BufDeque {
@@ -309,8 +311,6 @@ where
     }
 }
 
-const ESTIMATED_AVERAGE_REPORT_SIZE: usize = 80; // TODO: confirm/adjust
-
 impl<T, S> Stream for LengthDelimitedStream<T, S>
 where
     S: BytesStream,
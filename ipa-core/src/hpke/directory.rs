@@ -0,0 +1,238 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use hpke::{Deserializable, HpkeError};
+
+use super::{IpaPrivateKey, IpaPublicKey, KeyIdentifier, KeyPair, KeyRegistry};
+
+const PUBLIC_KEY_EXTENSION: &str = "pk";
+const PRIVATE_KEY_EXTENSION: &str = "sk";
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeyDirectoryError {
+    #[error("failed to access key directory at {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("key file {path} is not a valid hex-encoded key: {source}")]
+    Hex {
+        path: PathBuf,
+        source: hex::FromHexError,
+    },
+    #[error("key file {path} does not contain a valid key: {source}")]
+    Key { path: PathBuf, source: HpkeError },
+    #[error("private key for key {0} is missing")]
+    MissingPrivateKey(KeyIdentifier),
+    #[error("public key for key {0} is missing")]
+    MissingPublicKey(KeyIdentifier),
+    #[error("all key identifiers are used")]
+    Exhausted,
+}
+
+/// A directory of HPKE key pairs that helpers use to decrypt reports. Every key pair is stored in
+/// two files named after its [`KeyIdentifier`]: `{key_id}.pk` with the public key and
+/// `{key_id}.sk` with the private key, both hex-encoded. Other files are ignored.
+///
+/// Keys are rotated by adding the next key pair with [`Self::add`] and retired by deleting their
+/// files. Helpers pick up the changes when they reload the directory.
+#[derive(Clone, Debug)]
+pub struct KeyDirectory {
+    dir: PathBuf,
+}
+
+impl KeyDirectory {
+    /// Opens the key directory in `dir`, creating it if it does not exist.
+    ///
+    /// ## Errors
+    /// If the directory cannot be created.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, KeyDirectoryError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|source| KeyDirectoryError::Io {
+            path: dir.clone(),
+            source,
+        })?;
+
+        Ok(Self { dir })
+    }
+
+    /// Reads all key pairs from the directory.
+    ///
+    /// ## Errors
+    /// If the directory cannot be read, a key file is invalid or a key pair is incomplete.
+    pub fn load(&self) -> Result<KeyRegistry<KeyPair>, KeyDirectoryError> {
+        let mut public_keys = Vec::new();
+        let mut private_keys = Vec::new();
+        for (key_id, extension) in self.key_files()? {
+            match extension {
+                Extension::Public => public_keys.push(key_id),
+                Extension::Private => private_keys.push(key_id),
+            }
+        }
+        if let Some(&key_id) = private_keys.iter().find(|id| !public_keys.contains(id)) {
+            return Err(KeyDirectoryError::MissingPublicKey(key_id));
+        }
+
+        let keys = public_keys
+            .into_iter()
+            .map(|key_id| {
+                if !private_keys.contains(&key_id) {
+                    return Err(KeyDirectoryError::MissingPrivateKey(key_id));
+                }
+                let pk = self.read_key::<IpaPublicKey>(key_id, Extension::Public)?;
+                let sk = self.read_key::<IpaPrivateKey>(key_id, Extension::Private)?;
+                Ok((key_id, KeyPair::from((sk, pk))))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(KeyRegistry::from_key_ids(keys))
+    }
+
+    /// Returns the identifier that follows the most recent key in the directory, or the first
+    /// identifier if the directory is empty.
+    ///
+    /// ## Errors
+    /// If the directory cannot be read or the most recent key has the last possible identifier.
+    pub fn next_key_id(&self) -> Result<KeyIdentifier, KeyDirectoryError> {
+        match self.key_files()?.map(|(key_id, _)| key_id).max() {
+            None => Ok(0),
+            Some(key_id) => key_id.checked_add(1).ok_or(KeyDirectoryError::Exhausted),
+        }
+    }
+
+    /// Writes `keypair` to the directory under `key_id`. Existing keys are never overwritten.
+    ///
+    /// ## Errors
+    /// If the key files cannot be created, including when they already exist.
+    pub fn add(&self, key_id: KeyIdentifier, keypair: &KeyPair) -> Result<(), KeyDirectoryError> {
+        self.write_key(key_id, Extension::Public, &keypair.pk_bytes())?;
+        self.write_key(key_id, Extension::Private, &keypair.sk_bytes())
+    }
+
+    fn key_files(
+        &self,
+    ) -> Result<impl Iterator<Item = (KeyIdentifier, Extension)>, KeyDirectoryError> {
+        let entries = fs::read_dir(&self.dir)
+            .and_then(|entries| {
+                entries
+                    .map(|entry| entry.map(|entry| entry.path()))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .map_err(|source| KeyDirectoryError::Io {
+                path: self.dir.clone(),
+                source,
+            })?;
+
+        Ok(entries.into_iter().filter_map(|path| {
+            let key_id = path.file_stem()?.to_str()?.parse::<KeyIdentifier>().ok()?;
+            let extension = match path.extension()?.to_str()? {
+                PUBLIC_KEY_EXTENSION => Extension::Public,
+                PRIVATE_KEY_EXTENSION => Extension::Private,
+                _ => return None,
+            };
+            Some((key_id, extension))
+        }))
+    }
+
+    fn path(&self, key_id: KeyIdentifier, extension: Extension) -> PathBuf {
+        self.dir.join(format!("{key_id}.{}", extension.as_str()))
+    }
+
+    fn read_key<K: Deserializable>(
+        &self,
+        key_id: KeyIdentifier,
+        extension: Extension,
+    ) -> Result<K, KeyDirectoryError> {
+        let path = self.path(key_id, extension);
+        let hex = fs::read_to_string(&path).map_err(|source| KeyDirectoryError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let bytes = match hex::decode(hex.trim()) {
+            Ok(bytes) => bytes,
+            Err(source) => return Err(KeyDirectoryError::Hex { path, source }),
+        };
+        K::from_bytes(&bytes).map_err(|source| KeyDirectoryError::Key { path, source })
+    }
+
+    fn write_key(
+        &self,
+        key_id: KeyIdentifier,
+        extension: Extension,
+        key: &[u8],
+    ) -> Result<(), KeyDirectoryError> {
+        let path = self.path(key_id, extension);
+        File::options()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(hex::encode(key).as_bytes()))
+            .map_err(|source| KeyDirectoryError::Io { path, source })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Extension {
+    Public,
+    Private,
+}
+
+impl Extension {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Public => PUBLIC_KEY_EXTENSION,
+            Self::Private => PRIVATE_KEY_EXTENSION,
+        }
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use hpke::Serializable;
+    use rand::rngs::StdRng;
+    use rand_core::SeedableRng;
+
+    use super::*;
+    use crate::hpke::PublicKeyRegistry;
+
+    #[test]
+    fn rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = KeyDirectory::open(dir.path()).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        assert_eq!(0, keys.next_key_id().unwrap());
+
+        keys.add(0, &KeyPair::gen(&mut rng)).unwrap();
+        let second = KeyPair::gen(&mut rng);
+        keys.add(keys.next_key_id().unwrap(), &second).unwrap();
+        assert!(keys.add(1, &second).is_err());
+
+        let registry = keys.load().unwrap();
+        assert_eq!(vec![0, 1], registry.key_ids().collect::<Vec<_>>());
+        assert_eq!(
+            &*second.pk_bytes(),
+            registry.public_key(1).unwrap().to_bytes().as_slice()
+        );
+
+        // retire the first key
+        fs::remove_file(dir.path().join("0.pk")).unwrap();
+        fs::remove_file(dir.path().join("0.sk")).unwrap();
+        let registry = keys.load().unwrap();
+        assert_eq!(vec![1], registry.key_ids().collect::<Vec<_>>());
+        assert_eq!(2, keys.next_key_id().unwrap());
+    }
+
+    #[test]
+    fn incomplete_pair() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = KeyDirectory::open(dir.path()).unwrap();
+        keys.add(5, &KeyPair::gen(&mut StdRng::seed_from_u64(42)))
+            .unwrap();
+        fs::remove_file(dir.path().join("5.sk")).unwrap();
+
+        assert!(matches!(
+            keys.load(),
+            Err(KeyDirectoryError::MissingPrivateKey(5))
+        ));
+    }
+}
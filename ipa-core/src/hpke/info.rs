@@ -14,7 +14,7 @@ const DOMAIN: &str = "private-attribution";
 pub struct Info<'a> {
     pub(super) key_id: KeyIdentifier,
     pub(super) epoch: Epoch,
-    pub(super) event_type: EventType,
+    pub(super) event_type: Option<EventType>,
     pub(super) helper_origin: &'a str,
     pub(super) site_domain: &'a str,
 }
@@ -30,6 +30,30 @@ impl<'a> Info<'a> {
         event_type: EventType,
         helper_origin: &'a str,
         site_domain: &'a str,
+    ) -> Result<Self, NonAsciiStringError> {
+        Self::with_event_type(key_id, epoch, Some(event_type), helper_origin, site_domain)
+    }
+
+    /// Creates an instance for reports that seal the event type along with the other report
+    /// fields. The event type is then authenticated by the AEAD tag, not by the context.
+    ///
+    /// ## Errors
+    /// if helper or site origin is not a valid ASCII string.
+    pub fn without_event_type(
+        key_id: KeyIdentifier,
+        epoch: Epoch,
+        helper_origin: &'a str,
+        site_domain: &'a str,
+    ) -> Result<Self, NonAsciiStringError> {
+        Self::with_event_type(key_id, epoch, None, helper_origin, site_domain)
+    }
+
+    fn with_event_type(
+        key_id: KeyIdentifier,
+        epoch: Epoch,
+        event_type: Option<EventType>,
+        helper_origin: &'a str,
+        site_domain: &'a str,
     ) -> Result<Self, NonAsciiStringError> {
         // If the types of errors returned from this function change, then the validation in
         // `EncryptedReport::from_bytes` may need to change as well.
@@ -59,7 +83,7 @@ impl<'a> Info<'a> {
             + 3 // account for 3 delimiters
             + std::mem::size_of_val(&self.key_id)
             + std::mem::size_of_val(&self.epoch)
-            + std::mem::size_of_val(&self.event_type);
+            + self.event_type.map_or(0, |e| std::mem::size_of_val(&e));
         let mut r = Vec::with_capacity(info_len);
 
         r.extend_from_slice(DOMAIN.as_bytes());
@@ -72,7 +96,9 @@ impl<'a> Info<'a> {
         r.push(self.key_id);
         // Spec dictates epoch to be encoded in BE
         r.extend_from_slice(&self.epoch.to_be_bytes());
-        r.push((&self.event_type).into());
+        if let Some(event_type) = &self.event_type {
+            r.push(event_type.into());
+        }
 
         debug_assert_eq!(r.len(), info_len, "HPKE Info length estimation is incorrect and leads to extra allocation or wasted memory");
 
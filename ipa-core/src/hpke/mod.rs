@@ -12,9 +12,11 @@ use hpke::{
 use rand_core::{CryptoRng, RngCore};
 use typenum::U16;
 
+mod directory;
 mod info;
 mod registry;
 
+pub use directory::{KeyDirectory, KeyDirectoryError};
 pub use info::Info;
 pub use registry::{KeyPair, KeyRegistry, PublicKeyOnly, PublicKeyRegistry};
 
@@ -33,6 +35,9 @@ pub type IpaPublicKey = <IpaKem as hpke::kem::Kem>::PublicKey;
 pub type IpaPrivateKey = <IpaKem as hpke::kem::Kem>::PrivateKey;
 pub type IpaEncappedKey = <IpaKem as hpke::kem::Kem>::EncappedKey;
 
+/// Size of the authentication tag that [`seal_in_place`] produces alongside the ciphertext.
+pub type TagSize = <AeadTag<IpaAead> as Serializable>::OutputSize;
+
 pub use hpke::{Deserializable, Serializable};
 
 pub trait FieldShareCrypt: GaloisField + IpaSerializable {
@@ -442,7 +447,7 @@ mod tests {
                         ..encryption.info
                     },
                     3 => Info {
-                        event_type: EventType::try_from(trigger_bit ^ 1).unwrap(),
+                        event_type: Some(EventType::try_from(trigger_bit ^ 1).unwrap()),
                         ..encryption.info
                     },
                     4 => {
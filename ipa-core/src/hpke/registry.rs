@@ -1,4 +1,4 @@
-use std::ops::Deref;
+use std::{collections::BTreeMap, ops::Deref};
 
 use hpke::Serializable;
 
@@ -62,9 +62,10 @@ pub trait PublicKeyRegistry {
     fn public_key(&self, key_id: KeyIdentifier) -> Option<&IpaPublicKey>;
 }
 
-/// A registry that holds all the keys available for helper/UA to use.
+/// A registry that holds all the keys available for helper/UA to use, indexed by their
+/// identifiers.
 pub struct KeyRegistry<K> {
-    keys: Box<[K]>,
+    keys: BTreeMap<KeyIdentifier, K>,
 }
 
 impl<K> KeyRegistry<K> {
@@ -72,35 +73,65 @@ impl<K> KeyRegistry<K> {
     /// but this avoids `Option<KeyRegistry>` when the registry is ultimately not optional.
     #[must_use]
     pub fn empty() -> Self {
-        Self { keys: Box::new([]) }
+        Self {
+            keys: BTreeMap::new(),
+        }
     }
 
+    /// Creates a registry where keys are identified by their position in `pairs`.
+    ///
+    /// ## Panics
+    /// If there are more keys than key identifiers.
     pub fn from_keys<const N: usize, I: Into<K>>(pairs: [I; N]) -> Self {
+        Self::from_key_ids(
+            pairs
+                .into_iter()
+                .enumerate()
+                .map(|(key_id, key)| (KeyIdentifier::try_from(key_id).unwrap(), key)),
+        )
+    }
+
+    /// Creates a registry from keys with explicitly assigned identifiers. If an identifier
+    /// repeats, the last key wins.
+    pub fn from_key_ids<T: IntoIterator<Item = (KeyIdentifier, I)>, I: Into<K>>(keys: T) -> Self {
         Self {
-            keys: pairs
+            keys: keys
                 .into_iter()
-                .map(Into::into)
-                .collect::<Vec<_>>()
-                .into_boxed_slice(),
+                .map(|(key_id, key)| (key_id, key.into()))
+                .collect(),
         }
     }
 
+    /// Returns identifiers of all keys in this registry, in ascending order.
+    pub fn key_ids(&self) -> impl Iterator<Item = KeyIdentifier> + '_ {
+        self.keys.keys().copied()
+    }
+
     fn key(&self, key_id: KeyIdentifier) -> Option<&K> {
-        match key_id as usize {
-            key_id if key_id < self.keys.len() => Some(&self.keys[key_id]),
-            _ => None,
-        }
+        self.keys.get(&key_id)
     }
 }
 
 impl KeyRegistry<KeyPair> {
+    /// ## Panics
+    /// If there are more keys than key identifiers.
     #[cfg(any(test, feature = "test-fixture"))]
     pub fn random<R: rand::RngCore + rand::CryptoRng>(keys_count: usize, r: &mut R) -> Self {
-        let keys = (0..keys_count).map(|_| KeyPair::gen(r)).collect::<Vec<_>>();
+        Self::from_key_ids(
+            (0..keys_count)
+                .map(|key_id| (KeyIdentifier::try_from(key_id).unwrap(), KeyPair::gen(r))),
+        )
+    }
 
-        Self {
-            keys: keys.into_boxed_slice(),
-        }
+    /// Returns a registry with the public keys of the key pairs in this one, under the same
+    /// identifiers.
+    #[must_use]
+    pub fn public_keys(&self) -> KeyRegistry<PublicKeyOnly> {
+        KeyRegistry::from_key_ids(
+            self.keys
+                .iter()
+                .map(|(&key_id, keypair)| (key_id, PublicKeyOnly(keypair.pk.clone()))),
+        )
     }
 
     #[must_use]
@@ -185,4 +216,15 @@ mod tests {
             decrypt(registry.private_key(1).unwrap(), &ct_payload).unwrap_err()
         );
     }
+    #[test]
+    fn sparse_key_ids() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let registry =
+            KeyRegistry::from_key_ids([(7, KeyPair::gen(&mut rng)), (3, KeyPair::gen(&mut rng))]);
+
+        assert_eq!(vec![3, 7], registry.key_ids().collect::<Vec<_>>());
+        assert!(registry.private_key(3).is_some());
+        assert!(registry.public_key(7).is_some());
+        assert!(registry.public_key(0).is_none());
+    }
 }
@@ -1,32 +1,39 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     future::Future,
     io,
     io::{BufReader, Cursor},
     iter::repeat,
     pin::Pin,
     task::{ready, Context, Poll},
+    time::SystemTime,
 };
 
 use axum::http::uri::{self, Parts, Scheme};
 use futures::{Stream, StreamExt};
 use hyper::{
-    body, client::HttpConnector, header::HeaderName, http::HeaderValue, Body, Client, Request,
-    Response, StatusCode, Uri,
+    body,
+    client::HttpConnector,
+    header::{HeaderName, AUTHORIZATION},
+    http::HeaderValue,
+    Body, Client, Request, Response, StatusCode, Uri,
 };
 use hyper_rustls::{ConfigBuilderExt, HttpsConnector, HttpsConnectorBuilder};
 use pin_project::pin_project;
 use rustls::{Certificate, PrivateKey, RootCertStore};
-use tracing::error;
+use serde::de::DeserializeOwned;
+use tracing::{error, warn};
 
 use crate::{
-    config::{ClientConfig, HyperClientConfigurator, NetworkConfig, PeerConfig},
+    config::{ClientConfig, HyperClientConfigurator, NetworkConfig, PeerConfig, RetryConfig},
     helpers::{
-        query::{PrepareQuery, QueryConfig, QueryInput},
+        query::{PrepareQuery, QueryConfig, QueryInput, QueryInputChunk, ReceivedInputChunks},
         HelperIdentity,
     },
-    net::{http_serde, server::HTTP_CLIENT_ID_HEADER, Error},
+    hpke::IpaPublicKey,
+    net::{http_serde, server::HTTP_CLIENT_ID_HEADER, step_stream::ReplayBuffer, Error},
     protocol::{step::Gate, QueryId},
+    report::KeyIdentifier,
 };
 
 #[derive(Clone, Default)]
@@ -41,6 +48,11 @@ pub enum ClientIdentity {
     /// This is only supported for HTTPS clients.
     Certificate((Vec<Certificate>, PrivateKey)),
 
+    /// Authenticate a report collector with a bearer token.
+    ///
+    /// This is supported for both HTTP and HTTPS clients.
+    Token(String),
+
     /// Do not authenticate nor claim a helper identity.
     #[default]
     None,
@@ -98,6 +110,10 @@ impl<'a> ResponseFromEndpoint<'a> {
         self.inner.status()
     }
 
+    pub fn headers(&self) -> &hyper::HeaderMap {
+        self.inner.headers()
+    }
+
     pub fn into_body(self) -> Body {
         self.inner.into_body()
     }
@@ -136,6 +152,7 @@ pub struct MpcHelperClient {
     scheme: uri::Scheme,
     authority: uri::Authority,
     auth_header: Option<(HeaderName, HeaderValue)>,
+    retry: RetryConfig,
 }
 
 impl MpcHelperClient {
@@ -145,8 +162,9 @@ impl MpcHelperClient {
     /// helpers.
     ///
     /// `identity` configures whether and how the client will authenticate to the server. It is for
-    /// the helper making the calls, so the same one is used for all three of the clients.
-    /// Authentication is not required when calling the report collector APIs.
+    /// the helper or the report collector making the calls, so the same one is used for all three
+    /// of the clients. Report collectors only need to authenticate if the helpers restrict access
+    /// to the query APIs.
     #[must_use]
     #[allow(clippy::missing_panics_doc)]
     pub fn from_conf(conf: &NetworkConfig, identity: ClientIdentity) -> [MpcHelperClient; 3] {
@@ -172,6 +190,13 @@ impl MpcHelperClient {
         peer_config: PeerConfig,
         identity: ClientIdentity,
     ) -> Self {
+        let token_header = match &identity {
+            ClientIdentity::Token(token) => Some((
+                AUTHORIZATION,
+                HeaderValue::try_from(format!("Bearer {token}")).expect("token is not valid"),
+            )),
+            _ => None,
+        };
         let (connector, auth_header) = if peer_config.url.scheme() == Some(&Scheme::HTTP) {
             // This connector works for both http and https. A regular HttpConnector would suffice,
             // but would make the type of `self.client` variable.
@@ -184,6 +209,7 @@ impl MpcHelperClient {
                     HTTP_CLIENT_ID_HEADER.clone(),
                     id.try_into().expect("integer not ascii?"),
                 )),
+                ClientIdentity::Token(_) => token_header,
                 ClientIdentity::None => None,
             };
             (
@@ -212,7 +238,9 @@ impl MpcHelperClient {
                         error!("header-passed identity ignored for HTTPS client");
                         builder.with_no_client_auth()
                     }
-                    ClientIdentity::None => builder.with_no_client_auth(),
+                    ClientIdentity::Token(_) | ClientIdentity::None => {
+                        builder.with_no_client_auth()
+                    }
                 }
             } else {
                 builder.with_native_roots().with_no_client_auth()
@@ -227,18 +255,18 @@ impl MpcHelperClient {
                     .https_only()
                     .enable_http2()
                     .wrap_connector(http),
-                None,
+                token_header,
             )
         };
         Self::new_internal(peer_config.url, connector, auth_header, client_config)
     }
 
     #[must_use]
-    fn new_internal<C: HyperClientConfigurator>(
+    fn new_internal(
         addr: Uri,
         connector: HttpsConnector<HttpConnector>,
         auth_header: Option<(HeaderName, HeaderValue)>,
-        conf: &C,
+        conf: &ClientConfig,
     ) -> Self {
         let client = conf.configure(&mut Client::builder()).build(connector);
         let Parts {
@@ -254,6 +282,7 @@ impl MpcHelperClient {
             scheme,
             authority,
             auth_header,
+            retry: conf.retry.clone(),
         }
     }
 
@@ -267,6 +296,34 @@ impl MpcHelperClient {
         }
     }
 
+    /// Sends the request that `make_request` returns until it succeeds, or fails with an error
+    /// that is not transient, or the number of attempts allowed by [`RetryConfig`] runs out.
+    /// Only requests that can be safely repeated may be sent this way.
+    ///
+    /// ## Errors
+    /// The error of the last attempt.
+    async fn with_retry<T, F, Fut>(&self, mut make_request: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match make_request().await {
+                Err(e) if e.is_transient() && attempt < self.retry.max_attempts => {
+                    let backoff = self.retry.backoff(attempt);
+                    warn!(
+                        "attempt {attempt} to reach {} failed, retrying in {backoff:?}: {e}",
+                        self.authority
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
     /// Responds with whatever input is passed to it
     /// # Errors
     /// If the request has illegal arguments, or fails to deliver to helper
@@ -307,6 +364,19 @@ impl MpcHelperClient {
         }
     }
 
+    /// Helper to read the JSON body of a successful response, or the error otherwise
+    ///
+    /// # Errors
+    /// If there was an error reading or parsing the response body or if the request itself failed.
+    async fn resp_json<T: DeserializeOwned>(resp: ResponseFromEndpoint<'_>) -> Result<T, Error> {
+        if resp.status().is_success() {
+            let body_bytes = body::to_bytes(resp.into_body()).await?;
+            Ok(serde_json::from_slice(&body_bytes)?)
+        } else {
+            Err(Error::from_failed_resp(resp).await)
+        }
+    }
+
     /// Intended to be called externally, by the report collector. Informs the MPC ring that
     /// the external party wants to start a new query.
     /// # Errors
@@ -331,10 +401,13 @@ impl MpcHelperClient {
     /// # Errors
     /// If the request has illegal arguments, or fails to deliver to helper
     pub async fn prepare_query(&self, data: PrepareQuery) -> Result<(), Error> {
-        let req = http_serde::query::prepare::Request::new(data);
-        let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
-        let resp = self.request(req).await?;
-        Self::resp_ok(resp).await
+        self.with_retry(|| async {
+            let req = http_serde::query::prepare::Request::new(data.clone());
+            let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
+            let resp = self.request(req).await?;
+            Self::resp_ok(resp).await
+        })
+        .await
     }
 
     /// Intended to be called externally, e.g. by the report collector. After the report collector
@@ -349,6 +422,104 @@ impl MpcHelperClient {
         Self::resp_ok(resp).await
     }
 
+    /// Sends one chunk of the query input to the helper. The query starts running on this
+    /// helper once all chunks are received.
+    /// # Errors
+    /// If the request has illegal arguments, fails to deliver to helper, or the chunk is rejected
+    pub async fn query_input_chunk(
+        &self,
+        chunk: QueryInputChunk,
+    ) -> Result<ReceivedInputChunks, Error> {
+        let req = http_serde::query::input::chunk::Request::new(chunk);
+        let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
+        let resp = self.request(req).await?;
+        Self::resp_json(resp).await
+    }
+
+    /// Returns the chunks of the query input that helper has received so far.
+    /// # Errors
+    /// If the request has illegal arguments, or fails to deliver to helper
+    pub async fn received_input_chunks(
+        &self,
+        query_id: QueryId,
+    ) -> Result<ReceivedInputChunks, Error> {
+        let req = http_serde::query::input::chunks::Request::new(query_id);
+        let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
+        let resp = self.request(req).await?;
+        Self::resp_json(resp).await
+    }
+
+    /// Uploads the query input in chunks, skipping the chunks that helper already has. If the
+    /// upload is interrupted, calling this again with the same chunks resumes it.
+    /// # Errors
+    /// If any of the requests fails
+    pub async fn upload_input_chunks<I>(
+        &self,
+        query_id: QueryId,
+        chunks: I,
+    ) -> Result<ReceivedInputChunks, Error>
+    where
+        I: IntoIterator<Item = QueryInputChunk>,
+    {
+        let mut received = self.received_input_chunks(query_id).await?;
+        let already_received = received.clone();
+        for chunk in chunks {
+            if !already_received.contains(chunk.index) {
+                received = self.query_input_chunk(chunk).await?;
+            }
+        }
+
+        Ok(received)
+    }
+
+    /// Used to communicate from one helper to another. The helper that receives a "kill query"
+    /// request from an external party asks other helpers to cancel that query too.
+    /// # Errors
+    /// If the request has illegal arguments, or fails to deliver to helper
+    pub async fn abort_query(&self, query_id: QueryId) -> Result<(), Error> {
+        let req = http_serde::query::abort::Request::new(query_id);
+        let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
+        let resp = self.request(req).await?;
+        Self::resp_ok(resp).await
+    }
+
+    /// Fetches the public keys that the helper accepts reports encrypted with. If `certificate`
+    /// is provided, the keys must be signed with the private key of that certificate.
+    ///
+    /// # Errors
+    /// If the keys are not signed by the owner of `certificate`, are expired, or the request
+    /// fails to deliver.
+    pub async fn public_keys(
+        &self,
+        certificate: Option<&Certificate>,
+    ) -> Result<BTreeMap<KeyIdentifier, IpaPublicKey>, Error> {
+        self.with_retry(|| async {
+            let req = http_serde::keys::public::http_request(
+                self.scheme.clone(),
+                self.authority.clone(),
+            )?;
+            let resp = self.request(req).await?;
+            let body: http_serde::keys::public::ResponseBody = Self::resp_json(resp).await?;
+            body.verify(certificate, SystemTime::now())
+        })
+        .await
+    }
+
+    /// Asks the helper to reload the keys it uses to decrypt reports from its key directory and
+    /// returns the identifiers of the keys it accepts afterwards. The client must use the
+    /// certificate of one of the helpers.
+    ///
+    /// # Errors
+    /// If the helper does not load keys from a directory, fails to load them, or the request
+    /// fails to deliver.
+    pub async fn reload_keys(&self) -> Result<Vec<KeyIdentifier>, Error> {
+        let req =
+            http_serde::keys::reload::http_request(self.scheme.clone(), self.authority.clone())?;
+        let resp = self.request(req).await?;
+        let body: http_serde::keys::reload::ResponseBody = Self::resp_json(resp).await?;
+        Ok(body.key_ids)
+    }
+
     /// Sends a batch of messages associated with a query's step to another helper. Messages are a
     /// contiguous block of records. Also includes [`crate::protocol::RecordId`] information and
     /// [`crate::helpers::network::ChannelId`].
@@ -368,7 +539,33 @@ impl MpcHelperClient {
         Ok(self.request(req))
     }
 
-    /// Retrieve the status of a query.
+    /// Same as [`Self::step`], but if the connection to the other helper breaks, sends the data
+    /// again starting from the last few chunks that were sent, until the other helper receives
+    /// all of it. See [`crate::net::step_stream`] for details.
+    ///
+    /// # Errors
+    /// If the request has illegal arguments, or fails to deliver to helper after all attempts
+    /// allowed by [`RetryConfig`].
+    pub async fn resumable_step<S: Stream<Item = Vec<u8>> + Send + 'static>(
+        &self,
+        query_id: QueryId,
+        gate: &Gate,
+        data: S,
+    ) -> Result<(), Error> {
+        let data = ReplayBuffer::new(data, self.retry.resume_buffer_bytes);
+        self.with_retry(|| async {
+            let (offset, body) = data.resume();
+            let body = hyper::Body::wrap_stream::<_, _, Error>(body.map(Ok));
+            let req = http_serde::query::step::Request::new(query_id, gate.clone(), body)
+                .with_offset(offset);
+            let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
+            let resp = self.request(req).await?;
+            Self::resp_ok(resp).await
+        })
+        .await
+    }
+
+    /// Retrieve the status of a query, along with its progress if the query is running.
     ///
     /// ## Errors
     /// If the request has illegal arguments, or fails to deliver to helper
@@ -376,38 +573,77 @@ impl MpcHelperClient {
     pub async fn query_status(
         &self,
         query_id: QueryId,
-    ) -> Result<crate::query::QueryStatus, Error> {
-        let req = http_serde::query::status::Request::new(query_id);
-        let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
-
-        let resp = self.request(req).await?;
-        if resp.status().is_success() {
-            let body_bytes = body::to_bytes(resp.into_body()).await?;
-            let http_serde::query::status::ResponseBody { status } =
-                serde_json::from_slice(&body_bytes)?;
-            Ok(status)
-        } else {
-            Err(Error::from_failed_resp(resp).await)
-        }
+    ) -> Result<crate::query::QueryStatusReport, Error> {
+        self.with_retry(|| async {
+            let req = http_serde::query::status::Request::new(query_id);
+            let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
+            let resp = self.request(req).await?;
+            let body: http_serde::query::status::ResponseBody = Self::resp_json(resp).await?;
+            Ok(body.into())
+        })
+        .await
     }
 
     /// Wait for completion of the query and pull the results of this query. This is a blocking
     /// API so it is not supposed to be used outside of CLI context.
     ///
+    /// Results are streamed, this returns as soon as the helper starts sending them.
+    ///
     /// ## Errors
     /// If the request has illegal arguments, or fails to deliver to helper
     #[cfg(any(all(test, not(feature = "shuttle")), feature = "cli"))]
-    pub async fn query_results(&self, query_id: QueryId) -> Result<body::Bytes, Error> {
+    pub async fn query_results(&self, query_id: QueryId) -> Result<QueryResults, Error> {
         let req = http_serde::query::results::Request::new(query_id);
         let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
 
         let resp = self.request(req).await?;
         if resp.status().is_success() {
-            Ok(body::to_bytes(resp.into_body()).await?)
+            Ok(QueryResults {
+                header: http_serde::query::results::parse_result_header(resp.headers())?,
+                body: resp.into_body(),
+            })
         } else {
             Err(Error::from_failed_resp(resp).await)
         }
     }
+
+    /// Cancel the query on all helpers. The helper that receives this request forwards it to
+    /// the other helpers.
+    ///
+    /// ## Errors
+    /// If the request has illegal arguments, or fails to deliver to helper
+    #[cfg(any(all(test, not(feature = "shuttle")), feature = "cli"))]
+    pub async fn kill_query(&self, query_id: QueryId) -> Result<(), Error> {
+        let req = http_serde::query::kill::Request::new(query_id);
+        let req = req.try_into_http_request(self.scheme.clone(), self.authority.clone())?;
+        let resp = self.request(req).await?;
+        Self::resp_ok(resp).await
+    }
+}
+
+/// Results of a query received from one helper.
+#[cfg(any(all(test, not(feature = "shuttle")), feature = "cli"))]
+pub struct QueryResults {
+    /// Describes how to decode the results.
+    pub header: crate::query::ResultHeader,
+    body: Body,
+}
+
+#[cfg(any(all(test, not(feature = "shuttle")), feature = "cli"))]
+impl QueryResults {
+    /// Returns the stream of serialized records. Chunk boundaries do not have to be aligned with
+    /// the records.
+    pub fn into_body(self) -> Body {
+        self.body
+    }
+
+    /// Waits for all results to arrive.
+    ///
+    /// ## Errors
+    /// If the connection to the helper fails before all results are received.
+    pub async fn into_bytes(self) -> Result<body::Bytes, Error> {
+        Ok(body::to_bytes(self.body).await?)
+    }
 }
 
 fn make_http_connector() -> HttpConnector {
@@ -429,17 +665,19 @@ pub(crate) mod tests {
     };
 
     use futures::stream::{once, poll_immediate};
+    use rand::{rngs::StdRng, SeedableRng};
 
     use super::*;
     use crate::{
         ff::{FieldType, Fp31},
         helpers::{
-            query::QueryType::TestMultiply, BytesStream, RoleAssignment, Transport,
-            TransportCallbacks, MESSAGE_PAYLOAD_SIZE_BYTES,
+            query::QueryType::TestMultiply, BytesStream, ChannelProgress, QueryProgress, Role,
+            RoleAssignment, Transport, TransportCallbacks, MESSAGE_PAYLOAD_SIZE_BYTES,
         },
+        hpke::KeyRegistry,
         net::{test::TestServer, HttpTransport},
         protocol::step::StepNarrow,
-        query::ProtocolResult,
+        query::{ProtocolResult, QueryStatus, QueryStatusReport},
         secret_sharing::replicated::semi_honest::AdditiveShare as Replicated,
         sync::Arc,
     };
@@ -456,15 +694,29 @@ pub(crate) mod tests {
         fn wrap<T: 'static>(inner: &Arc<TransportCallbacks<T>>) -> TransportCallbacks<T> {
             let ri = Arc::clone(inner);
             let pi = Arc::clone(inner);
+            let aqi = Arc::clone(inner);
             let qi = Arc::clone(inner);
+            let qci = Arc::clone(inner);
+            let ici = Arc::clone(inner);
             let si = Arc::clone(inner);
             let ci = Arc::clone(inner);
+            let ki = Arc::clone(inner);
+            let ai = Arc::clone(inner);
+            let rki = Arc::clone(inner);
+            let pki = Arc::clone(inner);
             TransportCallbacks {
                 receive_query: Box::new(move |t, req| (ri.receive_query)(t, req)),
                 prepare_query: Box::new(move |t, req| (pi.prepare_query)(t, req)),
+                authorize_query: Box::new(move |t, id, rc| (aqi.authorize_query)(t, id, rc)),
                 query_input: Box::new(move |t, req| (qi.query_input)(t, req)),
+                query_input_chunk: Box::new(move |t, req| (qci.query_input_chunk)(t, req)),
+                input_chunks: Box::new(move |t, req| (ici.input_chunks)(t, req)),
                 query_status: Box::new(move |t, req| (si.query_status)(t, req)),
                 complete_query: Box::new(move |t, req| (ci.complete_query)(t, req)),
+                kill_query: Box::new(move |t, req| (ki.kill_query)(t, req)),
+                abort_query: Box::new(move |t, req| (ai.abort_query)(t, req)),
+                reload_keys: Box::new(move |t| (rki.reload_keys)(t)),
+                public_keys: Box::new(move |t| (pki.public_keys)(t)),
             }
         }
 
@@ -494,6 +746,84 @@ pub(crate) mod tests {
         assert!(matches!(res, Err(Error::ConnectError { inner: e, .. }) if e.is_connect()));
     }
 
+    #[tokio::test]
+    async fn retries_transient_errors() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        // accepts connections and closes them right away
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicU32::new(0));
+        let server = tokio::spawn({
+            let connections = Arc::clone(&connections);
+            async move {
+                loop {
+                    drop(listener.accept().await.unwrap());
+                    connections.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        });
+
+        let retry = RetryConfig {
+            max_attempts: 3,
+            initial_backoff: std::time::Duration::from_millis(1),
+            ..RetryConfig::default()
+        };
+        let peer_config = PeerConfig {
+            url: format!("http://localhost:{port}").parse().unwrap(),
+            certificate: None,
+            hpke_config: None,
+        };
+        let client = MpcHelperClient::new(
+            &ClientConfig::default().with_retry(retry),
+            peer_config,
+            ClientIdentity::Helper(HelperIdentity::ONE),
+        );
+        let err = client
+            .prepare_query(PrepareQuery {
+                query_id: QueryId::from(1),
+                config: QueryConfig::new(TestMultiply, FieldType::Fp31, 1).unwrap(),
+                roles: RoleAssignment::new(HelperIdentity::make_three()),
+            })
+            .await
+            .unwrap_err();
+        server.abort();
+
+        assert!(err.is_transient(), "{err:?}");
+        assert_eq!(3, connections.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn resumable_step() {
+        let TestServer {
+            client, transport, ..
+        } = TestServer::builder().build().await;
+        let step = Gate::default().narrow("test-step");
+        let payload = (0..4)
+            .map(|i| vec![i; MESSAGE_PAYLOAD_SIZE_BYTES])
+            .collect::<Vec<_>>();
+
+        let mut stream =
+            Arc::clone(&transport).receive(HelperIdentity::ONE, (QueryId::from(1), step.clone()));
+        let (sent, received) = futures::join!(
+            client.resumable_step(
+                QueryId::from(1),
+                &step,
+                futures::stream::iter(payload.clone())
+            ),
+            async {
+                let mut received = Vec::new();
+                while let Some(chunk) = stream.next().await {
+                    received.extend(chunk);
+                }
+                received
+            }
+        );
+
+        sent.unwrap();
+        assert_eq!(payload.concat(), received);
+    }
+
     /// tests that a query command runs as expected. Since query commands require the server to
     /// actively respond to a client request, the test must handle both ends of the request
     /// simultaneously. That means taking the client behavior (`clientf`) and the server behavior
@@ -554,7 +884,7 @@ pub(crate) mod tests {
 
     #[tokio::test]
     async fn create() {
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::from(1);
         let expected_query_config = QueryConfig::new(TestMultiply, FieldType::Fp31, 1).unwrap();
 
         let cb = TransportCallbacks {
@@ -575,7 +905,7 @@ pub(crate) mod tests {
     #[tokio::test]
     async fn prepare() {
         let input = PrepareQuery {
-            query_id: QueryId,
+            query_id: QueryId::from(1),
             config: QueryConfig::new(TestMultiply, FieldType::Fp31, 1).unwrap(),
             roles: RoleAssignment::new(HelperIdentity::make_three()),
         };
@@ -599,7 +929,7 @@ pub(crate) mod tests {
 
     #[tokio::test]
     async fn input() {
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::from(1);
         let expected_input = &[8u8; 25];
         let cb = TransportCallbacks {
             query_input: Box::new(move |_transport, query_input| {
@@ -624,12 +954,64 @@ pub(crate) mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn upload_input_chunks() {
+        let expected_query_id = QueryId::from(1);
+        let cb = TransportCallbacks {
+            input_chunks: Box::new(move |_transport, query_id| {
+                Box::pin(async move {
+                    assert_eq!(query_id, expected_query_id);
+                    Ok(ReceivedInputChunks {
+                        chunks: vec![0],
+                        records: 1,
+                        expected_records: 3,
+                    })
+                })
+            }),
+            query_input_chunk: Box::new(move |_transport, chunk| {
+                Box::pin(async move {
+                    // chunk 0 has been received already
+                    assert_eq!(chunk.index, 1);
+                    assert!(chunk.is_intact());
+                    Ok(ReceivedInputChunks {
+                        chunks: vec![0, 1],
+                        records: 3,
+                        expected_records: 3,
+                    })
+                })
+            }),
+            ..Default::default()
+        };
+        let received = test_query_command(
+            |client| async move {
+                let chunks = [(1, vec![1u8; 2]), (2, vec![2u8; 4])]
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, (records, data))| {
+                        QueryInputChunk::new(
+                            expected_query_id,
+                            u32::try_from(index).unwrap(),
+                            records,
+                            data.into(),
+                        )
+                    });
+                client
+                    .upload_input_chunks(expected_query_id, chunks)
+                    .await
+                    .unwrap()
+            },
+            cb,
+        )
+        .await;
+        assert!(received.is_complete());
+    }
+
     #[tokio::test]
     async fn step() {
         let TestServer {
             client, transport, ..
         } = TestServer::builder().build().await;
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::from(1);
         let expected_step = Gate::default().narrow("test-step");
         let expected_payload = vec![7u8; MESSAGE_PAYLOAD_SIZE_BYTES];
 
@@ -645,8 +1027,10 @@ pub(crate) mod tests {
 
         MpcHelperClient::resp_ok(resp).await.unwrap();
 
-        let mut stream =
-            Arc::clone(&transport).receive(HelperIdentity::ONE, (QueryId, expected_step.clone()));
+        let mut stream = Arc::clone(&transport).receive(
+            HelperIdentity::ONE,
+            (QueryId::from(1), expected_step.clone()),
+        );
 
         assert_eq!(
             poll_immediate(&mut stream).next().await,
@@ -654,13 +1038,50 @@ pub(crate) mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn status() {
+        let expected_query_id = QueryId::from(1);
+        let expected_report = QueryStatusReport {
+            status: QueryStatus::Running,
+            progress: Some(QueryProgress {
+                current_step: Some("compute_prf".to_string()),
+                elapsed_ms: 1500,
+                records_sent: 10,
+                records_received: 8,
+                bytes_sent: 40,
+                bytes_received: 32,
+                channels: vec![ChannelProgress {
+                    step: "protocol/compute_prf".to_string(),
+                    peer: Role::H2,
+                    total_records: Some(20),
+                    records_sent: 10,
+                    records_received: 8,
+                }],
+            }),
+        };
+        let report = expected_report.clone();
+        let cb = TransportCallbacks {
+            query_status: Box::new(move |_transport, query_id| {
+                assert_eq!(query_id, expected_query_id);
+                Box::pin(ready(Ok(report.clone())))
+            }),
+            ..Default::default()
+        };
+        let report = test_query_command(
+            |client| async move { client.query_status(expected_query_id).await.unwrap() },
+            cb,
+        )
+        .await;
+        assert_eq!(expected_report, report);
+    }
+
     #[tokio::test]
     async fn results() {
         let expected_results = Box::new(vec![Replicated::from((
             Fp31::try_from(1u128).unwrap(),
             Fp31::try_from(2u128).unwrap(),
         ))]);
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::from(1);
         let raw_results = expected_results.to_vec();
         let cb = TransportCallbacks {
             complete_query: Box::new(move |_transport, query_id| {
@@ -670,11 +1091,88 @@ pub(crate) mod tests {
             }),
             ..Default::default()
         };
-        let results = test_query_command(
-            |client| async move { client.query_results(expected_query_id).await.unwrap() },
+        let (header, results) = test_query_command(
+            |client| async move {
+                let results = client.query_results(expected_query_id).await.unwrap();
+                (results.header.clone(), results.into_bytes().await.unwrap())
+            },
             cb,
         )
         .await;
+        assert_eq!(1, header.records);
+        assert_eq!(2, header.record_size);
         assert_eq!(results.to_vec(), expected_results.into_bytes());
     }
+
+    #[tokio::test]
+    async fn kill() {
+        let expected_query_id = QueryId::from(1);
+        let cb = TransportCallbacks {
+            kill_query: Box::new(move |_transport, query_id| {
+                assert_eq!(query_id, expected_query_id);
+                Box::pin(ready(Ok(())))
+            }),
+            ..Default::default()
+        };
+        test_query_command(
+            |client| async move { client.kill_query(expected_query_id).await.unwrap() },
+            cb,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn abort() {
+        let expected_query_id = QueryId::from(1);
+        let cb = TransportCallbacks {
+            abort_query: Box::new(move |_transport, query_id| {
+                assert_eq!(query_id, expected_query_id);
+                Box::pin(ready(Ok(())))
+            }),
+            ..Default::default()
+        };
+        test_query_command(
+            |client| async move { client.abort_query(expected_query_id).await.unwrap() },
+            cb,
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn public_keys() {
+        let cb = TransportCallbacks {
+            public_keys: Box::new(|_transport| {
+                let mut rng = StdRng::seed_from_u64(42);
+                Box::pin(ready(KeyRegistry::random(2, &mut rng).public_keys()))
+            }),
+            ..Default::default()
+        };
+        let key_ids = test_query_command(
+            |client| async move {
+                client
+                    .public_keys(None)
+                    .await
+                    .unwrap()
+                    .into_keys()
+                    .collect::<Vec<_>>()
+            },
+            cb,
+        )
+        .await;
+        assert_eq!(vec![0, 1], key_ids);
+    }
+
+    #[tokio::test]
+    async fn reload_keys() {
+        let cb = TransportCallbacks {
+            reload_keys: Box::new(|_transport| Box::pin(ready(Ok(vec![0, 3])))),
+            ..Default::default()
+        };
+        let key_ids = test_query_command(
+            |client| async move { client.reload_keys().await.unwrap() },
+            cb,
+        )
+        .await;
+        assert_eq!(vec![0, 3], key_ids);
+    }
 }
@@ -25,7 +25,7 @@ pub enum Error {
     BodyAlreadyExtracted(#[from] axum::extract::rejection::BodyAlreadyExtracted),
     #[error(transparent)]
     MissingExtension(#[from] axum::extract::rejection::ExtensionRejection),
-    #[error("query id not found: {}", .0.as_ref())]
+    #[error("query id not found: {0}")]
     QueryIdNotFound(QueryId),
     #[error(transparent)]
     HyperPassthrough(#[from] hyper::Error),
@@ -37,6 +37,8 @@ pub enum Error {
     SerdePassthrough(#[from] serde_json::Error),
     #[error(transparent)]
     InvalidUri(#[from] hyper::http::uri::InvalidUri),
+    #[error("invalid public keys: {0}")]
+    InvalidPublicKeys(String),
     // `FailedHttpRequest` and `Application` are for the same errors, with slightly different
     // representation. Server side code uses `Application` and client side code uses
     // `FailedHttpRequest`.
@@ -86,6 +88,25 @@ impl Error {
             })
     }
 
+    /// Returns `true` if the request that failed with this error may succeed if it is sent again,
+    /// because the connection to the other side failed or the other side is temporarily
+    /// unavailable.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::HyperPassthrough(e) | Self::ConnectError { inner: e, .. } => {
+                !(e.is_parse() || e.is_user())
+            }
+            Self::FailedHttpRequest { status, .. } => matches!(
+                *status,
+                StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ),
+            _ => false,
+        }
+    }
+
     #[must_use]
     pub fn application<E: Into<BoxError>>(code: StatusCode, error: E) -> Self {
         Self::Application {
@@ -132,6 +153,13 @@ impl From<axum::extract::rejection::PathRejection> for Error {
     }
 }
 
+/// [`From`] implementation for `Error::AxumPassthrough`
+impl From<axum::extract::rejection::BytesRejection> for Error {
+    fn from(err: axum::extract::rejection::BytesRejection) -> Self {
+        Self::AxumPassthrough(axum::Error::new(err))
+    }
+}
+
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
         let status_code = match self {
@@ -151,6 +179,7 @@ impl IntoResponse for Error {
             | Self::HyperHttpPassthrough(_)
             | Self::FailedHttpRequest { .. }
             | Self::InvalidUri(_)
+            | Self::InvalidPublicKeys(_)
             | Self::BodyAlreadyExtracted(_)
             | Self::MissingExtension(_) => StatusCode::INTERNAL_SERVER_ERROR,
 
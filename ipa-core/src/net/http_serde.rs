@@ -87,8 +87,11 @@ pub mod query {
 
     use crate::{
         ff::FieldType,
-        helpers::query::{QueryConfig, QuerySize, QueryType},
+        helpers::query::{
+            InvalidReportPolicy, QueryConfig, QuerySize, QueryType, ReportCollectorId,
+        },
         net::Error,
+        report::ReportVersion,
     };
 
     /// wrapper around [`QueryConfig`] to enable extraction from an `Axum` request. To be used with
@@ -113,11 +116,14 @@ pub mod query {
                 size: QuerySize,
                 field_type: FieldType,
                 query_type: String,
+                #[serde(default)]
+                report_collector: ReportCollectorId,
             }
             let Query(QueryTypeParam {
                 size,
                 field_type,
                 query_type,
+                report_collector,
             }) = req.extract().await?;
 
             let query_type = match query_type.as_str() {
@@ -143,13 +149,23 @@ pub mod query {
                     let Query(q) = req.extract().await?;
                     Ok(QueryType::OprfIpa(q))
                 }
+                QueryType::FEATURE_LABEL_DOT_PRODUCT_STR => {
+                    let Query(q) = req.extract().await?;
+                    Ok(QueryType::FeatureLabelDotProduct(q))
+                }
                 other => Err(Error::bad_query_value("query_type", other)),
             }?;
-            Ok(QueryConfigQueryParams(QueryConfig {
+            let config = QueryConfig {
                 size,
                 field_type,
                 query_type,
-            }))
+                report_collector,
+            };
+            config
+                .validate()
+                .map_err(|e| Error::BadQueryString(e.into()))?;
+
+            Ok(QueryConfigQueryParams(config))
         }
     }
 
@@ -162,6 +178,9 @@ pub mod query {
                 f = self.field_type,
                 size = self.size
             )?;
+            if self.report_collector != ReportCollectorId::default() {
+                write!(f, "&report_collector={}", self.report_collector)?;
+            }
             match self.query_type {
                 #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
                 QueryType::TestMultiply => Ok(()),
@@ -170,8 +189,16 @@ pub mod query {
                 | QueryType::OprfIpa(config) => {
                     write!(
                         f,
-                        "&per_user_credit_cap={}&max_breakdown_key={}&num_multi_bits={}",
-                        config.per_user_credit_cap, config.max_breakdown_key, config.num_multi_bits,
+                        "&per_user_credit_cap={}&max_breakdown_key={}&num_multi_bits={}&epoch={}",
+                        config.per_user_credit_cap,
+                        config.max_breakdown_key,
+                        config.num_multi_bits,
+                        config.epoch,
+                    )?;
+                    write!(
+                        f,
+                        "&breakdown_key_bits={}&trigger_value_bits={}&timestamp_bits={}",
+                        config.breakdown_key_bits, config.trigger_value_bits, config.timestamp_bits,
                     )?;
 
                     if config.plaintext_match_keys {
@@ -182,6 +209,38 @@ pub mod query {
                         write!(f, "&attribution_window_seconds={}", window.get())?;
                     }
 
+                    if let Some(epoch) = config.min_report_epoch {
+                        write!(f, "&min_report_epoch={epoch}")?;
+                    }
+
+                    if let Some(epoch) = config.max_report_epoch {
+                        write!(f, "&max_report_epoch={epoch}")?;
+                    }
+
+                    if config.invalid_report_policy != InvalidReportPolicy::default() {
+                        write!(f, "&invalid_report_policy={}", config.invalid_report_policy)?;
+                    }
+
+                    if config.report_version != ReportVersion::default() {
+                        write!(f, "&report_version={}", config.report_version)?;
+                    }
+
+                    if let Some(epsilon) = config.dummy_records_epsilon {
+                        write!(
+                            f,
+                            "&dummy_records_epsilon={epsilon}&dummy_records_delta={}",
+                            config.dummy_records_delta
+                        )?;
+                    }
+
+                    if let Some(epsilon) = config.output_noise_epsilon {
+                        write!(
+                            f,
+                            "&output_noise_epsilon={epsilon}&output_noise_delta={}",
+                            config.output_noise_delta
+                        )?;
+                    }
+
                     Ok(())
                 }
                 QueryType::SemiHonestSparseAggregate(config)
@@ -192,6 +251,15 @@ pub mod query {
                         config.contribution_bits, config.num_contributions,
                     )?;
 
+                    Ok(())
+                }
+                QueryType::FeatureLabelDotProduct(config) => {
+                    write!(
+                        f,
+                        "&feature_vector_bits={}&per_user_credit_cap={}",
+                        config.feature_vector_bits, config.per_user_credit_cap,
+                    )?;
+
                     Ok(())
                 }
             }
@@ -297,7 +365,7 @@ pub mod query {
                     .path_and_query(format!(
                         "{}/{}?{}",
                         BASE_AXUM_PATH,
-                        self.data.query_id.as_ref(),
+                        self.data.query_id,
                         QueryConfigQueryParams(self.data.config),
                     ))
                     .build()?;
@@ -373,8 +441,7 @@ pub mod query {
                     .authority(authority)
                     .path_and_query(format!(
                         "{}/{}/input",
-                        BASE_AXUM_PATH,
-                        self.query_input.query_id.as_ref(),
+                        BASE_AXUM_PATH, self.query_input.query_id,
                     ))
                     .build()?;
                 let body = Body::wrap_stream(self.query_input.input_stream);
@@ -402,6 +469,157 @@ pub mod query {
         }
 
         pub const AXUM_PATH: &str = "/:query_id/input";
+
+        /// Upload of one [`QueryInputChunk`]. The number of records in the chunk and its
+        /// checksum are sent in the headers. Sending the same chunk more than once is allowed, so
+        /// the request uses `PUT`.
+        ///
+        /// The response is the JSON-encoded [`ReceivedInputChunks`].
+        ///
+        /// [`QueryInputChunk`]: crate::helpers::query::QueryInputChunk
+        /// [`ReceivedInputChunks`]: crate::helpers::query::ReceivedInputChunks
+        pub mod chunk {
+            use async_trait::async_trait;
+            use axum::{
+                extract::{FromRequest, Path, RequestParts},
+                http::uri,
+            };
+            use bytes::Bytes;
+            use hyper::{
+                header::{HeaderName, CONTENT_TYPE},
+                Body,
+            };
+
+            use crate::{
+                helpers::query::{InputChecksum, QueryInputChunk},
+                net::{http_serde::query::BASE_AXUM_PATH, Error},
+                protocol::QueryId,
+            };
+
+            /// The number of input records in the chunk.
+            pub static RECORDS_HEADER: HeaderName = HeaderName::from_static("x-ipa-chunk-records");
+
+            /// Hex-encoded SHA-256 digest of the chunk data.
+            pub static CHECKSUM_HEADER: HeaderName =
+                HeaderName::from_static("x-ipa-chunk-checksum");
+
+            #[derive(Debug)]
+            pub struct Request {
+                pub chunk: QueryInputChunk,
+            }
+
+            impl Request {
+                pub fn new(chunk: QueryInputChunk) -> Self {
+                    Self { chunk }
+                }
+
+                pub fn try_into_http_request(
+                    self,
+                    scheme: uri::Scheme,
+                    authority: uri::Authority,
+                ) -> Result<hyper::Request<Body>, Error> {
+                    let uri = uri::Uri::builder()
+                        .scheme(scheme)
+                        .authority(authority)
+                        .path_and_query(format!(
+                            "{}/{}/input/{}",
+                            BASE_AXUM_PATH, self.chunk.query_id, self.chunk.index,
+                        ))
+                        .build()?;
+                    Ok(hyper::Request::put(uri)
+                        .header(CONTENT_TYPE, "application/octet-stream")
+                        .header(&RECORDS_HEADER, self.chunk.records)
+                        .header(&CHECKSUM_HEADER, self.chunk.checksum.to_string())
+                        .body(Body::from(self.chunk.data))?)
+                }
+            }
+
+            #[async_trait]
+            impl FromRequest<Body> for Request {
+                type Rejection = Error;
+
+                async fn from_request(
+                    req: &mut RequestParts<Body>,
+                ) -> Result<Self, Self::Rejection> {
+                    let Path((query_id, index)) = req.extract::<Path<(QueryId, u32)>>().await?;
+                    let header = |name: &HeaderName| {
+                        req.headers()
+                            .get(name)
+                            .ok_or_else(|| Error::MissingHeader(name.to_string()))
+                    };
+                    let records = header(&RECORDS_HEADER)?.to_str()?.parse()?;
+                    let checksum = header(&CHECKSUM_HEADER)?
+                        .to_str()?
+                        .parse::<InputChecksum>()
+                        .map_err(|e| Error::InvalidHeader(e.into()))?;
+                    let data = req.extract::<Bytes>().await?;
+
+                    Ok(Self {
+                        chunk: QueryInputChunk {
+                            query_id,
+                            index,
+                            records,
+                            checksum,
+                            data,
+                        },
+                    })
+                }
+            }
+
+            pub const AXUM_PATH: &str = "/:query_id/input/:index";
+        }
+
+        /// Asks which chunks of the query input the helper has received, so that an interrupted
+        /// upload can be resumed. This is a `GET` request to the input path. The response is the
+        /// JSON-encoded [`ReceivedInputChunks`].
+        ///
+        /// [`ReceivedInputChunks`]: crate::helpers::query::ReceivedInputChunks
+        pub mod chunks {
+            use async_trait::async_trait;
+            use axum::{
+                extract::{FromRequest, Path, RequestParts},
+                http::uri,
+            };
+
+            use crate::{
+                net::{http_serde::query::BASE_AXUM_PATH, Error},
+                protocol::QueryId,
+            };
+
+            #[derive(Debug, Clone)]
+            pub struct Request {
+                pub query_id: QueryId,
+            }
+
+            impl Request {
+                pub fn new(query_id: QueryId) -> Self {
+                    Self { query_id }
+                }
+
+                pub fn try_into_http_request(
+                    self,
+                    scheme: uri::Scheme,
+                    authority: uri::Authority,
+                ) -> Result<hyper::Request<hyper::Body>, Error> {
+                    let uri = uri::Uri::builder()
+                        .scheme(scheme)
+                        .authority(authority)
+                        .path_and_query(format!("{}/{}/input", BASE_AXUM_PATH, self.query_id))
+                        .build()?;
+                    Ok(hyper::Request::get(uri).body(hyper::Body::empty())?)
+                }
+            }
+
+            #[async_trait]
+            impl<B: Send> FromRequest<B> for Request {
+                type Rejection = Error;
+
+                async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+                    let Path(query_id) = req.extract().await?;
+                    Ok(Request { query_id })
+                }
+            }
+        }
     }
 
     pub mod step {
@@ -411,18 +629,26 @@ pub mod query {
             http::uri,
         };
 
+        use hyper::header::HeaderName;
+
         use crate::{
             helpers::BodyStream,
             net::{http_serde::query::BASE_AXUM_PATH, Error},
             protocol::{step::Gate, QueryId},
         };
 
+        /// Position of the request body in the data of the step, in bytes. Requests that resume
+        /// sending the data after a broken connection start past the beginning of it. Missing
+        /// header means zero.
+        pub static OFFSET_HEADER: HeaderName = HeaderName::from_static("x-ipa-step-offset");
+
         // When this type is used on the client side, `B` is `hyper::Body`. When this type
         // is used on the server side, `B` can be any body type supported by axum.
         #[derive(Debug)]
         pub struct Request<B> {
             pub query_id: QueryId,
             pub gate: Gate,
+            pub offset: u64,
             pub body: B,
         }
 
@@ -431,9 +657,16 @@ pub mod query {
                 Self {
                     query_id,
                     gate,
+                    offset: 0,
                     body,
                 }
             }
+
+            #[must_use]
+            pub fn with_offset(mut self, offset: u64) -> Self {
+                self.offset = offset;
+                self
+            }
         }
 
         /// Convert to hyper request. Used on client side.
@@ -449,11 +682,15 @@ pub mod query {
                     .path_and_query(format!(
                         "{}/{}/step/{}",
                         BASE_AXUM_PATH,
-                        self.query_id.as_ref(),
+                        self.query_id,
                         self.gate.as_ref()
                     ))
                     .build()?;
-                Ok(hyper::Request::post(uri).body(self.body)?)
+                let mut req = hyper::Request::post(uri);
+                if self.offset > 0 {
+                    req = req.header(&OFFSET_HEADER, self.offset);
+                }
+                Ok(req.body(self.body)?)
             }
         }
 
@@ -473,10 +710,20 @@ pub mod query {
             // Error. Writing `Path` twice somehow avoids that.
             async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
                 let Path((query_id, gate)) = req.extract::<Path<_>>().await?;
+                let offset = match req.headers().get(&OFFSET_HEADER) {
+                    // `Error::from` does not resolve here because of the bound on `Error` above
+                    Some(offset) => offset
+                        .to_str()
+                        .map_err(|e| Error::InvalidHeader(e.into()))?
+                        .parse::<u64>()
+                        .map_err(|e| Error::InvalidHeader(e.into()))?,
+                    None => 0,
+                };
                 let body = req.extract().await?;
                 Ok(Self {
                     query_id,
                     gate,
+                    offset,
                     body,
                 })
             }
@@ -490,7 +737,12 @@ pub mod query {
         use axum::extract::{FromRequest, Path, RequestParts};
         use serde::{Deserialize, Serialize};
 
-        use crate::{net::Error, protocol::QueryId, query::QueryStatus};
+        use crate::{
+            helpers::QueryProgress,
+            net::Error,
+            protocol::QueryId,
+            query::{QueryStatus, QueryStatusReport},
+        };
 
         #[derive(Debug, Clone)]
         pub struct Request {
@@ -515,7 +767,7 @@ pub mod query {
                     .path_and_query(format!(
                         "{}/{}",
                         crate::net::http_serde::query::BASE_AXUM_PATH,
-                        self.query_id.as_ref()
+                        self.query_id
                     ))
                     .build()?;
                 Ok(hyper::Request::get(uri).body(hyper::Body::empty())?)
@@ -535,6 +787,27 @@ pub mod query {
         #[derive(Clone, Debug, Serialize, Deserialize)]
         pub struct ResponseBody {
             pub status: QueryStatus,
+            /// Data exchanged with other helpers so far. Only reported for running queries.
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub progress: Option<QueryProgress>,
+        }
+
+        impl From<QueryStatusReport> for ResponseBody {
+            fn from(report: QueryStatusReport) -> Self {
+                Self {
+                    status: report.status,
+                    progress: report.progress,
+                }
+            }
+        }
+
+        impl From<ResponseBody> for QueryStatusReport {
+            fn from(body: ResponseBody) -> Self {
+                Self {
+                    status: body.status,
+                    progress: body.progress,
+                }
+            }
         }
 
         pub const AXUM_PATH: &str = "/:query_id";
@@ -543,8 +816,29 @@ pub mod query {
     pub mod results {
         use async_trait::async_trait;
         use axum::extract::{FromRequest, Path, RequestParts};
+        use hyper::{header::HeaderName, http::HeaderValue, HeaderMap};
 
-        use crate::{net::Error, protocol::QueryId};
+        use crate::{net::Error, protocol::QueryId, query::ResultHeader};
+
+        /// Response header that describes how to decode the query results, see [`ResultHeader`].
+        /// Its value is a JSON object.
+        pub static RESULT_HEADER: HeaderName = HeaderName::from_static("x-ipa-result");
+
+        /// ## Panics
+        /// Never, JSON-encoded result header is always a valid header value.
+        #[must_use]
+        pub fn result_header_value(header: &ResultHeader) -> HeaderValue {
+            HeaderValue::from_str(&serde_json::to_string(header).unwrap()).unwrap()
+        }
+
+        /// ## Errors
+        /// If the response does not have the result header or it cannot be parsed.
+        pub fn parse_result_header(headers: &HeaderMap) -> Result<ResultHeader, Error> {
+            let value = headers
+                .get(&RESULT_HEADER)
+                .ok_or_else(|| Error::MissingHeader(RESULT_HEADER.to_string()))?;
+            Ok(serde_json::from_slice(value.as_bytes())?)
+        }
 
         #[derive(Debug, Clone)]
         pub struct Request {
@@ -569,7 +863,7 @@ pub mod query {
                     .path_and_query(format!(
                         "{}/{}/complete",
                         crate::net::http_serde::query::BASE_AXUM_PATH,
-                        self.query_id.as_ref()
+                        self.query_id
                     ))
                     .build()?;
                 Ok(hyper::Request::get(uri).body(hyper::Body::empty())?)
@@ -588,4 +882,349 @@ pub mod query {
 
         pub const AXUM_PATH: &str = "/:query_id/complete";
     }
+
+    pub mod kill {
+        use async_trait::async_trait;
+        use axum::extract::{FromRequest, Path, RequestParts};
+
+        use crate::{net::Error, protocol::QueryId};
+
+        #[derive(Debug, Clone)]
+        pub struct Request {
+            pub query_id: QueryId,
+        }
+
+        impl Request {
+            #[cfg(any(all(test, not(feature = "shuttle")), feature = "cli"))] // needed because client is blocking; remove when non-blocking
+            pub fn new(query_id: QueryId) -> Self {
+                Self { query_id }
+            }
+
+            #[cfg(any(all(test, not(feature = "shuttle")), feature = "cli"))] // needed because client is blocking; remove when non-blocking
+            pub fn try_into_http_request(
+                self,
+                scheme: axum::http::uri::Scheme,
+                authority: axum::http::uri::Authority,
+            ) -> Result<hyper::Request<hyper::Body>, Error> {
+                let uri = axum::http::uri::Uri::builder()
+                    .scheme(scheme)
+                    .authority(authority)
+                    .path_and_query(format!(
+                        "{}/{}/kill",
+                        crate::net::http_serde::query::BASE_AXUM_PATH,
+                        self.query_id
+                    ))
+                    .build()?;
+                Ok(hyper::Request::post(uri).body(hyper::Body::empty())?)
+            }
+        }
+
+        #[async_trait]
+        impl<B: Send> FromRequest<B> for Request {
+            type Rejection = Error;
+
+            async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+                let Path(query_id) = req.extract().await?;
+                Ok(Request { query_id })
+            }
+        }
+
+        pub const AXUM_PATH: &str = "/:query_id/kill";
+    }
+
+    pub mod abort {
+        use async_trait::async_trait;
+        use axum::{
+            extract::{FromRequest, Path, RequestParts},
+            http::uri,
+        };
+
+        use crate::{
+            net::{http_serde::query::BASE_AXUM_PATH, Error},
+            protocol::QueryId,
+        };
+
+        #[derive(Debug, Clone)]
+        pub struct Request {
+            pub query_id: QueryId,
+        }
+
+        impl Request {
+            pub fn new(query_id: QueryId) -> Self {
+                Self { query_id }
+            }
+
+            pub fn try_into_http_request(
+                self,
+                scheme: uri::Scheme,
+                authority: uri::Authority,
+            ) -> Result<hyper::Request<hyper::Body>, Error> {
+                let uri = uri::Uri::builder()
+                    .scheme(scheme)
+                    .authority(authority)
+                    .path_and_query(format!("{}/{}/abort", BASE_AXUM_PATH, self.query_id))
+                    .build()?;
+                Ok(hyper::Request::post(uri).body(hyper::Body::empty())?)
+            }
+        }
+
+        #[async_trait]
+        impl<B: Send> FromRequest<B> for Request {
+            type Rejection = Error;
+
+            async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+                let Path(query_id) = req.extract().await?;
+                Ok(Request { query_id })
+            }
+        }
+
+        pub const AXUM_PATH: &str = "/:query_id/abort";
+    }
+}
+
+pub mod keys {
+    pub const BASE_AXUM_PATH: &str = "/keys";
+
+    pub mod reload {
+        use axum::http::uri;
+        use serde::{Deserialize, Serialize};
+
+        use crate::{
+            net::{http_serde::keys::BASE_AXUM_PATH, Error},
+            report::KeyIdentifier,
+        };
+
+        /// The request does not have any parameters.
+        pub fn http_request(
+            scheme: uri::Scheme,
+            authority: uri::Authority,
+        ) -> Result<hyper::Request<hyper::Body>, Error> {
+            let uri = uri::Uri::builder()
+                .scheme(scheme)
+                .authority(authority)
+                .path_and_query(format!("{BASE_AXUM_PATH}{AXUM_PATH}"))
+                .build()?;
+            Ok(hyper::Request::post(uri).body(hyper::Body::empty())?)
+        }
+
+        #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+        pub struct ResponseBody {
+            /// Identifiers of the keys the helper accepts after the reload.
+            pub key_ids: Vec<KeyIdentifier>,
+        }
+
+        pub const AXUM_PATH: &str = "/reload";
+    }
+
+    pub mod public {
+        use std::{
+            collections::BTreeMap,
+            time::{Duration, SystemTime, UNIX_EPOCH},
+        };
+
+        use axum::http::uri;
+        use rustls::{sign::SigningKey, Certificate, SignatureScheme};
+        use serde::{Deserialize, Serialize};
+
+        use crate::{
+            hpke::{
+                Deserializable, IpaPublicKey, KeyRegistry, PublicKeyOnly, PublicKeyRegistry,
+                Serializable,
+            },
+            net::{http_serde::keys::BASE_AXUM_PATH, Error},
+            report::KeyIdentifier,
+        };
+
+        /// How long report collectors may use the public keys after they fetched them.
+        pub const VALIDITY: Duration = Duration::from_secs(24 * 60 * 60);
+
+        /// How far ahead of the report collector's clock the helper's clock may be.
+        pub const MAX_CLOCK_SKEW: Duration = Duration::from_secs(5 * 60);
+
+        /// Signature schemes helpers may sign public keys with, and the algorithms to verify
+        /// them with.
+        const SIGNATURE_SCHEMES: [(SignatureScheme, &webpki::SignatureAlgorithm); 4] = [
+            (
+                SignatureScheme::ECDSA_NISTP256_SHA256,
+                &webpki::ECDSA_P256_SHA256,
+            ),
+            (
+                SignatureScheme::ECDSA_NISTP384_SHA384,
+                &webpki::ECDSA_P384_SHA384,
+            ),
+            (SignatureScheme::ED25519, &webpki::ED25519),
+            (
+                SignatureScheme::RSA_PSS_SHA256,
+                &webpki::RSA_PSS_2048_8192_SHA256_LEGACY_KEY,
+            ),
+        ];
+
+        pub fn http_request(
+            scheme: uri::Scheme,
+            authority: uri::Authority,
+        ) -> Result<hyper::Request<hyper::Body>, Error> {
+            let uri = uri::Uri::builder()
+                .scheme(scheme)
+                .authority(authority)
+                .path_and_query(format!("{BASE_AXUM_PATH}{AXUM_PATH}"))
+                .build()?;
+            Ok(hyper::Request::get(uri).body(hyper::Body::empty())?)
+        }
+
+        /// Public keys that a helper accepts reports encrypted with, and the period when report
+        /// collectors may use them. Times are in seconds since the Unix epoch.
+        #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+        pub struct PublicKeys {
+            pub keys: Vec<PublicKey>,
+            pub not_before: u64,
+            pub not_after: u64,
+        }
+
+        #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+        pub struct PublicKey {
+            pub key_id: KeyIdentifier,
+            /// Hex-encoded public key
+            pub public_key: String,
+        }
+
+        impl PublicKeys {
+            /// Lists the keys in `registry` as valid for [`VALIDITY`] starting at `now`.
+            pub fn new(registry: &KeyRegistry<PublicKeyOnly>, now: SystemTime) -> Self {
+                let not_before = unix_time(now);
+                Self {
+                    keys: registry
+                        .key_ids()
+                        .map(|key_id| PublicKey {
+                            key_id,
+                            public_key: hex::encode(
+                                registry.public_key(key_id).unwrap().to_bytes(),
+                            ),
+                        })
+                        .collect(),
+                    not_before,
+                    not_after: not_before + VALIDITY.as_secs(),
+                }
+            }
+        }
+
+        #[derive(Clone, Debug, Serialize, Deserialize)]
+        pub struct ResponseBody {
+            /// [`PublicKeys`] serialized as JSON. They stay serialized, so that the signature can
+            /// be verified over the same bytes that the helper signed.
+            pub public_keys: String,
+            /// Signature of `public_keys` made with the TLS key of the helper. Helpers that do not
+            /// use TLS do not sign their public keys.
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub signature: Option<Signature>,
+        }
+
+        #[derive(Clone, Debug, Serialize, Deserialize)]
+        pub struct Signature {
+            /// TLS signature scheme code, as defined by RFC 8446.
+            pub scheme: u16,
+            /// Hex-encoded signature
+            pub value: String,
+        }
+
+        impl ResponseBody {
+            /// Serializes `public_keys` and signs them with `signing_key`, if there is one.
+            ///
+            /// ## Errors
+            /// If the key does not support any of the signature schemes that report collectors
+            /// can verify, or fails to sign.
+            pub fn sign(
+                public_keys: &PublicKeys,
+                signing_key: Option<&dyn SigningKey>,
+            ) -> Result<Self, Error> {
+                let public_keys = serde_json::to_string(public_keys)?;
+                let signature = match signing_key {
+                    None => None,
+                    Some(key) => {
+                        let schemes = SIGNATURE_SCHEMES.map(|(scheme, _)| scheme);
+                        let signer = key.choose_scheme(&schemes).ok_or_else(|| {
+                            Error::InvalidPublicKeys(
+                                "TLS key does not support any of the signature schemes".into(),
+                            )
+                        })?;
+                        let value = signer
+                            .sign(public_keys.as_bytes())
+                            .map_err(|e| Error::InvalidPublicKeys(e.to_string()))?;
+                        Some(Signature {
+                            scheme: signer.scheme().get_u16(),
+                            value: hex::encode(value),
+                        })
+                    }
+                };
+
+                Ok(Self {
+                    public_keys,
+                    signature,
+                })
+            }
+
+            /// Checks that the public keys are signed by the owner of `certificate` and can be
+            /// used at `now`, and returns them. If `certificate` is `None`, the helper is not
+            /// expected to sign them.
+            ///
+            /// ## Errors
+            /// If the signature is missing or invalid, the keys are not valid at `now`, or they
+            /// cannot be parsed.
+            pub fn verify(
+                self,
+                certificate: Option<&Certificate>,
+                now: SystemTime,
+            ) -> Result<BTreeMap<KeyIdentifier, IpaPublicKey>, Error> {
+                let invalid = |reason: String| Error::InvalidPublicKeys(reason);
+                if let Some(certificate) = certificate {
+                    let signature = self
+                        .signature
+                        .as_ref()
+                        .ok_or_else(|| invalid("public keys are not signed".into()))?;
+                    let scheme = SignatureScheme::from(signature.scheme);
+                    let (_, algorithm) = SIGNATURE_SCHEMES
+                        .iter()
+                        .find(|(supported, _)| *supported == scheme)
+                        .ok_or_else(|| {
+                            invalid(format!("unsupported signature scheme {scheme:?}"))
+                        })?;
+                    let value =
+                        hex::decode(&signature.value).map_err(|e| invalid(e.to_string()))?;
+                    webpki::EndEntityCert::try_from(certificate.0.as_slice())
+                        .and_then(|cert| {
+                            cert.verify_signature(algorithm, self.public_keys.as_bytes(), &value)
+                        })
+                        .map_err(|e| invalid(format!("signature is not valid: {e:?}")))?;
+                }
+
+                let public_keys: PublicKeys = serde_json::from_str(&self.public_keys)?;
+                let now = unix_time(now);
+                if now + MAX_CLOCK_SKEW.as_secs() < public_keys.not_before
+                    || public_keys.not_after < now
+                {
+                    return Err(invalid(format!(
+                        "public keys are valid from {} to {}, now is {now}",
+                        public_keys.not_before, public_keys.not_after
+                    )));
+                }
+
+                public_keys
+                    .keys
+                    .into_iter()
+                    .map(|PublicKey { key_id, public_key }| {
+                        let bytes = hex::decode(public_key).map_err(|e| invalid(e.to_string()))?;
+                        let public_key =
+                            IpaPublicKey::from_bytes(&bytes).map_err(|e| invalid(e.to_string()))?;
+                        Ok((key_id, public_key))
+                    })
+                    .collect()
+            }
+        }
+
+        fn unix_time(time: SystemTime) -> u64 {
+            time.duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs())
+        }
+
+        pub const AXUM_PATH: &str = "/public";
+    }
 }
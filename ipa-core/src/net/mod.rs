@@ -2,10 +2,13 @@ mod client;
 mod error;
 mod http_serde;
 mod server;
+mod step_stream;
 #[cfg(all(test, not(feature = "shuttle")))]
 pub mod test;
 mod transport;
 
+#[cfg(any(all(test, not(feature = "shuttle")), feature = "cli"))]
+pub use client::QueryResults;
 pub use client::{ClientIdentity, MpcHelperClient};
 pub use error::Error;
 pub use server::{MpcHelperServer, TracingSpanMaker};
@@ -0,0 +1,243 @@
+use std::time::SystemTime;
+
+use axum::{
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use hyper::StatusCode;
+use rustls::sign::SigningKey;
+
+use crate::{
+    net::{
+        http_serde::{self, keys::public::PublicKeys},
+        server::ClientIdentity,
+        Error, HttpTransport,
+    },
+    query::KeyReloadError,
+    sync::Arc,
+};
+
+/// Called by report collectors to get the public keys to encrypt reports with. The keys are
+/// signed with the TLS key of this helper, so that report collectors can make sure they are
+/// genuine.
+async fn public_handler(
+    transport: Extension<Arc<HttpTransport>>,
+    signing_key: Extension<Option<Arc<dyn SigningKey>>>,
+) -> Result<Json<http_serde::keys::public::ResponseBody>, Error> {
+    let registry = Arc::clone(&transport).public_keys().await;
+    let public_keys = PublicKeys::new(&registry, SystemTime::now());
+    http_serde::keys::public::ResponseBody::sign(&public_keys, signing_key.as_deref())
+        .map(Json)
+        .map_err(|e| Error::application(StatusCode::INTERNAL_SERVER_ERROR, e))
+}
+
+/// Called by helper operators to make the helper pick up the keys that were added to or removed
+/// from its key directory. Operators authenticate with the certificate of one of the helpers.
+async fn reload_handler(
+    transport: Extension<Arc<HttpTransport>>,
+    _from: Extension<ClientIdentity>, // require that client is an authenticated helper
+) -> Result<Json<http_serde::keys::reload::ResponseBody>, Error> {
+    match Arc::clone(&transport).reload_keys().await {
+        Ok(key_ids) => Ok(Json(http_serde::keys::reload::ResponseBody { key_ids })),
+        Err(e @ KeyReloadError::NotConfigured) => {
+            Err(Error::application(StatusCode::NOT_IMPLEMENTED, e))
+        }
+        Err(e @ KeyReloadError::Directory(_)) => {
+            Err(Error::application(StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
+    }
+}
+
+pub fn public_router(
+    transport: Arc<HttpTransport>,
+    signing_key: Option<Arc<dyn SigningKey>>,
+) -> Router {
+    Router::new()
+        .route(http_serde::keys::public::AXUM_PATH, get(public_handler))
+        .layer(Extension(transport))
+        .layer(Extension(signing_key))
+}
+
+pub fn reload_router(transport: Arc<HttpTransport>) -> Router {
+    Router::new()
+        .route(http_serde::keys::reload::AXUM_PATH, post(reload_handler))
+        .layer(Extension(transport))
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::{future::ready, time::Duration};
+
+    use rand::{rngs::StdRng, SeedableRng};
+    use rustls::{sign::any_supported_type, Certificate, PrivateKey};
+
+    use super::*;
+    use crate::{
+        helpers::{HelperIdentity, TransportCallbacks},
+        hpke::{KeyRegistry, PublicKeyRegistry, Serializable},
+        net::{
+            http_serde::keys::public::{ResponseBody, VALIDITY},
+            server::handlers::query::test_helpers::{assert_req_fails_with, IntoFailingReq},
+            test::{TestServer, TEST_CERTS_DER, TEST_KEYS},
+        },
+    };
+
+    fn signing_key(id: usize) -> Arc<dyn SigningKey> {
+        let mut pem = TEST_KEYS[id];
+        let key = match rustls_pemfile::read_one(&mut pem).unwrap() {
+            Some(rustls_pemfile::Item::PKCS8Key(key)) => PrivateKey(key),
+            _ => panic!("test key is not PKCS8"),
+        };
+        any_supported_type(&key).unwrap()
+    }
+
+    fn certificate(id: usize) -> Certificate {
+        Certificate(TEST_CERTS_DER[id].clone())
+    }
+
+    fn public_keys(now: SystemTime) -> PublicKeys {
+        let registry = KeyRegistry::random(2, &mut StdRng::seed_from_u64(42));
+        PublicKeys::new(&registry.public_keys(), now)
+    }
+
+    #[tokio::test]
+    async fn public() {
+        let registry = KeyRegistry::random(2, &mut StdRng::seed_from_u64(42)).public_keys();
+        let expected = registry
+            .key_ids()
+            .map(|key_id| (key_id, registry.public_key(key_id).unwrap().to_bytes()))
+            .collect::<Vec<_>>();
+        let cb = TransportCallbacks {
+            public_keys: Box::new(|_transport| {
+                Box::pin(ready(
+                    KeyRegistry::random(2, &mut StdRng::seed_from_u64(42)).public_keys(),
+                ))
+            }),
+            ..Default::default()
+        };
+        let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
+        let Json(resp) = public_handler(Extension(transport), Extension(Some(signing_key(0))))
+            .await
+            .unwrap();
+        let keys = resp
+            .verify(Some(&certificate(0)), SystemTime::now())
+            .unwrap()
+            .into_iter()
+            .map(|(key_id, pk)| (key_id, pk.to_bytes()))
+            .collect::<Vec<_>>();
+        assert_eq!(expected, keys);
+    }
+
+    #[test]
+    fn unsigned() {
+        let now = SystemTime::now();
+        let body = ResponseBody::sign(&public_keys(now), None).unwrap();
+        assert_eq!(2, body.clone().verify(None, now).unwrap().len());
+        assert!(matches!(
+            body.verify(Some(&certificate(0)), now),
+            Err(Error::InvalidPublicKeys(_))
+        ));
+    }
+
+    #[test]
+    fn wrong_certificate() {
+        let now = SystemTime::now();
+        let body = ResponseBody::sign(&public_keys(now), Some(&*signing_key(0))).unwrap();
+        assert!(matches!(
+            body.verify(Some(&certificate(1)), now),
+            Err(Error::InvalidPublicKeys(_))
+        ));
+    }
+
+    #[test]
+    fn tampered() {
+        let now = SystemTime::now();
+        let mut body = ResponseBody::sign(&public_keys(now), Some(&*signing_key(0))).unwrap();
+        let mut other = public_keys(now);
+        other.keys.pop();
+        body.public_keys = serde_json::to_string(&other).unwrap();
+        assert!(matches!(
+            body.verify(Some(&certificate(0)), now),
+            Err(Error::InvalidPublicKeys(_))
+        ));
+    }
+
+    #[test]
+    fn expired() {
+        let now = SystemTime::now();
+        let body = ResponseBody::sign(&public_keys(now), Some(&*signing_key(0))).unwrap();
+        assert!(matches!(
+            body.clone().verify(
+                Some(&certificate(0)),
+                now + VALIDITY + Duration::from_secs(1)
+            ),
+            Err(Error::InvalidPublicKeys(_))
+        ));
+        assert!(matches!(
+            body.verify(
+                Some(&certificate(0)),
+                now - Duration::from_secs(24 * 60 * 60)
+            ),
+            Err(Error::InvalidPublicKeys(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn reload() {
+        let cb = TransportCallbacks {
+            reload_keys: Box::new(|_transport| Box::pin(ready(Ok(vec![1, 2])))),
+            ..Default::default()
+        };
+        let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
+        let Json(resp) = reload_handler(
+            Extension(transport),
+            Extension(ClientIdentity(HelperIdentity::TWO)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(vec![1, 2], resp.key_ids);
+    }
+
+    #[tokio::test]
+    async fn not_configured() {
+        let cb = TransportCallbacks {
+            reload_keys: Box::new(|_transport| Box::pin(ready(Err(KeyReloadError::NotConfigured)))),
+            ..Default::default()
+        };
+        let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
+        let err = reload_handler(
+            Extension(transport),
+            Extension(ClientIdentity(HelperIdentity::TWO)),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Application {
+                code: StatusCode::NOT_IMPLEMENTED,
+                ..
+            }
+        ));
+    }
+
+    struct UnauthenticatedReq;
+
+    impl IntoFailingReq for UnauthenticatedReq {
+        fn into_req(self, port: u16) -> hyper::Request<hyper::Body> {
+            let uri = format!(
+                "http://localhost:{}{}{}",
+                port,
+                http_serde::keys::BASE_AXUM_PATH,
+                http_serde::keys::reload::AXUM_PATH
+            );
+            hyper::Request::post(uri)
+                .body(hyper::Body::empty())
+                .unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn auth_required() {
+        assert_req_fails_with(UnauthenticatedReq, StatusCode::UNAUTHORIZED).await;
+    }
+}
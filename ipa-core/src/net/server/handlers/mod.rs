@@ -1,18 +1,46 @@
 mod echo;
+mod keys;
 mod query;
 
 use axum::Router;
+use rustls::sign::SigningKey;
+use tower::layer::layer_fn;
 
 use crate::{
-    net::{http_serde, HttpTransport},
+    config::{HttpRoute, HttpServerConfig, NetworkConfig},
+    net::{http_serde, server::limits, HttpTransport},
     sync::Arc,
 };
 
-pub fn router(transport: Arc<HttpTransport>) -> Router {
-    echo::router().nest(
-        http_serde::query::BASE_AXUM_PATH,
-        Router::new()
-            .merge(query::query_router(Arc::clone(&transport)))
-            .merge(query::h2h_router(transport)),
-    )
+/// `signing_key` signs the public keys this helper publishes. Helpers that do not use TLS do not
+/// sign them.
+pub fn router(
+    transport: Arc<HttpTransport>,
+    config: &HttpServerConfig,
+    network: &NetworkConfig,
+    signing_key: Option<Arc<dyn SigningKey>>,
+) -> Router {
+    limits::apply(echo::router(), HttpRoute::Echo, config)
+        .nest(
+            http_serde::query::BASE_AXUM_PATH,
+            Router::new()
+                .merge(query::query_router(
+                    Arc::clone(&transport),
+                    config,
+                    network.report_collector_ids(),
+                ))
+                .merge(query::h2h_router(Arc::clone(&transport), config)),
+        )
+        .nest(
+            http_serde::keys::BASE_AXUM_PATH,
+            limits::apply(
+                keys::public_router(Arc::clone(&transport), signing_key),
+                HttpRoute::Keys,
+                config,
+            )
+            .merge(
+                limits::apply(keys::reload_router(transport), HttpRoute::Keys, config)
+                    .layer(layer_fn(query::HelperAuthentication::new)),
+            ),
+        )
 }
@@ -0,0 +1,107 @@
+use axum::{routing::post, Extension, Router};
+
+use crate::{
+    net::{
+        http_serde,
+        server::{handlers::query::kill::into_http_error, ClientIdentity},
+        Error, HttpTransport,
+    },
+    sync::Arc,
+};
+
+/// Called by the peer helper that received a request to kill the query from a report collector.
+async fn handler(
+    transport: Extension<Arc<HttpTransport>>,
+    _from: Extension<ClientIdentity>, // require that client is an authenticated helper
+    req: http_serde::query::abort::Request,
+) -> Result<(), Error> {
+    Arc::clone(&transport)
+        .abort_query(req.query_id)
+        .await
+        .map_err(into_http_error)
+}
+
+pub fn router(transport: Arc<HttpTransport>) -> Router {
+    Router::new()
+        .route(http_serde::query::abort::AXUM_PATH, post(handler))
+        .layer(Extension(transport))
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::future::ready;
+
+    use hyper::StatusCode;
+
+    use super::*;
+    use crate::{
+        helpers::{HelperIdentity, TransportCallbacks},
+        net::{
+            server::handlers::query::{
+                test_helpers::{assert_req_fails_with, IntoFailingReq},
+                MaybeExtensionExt,
+            },
+            test::TestServer,
+        },
+        protocol::QueryId,
+    };
+
+    #[tokio::test]
+    async fn abort_test() {
+        let expected_query_id = QueryId::from(1);
+        let cb = TransportCallbacks {
+            abort_query: Box::new(move |_transport, query_id| {
+                assert_eq!(query_id, expected_query_id);
+                Box::pin(ready(Ok(())))
+            }),
+            ..Default::default()
+        };
+        let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
+        let req = http_serde::query::abort::Request::new(expected_query_id);
+        handler(
+            Extension(transport),
+            Extension(ClientIdentity(HelperIdentity::TWO)),
+            req,
+        )
+        .await
+        .unwrap();
+    }
+
+    struct OverrideReq {
+        client_id: Option<ClientIdentity>,
+        query_id: String,
+    }
+
+    impl IntoFailingReq for OverrideReq {
+        fn into_req(self, port: u16) -> hyper::Request<hyper::Body> {
+            let uri = format!(
+                "http://localhost:{}{}/{}/abort",
+                port,
+                http_serde::query::BASE_AXUM_PATH,
+                self.query_id
+            );
+            hyper::Request::post(uri)
+                .maybe_extension(self.client_id)
+                .body(hyper::Body::empty())
+                .unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_query_id() {
+        let req = OverrideReq {
+            client_id: Some(ClientIdentity(HelperIdentity::TWO)),
+            query_id: "not-a-query-id".into(),
+        };
+        assert_req_fails_with(req, StatusCode::UNPROCESSABLE_ENTITY).await;
+    }
+
+    #[tokio::test]
+    async fn auth_required() {
+        let req = OverrideReq {
+            client_id: None,
+            query_id: QueryId::from(1).to_string(),
+        };
+        assert_req_fails_with(req, StatusCode::UNAUTHORIZED).await;
+    }
+}
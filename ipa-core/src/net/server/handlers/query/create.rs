@@ -2,24 +2,33 @@ use axum::{routing::post, Extension, Json, Router};
 use hyper::StatusCode;
 
 use crate::{
-    helpers::Transport,
+    helpers::{query::ReportCollectorId, Transport},
     net::{http_serde, Error, HttpTransport},
-    query::NewQueryError,
+    query::{NewQueryError, PrivacyBudgetError},
     sync::Arc,
 };
 
 /// Takes details from the HTTP request and creates a `[TransportCommand]::CreateQuery` that is sent
 /// to the [`HttpTransport`].
+///
+/// Report collectors that authenticate with a TLS certificate or a bearer token are charged privacy
+/// budget under their own identity and own the query they create, all others share the default one.
 async fn handler(
     transport: Extension<Arc<HttpTransport>>,
+    report_collector: Option<Extension<ReportCollectorId>>,
     req: http_serde::query::create::Request,
 ) -> Result<Json<http_serde::query::create::ResponseBody>, Error> {
     let transport = Transport::clone_ref(&*transport);
-    match transport.receive_query(req.query_config).await {
+    let mut query_config = req.query_config;
+    query_config.report_collector = report_collector.map(|e| e.0).unwrap_or_default();
+    match transport.receive_query(query_config).await {
         Ok(query_id) => Ok(Json(http_serde::query::create::ResponseBody { query_id })),
         Err(err @ NewQueryError::State { .. }) => {
             Err(Error::application(StatusCode::CONFLICT, err))
         }
+        Err(err @ NewQueryError::PrivacyBudget(PrivacyBudgetError::Exceeded { .. })) => {
+            Err(Error::application(StatusCode::FORBIDDEN, err))
+        }
         Err(err) => Err(Error::application(StatusCode::INTERNAL_SERVER_ERROR, err)),
     }
 }
@@ -44,7 +53,10 @@ mod tests {
     use crate::{
         ff::FieldType,
         helpers::{
-            query::{IpaQueryConfig, QueryConfig, QueryType, SparseAggregateQueryConfig},
+            query::{
+                DpParameter, InvalidReportPolicy, IpaQueryConfig, QueryConfig, QueryType,
+                ReportCollectorId, SparseAggregateQueryConfig,
+            },
             TransportCallbacks,
         },
         net::{
@@ -52,13 +64,14 @@ mod tests {
             test::TestServer,
         },
         protocol::QueryId,
+        report::ReportVersion,
     };
 
     async fn create_test(expected_query_config: QueryConfig) {
         let cb = TransportCallbacks {
             receive_query: Box::new(move |_transport, query_config| {
                 assert_eq!(query_config, expected_query_config);
-                Box::pin(ready(Ok(QueryId)))
+                Box::pin(ready(Ok(QueryId::from(1))))
             }),
             ..Default::default()
         };
@@ -77,7 +90,7 @@ mod tests {
 
         let http_serde::query::create::ResponseBody { query_id } =
             serde_json::from_slice(&body_bytes).unwrap();
-        assert_eq!(QueryId, query_id);
+        assert_eq!(QueryId::from(1), query_id);
     }
 
     #[tokio::test]
@@ -95,6 +108,18 @@ mod tests {
                     attribution_window_seconds: None,
                     num_multi_bits: 3,
                     plaintext_match_keys: true,
+                    dummy_records_epsilon: None,
+                    dummy_records_delta: DpParameter::DEFAULT_DELTA,
+                    output_noise_epsilon: None,
+                    output_noise_delta: DpParameter::DEFAULT_DELTA,
+                    epoch: 0,
+                    min_report_epoch: None,
+                    max_report_epoch: None,
+                    breakdown_key_bits: 8,
+                    trigger_value_bits: 3,
+                    timestamp_bits: 20,
+                    invalid_report_policy: InvalidReportPolicy::Fail,
+                    report_version: ReportVersion::MatchKeyOnly,
                 }),
                 FieldType::Fp32BitPrime,
                 1,
@@ -115,7 +140,99 @@ mod tests {
                 attribution_window_seconds: NonZeroU32::new(86_400),
                 num_multi_bits: 3,
                 plaintext_match_keys: true,
+                dummy_records_epsilon: None,
+                dummy_records_delta: DpParameter::DEFAULT_DELTA,
+                output_noise_epsilon: None,
+                output_noise_delta: DpParameter::DEFAULT_DELTA,
+                epoch: 0,
+                min_report_epoch: None,
+                max_report_epoch: None,
+                breakdown_key_bits: 8,
+                trigger_value_bits: 3,
+                timestamp_bits: 20,
+                invalid_report_policy: InvalidReportPolicy::Fail,
+                report_version: ReportVersion::MatchKeyOnly,
             }),
+            report_collector: ReportCollectorId::default(),
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn create_test_oprf_ipa_with_dummy_records() {
+        create_test(QueryConfig {
+            size: 1.try_into().unwrap(),
+            field_type: FieldType::Fp32BitPrime,
+            query_type: QueryType::OprfIpa(
+                IpaQueryConfig::no_window(8, 20, 3).with_dummy_records(0.5, 1e-9),
+            ),
+            report_collector: ReportCollectorId::default(),
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn create_test_oprf_ipa_with_output_noise() {
+        create_test(QueryConfig {
+            size: 1.try_into().unwrap(),
+            field_type: FieldType::Fp32BitPrime,
+            query_type: QueryType::OprfIpa(
+                IpaQueryConfig::no_window(8, 20, 3).with_output_noise(1.5, 1e-7),
+            ),
+            report_collector: ReportCollectorId::default(),
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn create_test_oprf_ipa_with_report_field_bits() {
+        create_test(QueryConfig {
+            size: 1.try_into().unwrap(),
+            field_type: FieldType::Fp32BitPrime,
+            query_type: QueryType::OprfIpa(
+                IpaQueryConfig::no_window(256, 300, 3).with_report_field_bits(9, 8, 20),
+            ),
+            report_collector: ReportCollectorId::default(),
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn create_test_oprf_ipa_with_report_epochs() {
+        create_test(QueryConfig {
+            size: 1.try_into().unwrap(),
+            field_type: FieldType::Fp32BitPrime,
+            query_type: QueryType::OprfIpa(
+                IpaQueryConfig::no_window(8, 20, 3).with_report_epochs(3..=5),
+            ),
+            report_collector: ReportCollectorId::default(),
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn create_test_ipa_with_invalid_report_policy() {
+        create_test(QueryConfig {
+            size: 1.try_into().unwrap(),
+            field_type: FieldType::Fp32BitPrime,
+            query_type: QueryType::SemiHonestIpa(
+                IpaQueryConfig::no_window(8, 20, 3)
+                    .with_invalid_report_policy(InvalidReportPolicy::ReplaceWithNullEvent),
+            ),
+            report_collector: ReportCollectorId::default(),
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn create_test_ipa_with_report_version() {
+        create_test(QueryConfig {
+            size: 1.try_into().unwrap(),
+            field_type: FieldType::Fp32BitPrime,
+            query_type: QueryType::SemiHonestIpa(
+                IpaQueryConfig::no_window(8, 20, 3).with_report_version(ReportVersion::AllFields),
+            ),
+            report_collector: ReportCollectorId::default(),
         })
         .await;
     }
@@ -129,6 +246,7 @@ mod tests {
                 contribution_bits: 8.try_into().unwrap(),
                 num_contributions: 20,
             }),
+            report_collector: ReportCollectorId::default(),
         })
         .await;
         create_test(QueryConfig {
@@ -138,6 +256,7 @@ mod tests {
                 contribution_bits: 8.try_into().unwrap(),
                 num_contributions: 20,
             }),
+            report_collector: ReportCollectorId::default(),
         })
         .await;
     }
@@ -291,6 +410,19 @@ mod tests {
         assert_req_fails_with(req, StatusCode::UNPROCESSABLE_ENTITY).await;
     }
 
+    #[tokio::test]
+    async fn empty_report_epochs_ipa() {
+        let req = OverrideReq {
+            field_type: format!("{:?}", FieldType::Fp32BitPrime),
+            query_type_params: format!(
+                "query_type={}&per_user_credit_cap=8&max_breakdown_key=20&num_multi_bits=3\
+                 &min_report_epoch=5&max_report_epoch=3",
+                QueryType::OPRF_IPA_STR
+            ),
+        };
+        assert_req_fails_with(req, StatusCode::UNPROCESSABLE_ENTITY).await;
+    }
+
     #[tokio::test]
     async fn malformed_num_multi_bits_ipa() {
         let req = OverrideIPAReq {
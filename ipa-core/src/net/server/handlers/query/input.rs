@@ -1,16 +1,25 @@
-use axum::{routing::post, Extension, Router};
+use axum::{
+    routing::{post, put},
+    Extension, Json, Router,
+};
 use hyper::StatusCode;
 
 use crate::{
-    helpers::Transport,
-    net::{http_serde, Error, HttpTransport},
+    helpers::{
+        query::{ReceivedInputChunks, ReportCollectorId},
+        Transport,
+    },
+    net::{http_serde, server::handlers::query::authorize, Error, HttpTransport},
+    query::QueryInputError,
     sync::Arc,
 };
 
 async fn handler(
     transport: Extension<Arc<HttpTransport>>,
+    report_collector: Option<Extension<ReportCollectorId>>,
     req: http_serde::query::input::Request,
 ) -> Result<(), Error> {
+    authorize(&transport, req.query_input.query_id, report_collector).await?;
     let transport = Transport::clone_ref(&*transport);
     transport
         .query_input(req.query_input)
@@ -18,9 +27,65 @@ async fn handler(
         .map_err(|e| Error::application(StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
+async fn chunk_handler(
+    transport: Extension<Arc<HttpTransport>>,
+    report_collector: Option<Extension<ReportCollectorId>>,
+    req: http_serde::query::input::chunk::Request,
+) -> Result<Json<ReceivedInputChunks>, Error> {
+    authorize(&transport, req.chunk.query_id, report_collector).await?;
+    let transport = Transport::clone_ref(&*transport);
+    transport
+        .query_input_chunk(req.chunk)
+        .await
+        .map(Json)
+        .map_err(chunk_error)
+}
+
+async fn chunks_handler(
+    transport: Extension<Arc<HttpTransport>>,
+    report_collector: Option<Extension<ReportCollectorId>>,
+    req: http_serde::query::input::chunks::Request,
+) -> Result<Json<ReceivedInputChunks>, Error> {
+    authorize(&transport, req.query_id, report_collector).await?;
+    let transport = Transport::clone_ref(&*transport);
+    transport
+        .input_chunks(req.query_id)
+        .await
+        .map(Json)
+        .map_err(chunk_error)
+}
+
+fn chunk_error(e: QueryInputError) -> Error {
+    let code = match e {
+        QueryInputError::CorruptedChunk { .. }
+        | QueryInputError::EmptyChunk { .. }
+        | QueryInputError::TooManyRecords { .. }
+        | QueryInputError::WrongRecordCount { .. }
+        | QueryInputError::MisalignedInput { .. }
+        | QueryInputError::InputMismatch { .. } => StatusCode::BAD_REQUEST,
+        QueryInputError::NoSuchQuery(_) => StatusCode::NOT_FOUND,
+        QueryInputError::StateError { .. } => StatusCode::CONFLICT,
+    };
+    Error::application(code, e)
+}
+
 pub fn router(transport: Arc<HttpTransport>) -> Router {
     Router::new()
-        .route(http_serde::query::input::AXUM_PATH, post(handler))
+        .route(
+            http_serde::query::input::AXUM_PATH,
+            post(handler).get(chunks_handler),
+        )
+        .layer(Extension(transport))
+}
+
+/// Unlike the whole input, which is streamed into the query, chunks are kept in memory until the
+/// input is complete. They are served separately, so that their size can be limited.
+pub fn chunk_router(transport: Arc<HttpTransport>) -> Router {
+    Router::new()
+        .route(
+            http_serde::query::input::chunk::AXUM_PATH,
+            put(chunk_handler),
+        )
         .layer(Extension(transport))
 }
 
@@ -31,9 +96,14 @@ mod tests {
 
     use super::*;
     use crate::{
-        helpers::{query::QueryInput, BytesStream, TransportCallbacks},
+        helpers::{
+            query::{QueryInput, QueryInputChunk},
+            BytesStream, TransportCallbacks,
+        },
         net::{
-            server::handlers::query::test_helpers::{assert_req_fails_with, IntoFailingReq},
+            server::handlers::query::test_helpers::{
+                allow_all_queries, assert_req_fails_with, IntoFailingReq,
+            },
             test::TestServer,
         },
         protocol::QueryId,
@@ -41,7 +111,7 @@ mod tests {
 
     #[tokio::test]
     async fn input_test() {
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::from(1);
         let expected_input = &[4u8; 4];
         let cb = TransportCallbacks {
             query_input: Box::new(move |_transport, query_input| {
@@ -51,6 +121,7 @@ mod tests {
                     Ok(())
                 })
             }),
+            authorize_query: allow_all_queries(),
             ..Default::default()
         };
         let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
@@ -58,7 +129,7 @@ mod tests {
             query_id: expected_query_id,
             input_stream: expected_input.to_vec().into(),
         });
-        handler(Extension(transport), req).await.unwrap();
+        handler(Extension(transport), None, req).await.unwrap();
     }
 
     struct OverrideReq {
@@ -83,7 +154,7 @@ mod tests {
     impl Default for OverrideReq {
         fn default() -> Self {
             Self {
-                query_id: QueryId.as_ref().to_string(),
+                query_id: QueryId::from(1).to_string(),
                 input_stream: vec![4; 4],
             }
         }
@@ -97,4 +168,78 @@ mod tests {
         };
         assert_req_fails_with(req, StatusCode::UNPROCESSABLE_ENTITY).await;
     }
+
+    #[tokio::test]
+    async fn chunk_test() {
+        let expected_query_id = QueryId::from(1);
+        let cb = TransportCallbacks {
+            query_input_chunk: Box::new(move |_transport, chunk| {
+                Box::pin(async move {
+                    assert_eq!(chunk.query_id, expected_query_id);
+                    assert_eq!((3, 2), (chunk.index, chunk.records));
+                    assert!(chunk.is_intact());
+                    Ok(ReceivedInputChunks {
+                        chunks: vec![chunk.index],
+                        records: chunk.records,
+                        expected_records: 4,
+                    })
+                })
+            }),
+            authorize_query: allow_all_queries(),
+            ..Default::default()
+        };
+        let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
+        let req = http_serde::query::input::chunk::Request::new(QueryInputChunk::new(
+            expected_query_id,
+            3,
+            2,
+            vec![4u8; 4].into(),
+        ));
+        let Json(received) = chunk_handler(Extension(transport), None, req)
+            .await
+            .unwrap();
+        assert_eq!(vec![3], received.chunks);
+        assert!(!received.is_complete());
+    }
+
+    struct OverrideChunkReq {
+        records: Option<&'static str>,
+        checksum: Option<&'static str>,
+    }
+
+    impl IntoFailingReq for OverrideChunkReq {
+        fn into_req(self, port: u16) -> Request<Body> {
+            let uri = format!(
+                "http://localhost:{}{}/1/input/0",
+                port,
+                http_serde::query::BASE_AXUM_PATH,
+            );
+            let mut req = hyper::Request::put(uri);
+            if let Some(records) = self.records {
+                req = req.header(&http_serde::query::input::chunk::RECORDS_HEADER, records);
+            }
+            if let Some(checksum) = self.checksum {
+                req = req.header(&http_serde::query::input::chunk::CHECKSUM_HEADER, checksum);
+            }
+            req.body(hyper::Body::from(vec![4; 4])).unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn chunk_without_checksum() {
+        let req = OverrideChunkReq {
+            records: Some("2"),
+            checksum: None,
+        };
+        assert_req_fails_with(req, StatusCode::UNPROCESSABLE_ENTITY).await;
+    }
+
+    #[tokio::test]
+    async fn chunk_malformed_checksum() {
+        let req = OverrideChunkReq {
+            records: Some("2"),
+            checksum: Some("not_a_checksum"),
+        };
+        assert_req_fails_with(req, StatusCode::BAD_REQUEST).await;
+    }
 }
@@ -0,0 +1,122 @@
+use axum::{routing::post, Extension, Router};
+use hyper::StatusCode;
+
+use crate::{
+    helpers::{query::ReportCollectorId, Transport},
+    net::{http_serde, server::handlers::query::authorize, Error, HttpTransport},
+    query::QueryKillError,
+    sync::Arc,
+};
+
+/// Called by report collectors to cancel a query. The helper that receives this request asks its
+/// peers to cancel the query too.
+async fn handler(
+    transport: Extension<Arc<HttpTransport>>,
+    report_collector: Option<Extension<ReportCollectorId>>,
+    req: http_serde::query::kill::Request,
+) -> Result<(), Error> {
+    authorize(&transport, req.query_id, report_collector).await?;
+    let transport = Transport::clone_ref(&*transport);
+    transport
+        .kill_query(req.query_id)
+        .await
+        .map_err(into_http_error)
+}
+
+pub(super) fn into_http_error(err: QueryKillError) -> Error {
+    let code = match err {
+        QueryKillError::NoSuchQuery(_) => StatusCode::NOT_FOUND,
+        QueryKillError::StateError { .. } => StatusCode::CONFLICT,
+        QueryKillError::Transport(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    Error::application(code, err)
+}
+
+pub fn router(transport: Arc<HttpTransport>) -> Router {
+    Router::new()
+        .route(http_serde::query::kill::AXUM_PATH, post(handler))
+        .layer(Extension(transport))
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::future::ready;
+
+    use axum::http::Request;
+    use hyper::StatusCode;
+
+    use super::*;
+    use crate::{
+        helpers::TransportCallbacks,
+        net::{
+            server::handlers::query::test_helpers::{
+                allow_all_queries, assert_req_fails_with, IntoFailingReq,
+            },
+            test::TestServer,
+        },
+        protocol::QueryId,
+    };
+
+    #[tokio::test]
+    async fn kill_test() {
+        let expected_query_id = QueryId::from(1);
+        let cb = TransportCallbacks {
+            kill_query: Box::new(move |_transport, query_id| {
+                assert_eq!(query_id, expected_query_id);
+                Box::pin(ready(Ok(())))
+            }),
+            authorize_query: allow_all_queries(),
+            ..Default::default()
+        };
+        let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
+        let req = http_serde::query::kill::Request::new(expected_query_id);
+        handler(Extension(transport), None, req).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn no_such_query() {
+        let cb = TransportCallbacks {
+            kill_query: Box::new(|_transport, query_id| {
+                Box::pin(ready(Err(QueryKillError::NoSuchQuery(query_id))))
+            }),
+            authorize_query: allow_all_queries(),
+            ..Default::default()
+        };
+        let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
+        let req = http_serde::query::kill::Request::new(QueryId::from(1));
+        assert!(matches!(
+            handler(Extension(transport), None, req).await.unwrap_err(),
+            Error::Application {
+                code: StatusCode::NOT_FOUND,
+                ..
+            }
+        ));
+    }
+
+    struct OverrideReq {
+        query_id: String,
+    }
+
+    impl IntoFailingReq for OverrideReq {
+        fn into_req(self, port: u16) -> Request<hyper::Body> {
+            let uri = format!(
+                "http://localhost:{}{}/{}/kill",
+                port,
+                http_serde::query::BASE_AXUM_PATH,
+                self.query_id
+            );
+            hyper::Request::post(uri)
+                .body(hyper::Body::empty())
+                .unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_query_id() {
+        let req = OverrideReq {
+            query_id: "not-a-query-id".into(),
+        };
+
+        assert_req_fails_with(req, StatusCode::UNPROCESSABLE_ENTITY).await;
+    }
+}
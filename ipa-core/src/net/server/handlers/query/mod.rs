@@ -1,25 +1,34 @@
+mod abort;
 mod create;
 mod input;
+mod kill;
 mod prepare;
 mod results;
 mod status;
 mod step;
 
-use std::any::Any;
+use std::{any::Any, collections::HashSet};
 
 use axum::{
     response::{IntoResponse, Response},
-    Router,
+    Extension, Router,
 };
 use futures_util::{
     future::{ready, Either, Ready},
     FutureExt,
 };
-use hyper::{http::request, Request, StatusCode};
+use hyper::{header::AUTHORIZATION, http::request, Request, StatusCode};
 use tower::{layer::layer_fn, Service};
 
 use crate::{
-    net::{server::ClientIdentity, HttpTransport},
+    config::{HttpRoute, HttpServerConfig},
+    helpers::query::ReportCollectorId,
+    net::{
+        server::{limits, ClientIdentity},
+        Error, HttpTransport,
+    },
+    protocol::QueryId,
+    query::QueryAccessError,
     sync::Arc,
 };
 
@@ -28,12 +37,51 @@ use crate::{
 /// In principle, this web service could be backed by either an HTTP-interconnected helper network or
 /// an in-memory helper network. These are the APIs used by external callers (report collectors) to
 /// examine attribution results.
-pub fn query_router(transport: Arc<HttpTransport>) -> Router {
+///
+/// Only the report collectors listed in `report_collectors` may identify themselves to these APIs.
+/// If the list is empty, only unauthenticated requests are accepted, see
+/// [`ReportCollectorAuthentication`].
+pub fn query_router(
+    transport: Arc<HttpTransport>,
+    config: &HttpServerConfig,
+    report_collectors: HashSet<ReportCollectorId>,
+) -> Router {
+    let report_collectors = Arc::new(report_collectors);
     Router::new()
-        .merge(create::router(Arc::clone(&transport)))
-        .merge(input::router(Arc::clone(&transport)))
-        .merge(status::router(Arc::clone(&transport)))
-        .merge(results::router(transport))
+        .merge(limits::apply(
+            create::router(Arc::clone(&transport)),
+            HttpRoute::Create,
+            config,
+        ))
+        .merge(limits::apply(
+            input::router(Arc::clone(&transport)),
+            HttpRoute::Input,
+            config,
+        ))
+        .merge(limits::apply(
+            input::chunk_router(Arc::clone(&transport)),
+            HttpRoute::InputChunk,
+            config,
+        ))
+        .merge(limits::apply(
+            status::router(Arc::clone(&transport)),
+            HttpRoute::Status,
+            config,
+        ))
+        .merge(limits::apply(
+            kill::router(Arc::clone(&transport)),
+            HttpRoute::Kill,
+            config,
+        ))
+        .merge(limits::apply(
+            results::router(transport),
+            HttpRoute::Results,
+            config,
+        ))
+        .layer(layer_fn(move |inner| ReportCollectorAuthentication {
+            inner,
+            allowed: Arc::clone(&report_collectors),
+        }))
 }
 
 /// Construct router for helper-to-helper communications
@@ -43,10 +91,23 @@ pub fn query_router(transport: Arc<HttpTransport>) -> Router {
 /// particular query, to coordinate servicing that query.
 //
 // It might make sense to split the query and h2h handlers into two modules.
-pub fn h2h_router(transport: Arc<HttpTransport>) -> Router {
+pub fn h2h_router(transport: Arc<HttpTransport>, config: &HttpServerConfig) -> Router {
     Router::new()
-        .merge(prepare::router(Arc::clone(&transport)))
-        .merge(step::router(transport))
+        .merge(limits::apply(
+            prepare::router(Arc::clone(&transport)),
+            HttpRoute::Prepare,
+            config,
+        ))
+        .merge(limits::apply(
+            abort::router(Arc::clone(&transport)),
+            HttpRoute::Abort,
+            config,
+        ))
+        .merge(limits::apply(
+            step::router(transport),
+            HttpRoute::Step,
+            config,
+        ))
         .layer(layer_fn(HelperAuthentication::new))
 }
 
@@ -68,7 +129,7 @@ pub struct HelperAuthentication<S> {
 }
 
 impl<S> HelperAuthentication<S> {
-    fn new(inner: S) -> Self {
+    pub(super) fn new(inner: S) -> Self {
         Self { inner }
     }
 }
@@ -100,6 +161,83 @@ impl<B, S: Service<Request<B>, Response = Response>> Service<Request<B>>
     }
 }
 
+/// Identifies the report collector that sent the request and returns HTTP 401 Unauthorized if it
+/// is not allowed to call the query APIs. Report collectors that identify themselves must be listed
+/// as allowed. If no report collectors are listed, only requests without an identity are accepted,
+/// and they all act as the default report collector.
+///
+/// Report collectors authenticate either with a TLS client certificate, in which case the
+/// `ReportCollectorId` request extension is populated by `ClientCertRecognizingAcceptor`, or with
+/// a bearer token in the `Authorization` header, which this middleware turns into the extension.
+#[derive(Clone)]
+pub struct ReportCollectorAuthentication<S> {
+    inner: S,
+    allowed: Arc<HashSet<ReportCollectorId>>,
+}
+
+impl<B, S: Service<Request<B>, Response = Response>> Service<Request<B>>
+    for ReportCollectorAuthentication<S>
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<Response, S::Error>>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<B>) -> Self::Future {
+        let unauthorized = |message| ready(Ok((StatusCode::UNAUTHORIZED, message).into_response()));
+        if let Some(value) = req.headers().get(AUTHORIZATION) {
+            let Some(token) = value.to_str().ok().and_then(|v| v.strip_prefix("Bearer ")) else {
+                return unauthorized("Authorization header must contain a bearer token")
+                    .right_future();
+            };
+            let report_collector = ReportCollectorId::from_token(token);
+            req.extensions_mut().insert(report_collector);
+        }
+
+        // Every identity gets its own privacy budget, so identities that are not listed must be
+        // rejected even if the list is empty. Otherwise anyone could get a fresh budget by making
+        // up a new token.
+        let authorized = match req.extensions().get::<ReportCollectorId>() {
+            Some(id) => self.allowed.contains(id),
+            None => self.allowed.is_empty(),
+        };
+        if authorized {
+            self.inner.call(req).left_future()
+        } else {
+            unauthorized("This API requires an authorized report collector").right_future()
+        }
+    }
+}
+
+/// Makes sure that the report collector that sent the request is the one that requested the
+/// query. Report collectors that did not authenticate are treated as the default report
+/// collector, so they can only access queries that were created without authentication.
+async fn authorize(
+    transport: &Arc<HttpTransport>,
+    query_id: QueryId,
+    report_collector: Option<Extension<ReportCollectorId>>,
+) -> Result<(), Error> {
+    let report_collector =
+        report_collector.map_or_else(ReportCollectorId::default, |Extension(id)| id);
+    Arc::clone(transport)
+        .authorize_query(query_id, report_collector)
+        .await
+        .map_err(|e| {
+            let code = match e {
+                QueryAccessError::NoSuchQuery(_) => StatusCode::NOT_FOUND,
+                QueryAccessError::Forbidden(_) => StatusCode::FORBIDDEN,
+                QueryAccessError::ResultStore(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            Error::application(code, e)
+        })
+}
+
 /// Helper trait for optionally adding an extension to a request.
 trait MaybeExtensionExt {
     fn maybe_extension<T: Any + Send + Sync + 'static>(self, extension: Option<T>) -> Self;
@@ -121,7 +259,12 @@ pub mod test_helpers {
     use hyper::{service::Service, StatusCode};
     use tower::ServiceExt;
 
-    use crate::net::test::TestServer;
+    use crate::{helpers::AuthorizeQueryCallback, net::test::TestServer};
+
+    /// Lets every report collector access every query, for tests of handlers that check access.
+    pub fn allow_all_queries<T>() -> Box<dyn AuthorizeQueryCallback<T>> {
+        Box::new(|_, _, _| Box::pin(async { Ok(()) }))
+    }
 
     /// types that implement `IntoFailingReq` are intended to induce some failure in the process of
     /// axum routing. Pair with `assert_req_fails_with` to detect specific [`StatusCode`] failures.
@@ -135,7 +278,7 @@ pub mod test_helpers {
     pub async fn assert_req_fails_with<I: IntoFailingReq>(req: I, expected_status: StatusCode) {
         let TestServer { server, .. } = TestServer::default().await;
 
-        let mut router = server.router();
+        let mut router = server.router(None);
         let ready = poll_immediate(router.ready()).await.unwrap().unwrap();
         let resp = poll_immediate(ready.call(req.into_req(0)))
             .await
@@ -144,3 +287,54 @@ pub mod test_helpers {
         assert_eq!(resp.status(), expected_status);
     }
 }
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use axum::routing::get;
+    use hyper::Body;
+
+    use super::*;
+
+    async fn send(allowed: &[ReportCollectorId], authorization: Option<&str>) -> StatusCode {
+        let allowed = Arc::new(allowed.iter().copied().collect::<HashSet<_>>());
+        let mut router = Router::new()
+            .route("/", get(|| async {}))
+            .layer(layer_fn(move |inner| ReportCollectorAuthentication {
+                inner,
+                allowed: Arc::clone(&allowed),
+            }));
+        let mut req = Request::get("/");
+        if let Some(authorization) = authorization {
+            req = req.header(AUTHORIZATION, authorization);
+        }
+        let router = tower::ServiceExt::ready(&mut router).await.unwrap();
+        router
+            .call(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn report_collector_authentication() {
+        let allowed = ReportCollectorId::from_token("allowed");
+        assert_eq!(StatusCode::OK, send(&[], None).await);
+        assert_eq!(
+            StatusCode::UNAUTHORIZED,
+            send(&[], Some("Bearer other")).await
+        );
+        assert_eq!(
+            StatusCode::OK,
+            send(&[allowed], Some("Bearer allowed")).await
+        );
+        assert_eq!(
+            StatusCode::UNAUTHORIZED,
+            send(&[allowed], Some("Bearer other")).await
+        );
+        assert_eq!(StatusCode::UNAUTHORIZED, send(&[allowed], None).await);
+        assert_eq!(
+            StatusCode::UNAUTHORIZED,
+            send(&[], Some("Basic YWxsb3dlZA==")).await
+        );
+    }
+}
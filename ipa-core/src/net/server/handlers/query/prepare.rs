@@ -60,7 +60,7 @@ mod tests {
     #[tokio::test]
     async fn prepare_test() {
         let req = http_serde::query::prepare::Request::new(PrepareQuery {
-            query_id: QueryId,
+            query_id: QueryId::from(1),
             config: QueryConfig::new(TestMultiply, FieldType::Fp31, 1).unwrap(),
             roles: RoleAssignment::new(HelperIdentity::make_three()),
         });
@@ -116,7 +116,7 @@ mod tests {
                 .to_vec();
             Self {
                 client_id: Some(ClientIdentity(HelperIdentity::TWO)),
-                query_id: QueryId.as_ref().to_string(),
+                query_id: QueryId::from(1).to_string(),
                 field_type: format!("{:?}", FieldType::Fp31),
                 size: Some(1),
                 roles,
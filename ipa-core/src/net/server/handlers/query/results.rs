@@ -1,22 +1,41 @@
 use std::sync::Arc;
 
-use axum::{routing::get, Extension, Router};
+use axum::{
+    body::StreamBody,
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Router,
+};
 use hyper::StatusCode;
 
 use crate::{
-    helpers::Transport,
-    net::{http_serde, server::Error, HttpTransport},
+    helpers::{query::ReportCollectorId, Transport},
+    net::{
+        http_serde::{self, query::results},
+        server::{handlers::query::authorize, Error},
+        HttpTransport,
+    },
 };
 
-/// Handles the completion of the query by blocking the sender until query is completed.
+/// Handles the completion of the query by blocking the sender until query is completed. Results
+/// are streamed back as they are serialized, the layout of them is described in the
+/// [`results::RESULT_HEADER`] response header.
 async fn handler(
     transport: Extension<Arc<HttpTransport>>,
+    report_collector: Option<Extension<ReportCollectorId>>,
     req: http_serde::query::results::Request,
-) -> Result<Vec<u8>, Error> {
-    // TODO: we may be able to stream the response
+) -> Result<Response, Error> {
+    authorize(&transport, req.query_id, report_collector).await?;
     let transport = Transport::clone_ref(&*transport);
     match transport.complete_query(req.query_id).await {
-        Ok(result) => Ok(result.into_bytes()),
+        Ok(result) => {
+            let header = results::result_header_value(&result.header());
+            Ok((
+                [(results::RESULT_HEADER.clone(), header)],
+                StreamBody::new(result.into_stream()),
+            )
+                .into_response())
+        }
         Err(e) => Err(Error::application(StatusCode::INTERNAL_SERVER_ERROR, e)),
     }
 }
@@ -39,11 +58,13 @@ mod tests {
         ff::Fp31,
         helpers::TransportCallbacks,
         net::{
-            server::handlers::query::test_helpers::{assert_req_fails_with, IntoFailingReq},
+            server::handlers::query::test_helpers::{
+                allow_all_queries, assert_req_fails_with, IntoFailingReq,
+            },
             test::TestServer,
         },
         protocol::QueryId,
-        query::ProtocolResult,
+        query::{ProtocolResult, RejectedReports, ResultHeader},
         secret_sharing::replicated::semi_honest::AdditiveShare as Replicated,
     };
 
@@ -53,7 +74,7 @@ mod tests {
             Fp31::try_from(1u128).unwrap(),
             Fp31::try_from(2u128).unwrap(),
         ))]);
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::from(1);
         let raw_results = expected_results.to_vec();
         let cb = TransportCallbacks {
             complete_query: Box::new(move |_transport, query_id| {
@@ -61,11 +82,24 @@ mod tests {
                 assert_eq!(query_id, expected_query_id);
                 Box::pin(ready(Ok(results)))
             }),
+            authorize_query: allow_all_queries(),
             ..Default::default()
         };
         let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
-        let req = http_serde::query::results::Request::new(QueryId);
-        let results = handler(Extension(transport), req.clone()).await.unwrap();
+        let req = http_serde::query::results::Request::new(QueryId::from(1));
+        let response = handler(Extension(transport), None, req.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            ResultHeader {
+                field_type: None,
+                records: 1,
+                record_size: 2,
+                rejected_reports: RejectedReports::new(),
+            },
+            results::parse_result_header(response.headers()).unwrap()
+        );
+        let results = hyper::body::to_bytes(response.into_body()).await.unwrap();
         assert_eq!(results, expected_results.into_bytes());
     }
 
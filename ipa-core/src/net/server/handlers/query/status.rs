@@ -4,17 +4,23 @@ use axum::{routing::get, Extension, Json, Router};
 use hyper::StatusCode;
 
 use crate::{
-    helpers::Transport,
-    net::{http_serde::query::status, server::Error, HttpTransport},
+    helpers::{query::ReportCollectorId, Transport},
+    net::{
+        http_serde::query::status,
+        server::{handlers::query::authorize, Error},
+        HttpTransport,
+    },
 };
 
 async fn handler(
     transport: Extension<Arc<HttpTransport>>,
+    report_collector: Option<Extension<ReportCollectorId>>,
     req: status::Request,
 ) -> Result<Json<status::ResponseBody>, Error> {
+    authorize(&transport, req.query_id, report_collector).await?;
     let transport = Transport::clone_ref(&*transport);
     match transport.query_status(req.query_id).await {
-        Ok(state) => Ok(Json(status::ResponseBody { status: state })),
+        Ok(report) => Ok(Json(report.into())),
         Err(e) => Err(Error::application(StatusCode::INTERNAL_SERVER_ERROR, e)),
     }
 }
@@ -37,30 +43,60 @@ mod tests {
         helpers::TransportCallbacks,
         net::{
             http_serde,
-            server::handlers::query::test_helpers::{assert_req_fails_with, IntoFailingReq},
+            server::handlers::query::test_helpers::{
+                allow_all_queries, assert_req_fails_with, IntoFailingReq,
+            },
             test::TestServer,
         },
         protocol::QueryId,
-        query::QueryStatus,
+        query::{QueryAccessError, QueryStatus, QueryStatusReport},
     };
 
     #[tokio::test]
     async fn status_test() {
         let expected_status = QueryStatus::Running;
-        let expected_query_id = QueryId;
+        let expected_query_id = QueryId::from(1);
         let cb = TransportCallbacks {
             query_status: Box::new(move |_transport, query_id| {
                 assert_eq!(query_id, expected_query_id);
-                Box::pin(ready(Ok(expected_status)))
+                Box::pin(ready(Ok(QueryStatusReport::from(expected_status))))
             }),
+            authorize_query: allow_all_queries(),
             ..Default::default()
         };
         let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
-        let req = http_serde::query::status::Request::new(QueryId);
-        let response = handler(Extension(transport), req.clone()).await.unwrap();
+        let req = http_serde::query::status::Request::new(QueryId::from(1));
+        let response = handler(Extension(transport), None, req.clone())
+            .await
+            .unwrap();
 
-        let Json(http_serde::query::status::ResponseBody { status }) = response;
+        let Json(http_serde::query::status::ResponseBody { status, progress }) = response;
         assert_eq!(status, expected_status);
+        assert_eq!(progress, None);
+    }
+
+    #[tokio::test]
+    async fn unauthenticated_is_default_report_collector() {
+        let cb = TransportCallbacks {
+            authorize_query: Box::new(move |_transport, query_id, report_collector| {
+                Box::pin(ready(if report_collector == ReportCollectorId::default() {
+                    Err(QueryAccessError::Forbidden(query_id))
+                } else {
+                    Ok(())
+                }))
+            }),
+            ..Default::default()
+        };
+        let TestServer { transport, .. } = TestServer::builder().with_callbacks(cb).build().await;
+        let req = http_serde::query::status::Request::new(QueryId::from(1));
+        let err = handler(Extension(transport), None, req).await.unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Application {
+                code: StatusCode::FORBIDDEN,
+                ..
+            }
+        ));
     }
 
     struct OverrideReq {
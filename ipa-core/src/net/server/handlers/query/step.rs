@@ -10,15 +10,15 @@ use crate::{
     sync::Arc,
 };
 
-#[allow(clippy::unused_async)] // axum doesn't like synchronous handler
 async fn handler(
     transport: Extension<Arc<HttpTransport>>,
     from: Extension<ClientIdentity>,
     req: http_serde::query::step::Request<BodyStream>,
 ) -> Result<(), Error> {
     let transport = Transport::clone_ref(&*transport);
-    transport.receive_stream(req.query_id, req.gate, **from, req.body);
-    Ok(())
+    transport
+        .receive_step(req.query_id, req.gate, **from, req.offset, req.body)
+        .await
 }
 
 pub fn router(transport: Arc<HttpTransport>) -> Router {
@@ -59,8 +59,11 @@ mod tests {
 
         let step = Gate::default().narrow("test");
         let payload = vec![213; DATA_LEN * MESSAGE_PAYLOAD_SIZE_BYTES];
-        let req =
-            http_serde::query::step::Request::new(QueryId, step.clone(), payload.clone().into());
+        let req = http_serde::query::step::Request::new(
+            QueryId::from(1),
+            step.clone(),
+            payload.clone().into(),
+        );
 
         handler(
             Extension(Arc::clone(&transport)),
@@ -70,7 +73,8 @@ mod tests {
         .await
         .unwrap();
 
-        let mut stream = Arc::clone(&transport).receive(HelperIdentity::TWO, (QueryId, step));
+        let mut stream =
+            Arc::clone(&transport).receive(HelperIdentity::TWO, (QueryId::from(1), step));
 
         assert_eq!(
             poll_immediate(&mut stream).next().await,
@@ -105,7 +109,7 @@ mod tests {
         fn default() -> Self {
             Self {
                 client_id: Some(ClientIdentity(HelperIdentity::ONE)),
-                query_id: QueryId.as_ref().to_string(),
+                query_id: QueryId::from(1).to_string(),
                 gate: Gate::default().narrow("test"),
                 payload: vec![1; DATA_LEN * MESSAGE_PAYLOAD_SIZE_BYTES],
             }
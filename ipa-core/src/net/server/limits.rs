@@ -0,0 +1,252 @@
+use std::{
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use axum::{
+    response::{IntoResponse, Response},
+    Router,
+};
+use futures::{
+    future::{ready, BoxFuture, Either, Ready},
+    FutureExt, StreamExt,
+};
+use hyper::{header::CONTENT_LENGTH, Body, Request, StatusCode};
+use tower::{layer::layer_fn, Service};
+
+use crate::{
+    config::{HttpRoute, HttpServerConfig},
+    error::BoxError,
+};
+
+/// Applies the limits that `config` sets for `route` to the router that serves it.
+pub fn apply(router: Router, route: HttpRoute, config: &HttpServerConfig) -> Router {
+    let router = match config.timeouts.get(&route) {
+        Some(&timeout) => router.layer(layer_fn(move |inner| RequestTimeout { inner, timeout })),
+        None => router,
+    };
+    let body_limit = match route {
+        HttpRoute::InputChunk => config
+            .max_input_chunk_bytes
+            .or(config.max_request_body_bytes),
+        route if route.is_streaming() => None,
+        _ => config.max_request_body_bytes,
+    };
+    match body_limit {
+        Some(limit) => router.layer(layer_fn(move |inner| BodyLimit { inner, limit })),
+        None => router,
+    }
+}
+
+/// Returns HTTP 408 Request Timeout if the request is not handled within the given time.
+#[derive(Clone)]
+pub struct RequestTimeout<S> {
+    inner: S,
+    timeout: Duration,
+}
+
+impl<B, S> Service<Request<B>> for RequestTimeout<S>
+where
+    S: Service<Request<B>, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let timeout = self.timeout;
+        let response = self.inner.call(req);
+        async move {
+            ::tokio::time::timeout(timeout, response)
+                .await
+                .unwrap_or_else(|_| {
+                    Ok((
+                        StatusCode::REQUEST_TIMEOUT,
+                        format!("request was not handled within {timeout:?}"),
+                    )
+                        .into_response())
+                })
+        }
+        .boxed()
+    }
+}
+
+/// Returns HTTP 413 Payload Too Large if the request declares a body larger than the limit.
+/// Bodies without declared length are cut off with an error once they exceed it.
+#[derive(Clone)]
+pub struct BodyLimit<S> {
+    inner: S,
+    limit: u64,
+}
+
+impl<S: Service<Request<Body>, Response = Response>> Service<Request<Body>> for BodyLimit<S> {
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<Response, S::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let limit = self.limit;
+        let declared_len = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|len| len.to_str().ok()?.parse::<u64>().ok());
+        if declared_len.map_or(false, |len| len > limit) {
+            return ready(Ok((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("request body must not be larger than {limit} bytes"),
+            )
+                .into_response()))
+            .right_future();
+        }
+
+        let req = req.map(|body| {
+            let mut received = 0_u64;
+            Body::wrap_stream(body.map(move |chunk| -> Result<_, BoxError> {
+                let chunk = chunk?;
+                received += chunk.len() as u64;
+                if received > limit {
+                    return Err(format!("request body is larger than {limit} bytes").into());
+                }
+                Ok(chunk)
+            }))
+        });
+        self.inner.call(req).left_future()
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use axum::routing::post;
+    use bytes::Bytes;
+
+    use super::*;
+
+    fn router(config: &HttpServerConfig, route: HttpRoute) -> Router {
+        apply(
+            Router::new().route(
+                "/",
+                post(|body: Bytes| async move {
+                    ::tokio::time::sleep(Duration::from_millis(50)).await;
+                    body.len().to_string()
+                }),
+            ),
+            route,
+            config,
+        )
+    }
+
+    async fn send(router: Router, req: Request<Body>) -> StatusCode {
+        let mut router = router;
+        let router = tower::ServiceExt::ready(&mut router).await.unwrap();
+        hyper::service::Service::call(router, req)
+            .await
+            .unwrap()
+            .status()
+    }
+
+    fn sized(len: usize) -> Request<Body> {
+        Request::post("/")
+            .header(CONTENT_LENGTH, len)
+            .body(Body::from(vec![0; len]))
+            .unwrap()
+    }
+
+    fn chunked(len: usize) -> Request<Body> {
+        let body = Body::wrap_stream(futures::stream::iter(
+            [len / 2, len - len / 2].map(|len| Ok::<_, BoxError>(Bytes::from(vec![0; len]))),
+        ));
+        Request::post("/").body(body).unwrap()
+    }
+
+    fn empty() -> Request<Body> {
+        Request::post("/").body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn body_limit() {
+        let config = HttpServerConfig {
+            max_request_body_bytes: Some(10),
+            ..HttpServerConfig::default()
+        };
+        assert_eq!(
+            StatusCode::OK,
+            send(router(&config, HttpRoute::Prepare), sized(10)).await
+        );
+        assert_eq!(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            send(router(&config, HttpRoute::Prepare), sized(11)).await
+        );
+        assert_eq!(
+            StatusCode::OK,
+            send(router(&config, HttpRoute::Prepare), chunked(10)).await
+        );
+        assert!(send(router(&config, HttpRoute::Prepare), chunked(12))
+            .await
+            .is_client_error());
+        // streamed data is not limited
+        assert_eq!(
+            StatusCode::OK,
+            send(router(&config, HttpRoute::Step), sized(11)).await
+        );
+        // input chunks are held in memory, so they are limited
+        assert_eq!(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            send(router(&config, HttpRoute::InputChunk), sized(11)).await
+        );
+    }
+
+    #[tokio::test]
+    async fn input_chunk_limit() {
+        let config = HttpServerConfig {
+            max_request_body_bytes: Some(10),
+            max_input_chunk_bytes: Some(20),
+            ..HttpServerConfig::default()
+        };
+        assert_eq!(
+            StatusCode::OK,
+            send(router(&config, HttpRoute::InputChunk), sized(20)).await
+        );
+        assert_eq!(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            send(router(&config, HttpRoute::InputChunk), sized(21)).await
+        );
+        assert_eq!(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            send(router(&config, HttpRoute::Create), sized(11)).await
+        );
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let config = HttpServerConfig {
+            timeouts: BTreeMap::from([
+                (HttpRoute::Prepare, Duration::from_millis(1)),
+                (HttpRoute::Status, Duration::from_secs(10)),
+            ]),
+            ..HttpServerConfig::default()
+        };
+        assert_eq!(
+            StatusCode::REQUEST_TIMEOUT,
+            send(router(&config, HttpRoute::Prepare), empty()).await
+        );
+        assert_eq!(
+            StatusCode::OK,
+            send(router(&config, HttpRoute::Status), empty()).await
+        );
+        assert_eq!(
+            StatusCode::OK,
+            send(router(&config, HttpRoute::Kill), empty()).await
+        );
+    }
+}
@@ -1,4 +1,5 @@
 mod handlers;
+mod limits;
 
 use std::{
     borrow::Cow,
@@ -31,7 +32,9 @@ use futures::{
 use hyper::{header::HeaderName, server::conn::AddrStream, Request};
 use metrics::increment_counter;
 use rustls::{
-    server::AllowAnyAnonymousOrAuthenticatedClient, Certificate, PrivateKey, RootCertStore,
+    server::AllowAnyAnonymousOrAuthenticatedClient,
+    sign::{any_supported_type, SigningKey},
+    Certificate, PrivateKey, RootCertStore,
 };
 use rustls_pemfile::Item;
 #[cfg(all(feature = "shuttle", test))]
@@ -39,12 +42,12 @@ use shuttle::future as tokio;
 use tokio_rustls::server::TlsStream;
 use tower::{layer::layer_fn, Service};
 use tower_http::trace::TraceLayer;
-use tracing::{error, Span};
+use tracing::{debug, error, Span};
 
 use crate::{
-    config::{NetworkConfig, ServerConfig, TlsConfig},
+    config::{HttpServerConfig, NetworkConfig, ReportCollectorConfig, ServerConfig, TlsConfig},
     error::BoxError,
-    helpers::HelperIdentity,
+    helpers::{query::ReportCollectorId, HelperIdentity},
     net::{Error, HttpTransport},
     sync::Arc,
     task::JoinHandle,
@@ -93,13 +96,18 @@ impl MpcHelperServer {
         }
     }
 
-    fn router(&self) -> Router {
-        handlers::router(Arc::clone(&self.transport))
+    fn router(&self, signing_key: Option<Arc<dyn SigningKey>>) -> Router {
+        handlers::router(
+            Arc::clone(&self.transport),
+            &self.config.http,
+            &self.network_config,
+            signing_key,
+        )
     }
 
     #[cfg(all(test, unit_test))]
     async fn handle_req(&self, req: hyper::Request<hyper::Body>) -> axum::response::Response {
-        let mut router = self.router();
+        let mut router = self.router(None);
         let router = tower::ServiceExt::ready(&mut router).await.unwrap();
         hyper::service::Service::call(router, req).await.unwrap()
     }
@@ -130,7 +138,16 @@ impl MpcHelperServer {
         #[cfg(not(test))]
         const BIND_ADDRESS: Ipv4Addr = Ipv4Addr::UNSPECIFIED;
 
-        let svc = self.router().layer(
+        let signing_key = if self.config.disable_https {
+            None
+        } else {
+            Some(
+                signing_key(&self.config)
+                    .await
+                    .expect("invalid TLS configuration"),
+            )
+        };
+        let svc = self.router(signing_key).layer(
             TraceLayer::new_for_http()
                 .make_span_with(move |_request: &hyper::Request<hyper::Body>| tracing.make_span())
                 .on_request(|request: &hyper::Request<hyper::Body>, _: &Span| {
@@ -139,20 +156,27 @@ impl MpcHelperServer {
                 }),
         );
         let handle = Handle::new();
+        let http_config = http_config(&self.config.http);
 
         let task_handle = match (self.config.disable_https, listener) {
             (true, Some(listener)) => {
                 let svc = svc
                     .layer(layer_fn(SetClientIdentityFromHeader::new))
                     .into_make_service();
-                spawn_server(axum_server::from_tcp(listener), handle.clone(), svc).await
+                spawn_server(
+                    axum_server::from_tcp(listener),
+                    handle.clone(),
+                    http_config,
+                    svc,
+                )
+                .await
             }
             (true, None) => {
                 let addr = SocketAddr::new(BIND_ADDRESS.into(), self.config.port.unwrap_or(0));
                 let svc = svc
                     .layer(layer_fn(SetClientIdentityFromHeader::new))
                     .into_make_service();
-                spawn_server(axum_server::bind(addr), handle.clone(), svc).await
+                spawn_server(axum_server::bind(addr), handle.clone(), http_config, svc).await
             }
             (false, Some(listener)) => {
                 let rustls_config = rustls_config(&self.config, &self.network_config)
@@ -163,6 +187,7 @@ impl MpcHelperServer {
                         ClientCertRecognizingAcceptor::new(a, self.network_config.clone())
                     }),
                     handle.clone(),
+                    http_config,
                     svc.into_make_service(),
                 )
                 .await
@@ -177,6 +202,7 @@ impl MpcHelperServer {
                         ClientCertRecognizingAcceptor::new(a, self.network_config.clone())
                     }),
                     handle.clone(),
+                    http_config,
                     svc.into_make_service(),
                 )
                 .await
@@ -212,6 +238,7 @@ impl MpcHelperServer {
 async fn spawn_server<A>(
     server: Server<A>,
     handle: Handle,
+    http_config: HttpConfig,
     svc: IntoMakeService<Router>,
 ) -> JoinHandle<()>
 where
@@ -232,8 +259,7 @@ where
     tokio::spawn({
         async move {
             server
-                // TODO: configuration
-                .http_config(HttpConfig::default())
+                .http_config(http_config)
                 .handle(handle)
                 .serve(svc)
                 .await
@@ -242,6 +268,27 @@ where
     })
 }
 
+/// Settings that are not present in `config` keep the defaults of `hyper`.
+fn http_config(config: &HttpServerConfig) -> HttpConfig {
+    let mut http_config = HttpConfig::new();
+    if let Some(size) = config.stream_window_size {
+        http_config.http2_initial_stream_window_size(size);
+    }
+    if let Some(size) = config.connection_window_size {
+        http_config.http2_initial_connection_window_size(size);
+    }
+    if let Some(max) = config.max_concurrent_streams {
+        http_config.http2_max_concurrent_streams(max);
+    }
+    if let Some(interval) = config.keep_alive_interval {
+        http_config.http2_keep_alive_interval(interval);
+    }
+    if let Some(timeout) = config.keep_alive_timeout {
+        http_config.http2_keep_alive_timeout(timeout);
+    }
+    http_config.build()
+}
+
 async fn certificate_and_key(
     config: &ServerConfig,
 ) -> Result<(Vec<Certificate>, PrivateKey), BoxError> {
@@ -276,6 +323,12 @@ async fn certificate_and_key(
     Ok((cert, key))
 }
 
+/// Loads the TLS key of the helper to sign the public keys it publishes with.
+async fn signing_key(config: &ServerConfig) -> Result<Arc<dyn SigningKey>, BoxError> {
+    let (_, key) = certificate_and_key(config).await?;
+    Ok(any_supported_type(&key)?)
+}
+
 /// Create a `RustlsConfig` for the `ServerConfig`.
 ///
 /// `RustlsConfig` is an axum type. The native rustls configuration is `rustls::ServerConfig`, which
@@ -291,10 +344,18 @@ async fn rustls_config(
     let (cert, key) = certificate_and_key(config).await?;
 
     let mut trusted_certs = RootCertStore::empty();
+    let report_collector_certs = network
+        .report_collectors
+        .iter()
+        .filter_map(|report_collector| match report_collector {
+            ReportCollectorConfig::Certificate { certificate } => Some(certificate),
+            ReportCollectorConfig::Token { .. } => None,
+        });
     for cert in network
         .peers()
         .iter()
         .filter_map(|peer| peer.certificate.as_ref())
+        .chain(report_collector_certs)
     {
         // Note that this uses `webpki::TrustAnchor::try_from_cert_der`, which *does not* validate
         // the certificate. That is not required for security, but might be desirable to flag
@@ -361,8 +422,8 @@ impl ClientCertRecognizingAcceptor {
                 return Some(ClientIdentity(id));
             }
         }
-        // It might be nice to log something here. We could log the certificate base64?
-        error!(
+        // Report collectors present certificates too, so this is not necessarily an error.
+        debug!(
             "A client certificate was presented that does not match a known helper. Certificate: {}",
             BASE64.encode(cert),
         );
@@ -396,15 +457,22 @@ where
             //    certificate here, because the certificate must have passed full verification at
             //    connection time. But it's possible the certificate subject is not something we
             //    recognize as a helper.
-            let id = Self::identify_client(
-                &network_config,
-                stream
-                    .get_ref()
-                    .1
-                    .peer_certificates()
-                    .and_then(<[_]>::first),
-            );
-            let service = SetClientIdentityFromCertificate { inner: service, id };
+            let cert = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(<[_]>::first);
+            let id = Self::identify_client(&network_config, cert);
+            // Any certificate that does not belong to a helper identifies a report collector.
+            let report_collector = match (id, cert) {
+                (None, Some(cert)) => Some(ReportCollectorId::from_certificate(cert.as_ref())),
+                _ => None,
+            };
+            let service = SetClientIdentityFromCertificate {
+                inner: service,
+                id,
+                report_collector,
+            };
             Ok((stream, service))
         })
     }
@@ -414,6 +482,7 @@ where
 struct SetClientIdentityFromCertificate<S> {
     inner: S,
     id: Option<ClientIdentity>,
+    report_collector: Option<ReportCollectorId>,
 }
 
 impl<B, S: Service<Request<B>>> Service<Request<B>> for SetClientIdentityFromCertificate<S> {
@@ -430,6 +499,9 @@ impl<B, S: Service<Request<B>>> Service<Request<B>> for SetClientIdentityFromCer
         if let Some(id) = self.id {
             req.extensions_mut().insert(id);
         }
+        if let Some(report_collector) = self.report_collector {
+            req.extensions_mut().insert(report_collector);
+        }
         self.inner.call(req)
     }
 }
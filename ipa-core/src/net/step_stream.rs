@@ -0,0 +1,358 @@
+//! Step data that survives broken connections between helpers.
+//!
+//! A helper sends all records of a step to another helper in the body of a single request. If
+//! the connection breaks, the sender sends a new request for the same step with the
+//! [`OFFSET_HEADER`] set to the position of its data in the step's byte stream. The sender
+//! keeps the most recently sent data in a [`ReplayBuffer`] and starts every new request with it.
+//! The receiver knows how many bytes it passed on to the protocol already, so it skips the ones
+//! that it has seen and keeps the protocol's stream open until some request delivers the end of
+//! the data. The receiver responds once the request body is read in full, so a successful
+//! response acknowledges all data of the step.
+//!
+//! [`OFFSET_HEADER`]: crate::net::http_serde::query::step::OFFSET_HEADER
+
+use std::{
+    collections::{HashMap, VecDeque},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use hyper::StatusCode;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::{
+    error::BoxError,
+    helpers::{BodyStream, StreamKey},
+    net::Error,
+    protocol::QueryId,
+    sync::{Arc, Mutex},
+};
+
+/// Chunks of step data that are buffered between the request body and the protocol.
+const INBOUND_CHUNKS: usize = 16;
+
+/// Data of a step that is being sent to another helper.
+pub struct ReplayBuffer<S> {
+    inner: Arc<Mutex<ReplayBufferInner<S>>>,
+}
+
+struct ReplayBufferInner<S> {
+    source: Pin<Box<S>>,
+    /// The most recently sent chunks, the first one starts at `offset`.
+    sent: VecDeque<Bytes>,
+    offset: u64,
+    sent_len: usize,
+    limit: usize,
+    /// Only the stream of the latest request reads from `source`.
+    attempt: u64,
+}
+
+impl<S: Stream<Item = Vec<u8>> + Send + 'static> ReplayBuffer<S> {
+    /// Creates a buffer that keeps at least `limit` bytes of data read from `source`, or all of
+    /// it if there is less.
+    pub fn new(source: S, limit: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(ReplayBufferInner {
+                source: Box::pin(source),
+                sent: VecDeque::new(),
+                offset: 0,
+                sent_len: 0,
+                limit,
+                attempt: 0,
+            })),
+        }
+    }
+
+    /// Returns the body of a new request, along with its offset in the step data. The body
+    /// starts with the data that is still buffered and continues with the data that has not been
+    /// sent yet. Bodies returned earlier end once this is called.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    pub fn resume(&self) -> (u64, ReplayStream<S>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.attempt += 1;
+        let stream = ReplayStream {
+            inner: Arc::clone(&self.inner),
+            attempt: inner.attempt,
+            pos: inner.offset,
+        };
+        (inner.offset, stream)
+    }
+}
+
+pub struct ReplayStream<S> {
+    inner: Arc<Mutex<ReplayBufferInner<S>>>,
+    attempt: u64,
+    /// Position of the next chunk in the step data.
+    pos: u64,
+}
+
+impl<S: Stream<Item = Vec<u8>> + Send + 'static> Stream for ReplayStream<S> {
+    type Item = Bytes;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let mut inner = this.inner.lock().unwrap();
+        if inner.attempt != this.attempt {
+            return Poll::Ready(None);
+        }
+
+        // Chunks are sent and dropped from the buffer whole, so `pos` is always at the start of
+        // a buffered chunk or at the end of the buffer.
+        let mut start = inner.offset;
+        for chunk in &inner.sent {
+            if start == this.pos {
+                this.pos += chunk.len() as u64;
+                return Poll::Ready(Some(chunk.clone()));
+            }
+            start += chunk.len() as u64;
+        }
+
+        let Some(chunk) = futures::ready!(inner.source.poll_next_unpin(cx)) else {
+            return Poll::Ready(None);
+        };
+        let chunk = Bytes::from(chunk);
+        inner.sent_len += chunk.len();
+        inner.sent.push_back(chunk.clone());
+        while inner.sent_len - inner.sent[0].len() >= inner.limit {
+            let dropped = inner.sent.pop_front().unwrap();
+            inner.sent_len -= dropped.len();
+            inner.offset += dropped.len() as u64;
+        }
+        this.pos += chunk.len() as u64;
+
+        Poll::Ready(Some(chunk))
+    }
+}
+
+/// Step data that other helpers are sending to this one.
+#[derive(Default)]
+pub struct InboundSteps {
+    inner: Mutex<HashMap<StreamKey, Arc<tokio::sync::Mutex<InboundStep>>>>,
+}
+
+struct InboundStep {
+    /// Closed once the end of the data is received.
+    tx: Option<mpsc::Sender<Result<Bytes, BoxError>>>,
+    /// The number of bytes passed on to the protocol.
+    received: u64,
+    /// Incremented by every request, so that requests on broken connections stop forwarding
+    /// data once a new one arrives.
+    connection: u64,
+}
+
+impl InboundSteps {
+    /// Passes the data of a request that starts at `offset` in the step data on to the
+    /// protocol. The first request for `key` returns the stream that the protocol reads from,
+    /// the following ones continue where the previous ones stopped.
+    ///
+    /// ## Errors
+    /// If the request body fails, there is a gap between the data received before and `offset`,
+    /// or the protocol stopped reading the data.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    pub async fn receive<F: FnOnce(BodyStream)>(
+        &self,
+        key: StreamKey,
+        offset: u64,
+        mut body: BodyStream,
+        on_new_stream: F,
+    ) -> Result<(), Error> {
+        let step = {
+            let mut steps = self.inner.lock().unwrap();
+            Arc::clone(steps.entry(key.clone()).or_insert_with(|| {
+                let (tx, rx) = mpsc::channel(INBOUND_CHUNKS);
+                on_new_stream(BodyStream::from_bytes_stream(ReceiverStream::new(rx)));
+                Arc::new(tokio::sync::Mutex::new(InboundStep {
+                    tx: Some(tx),
+                    received: 0,
+                    connection: 0,
+                }))
+            }))
+        };
+
+        let connection = {
+            let mut step = step.lock().await;
+            if step.tx.is_none() {
+                // the response to the request that completed the step was lost
+                return Ok(());
+            }
+            if offset > step.received {
+                return Err(Error::application(
+                    StatusCode::CONFLICT,
+                    format!(
+                        "{key:?} cannot resume from byte {offset}, received {} bytes",
+                        step.received
+                    ),
+                ));
+            }
+            step.connection += 1;
+            step.connection
+        };
+
+        let mut pos = offset;
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|e| Error::application(StatusCode::BAD_REQUEST, e))?;
+            let mut step = step.lock().await;
+            if step.connection != connection {
+                return Err(Error::application(
+                    StatusCode::CONFLICT,
+                    format!("{key:?} is received on a newer connection"),
+                ));
+            }
+            let end = pos + chunk.len() as u64;
+            if end > step.received {
+                let skip = usize::try_from(step.received - pos).unwrap();
+                let tx = step.tx.as_ref().unwrap();
+                tx.send(Ok(chunk.slice(skip..))).await.map_err(|_| {
+                    Error::application(StatusCode::GONE, format!("{key:?} is not received anymore"))
+                })?;
+                step.received = end;
+            }
+            pos = end;
+        }
+
+        let mut step = step.lock().await;
+        if step.connection == connection {
+            step.tx = None;
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the steps of the given query.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    pub fn clear_query(&self, query_id: QueryId) {
+        self.inner
+            .lock()
+            .unwrap()
+            .retain(|(step_query_id, _, _), _| *step_query_id != query_id);
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use futures::stream;
+
+    use super::*;
+    use crate::{
+        helpers::{BytesStream, HelperIdentity},
+        protocol::step::Gate,
+    };
+
+    fn key() -> StreamKey {
+        (QueryId::from(1), HelperIdentity::ONE, Gate::default())
+    }
+
+    fn body(chunks: &[&'static [u8]]) -> BodyStream {
+        BodyStream::from_bytes_stream(stream::iter(
+            chunks
+                .iter()
+                .map(|chunk| Ok(Bytes::from_static(chunk)))
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    fn broken_body(chunks: &[&'static [u8]]) -> BodyStream {
+        BodyStream::from_bytes_stream(
+            stream::iter(
+                chunks
+                    .iter()
+                    .map(|chunk| Ok(Bytes::from_static(chunk)))
+                    .collect::<Vec<_>>(),
+            )
+            .chain(stream::once(async { Err("connection reset".into()) })),
+        )
+    }
+
+    #[tokio::test]
+    async fn replay_from_buffer() {
+        let buffer = ReplayBuffer::new(stream::iter([vec![1, 2], vec![3], vec![4, 5, 6]]), 3);
+
+        let (offset, mut first) = buffer.resume();
+        assert_eq!(0, offset);
+        assert_eq!(Some(Bytes::from(vec![1, 2])), first.next().await);
+        assert_eq!(Some(Bytes::from(vec![3])), first.next().await);
+
+        // both chunks are kept, the second one alone is smaller than the limit
+        let (offset, mut second) = buffer.resume();
+        assert_eq!(0, offset);
+        assert_eq!(None, first.next().await);
+        assert_eq!(Some(Bytes::from(vec![1, 2])), second.next().await);
+        assert_eq!(Some(Bytes::from(vec![3])), second.next().await);
+        assert_eq!(Some(Bytes::from(vec![4, 5, 6])), second.next().await);
+        assert_eq!(None, second.next().await);
+
+        let (offset, third) = buffer.resume();
+        assert_eq!(3, offset);
+        assert_eq!(
+            vec![vec![4, 5, 6]],
+            third.map(Vec::from).collect::<Vec<_>>().await
+        );
+    }
+
+    #[tokio::test]
+    async fn resume_after_broken_connection() {
+        let steps = InboundSteps::default();
+        let mut protocol_stream = None;
+        assert!(steps
+            .receive(key(), 0, broken_body(&[b"ab", b"cd"]), |s| {
+                protocol_stream = Some(s);
+            })
+            .await
+            .is_err());
+
+        // the sender resends data that the receiver has seen already
+        steps
+            .receive(key(), 2, body(&[b"cd", b"ef"]), |_| panic!("new stream"))
+            .await
+            .unwrap();
+
+        assert_eq!(b"abcdef".to_vec(), protocol_stream.unwrap().to_vec().await);
+    }
+
+    #[tokio::test]
+    async fn gap_is_rejected() {
+        let steps = InboundSteps::default();
+        let mut protocol_stream = None;
+        assert!(steps
+            .receive(key(), 0, broken_body(&[b"ab"]), |s| {
+                protocol_stream = Some(s);
+            })
+            .await
+            .is_err());
+
+        assert!(matches!(
+            steps
+                .receive(key(), 3, body(&[b"d"]), |_| panic!("new stream"))
+                .await,
+            Err(Error::Application {
+                code: StatusCode::CONFLICT,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn retry_after_completion() {
+        let steps = InboundSteps::default();
+        let mut protocol_stream = None;
+        steps
+            .receive(key(), 0, body(&[b"ab"]), |s| protocol_stream = Some(s))
+            .await
+            .unwrap();
+        steps
+            .receive(key(), 0, body(&[b"ab"]), |_| panic!("new stream"))
+            .await
+            .unwrap();
+
+        assert_eq!(b"ab".to_vec(), protocol_stream.unwrap().to_vec().await);
+    }
+}
@@ -20,8 +20,8 @@ use tokio::task::JoinHandle;
 
 use crate::{
     config::{
-        ClientConfig, HpkeClientConfig, HpkeServerConfig, NetworkConfig, PeerConfig, ServerConfig,
-        TlsConfig,
+        ClientConfig, HpkeClientConfig, HpkeServerConfig, HttpServerConfig, NetworkConfig,
+        PeerConfig, ServerConfig, TlsConfig,
     },
     helpers::{HelperIdentity, TransportCallbacks},
     hpke::{Deserializable as _, IpaPublicKey},
@@ -71,6 +71,8 @@ fn server_config_insecure_http(port: u16, matchkey_encryption: bool) -> ServerCo
         disable_https: true,
         tls: None,
         hpke_config: get_dummy_matchkey_encryption_info(matchkey_encryption),
+        result_store: None,
+        http: HttpServerConfig::default(),
     }
 }
 
@@ -89,6 +91,8 @@ pub fn server_config_https(
             private_key: String::from_utf8(private_key.to_owned()).unwrap(),
         }),
         hpke_config: get_dummy_matchkey_encryption_info(matchkey_encryption),
+        result_store: None,
+        http: HttpServerConfig::default(),
     }
 }
 
@@ -188,6 +192,8 @@ impl TestConfigBuilder {
                 .use_http1
                 .then(ClientConfig::use_http1)
                 .unwrap_or_default(),
+            report_collectors: Vec::new(),
+            helper_origin: NetworkConfig::default_helper_origin(),
         };
         let servers = if self.disable_https {
             ports.map(|ports| server_config_insecure_http(ports, !self.disable_matchkey_encryption))
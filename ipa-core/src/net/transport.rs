@@ -7,19 +7,20 @@ use std::{
 
 use async_trait::async_trait;
 use bytes::Bytes;
-use futures::{Stream, TryFutureExt};
+use futures::Stream;
 
 use crate::{
     config::{NetworkConfig, ServerConfig},
     error::BoxError,
     helpers::{
-        query::{PrepareQuery, QueryConfig, QueryInput},
-        BodyStream, CompleteQueryResult, HelperIdentity, LogErrors, NoResourceIdentifier,
-        PrepareQueryResult, QueryIdBinding, QueryInputResult, QueryStatusResult,
-        ReceiveQueryResult, ReceiveRecords, RouteId, RouteParams, StepBinding, StreamCollection,
-        Transport, TransportCallbacks,
+        query::{PrepareQuery, QueryConfig, QueryInput, QueryInputChunk, ReportCollectorId},
+        AbortQueryResult, AuthorizeQueryResult, BodyStream, CompleteQueryResult, HelperIdentity,
+        InputChunksResult, KillQueryResult, LogErrors, NoResourceIdentifier, PrepareQueryResult,
+        PublicKeysResult, QueryIdBinding, QueryInputChunkResult, QueryInputResult,
+        QueryStatusResult, ReceiveQueryResult, ReceiveRecords, ReloadKeysResult, RouteId,
+        RouteParams, StepBinding, StreamCollection, Transport, TransportCallbacks,
     },
-    net::{client::MpcHelperClient, error::Error, MpcHelperServer},
+    net::{client::MpcHelperClient, error::Error, step_stream::InboundSteps, MpcHelperServer},
     protocol::{step::Gate, QueryId},
     sync::Arc,
 };
@@ -31,9 +32,8 @@ pub struct HttpTransport {
     identity: HelperIdentity,
     callbacks: TransportCallbacks<Arc<HttpTransport>>,
     clients: [MpcHelperClient; 3],
-    // TODO(615): supporting multiple queries likely require a hashmap here. It will be ok if we
-    // only allow one query at a time.
     record_streams: StreamCollection<LogHttpErrors>,
+    step_streams: InboundSteps,
 }
 
 impl HttpTransport {
@@ -60,6 +60,7 @@ impl HttpTransport {
             callbacks,
             clients,
             record_streams: StreamCollection::default(),
+            step_streams: InboundSteps::default(),
         })
     }
 
@@ -71,19 +72,36 @@ impl HttpTransport {
         (Arc::clone(&self).callbacks.prepare_query)(self, req)
     }
 
+    pub fn authorize_query(
+        self: Arc<Self>,
+        query_id: QueryId,
+        report_collector: ReportCollectorId,
+    ) -> AuthorizeQueryResult {
+        (Arc::clone(&self).callbacks.authorize_query)(self, query_id, report_collector)
+    }
+
     pub fn query_input(self: Arc<Self>, req: QueryInput) -> QueryInputResult {
         (Arc::clone(&self).callbacks.query_input)(self, req)
     }
 
+    pub fn query_input_chunk(self: Arc<Self>, req: QueryInputChunk) -> QueryInputChunkResult {
+        (Arc::clone(&self).callbacks.query_input_chunk)(self, req)
+    }
+
+    pub fn input_chunks(self: Arc<Self>, query_id: QueryId) -> InputChunksResult {
+        (Arc::clone(&self).callbacks.input_chunks)(self, query_id)
+    }
+
     pub fn query_status(self: Arc<Self>, query_id: QueryId) -> QueryStatusResult {
         (Arc::clone(&self).callbacks.query_status)(self, query_id)
     }
 
     pub fn complete_query(self: Arc<Self>, query_id: QueryId) -> CompleteQueryResult {
-        /// Cleans up the `records_stream` collection after drop to ensure this transport
-        /// can process the next query even in case of a panic.
+        /// Cleans up the streams that belong to this query inside the `records_stream` collection
+        /// after drop to ensure this transport does not leak them even in case of a panic.
         struct ClearOnDrop {
             transport: Arc<HttpTransport>,
+            query_id: QueryId,
             qr: CompleteQueryResult,
         }
 
@@ -97,16 +115,46 @@ impl HttpTransport {
 
         impl Drop for ClearOnDrop {
             fn drop(&mut self) {
-                self.transport.record_streams.clear();
+                self.transport.record_streams.clear_query(self.query_id);
+                self.transport.step_streams.clear_query(self.query_id);
             }
         }
 
         Box::pin(ClearOnDrop {
             transport: Arc::clone(&self),
+            query_id,
             qr: Box::pin((Arc::clone(&self).callbacks.complete_query)(self, query_id)),
         })
     }
 
+    pub fn kill_query(self: Arc<Self>, query_id: QueryId) -> KillQueryResult {
+        let callback = (Arc::clone(&self).callbacks.kill_query)(Arc::clone(&self), query_id);
+        Box::pin(async move {
+            let result = callback.await;
+            self.record_streams.clear_query(query_id);
+            self.step_streams.clear_query(query_id);
+            result
+        })
+    }
+
+    pub fn abort_query(self: Arc<Self>, query_id: QueryId) -> AbortQueryResult {
+        let callback = (Arc::clone(&self).callbacks.abort_query)(Arc::clone(&self), query_id);
+        Box::pin(async move {
+            let result = callback.await;
+            self.record_streams.clear_query(query_id);
+            self.step_streams.clear_query(query_id);
+            result
+        })
+    }
+
+    pub fn reload_keys(self: Arc<Self>) -> ReloadKeysResult {
+        (Arc::clone(&self).callbacks.reload_keys)(self)
+    }
+
+    pub fn public_keys(self: Arc<Self>) -> PublicKeysResult {
+        (Arc::clone(&self).callbacks.public_keys)(self)
+    }
+
     /// Connect an inbound stream of MPC record data.
     ///
     /// This is called by peer helpers via the HTTP server.
@@ -120,6 +168,31 @@ impl HttpTransport {
         self.record_streams
             .add_stream((query_id, from, gate), LogErrors::new(stream));
     }
+
+    /// Connect an inbound stream of MPC record data that starts at `offset` bytes into the data
+    /// of the step. Unlike [`Self::receive_stream`], the data of a step may arrive in several
+    /// requests if the connection breaks, see [`crate::net::step_stream`]. This returns once the
+    /// protocol has all the data of the request.
+    ///
+    /// This is called by peer helpers via the HTTP server.
+    ///
+    /// ## Errors
+    /// If the request body fails or it does not continue the data received before.
+    pub async fn receive_step(
+        self: Arc<Self>,
+        query_id: QueryId,
+        gate: Gate,
+        from: HelperIdentity,
+        offset: u64,
+        stream: BodyStream,
+    ) -> Result<(), Error> {
+        let key = (query_id, from, gate);
+        self.step_streams
+            .receive(key.clone(), offset, stream, |stream| {
+                self.record_streams.add_stream(key, LogErrors::new(stream));
+            })
+            .await
+    }
 }
 
 #[async_trait]
@@ -154,19 +227,21 @@ impl Transport for Arc<HttpTransport> {
                     .expect("query_id required when sending records");
                 let step =
                     <Option<Gate>>::from(route.gate()).expect("step required when sending records");
-                let resp_future = self.clients[dest].step(query_id, &step, data)?;
                 // we don't need to spawn a task here. Gateway's sender interface already does that
                 // so this can just poll this future.
-                resp_future
-                    .map_err(Into::into)
-                    .and_then(MpcHelperClient::resp_ok)
-                    .await?;
-                Ok(())
+                self.clients[dest]
+                    .resumable_step(query_id, &step, data)
+                    .await
             }
             RouteId::PrepareQuery => {
                 let req = serde_json::from_str(route.extra().borrow()).unwrap();
                 self.clients[dest].prepare_query(req).await
             }
+            RouteId::AbortQuery => {
+                let query_id = <Option<QueryId>>::from(route.query_id())
+                    .expect("query_id required when aborting a query");
+                self.clients[dest].abort_query(query_id).await
+            }
             RouteId::ReceiveQuery => {
                 unimplemented!("attempting to send ReceiveQuery to another helper")
             }
@@ -226,11 +301,16 @@ mod tests {
         );
 
         // Register the stream with the transport (normally called by step data HTTP API handler)
-        Arc::clone(&transport).receive_stream(QueryId, STEP.clone(), HelperIdentity::TWO, body);
+        Arc::clone(&transport).receive_stream(
+            QueryId::from(1),
+            STEP.clone(),
+            HelperIdentity::TWO,
+            body,
+        );
 
         // Request step data reception (normally called by protocol)
         let mut stream =
-            Arc::clone(&transport).receive(HelperIdentity::TWO, (QueryId, STEP.clone()));
+            Arc::clone(&transport).receive(HelperIdentity::TWO, (QueryId::from(1), STEP.clone()));
 
         // make sure it is not ready as it hasn't received any data yet.
         assert!(matches!(
@@ -321,6 +401,21 @@ mod tests {
         test_multiply(&clients).await;
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn concurrent_queries() {
+        let mut conf = TestConfigBuilder::with_open_ports().build();
+        let clients = MpcHelperClient::from_conf(&conf.network, ClientIdentity::None);
+        let _helpers = make_helpers(
+            conf.sockets.take().unwrap(),
+            conf.servers,
+            &conf.network,
+            conf.disable_https,
+        )
+        .await;
+
+        join_all([test_multiply(&clients), test_multiply(&clients)]).await;
+    }
+
     async fn test_multiply(clients: &[MpcHelperClient; 3]) {
         const SZ: usize = <AdditiveShare<Fp31> as Serializable>::Size::USIZE;
 
@@ -353,7 +448,13 @@ mod tests {
         try_join_all(handle_resps).await.unwrap();
 
         let result: [_; 3] = join_all(clients.clone().map(|client| async move {
-            let r = client.query_results(query_id).await.unwrap();
+            let r = client
+                .query_results(query_id)
+                .await
+                .unwrap()
+                .into_bytes()
+                .await
+                .unwrap();
             AdditiveShare::<Fp31>::from_byte_slice(&r).collect::<Vec<_>>()
         }))
         .await
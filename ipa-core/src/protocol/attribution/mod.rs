@@ -25,6 +25,7 @@ use crate::{
         basics::SecureMul,
         boolean::{bitwise_equal::bitwise_equal_gf2, or::or},
         context::{Context, UpgradableContext, UpgradedContext, Validator},
+        dp::{add_binomial_noise, BinomialNoise},
         ipa::{ArithmeticallySharedIPAInputs, BinarySharedIPAInputs},
         modulus_conversion::convert_bits,
         sort::generate_permutation::ShuffledPermutationWrapper,
@@ -72,6 +73,7 @@ where
     F: PrimeField + ExtendableField,
     ShuffledPermutationWrapper<S, C::UpgradedContext<F>>: DowngradeMalicious<Target = Vec<u32>>,
 {
+    let output_noise = BinomialNoise::for_query(&config)?;
     let row_count = arithmetically_shared_values.len();
     assert_eq!(row_count, binary_shared_values.len());
     let m_ctx = validator.context();
@@ -157,6 +159,18 @@ where
     )
     .await?;
 
+    let output = match output_noise {
+        Some(noise) => {
+            add_binomial_noise(
+                validator.context().narrow(&AttributionStep::AddNoise),
+                noise,
+                output,
+            )
+            .await?
+        }
+        None => output,
+    };
+
     //Validate before returning the result to the report collector
     validator.validate(output).await
 }
@@ -167,6 +181,7 @@ pub(crate) enum AttributionStep {
     ApplyAttributionWindow,
     AccumulateCredit,
     PerformUserCapping,
+    AddNoise,
 }
 
 ///
@@ -1,5 +1,7 @@
 mod distributions;
 mod insecure;
+mod secure;
 
 #[cfg(any(test, feature = "test-fixture", feature = "cli"))]
 pub use insecure::DiscreteDp as InsecureDiscreteDp;
+pub use secure::{add_binomial_noise, BinomialNoise};
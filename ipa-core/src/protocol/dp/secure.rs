@@ -0,0 +1,272 @@
+use futures::stream::{iter as stream_iter, TryStreamExt};
+use ipa_macros::Step;
+
+use crate::{
+    error::Error,
+    ff::{Gf32Bit, PrimeField},
+    helpers::query::IpaQueryConfig,
+    protocol::{
+        basics::SecureMul, context::UpgradedContext, modulus_conversion::convert_bits,
+        prss::SharedRandomness, RecordId,
+    },
+    secret_sharing::{
+        replicated::{
+            malicious::ExtendableField, semi_honest::AdditiveShare as Replicated,
+            ReplicatedSecretSharing,
+        },
+        Linear as LinearSecretSharing, SharedValue,
+    },
+};
+
+/// Random bits are generated in batches of this size, one PRSS-generated sharing at a time.
+const BITS_PER_SHARING: u32 = <Gf32Bit as SharedValue>::BITS;
+
+/// Upper bound on the number of random bits added to, and subtracted from, every value. Each of
+/// them is converted to the output field in MPC, so this bounds that work to about two million
+/// bit conversions per value.
+const MAX_BITS_PER_SIDE: u32 = 1 << 20;
+
+#[derive(Step)]
+pub(crate) enum BinomialNoiseStep {
+    GenerateRandomBits,
+    ConvertRandomBits,
+}
+
+/// Parameters of the noise that helpers add to every output of IPA before it leaves them.
+///
+/// The noise is the difference of two binomial random variables `B(n, 1/2)`, whose variance
+/// `n/2` is at least the variance of the Gaussian mechanism for the same (`epsilon`, `delta`) and
+/// sensitivity. The binomial distribution approximates the Gaussian one well enough for the
+/// values of `n` it takes to satisfy any reasonable privacy budget.
+///
+/// `n` is twice the variance, so it grows with the square of `cap / epsilon`, and so does the
+/// cost of the noise: every value needs `2n` random bits converted to the output field. For
+/// instance, a cap of 32 with `epsilon = 1` and `delta = 1e-6` takes about 58 thousand bits per
+/// side. Budgets that need more than [`MAX_BITS_PER_SIDE`] are rejected.
+#[derive(Debug, Copy, Clone)]
+pub struct BinomialNoise {
+    /// Every value gets `n` random bits added and `n` subtracted, and this is `n` divided by the
+    /// number of bits in one PRSS-generated sharing.
+    sharings_per_side: usize,
+}
+
+impl BinomialNoise {
+    /// ## Errors
+    /// If epsilon is not positive, delta is not within `(0, 1)`, or they require more than
+    /// [`MAX_BITS_PER_SIDE`] random bits.
+    pub fn new(epsilon: f64, delta: f64, cap: u32) -> Result<Self, Error> {
+        if !epsilon.is_finite() || epsilon < f64::MIN_POSITIVE {
+            return Err(Error::InvalidQueryParameter(
+                format!("output noise epsilon must be a positive number, got {epsilon}").into(),
+            ));
+        }
+        if !(f64::MIN_POSITIVE..1.0).contains(&delta) {
+            return Err(Error::InvalidQueryParameter(
+                format!("output noise delta must be within (0, 1), got {delta}").into(),
+            ));
+        }
+
+        // Same standard deviation as the one `insecure::Dp` uses.
+        let std = f64::from(cap) / epsilon * f64::sqrt(2.0 * f64::ln(1.25 / delta));
+        // `n` bits on each side contribute `n / 4` to the variance each.
+        let bits_per_side = (2.0 * std * std).ceil();
+        let sharings_per_side = (bits_per_side / f64::from(BITS_PER_SHARING)).ceil();
+        if bits_per_side > f64::from(MAX_BITS_PER_SIDE) {
+            return Err(Error::InvalidQueryParameter(
+                format!("epsilon={epsilon} and delta={delta} require too much noise").into(),
+            ));
+        }
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        Ok(Self {
+            sharings_per_side: (sharings_per_side as usize).max(1),
+        })
+    }
+
+    /// Returns the noise that `config` asks for, if any. The sensitivity of IPA outputs is the
+    /// per-user credit cap.
+    /// ## Errors
+    /// If the privacy budget in `config` is invalid.
+    pub fn for_query(config: &IpaQueryConfig) -> Result<Option<Self>, Error> {
+        config
+            .output_noise_epsilon
+            .map(|epsilon| {
+                Self::new(
+                    epsilon.into(),
+                    config.output_noise_delta.into(),
+                    config.per_user_credit_cap,
+                )
+            })
+            .transpose()
+    }
+
+    /// The number of random bits added to, and subtracted from, every value.
+    #[must_use]
+    pub fn bits_per_side(&self) -> usize {
+        self.sharings_per_side * BITS_PER_SHARING as usize
+    }
+}
+
+/// Adds binomial noise to every one of `values`. The noise is made of random bits generated with
+/// PRSS and converted to `F`, so none of the helpers knows it.
+///
+/// Values close to zero may wrap around to values close to the prime of `F` once the noise is
+/// added, so `F` must be large enough for the caller to tell them apart from large values.
+/// ## Errors
+/// Propagates errors from converting the random bits to `F`.
+pub async fn add_binomial_noise<C, F, S>(
+    ctx: C,
+    noise: BinomialNoise,
+    values: Vec<S>,
+) -> Result<Vec<S>, Error>
+where
+    C: UpgradedContext<F, Share = S>,
+    S: LinearSecretSharing<F> + SecureMul<C>,
+    F: PrimeField + ExtendableField,
+{
+    let sharings_per_value = 2 * noise.sharings_per_side;
+    let total = sharings_per_value * values.len();
+
+    let random_bits = {
+        let generate_ctx = ctx.narrow(&BinomialNoiseStep::GenerateRandomBits);
+        let prss = generate_ctx.prss();
+        (0..total)
+            .map(|i| {
+                let (left, right) = prss.generate_fields::<Gf32Bit, _>(RecordId::from(i));
+                Replicated::new(left, right)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let random_bits = convert_bits(
+        ctx.narrow(&BinomialNoiseStep::ConvertRandomBits)
+            .set_total_records(total),
+        stream_iter(random_bits),
+        0..BITS_PER_SHARING,
+    )
+    .try_collect::<Vec<_>>()
+    .await?;
+
+    Ok(values
+        .into_iter()
+        .zip(random_bits.chunks(sharings_per_value))
+        .map(|(value, bits)| {
+            let (plus, minus) = bits.split_at(noise.sharings_per_side);
+            let value = plus
+                .iter()
+                .flat_map(|b| b.iter())
+                .fold(value, |acc, b| acc + b);
+            minus
+                .iter()
+                .flat_map(|b| b.iter())
+                .fold(value, |acc, b| acc - b)
+        })
+        .collect())
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::{add_binomial_noise, BinomialNoise};
+    use crate::{
+        error::Error,
+        ff::{Field, Fp32BitPrime, PrimeField},
+        protocol::context::{UpgradableContext, UpgradedContext, Validator},
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    /// Interprets `v` as a signed value, with the upper half of the field being negative.
+    fn signed(v: Fp32BitPrime) -> i64 {
+        let v = i64::try_from(v.as_u128()).unwrap();
+        let prime = i64::from(Fp32BitPrime::PRIME);
+        if v > prime / 2 {
+            v - prime
+        } else {
+            v
+        }
+    }
+
+    fn check_noise(input: &[u128], output: &[Fp32BitPrime], noise: BinomialNoise) {
+        let bound = i64::try_from(noise.bits_per_side()).unwrap();
+        let diffs = input
+            .iter()
+            .zip(output)
+            .map(|(&i, &o)| signed(o) - i64::try_from(i).unwrap())
+            .collect::<Vec<_>>();
+        assert!(
+            diffs.iter().all(|d| (-bound..=bound).contains(d)),
+            "{diffs:?}"
+        );
+        // every value gets 64 bits of noise, so this fails with probability ~0.1^8
+        assert!(diffs.iter().any(|&d| d != 0));
+    }
+
+    #[test]
+    fn noise_size() {
+        // std = 1 / 1 * sqrt(2 * ln(1.25 / 0.5)) ~ 1.35, so 2 * std^2 ~ 3.7 bits per side
+        assert_eq!(BinomialNoise::new(1.0, 0.5, 1).unwrap().bits_per_side(), 32);
+        // std ~ 5 * 1.35 = 6.77, so 2 * std^2 ~ 91.6 bits per side
+        assert_eq!(BinomialNoise::new(1.0, 0.5, 5).unwrap().bits_per_side(), 96);
+        let small = BinomialNoise::new(1.0, 1e-6, 10).unwrap().bits_per_side();
+        let large = BinomialNoise::new(0.1, 1e-6, 10).unwrap().bits_per_side();
+        assert!(small * 90 < large && large < small * 110);
+    }
+
+    #[test]
+    fn rejects_bad_parameters() {
+        for (epsilon, delta) in [
+            (0.0, 1e-6),
+            (f64::INFINITY, 1e-6),
+            (1.0, 0.0),
+            (1.0, 1.0),
+            (1e-3, 1e-6),
+        ] {
+            assert!(matches!(
+                BinomialNoise::new(epsilon, delta, 1),
+                Err(Error::InvalidQueryParameter(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn semi_honest() {
+        const INPUT: &[u128] = &[0, 1, 2, 3, 10, 20, 30, 40];
+        let noise = BinomialNoise::new(1.0, 0.5, 1).unwrap();
+
+        let result: Vec<Fp32BitPrime> = TestWorld::default()
+            .semi_honest(
+                INPUT.iter().map(|&v| Fp32BitPrime::truncate_from(v)),
+                |ctx, values| async move {
+                    let validator = ctx.validator::<Fp32BitPrime>();
+                    add_binomial_noise(validator.context(), noise, values)
+                        .await
+                        .unwrap()
+                },
+            )
+            .await
+            .reconstruct();
+
+        check_noise(INPUT, &result, noise);
+    }
+
+    #[tokio::test]
+    async fn malicious() {
+        const INPUT: &[u128] = &[0, 1, 2, 3, 10, 20, 30, 40];
+        let noise = BinomialNoise::new(1.0, 0.5, 1).unwrap();
+
+        let result: Vec<Fp32BitPrime> = TestWorld::default()
+            .malicious(
+                INPUT.iter().map(|&v| Fp32BitPrime::truncate_from(v)),
+                |ctx, values| async move {
+                    let validator = ctx.validator::<Fp32BitPrime>();
+                    let m_ctx = validator.context();
+                    let values = m_ctx.upgrade(values).await.unwrap();
+                    let result = add_binomial_noise(m_ctx, noise, values).await.unwrap();
+                    validator.validate(result).await.unwrap()
+                },
+            )
+            .await
+            .reconstruct();
+
+        check_noise(INPUT, &result, noise);
+    }
+}
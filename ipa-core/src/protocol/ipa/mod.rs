@@ -456,11 +456,15 @@ pub mod tests {
 
     use super::ipa;
     use crate::{
-        ff::{Field, Fp31, Fp32BitPrime},
-        helpers::{query::IpaQueryConfig, GatewayConfig},
+        ff::{Field, Fp31, Fp32BitPrime, PrimeField},
+        helpers::{
+            query::{DpParameter, InvalidReportPolicy, IpaQueryConfig},
+            GatewayConfig,
+        },
         ipa_test_input,
-        protocol::{BreakdownKey, MatchKey},
+        protocol::{dp::BinomialNoise, BreakdownKey, MatchKey},
         rand::{thread_rng, Rng},
+        report::ReportVersion,
         test_executor::{run, run_with},
         test_fixture::{
             input::GenericReportTestInput,
@@ -544,6 +548,59 @@ pub mod tests {
         });
     }
 
+    #[test]
+    fn malicious_with_output_noise() {
+        const PER_USER_CAP: u32 = 3;
+        const EXPECTED: &[u128] = &[0, 2, 3];
+        const MAX_BREAKDOWN_KEY: u32 = 3;
+        const NUM_MULTI_BITS: u32 = 3;
+
+        run(|| async {
+            let world = TestWorld::default();
+
+            let records: Vec<GenericReportTestInput<Fp32BitPrime, MatchKey, BreakdownKey>> = ipa_test_input!(
+                [
+                    { timestamp: 1, match_key: 12345, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                    { timestamp: 2, match_key: 12345, is_trigger_report: 0, breakdown_key: 2, trigger_value: 0 },
+                    { timestamp: 3, match_key: 68362, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                    { timestamp: 4, match_key: 12345, is_trigger_report: 1, breakdown_key: 0, trigger_value: 5 },
+                    { timestamp: 5, match_key: 68362, is_trigger_report: 1, breakdown_key: 0, trigger_value: 2 },
+                ];
+                (Fp32BitPrime, MatchKey, BreakdownKey)
+            );
+            let config = IpaQueryConfig::no_window(PER_USER_CAP, MAX_BREAKDOWN_KEY, NUM_MULTI_BITS)
+                .with_output_noise(10.0, 0.5);
+            let bound = i64::try_from(
+                BinomialNoise::for_query(&config)
+                    .unwrap()
+                    .unwrap()
+                    .bits_per_side(),
+            )
+            .unwrap();
+
+            let result: Vec<Fp32BitPrime> = world
+                .malicious(records.into_iter(), |ctx, input_rows| async move {
+                    ipa::<_, _, _, _, MatchKey, BreakdownKey>(ctx, &input_rows, config)
+                        .await
+                        .unwrap()
+                })
+                .await
+                .reconstruct();
+
+            let prime = i64::from(Fp32BitPrime::PRIME);
+            for (actual, &expected) in result.iter().zip(EXPECTED) {
+                let actual = i64::try_from(actual.as_u128()).unwrap();
+                let actual = if actual > prime / 2 {
+                    actual - prime
+                } else {
+                    actual
+                };
+                let noise = actual - i64::try_from(expected).unwrap();
+                assert!((-bound..=bound).contains(&noise), "noise is {noise}");
+            }
+        });
+    }
+
     #[test]
     fn semi_honest_with_attribution_window() {
         const PER_USER_CAP: u32 = 3;
@@ -838,6 +895,18 @@ pub mod tests {
                     attribution_window_seconds: ATTRIBUTION_WINDOW_SECONDS,
                     num_multi_bits: NUM_MULTI_BITS,
                     plaintext_match_keys: true,
+                    dummy_records_epsilon: None,
+                    dummy_records_delta: DpParameter::DEFAULT_DELTA,
+                    output_noise_epsilon: None,
+                    output_noise_delta: DpParameter::DEFAULT_DELTA,
+                    epoch: 0,
+                    min_report_epoch: None,
+                    max_report_epoch: None,
+                    breakdown_key_bits: 8,
+                    trigger_value_bits: 3,
+                    timestamp_bits: 20,
+                    invalid_report_policy: InvalidReportPolicy::Fail,
+                    report_version: ReportVersion::MatchKeyOnly,
                 },
                 security,
             )
@@ -0,0 +1,342 @@
+use std::iter::repeat;
+
+use futures::future::try_join;
+use ipa_macros::Step;
+use rand::{distributions::Distribution, Rng};
+
+use crate::{
+    error::Error,
+    ff::{
+        boolean_array::{BA32, BA64},
+        Field,
+    },
+    helpers::{Direction, Role},
+    protocol::{context::Context, RecordId},
+    report::OprfReport,
+    secret_sharing::{
+        replicated::{semi_honest::AdditiveShare as Replicated, ReplicatedSecretSharing},
+        WeakSharedValue,
+    },
+};
+
+/// Dummy users are generated with every number of reports between 1 and this value, which is the
+/// most reports of a single user that OPRF IPA can attribute. Must match the `dynamic` attribute
+/// of [`UserNthRowStep::Row`], so that the count of users is noised for every number of reports
+/// that can be revealed.
+///
+/// Every pair of helpers adds about `shift * 64 * 65 / 2` dummy records, where `shift` is
+/// `ln(1 / delta) / epsilon`.
+///
+/// [`UserNthRowStep::Row`]: crate::protocol::ipa_prf::prf_sharding::UserNthRowStep::Row
+pub const MAX_DUMMY_CARDINALITY: u32 = 64;
+
+/// Upper bound on the number of dummy users with a given cardinality that a pair of helpers may
+/// generate is twice this value. This keeps the number of dummy records a pair generates below
+/// `u32::MAX`.
+const MAX_SHIFT: u32 = 1 << 16;
+
+#[derive(Step)]
+pub(crate) enum DummyRecordsStep {
+    Generate,
+    ExchangeCount,
+}
+
+/// Privacy parameters for the dummy records that mask the histogram of users by the number of
+/// reports they have.
+#[derive(Debug, Copy, Clone)]
+pub struct DummyRecordParams {
+    epsilon: f64,
+    shift: u32,
+    max_cardinality: u32,
+}
+
+impl DummyRecordParams {
+    /// ## Errors
+    /// If epsilon is not positive, delta is not within `(0, 1)`, or the number of dummy records
+    /// required to satisfy them is too large.
+    pub fn new(epsilon: f64, delta: f64) -> Result<Self, Error> {
+        if !epsilon.is_finite() || epsilon < f64::MIN_POSITIVE {
+            return Err(Error::InvalidQueryParameter(
+                format!("dummy records epsilon must be a positive number, got {epsilon}").into(),
+            ));
+        }
+        if !(f64::MIN_POSITIVE..1.0).contains(&delta) {
+            return Err(Error::InvalidQueryParameter(
+                format!("dummy records delta must be within (0, 1), got {delta}").into(),
+            ));
+        }
+
+        // The noise never exceeds `shift` in absolute value, and values beyond that carry at most
+        // `e^(-epsilon * shift) <= delta` of the probability mass of the untruncated distribution.
+        let shift = (f64::ln(1.0 / delta) / epsilon).ceil();
+        if shift > f64::from(MAX_SHIFT) {
+            return Err(Error::InvalidQueryParameter(
+                format!("epsilon={epsilon} and delta={delta} require too many dummy records")
+                    .into(),
+            ));
+        }
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        Ok(Self {
+            epsilon,
+            shift: shift as u32,
+            max_cardinality: MAX_DUMMY_CARDINALITY,
+        })
+    }
+
+    /// Generates dummy users with up to `max_cardinality` reports only, so that tests do not
+    /// need to process thousands of dummy records.
+    #[cfg(all(test, unit_test))]
+    pub(crate) fn with_max_cardinality(self, max_cardinality: u32) -> Self {
+        Self {
+            max_cardinality,
+            ..self
+        }
+    }
+
+    fn distribution(&self) -> TruncatedDoubleGeometric {
+        TruncatedDoubleGeometric::new(self.epsilon, self.shift)
+    }
+}
+
+/// Two-sided geometric distribution with parameter `e^-epsilon`, shifted right by `shift` and
+/// truncated to `0..=2 * shift`, so that it can be used to count things.
+#[derive(Debug)]
+struct TruncatedDoubleGeometric {
+    cdf: Vec<f64>,
+}
+
+impl TruncatedDoubleGeometric {
+    fn new(epsilon: f64, shift: u32) -> Self {
+        let p = f64::exp(-epsilon);
+        let mut total = 0.0;
+        let cdf = (0..=2 * shift)
+            .map(|x| {
+                total += p.powf(f64::from(x.abs_diff(shift)));
+                total
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            cdf: cdf.into_iter().map(|v| v / total).collect(),
+        }
+    }
+}
+
+impl Distribution<u32> for TruncatedDoubleGeometric {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> u32 {
+        let u = rng.gen::<f64>();
+        let x = self
+            .cdf
+            .partition_point(|&v| v <= u)
+            .min(self.cdf.len() - 1);
+        u32::try_from(x).unwrap()
+    }
+}
+
+/// Generates the match keys of dummy users: for every cardinality `k` up to
+/// [`MAX_DUMMY_CARDINALITY`] (unless lowered for tests), a random number of users, each with a
+/// random match key that is repeated `k` times.
+fn dummy_match_keys<R: Rng>(rng: &mut R, params: DummyRecordParams) -> Vec<BA64> {
+    let distribution = params.distribution();
+    let mut match_keys = Vec::new();
+    for cardinality in 1..=params.max_cardinality {
+        for _ in 0..distribution.sample(rng) {
+            let match_key = rng.gen::<BA64>();
+            match_keys.extend(repeat(match_key).take(cardinality as usize));
+        }
+    }
+
+    match_keys
+}
+
+fn dummy_record<BK, TV, TS>(match_key: Replicated<BA64>) -> OprfReport<BK, TV, TS>
+where
+    BK: WeakSharedValue,
+    TV: WeakSharedValue,
+    TS: WeakSharedValue,
+{
+    OprfReport {
+        match_key,
+        is_trigger: Replicated::ZERO,
+        breakdown_key: Replicated::ZERO,
+        trigger_value: Replicated::ZERO,
+        timestamp: Replicated::ZERO,
+    }
+}
+
+/// Appends dummy records to `input_rows`, so that the histogram of users by the number of reports
+/// they have, which OPRF IPA reveals, is differentially private.
+///
+/// Every pair of helpers uses the randomness it shares to add a noisy number of dummy users with
+/// each cardinality. The third helper does not learn these numbers, only the total count of
+/// dummy records the pair added, which it needs to hold its (zero) shares of them. This way, the
+/// noise added by the pair that excludes a given helper protects the histogram from that helper.
+///
+/// Dummy records are source events with no breakdown key, so they do not contribute to the
+/// result. They must be shuffled together with the real records before their pseudonyms are
+/// revealed.
+/// # Errors
+/// Propagates errors from exchanging the number of dummy records with other helpers.
+/// # Panics
+/// If a pair of helpers generates more than `u32::MAX` dummy records, which parameter validation
+/// rules out.
+pub async fn add_dummy_records<C, BK, TV, TS>(
+    ctx: C,
+    params: DummyRecordParams,
+    input_rows: &mut Vec<OprfReport<BK, TV, TS>>,
+) -> Result<(), Error>
+where
+    C: Context,
+    BK: WeakSharedValue,
+    TV: WeakSharedValue,
+    TS: WeakSharedValue,
+{
+    let (left, right) = {
+        let generate_ctx = ctx.narrow(&DummyRecordsStep::Generate);
+        let (mut left_rng, mut right_rng) = generate_ctx.prss_rng();
+        (
+            dummy_match_keys(&mut left_rng, params),
+            dummy_match_keys(&mut right_rng, params),
+        )
+    };
+
+    // The helper to the left does not know how many dummy records this helper and the one to its
+    // right generated, and vice versa.
+    let ctx = ctx
+        .narrow(&DummyRecordsStep::ExchangeCount)
+        .set_total_records(1);
+    let send_channel = ctx.send_channel::<BA32>(ctx.role().peer(Direction::Left));
+    let recv_channel = ctx.recv_channel::<BA32>(ctx.role().peer(Direction::Right));
+    let ((), other_count) = try_join(
+        send_channel.send(
+            RecordId::FIRST,
+            BA32::truncate_from(u32::try_from(right.len()).unwrap()),
+        ),
+        recv_channel.receive(RecordId::FIRST),
+    )
+    .await?;
+    let other_count = usize::try_from(other_count.as_u128()).unwrap();
+
+    // The pair made of a helper and the one to its right knows the share they both hold. The
+    // records of all pairs are laid out in the same order on every helper.
+    let role = ctx.role();
+    for &pair_start in Role::all() {
+        if pair_start == role {
+            input_rows.extend(
+                right
+                    .iter()
+                    .map(|&mk| dummy_record(Replicated::new(BA64::ZERO, mk))),
+            );
+        } else if pair_start == role.peer(Direction::Left) {
+            input_rows.extend(
+                left.iter()
+                    .map(|&mk| dummy_record(Replicated::new(mk, BA64::ZERO))),
+            );
+        } else {
+            input_rows.extend(repeat(Replicated::ZERO).take(other_count).map(dummy_record));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::{distributions::Distribution, rngs::StdRng, SeedableRng};
+
+    use super::{
+        add_dummy_records, DummyRecordParams, TruncatedDoubleGeometric, MAX_DUMMY_CARDINALITY,
+    };
+    use crate::{
+        error::Error,
+        ff::{
+            boolean::Boolean,
+            boolean_array::{BA20, BA3, BA64, BA8},
+            Field,
+        },
+        secret_sharing::WeakSharedValue,
+        test_fixture::{ipa::TestRawDataRecord, Reconstruct, Runner, TestWorld},
+    };
+
+    #[test]
+    fn truncated_double_geometric() {
+        let distribution = TruncatedDoubleGeometric::new(0.5, 10);
+        let mut rng = StdRng::seed_from_u64(1);
+        let samples = (0..10_000)
+            .map(|_| distribution.sample(&mut rng))
+            .collect::<Vec<_>>();
+
+        assert!(samples.iter().all(|&x| x <= 20));
+        let mean = f64::from(samples.iter().sum::<u32>()) / 10_000.0;
+        assert!((mean - 10.0).abs() < 0.2, "mean is {mean}");
+        // P(X = 10) = (1 - p) / (1 + p) for the untruncated distribution, p = e^-0.5
+        let center = samples.iter().filter(|&&x| x == 10).count();
+        assert!((2300..2600).contains(&center), "{center} samples at 10");
+    }
+
+    #[test]
+    fn rejects_bad_parameters() {
+        for (epsilon, delta) in [
+            (0.0, 1e-6),
+            (-1.0, 1e-6),
+            (f64::NAN, 1e-6),
+            (1.0, 0.0),
+            (1.0, 1.0),
+            (1e-12, 1e-6),
+        ] {
+            assert!(matches!(
+                DummyRecordParams::new(epsilon, delta),
+                Err(Error::InvalidQueryParameter(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn dummy_records_are_zero_except_for_match_keys() {
+        let params = DummyRecordParams::new(1.0, 0.1).unwrap();
+        let records: Vec<TestRawDataRecord> = Vec::new();
+
+        let [h1, h2, h3] = TestWorld::default()
+            .semi_honest(records.into_iter(), |ctx, mut rows| async move {
+                add_dummy_records::<_, BA8, BA3, BA20>(ctx, params, &mut rows)
+                    .await
+                    .unwrap();
+                rows
+            })
+            .await;
+
+        assert!(!h1.is_empty());
+        assert_eq!(h1.len(), h2.len());
+        assert_eq!(h1.len(), h3.len());
+
+        let mut cardinalities = HashMap::<u128, u32>::new();
+        for ((r1, r2), r3) in h1.iter().zip(&h2).zip(&h3) {
+            let match_key: BA64 = [&r1.match_key, &r2.match_key, &r3.match_key].reconstruct();
+            *cardinalities.entry(match_key.as_u128()).or_default() += 1;
+
+            assert_eq!(
+                [&r1.is_trigger, &r2.is_trigger, &r3.is_trigger].reconstruct(),
+                Boolean::ZERO
+            );
+            assert_eq!(
+                [&r1.breakdown_key, &r2.breakdown_key, &r3.breakdown_key].reconstruct(),
+                BA8::ZERO
+            );
+            assert_eq!(
+                [&r1.trigger_value, &r2.trigger_value, &r3.trigger_value].reconstruct(),
+                BA3::ZERO
+            );
+            assert_eq!(
+                [&r1.timestamp, &r2.timestamp, &r3.timestamp].reconstruct(),
+                BA20::ZERO
+            );
+        }
+
+        assert!(cardinalities
+            .values()
+            .all(|k| (1..=MAX_DUMMY_CARDINALITY).contains(k)));
+    }
+}
@@ -1,23 +1,29 @@
-use std::num::NonZeroU32;
+use std::{iter::zip, ops::Range};
 
 use ipa_macros::Step;
 
 use crate::{
     error::Error,
     ff::{boolean::Boolean, boolean_array::BA64, CustomArray, Field, PrimeField, Serializable},
+    helpers::query::{FeatureLabelDotProductQueryConfig, IpaQueryConfig},
     protocol::{
-        context::{UpgradableContext, UpgradedContext},
+        context::{Context, UpgradableContext, UpgradedContext},
+        dp::BinomialNoise,
         ipa_prf::{
             boolean_ops::convert_to_fp25519,
+            dummy_records::{add_dummy_records, DummyRecordParams},
             prf_eval::{eval_dy_prf, gen_prf_key},
             prf_sharding::{
                 attribute_cap_aggregate, compute_histogram_of_users_with_row_count,
-                PrfShardedIpaInputRow,
+                feature_label_dot_product::{self, compute_feature_label_dot_product},
+                GroupingKey, PrfShardedIpaInputRow,
             },
+            quicksort::quicksort_ranges_by_key_insecure,
+            shuffle::{shuffle_feature_label_reports, shuffle_inputs},
         },
         RecordId,
     },
-    report::OprfReport,
+    report::{FeatureLabelDotProductReport, OprfReport},
     secret_sharing::{
         replicated::{malicious::ExtendableField, semi_honest::AdditiveShare as Replicated},
         WeakSharedValue,
@@ -25,43 +31,48 @@ use crate::{
 };
 
 mod boolean_ops;
+pub mod dummy_records;
 pub mod prf_eval;
 pub mod prf_sharding;
-#[cfg(feature = "descriptive-gate")]
+pub mod quicksort;
 pub mod shuffle;
 
 #[derive(Step)]
 pub(crate) enum Step {
+    AddDummyRecords,
     ConvertFp25519,
     EvalPrf,
     ConvertInputRowsToPrf,
+    Shuffle,
+    SortByTimestamp,
 }
 
 /// IPA OPRF Protocol
 ///
 /// The output of this function is a vector of secret-shared totals, one per breakdown key
 /// This protocol performs the following steps
-/// 1. Converts secret-sharings of boolean arrays to secret-sharings of elliptic curve points
-/// 2. Generates a random number of "dummy records" (needed to mask the information that will
+/// 1. Generates a random number of "dummy records" (needed to mask the information that will
 ///    be revealed in a later step, and thereby provide a differential privacy guarantee on that
-///    information leakage) (TBD)
-/// 3. Shuffles the input (TBD)
+///    information leakage), if `config` specifies a privacy budget for them
+/// 2. Shuffles the input
+/// 3. Converts secret-sharings of boolean arrays to secret-sharings of elliptic curve points
 /// 4. Computes an OPRF of these elliptic curve points and reveals this "pseudonym"
-/// 5. Groups together rows with the same OPRF, and then obliviously sorts each group by the
-///    secret-shared timestamp (TBD)
+/// 5. Groups together rows with the same OPRF, and then sorts each group by the
+///    secret-shared timestamp
 /// 6. Attributes trigger events to source events
 /// 7. Caps each user's total contribution to the final result
 /// 8. Aggregates the contributions of all users
 /// 9. Adds random noise to the total for each breakdown key (to provide a differential
-///    privacy guarantee) (TBD)
+///    privacy guarantee), if `config` specifies a privacy budget for it
+///
 /// # Errors
 /// Propagates errors from config issues or while running the protocol
 /// # Panics
 /// Propagates errors from config issues or while running the protocol
 pub async fn oprf_ipa<C, BK, TV, TS, SS, F>(
     ctx: C,
-    input_rows: Vec<OprfReport<BK, TV, TS>>,
-    attribution_window_seconds: Option<NonZeroU32>,
+    mut input_rows: Vec<OprfReport<BK, TV, TS>>,
+    config: IpaQueryConfig,
 ) -> Result<Vec<Replicated<F>>, Error>
 where
     C: UpgradableContext,
@@ -81,83 +92,287 @@ where
     F: PrimeField + ExtendableField,
     Replicated<F>: Serializable,
 {
-    // TODO (richaj): Add shuffle either before the protocol starts or, after converting match keys to elliptical curve.
-    // We might want to do it earlier as that's a cleaner code
+    let output_noise = BinomialNoise::for_query(&config)?;
+    if let Some(epsilon) = config.dummy_records_epsilon {
+        let params = DummyRecordParams::new(epsilon.into(), config.dummy_records_delta.into())?;
+        add_dummy_records(ctx.narrow(&Step::AddDummyRecords), params, &mut input_rows).await?;
+    }
 
-    let prfd_inputs =
-        compute_prf_for_inputs(ctx.narrow(&Step::ConvertInputRowsToPrf), input_rows).await?;
+    let shuffled = shuffle_inputs(ctx.narrow(&Step::Shuffle), input_rows).await?;
+    let mut prfd_inputs =
+        compute_prf_for_inputs(ctx.narrow(&Step::ConvertInputRowsToPrf), shuffled).await?;
+
+    // The PRF is revealed, so grouping rows by user does not need to happen in MPC.
+    prfd_inputs.sort_by_key(|row| row.prf_of_match_key);
+    sort_by_timestamp_within_users(ctx.clone(), &mut prfd_inputs).await?;
 
     let histogram = compute_histogram_of_users_with_row_count(&prfd_inputs);
 
-    // TODO (richaj) : Call quicksort on match keys followed by timestamp before calling attribution logic
     attribute_cap_aggregate::<C, BK, TV, TS, SS, Replicated<F>, F>(
         ctx,
         prfd_inputs,
-        attribution_window_seconds,
+        config.attribution_window_seconds,
         &histogram,
+        usize::try_from(config.max_breakdown_key).unwrap(),
+        output_noise,
     )
     .await
 }
 
-async fn compute_prf_for_inputs<C, BK, TV, TS, F>(
+async fn compute_prf_for_inputs<C, BK, TV, TS>(
     ctx: C,
     input_rows: Vec<OprfReport<BK, TV, TS>>,
 ) -> Result<Vec<PrfShardedIpaInputRow<BK, TV, TS>>, Error>
 where
-    C: UpgradableContext,
-    C::UpgradedContext<Boolean>: UpgradedContext<Boolean, Share = Replicated<Boolean>>,
-    C::UpgradedContext<F>: UpgradedContext<F, Share = Replicated<F>>,
+    C: Context,
     BK: WeakSharedValue + CustomArray<Element = Boolean> + Field,
     TV: WeakSharedValue + CustomArray<Element = Boolean> + Field,
     TS: WeakSharedValue + CustomArray<Element = Boolean> + Field,
-    for<'a> &'a Replicated<TS>: IntoIterator<Item = Replicated<Boolean>>,
-    for<'a> &'a Replicated<TV>: IntoIterator<Item = Replicated<Boolean>>,
-    for<'a> &'a Replicated<BK>: IntoIterator<Item = Replicated<Boolean>>,
-    for<'a> <&'a Replicated<TV> as IntoIterator>::IntoIter: Send,
-    for<'a> <&'a Replicated<TS> as IntoIterator>::IntoIter: Send,
-    F: PrimeField + ExtendableField,
-    Replicated<F>: Serializable,
 {
-    let ctx = ctx.set_total_records(input_rows.len());
+    let match_keys = input_rows
+        .iter()
+        .map(|row| row.match_key.clone())
+        .collect::<Vec<_>>();
+    let prfs = compute_prf_of_match_keys(ctx, &match_keys).await?;
+
+    Ok(zip(input_rows, prfs)
+        .map(|(record, prf_of_match_key)| PrfShardedIpaInputRow {
+            prf_of_match_key,
+            is_trigger_bit: record.is_trigger,
+            breakdown_key: record.breakdown_key,
+            trigger_value: record.trigger_value,
+            timestamp: record.timestamp,
+        })
+        .collect())
+}
+
+/// Converts every match key to an elliptic curve point and evaluates the PRF on it. The PRF
+/// values are revealed.
+async fn compute_prf_of_match_keys<C>(
+    ctx: C,
+    match_keys: &[Replicated<BA64>],
+) -> Result<Vec<u64>, Error>
+where
+    C: Context,
+{
+    let ctx = ctx.set_total_records(match_keys.len());
     let convert_ctx = ctx.narrow(&Step::ConvertFp25519);
     let eval_ctx = ctx.narrow(&Step::EvalPrf);
 
     let prf_key = gen_prf_key(&convert_ctx);
 
-    ctx.parallel_join(input_rows.into_iter().enumerate().map(|(idx, record)| {
+    ctx.parallel_join(match_keys.iter().enumerate().map(|(idx, match_key)| {
         let convert_ctx = convert_ctx.clone();
         let eval_ctx = eval_ctx.clone();
         let prf_key = prf_key.clone();
         async move {
             let record_id = RecordId::from(idx);
             let elliptic_curve_pt =
-                convert_to_fp25519::<_, BA64>(convert_ctx, record_id, &record.match_key).await?;
-            let elliptic_curve_pt =
-                eval_dy_prf(eval_ctx, record_id, &prf_key, &elliptic_curve_pt).await?;
-
-            Ok::<_, Error>(PrfShardedIpaInputRow {
-                prf_of_match_key: elliptic_curve_pt,
-                is_trigger_bit: record.is_trigger,
-                breakdown_key: record.breakdown_key,
-                trigger_value: record.trigger_value,
-                timestamp: record.timestamp,
-            })
+                convert_to_fp25519::<_, BA64>(convert_ctx, record_id, match_key).await?;
+            eval_dy_prf(eval_ctx, record_id, &prf_key, &elliptic_curve_pt).await
         }
     }))
     .await
 }
+
+/// Returns the ranges of `rows` that belong to the same user. Expects `rows` to be grouped by
+/// user already.
+fn user_ranges<R: GroupingKey>(rows: &[R]) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    for i in 1..=rows.len() {
+        if i == rows.len() || rows[i].get_grouping_key() != rows[start].get_grouping_key() {
+            ranges.push(start..i);
+            start = i;
+        }
+    }
+    ranges
+}
+
+/// Orders the rows of every user by timestamp. Expects `rows` to be grouped by user already,
+/// with the rows of a user in shuffled order.
+///
+/// The comparisons made by the sort are revealed, which is fine as long as the rows have been
+/// shuffled before the PRF was computed. Rows with equal timestamps keep their shuffled order,
+/// so attribution between source events with equal timestamps is up to the shuffle.
+async fn sort_by_timestamp_within_users<C, BK, TV, TS>(
+    ctx: C,
+    rows: &mut [PrfShardedIpaInputRow<BK, TV, TS>],
+) -> Result<(), Error>
+where
+    C: Context,
+    BK: WeakSharedValue,
+    TV: WeakSharedValue,
+    TS: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+    for<'a> &'a Replicated<TS>: IntoIterator<Item = Replicated<Boolean>>,
+{
+    let ranges = user_ranges(rows);
+    quicksort_ranges_by_key_insecure(
+        ctx.narrow(&Step::SortByTimestamp),
+        rows,
+        false,
+        |row| &row.timestamp,
+        ranges,
+    )
+    .await
+}
+
+/// A feature-label dot product input row, along with the timestamp it is ordered by.
+struct TimestampedRow<FV: WeakSharedValue, TS: WeakSharedValue> {
+    row: feature_label_dot_product::PrfShardedIpaInputRow<FV>,
+    timestamp: Replicated<TS>,
+}
+
+impl<FV: WeakSharedValue, TS: WeakSharedValue> GroupingKey for TimestampedRow<FV, TS> {
+    fn get_grouping_key(&self) -> u64 {
+        self.row.prf_of_match_key
+    }
+}
+
+/// Feature-label dot product protocol
+///
+/// The output of this function is a vector of secret-shared sums, one per feature: the number of
+/// source events with that feature which received attribution. It runs the same first steps as
+/// [`oprf_ipa`]:
+/// 1. Shuffles the input
+/// 2. Computes an OPRF of the match keys and reveals this "pseudonym"
+/// 3. Groups together rows with the same OPRF, and then sorts each group by the secret-shared
+///    timestamp, most recent first
+/// 4. Labels every source event that is followed by a trigger event of the same user, capping
+///    the number of labelled source events of every user at `config.per_user_credit_cap`
+/// 5. Adds up the feature vectors of all labelled source events
+/// # Errors
+/// Propagates errors from config issues or while running the protocol
+/// # Panics
+/// If the per-user credit cap in `config` is not a power of two.
+pub async fn feature_label_dot_product<C, FV, TS, F>(
+    ctx: C,
+    input_rows: Vec<FeatureLabelDotProductReport<FV, TS>>,
+    config: FeatureLabelDotProductQueryConfig,
+) -> Result<Vec<Replicated<F>>, Error>
+where
+    C: UpgradableContext,
+    C::UpgradedContext<Boolean>: UpgradedContext<Boolean, Share = Replicated<Boolean>>,
+    C::UpgradedContext<F>: UpgradedContext<F, Share = Replicated<F>>,
+    FV: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+    TS: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+    for<'a> &'a Replicated<TS>: IntoIterator<Item = Replicated<Boolean>>,
+    F: PrimeField + ExtendableField,
+    Replicated<F>: Serializable,
+{
+    let shuffled = shuffle_feature_label_reports(ctx.narrow(&Step::Shuffle), input_rows).await?;
+    let match_keys = shuffled
+        .iter()
+        .map(|row| row.match_key.clone())
+        .collect::<Vec<_>>();
+    let prfs =
+        compute_prf_of_match_keys(ctx.narrow(&Step::ConvertInputRowsToPrf), &match_keys).await?;
+
+    let mut rows = zip(shuffled, prfs)
+        .map(|(record, prf_of_match_key)| TimestampedRow {
+            row: feature_label_dot_product::PrfShardedIpaInputRow {
+                prf_of_match_key,
+                is_trigger_bit: record.is_trigger,
+                feature_vector: record.feature_vector,
+            },
+            timestamp: record.timestamp,
+        })
+        .collect::<Vec<_>>();
+
+    // The PRF is revealed, so grouping rows by user does not need to happen in MPC. Unlike
+    // `oprf_ipa`, the per-user circuit processes the rows of a user from newest to oldest.
+    rows.sort_by_key(GroupingKey::get_grouping_key);
+    let ranges = user_ranges(&rows);
+    quicksort_ranges_by_key_insecure(
+        ctx.narrow(&Step::SortByTimestamp),
+        &mut rows,
+        true,
+        |row| &row.timestamp,
+        ranges,
+    )
+    .await?;
+
+    let histogram = if rows.is_empty() {
+        Vec::new()
+    } else {
+        compute_histogram_of_users_with_row_count(&rows)
+    };
+
+    compute_feature_label_dot_product::<C, FV, F, Replicated<F>>(
+        ctx,
+        rows.into_iter().map(|r| r.row).collect(),
+        &histogram,
+        config.per_user_credit_cap,
+    )
+    .await
+}
+
 #[cfg(all(test, any(unit_test, feature = "shuttle")))]
 pub mod tests {
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
     use crate::{
         ff::{
             boolean_array::{BA20, BA3, BA5, BA8},
-            Fp31,
+            Field, Fp31, Fp32BitPrime, PrimeField,
         },
-        protocol::ipa_prf::oprf_ipa,
+        helpers::query::{DpParameter, InvalidReportPolicy, IpaQueryConfig},
+        protocol::{
+            context::Context,
+            dp::BinomialNoise,
+            ipa_prf::{
+                dummy_records::{add_dummy_records, DummyRecordParams},
+                oprf_ipa,
+            },
+        },
+        report::ReportVersion,
         test_executor::run,
-        test_fixture::{ipa::TestRawDataRecord, Reconstruct, Runner, TestWorld},
+        test_fixture::{
+            ipa::{ipa_in_the_clear, test_oprf_ipa, CappingOrder, TestRawDataRecord},
+            EventGenerator, EventGeneratorConfig, Reconstruct, Runner, TestWorld,
+        },
     };
 
+    fn test_input() -> Vec<TestRawDataRecord> {
+        vec![
+            TestRawDataRecord {
+                timestamp: 0,
+                user_id: 12345,
+                is_trigger_report: false,
+                breakdown_key: 1,
+                trigger_value: 0,
+            },
+            TestRawDataRecord {
+                timestamp: 5,
+                user_id: 12345,
+                is_trigger_report: false,
+                breakdown_key: 2,
+                trigger_value: 0,
+            },
+            TestRawDataRecord {
+                timestamp: 10,
+                user_id: 12345,
+                is_trigger_report: true,
+                breakdown_key: 0,
+                trigger_value: 5,
+            },
+            TestRawDataRecord {
+                timestamp: 0,
+                user_id: 68362,
+                is_trigger_report: false,
+                breakdown_key: 1,
+                trigger_value: 0,
+            },
+            TestRawDataRecord {
+                timestamp: 20,
+                user_id: 68362,
+                is_trigger_report: true,
+                breakdown_key: 0,
+                trigger_value: 2,
+            },
+        ]
+    }
+
     #[test]
     fn semi_honest() {
         const EXPECTED: &[u128] = &[0, 2, 5, 0, 0, 0, 0, 0];
@@ -165,52 +380,138 @@ pub mod tests {
         run(|| async {
             let world = TestWorld::default();
 
-            let records: Vec<TestRawDataRecord> = vec![
-                TestRawDataRecord {
-                    timestamp: 0,
-                    user_id: 12345,
-                    is_trigger_report: false,
-                    breakdown_key: 1,
-                    trigger_value: 0,
-                },
-                TestRawDataRecord {
-                    timestamp: 0,
-                    user_id: 12345,
-                    is_trigger_report: false,
-                    breakdown_key: 2,
-                    trigger_value: 0,
-                },
-                TestRawDataRecord {
-                    timestamp: 10,
-                    user_id: 12345,
-                    is_trigger_report: true,
-                    breakdown_key: 0,
-                    trigger_value: 5,
-                },
-                TestRawDataRecord {
-                    timestamp: 0,
-                    user_id: 68362,
-                    is_trigger_report: false,
-                    breakdown_key: 1,
-                    trigger_value: 0,
-                },
-                TestRawDataRecord {
-                    timestamp: 20,
-                    user_id: 68362,
-                    is_trigger_report: true,
-                    breakdown_key: 0,
-                    trigger_value: 2,
-                },
-            ];
+            let records = test_input();
 
             let mut result: Vec<_> = world
                 .semi_honest(records.into_iter(), |ctx, input_rows| async move {
-                    oprf_ipa::<_, BA8, BA3, BA20, BA5, Fp31>(ctx, input_rows, None)
+                    oprf_ipa::<_, BA8, BA3, BA20, BA5, Fp31>(
+                        ctx,
+                        input_rows,
+                        IpaQueryConfig::default(),
+                    )
+                    .await
+                    .unwrap()
+                })
+                .await
+                .reconstruct();
+            result.truncate(EXPECTED.len());
+            assert_eq!(
+                result,
+                EXPECTED
+                    .iter()
+                    .map(|i| Fp31::try_from(*i).unwrap())
+                    .collect::<Vec<_>>()
+            );
+        });
+    }
+
+    /// Dummy records must not change the result.
+    #[test]
+    fn semi_honest_with_dummy_records() {
+        const EXPECTED: &[u128] = &[0, 2, 5, 0, 0, 0, 0, 0];
+
+        run(|| async {
+            let world = TestWorld::default();
+
+            let records = test_input();
+            // A large budget and few cardinalities keep the number of dummy records small. The
+            // query itself would add dummy users with every cardinality it supports, so they are
+            // added here instead.
+            let params = DummyRecordParams::new(10.0, 0.5)
+                .unwrap()
+                .with_max_cardinality(4);
+
+            let mut result: Vec<_> = world
+                .semi_honest(records.into_iter(), |ctx, mut input_rows| async move {
+                    add_dummy_records(ctx.narrow("dummy_records"), params, &mut input_rows)
+                        .await
+                        .unwrap();
+                    oprf_ipa::<_, BA8, BA3, BA20, BA5, Fp31>(
+                        ctx,
+                        input_rows,
+                        IpaQueryConfig::default(),
+                    )
+                    .await
+                    .unwrap()
+                })
+                .await
+                .reconstruct();
+            result.truncate(EXPECTED.len());
+            assert_eq!(
+                result,
+                EXPECTED
+                    .iter()
+                    .map(|i| Fp31::try_from(*i).unwrap())
+                    .collect::<Vec<_>>()
+            );
+        });
+    }
+
+    /// Every breakdown gets noise that is bounded by the number of random bits used to make it.
+    #[test]
+    fn semi_honest_with_output_noise() {
+        const EXPECTED: &[u128] = &[0, 2, 5, 0, 0, 0, 0, 0];
+
+        run(|| async {
+            let world = TestWorld::default();
+
+            let records = test_input();
+            let config = IpaQueryConfig::default().with_output_noise(10.0, 0.5);
+            let bound = i64::try_from(
+                BinomialNoise::for_query(&config)
+                    .unwrap()
+                    .unwrap()
+                    .bits_per_side(),
+            )
+            .unwrap();
+
+            let result: Vec<Fp32BitPrime> = world
+                .semi_honest(records.into_iter(), |ctx, input_rows| async move {
+                    oprf_ipa::<_, BA8, BA3, BA20, BA5, Fp32BitPrime>(ctx, input_rows, config)
                         .await
                         .unwrap()
                 })
                 .await
                 .reconstruct();
+
+            let prime = i64::from(Fp32BitPrime::PRIME);
+            for (actual, &expected) in result.iter().zip(EXPECTED) {
+                let actual = i64::try_from(actual.as_u128()).unwrap();
+                let actual = if actual > prime / 2 {
+                    actual - prime
+                } else {
+                    actual
+                };
+                let noise = actual - i64::try_from(expected).unwrap();
+                assert!((-bound..=bound).contains(&noise), "noise is {noise}");
+            }
+        });
+    }
+
+    /// Helpers must not depend on the report collector grouping reports by user or sorting
+    /// them by time.
+    #[test]
+    fn unsorted_input() {
+        const EXPECTED: &[u128] = &[0, 2, 5, 0, 0, 0, 0, 0];
+
+        run(|| async {
+            let world = TestWorld::default();
+
+            let mut records = test_input();
+            records.reverse();
+
+            let mut result: Vec<_> = world
+                .semi_honest(records.into_iter(), |ctx, input_rows| async move {
+                    oprf_ipa::<_, BA8, BA3, BA20, BA5, Fp31>(
+                        ctx,
+                        input_rows,
+                        IpaQueryConfig::default(),
+                    )
+                    .await
+                    .unwrap()
+                })
+                .await
+                .reconstruct();
             result.truncate(EXPECTED.len());
             assert_eq!(
                 result,
@@ -221,4 +522,99 @@ pub mod tests {
             );
         });
     }
+
+    /// Sorting the rows of a user by timestamp must not need a pass for every row when the
+    /// timestamps are equal.
+    #[test]
+    fn many_equal_timestamps() {
+        // the attribution circuit supports up to 64 rows per user
+        const TRIGGERS: usize = 63;
+
+        run(|| async {
+            let world = TestWorld::default();
+
+            let records = std::iter::once(TestRawDataRecord {
+                timestamp: 0,
+                user_id: 12345,
+                is_trigger_report: false,
+                breakdown_key: 1,
+                trigger_value: 0,
+            })
+            .chain(std::iter::repeat_with(|| TestRawDataRecord {
+                timestamp: 10,
+                user_id: 12345,
+                is_trigger_report: true,
+                breakdown_key: 0,
+                trigger_value: 1,
+            }))
+            .take(TRIGGERS + 1)
+            .collect::<Vec<_>>();
+
+            let result: Vec<_> = world
+                .semi_honest(records.into_iter(), |ctx, input_rows| async move {
+                    oprf_ipa::<_, BA8, BA3, BA20, BA5, Fp32BitPrime>(
+                        ctx,
+                        input_rows,
+                        IpaQueryConfig::default(),
+                    )
+                    .await
+                    .unwrap()
+                })
+                .await
+                .reconstruct();
+            // the user's contribution is capped at 2^5
+            assert_eq!(result[1], Fp32BitPrime::truncate_from(32_u128));
+        });
+    }
+
+    #[test]
+    fn random_unsorted_input() {
+        const MAX_BREAKDOWN_KEY: u32 = 8;
+        const PER_USER_CAP: u32 = 8;
+
+        run(|| async {
+            let mut rng = StdRng::seed_from_u64(42);
+            let mut records = EventGenerator::with_config(
+                StdRng::seed_from_u64(7),
+                EventGeneratorConfig::new(5, 5, MAX_BREAKDOWN_KEY, 1, 10, 604_800),
+            )
+            .take(40)
+            .collect::<Vec<_>>();
+            records.shuffle(&mut rng);
+
+            let expected = ipa_in_the_clear(
+                &records,
+                PER_USER_CAP,
+                None,
+                MAX_BREAKDOWN_KEY,
+                &CappingOrder::CapMostRecentFirst,
+            );
+
+            test_oprf_ipa::<Fp32BitPrime>(
+                &TestWorld::default(),
+                records,
+                &expected,
+                IpaQueryConfig {
+                    per_user_credit_cap: PER_USER_CAP,
+                    max_breakdown_key: MAX_BREAKDOWN_KEY,
+                    attribution_window_seconds: None,
+                    num_multi_bits: 3,
+                    plaintext_match_keys: true,
+                    dummy_records_epsilon: None,
+                    dummy_records_delta: DpParameter::DEFAULT_DELTA,
+                    output_noise_epsilon: None,
+                    output_noise_delta: DpParameter::DEFAULT_DELTA,
+                    epoch: 0,
+                    min_report_epoch: None,
+                    max_report_epoch: None,
+                    breakdown_key_bits: 8,
+                    trigger_value_bits: 3,
+                    timestamp_bits: 20,
+                    invalid_report_policy: InvalidReportPolicy::Fail,
+                    report_version: ReportVersion::MatchKeyOnly,
+                },
+            )
+            .await;
+        });
+    }
 }
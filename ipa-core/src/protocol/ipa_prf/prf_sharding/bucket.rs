@@ -13,9 +13,13 @@ use crate::{
     },
 };
 
+/// The largest number of buckets [`move_single_value_to_bucket`] can move values to. It is
+/// constrained by the number of dynamic steps [`BucketStep`] can generate.
+pub const MAX_BREAKDOWNS: usize = 2048;
+
 #[derive(Step)]
 pub enum BucketStep {
-    #[dynamic(256)]
+    #[dynamic(1024)]
     Bit(usize),
 }
 
@@ -75,7 +79,7 @@ impl From<MoveToBucketError> for Error {
 /// This can be by passing `robust` as true.
 ///
 /// ## Errors
-/// If `breakdown_count` does not fit into `BK` bits or greater than or equal to $2^9$
+/// If `breakdown_count` does not fit into `BK` bits or greater than [`MAX_BREAKDOWNS`]
 pub async fn move_single_value_to_bucket<C, S, F>(
     ctx: C,
     record_id: RecordId,
@@ -89,7 +93,6 @@ where
     S: LinearSecretSharing<F> + Serializable + SecureMul<C>,
     F: PrimeField + ExtendableField,
 {
-    const MAX_BREAKDOWNS: usize = 512; // constrained by the compact step ability to generate dynamic steps
     let mut step: usize = 1 << bd_key.len();
 
     if breakdown_count > step {
@@ -100,10 +103,9 @@ where
     }
 
     if breakdown_count > MAX_BREAKDOWNS {
-        Err(MoveToBucketError::InvalidBreakdownKey(
+        Err(MoveToBucketError::InvalidBreakdownKey(format!(
             "Our step implementation (BucketStep) cannot go past {MAX_BREAKDOWNS} breakdown keys"
-                .to_string(),
-        ))?;
+        )))?;
     }
 
     let mut row_contribution = vec![value; breakdown_count];
@@ -149,7 +151,7 @@ pub mod tests {
         ff::{Field, Fp32BitPrime, Gf8Bit, Gf9Bit},
         protocol::{
             context::{Context, UpgradableContext, Validator},
-            ipa_prf::prf_sharding::bucket::move_single_value_to_bucket,
+            ipa_prf::prf_sharding::bucket::{move_single_value_to_bucket, MAX_BREAKDOWNS},
             RecordId,
         },
         rand::Rng,
@@ -281,4 +283,33 @@ pub mod tests {
                 .await;
         });
     }
+
+    #[test]
+    #[should_panic(expected = "cannot go past")]
+    fn move_out_of_range_too_many_buckets_max_breakdowns() {
+        run(move || async move {
+            let breakdown_key_bits = get_bits::<Fp32BitPrime>(0, 12);
+            let value = Fp32BitPrime::truncate_from(VALUE);
+
+            _ = TestWorld::default()
+                .semi_honest(
+                    (breakdown_key_bits, value),
+                    |ctx, (breakdown_key_share, value_share)| async move {
+                        let validator = ctx.validator();
+                        let ctx = validator.context();
+                        move_single_value_to_bucket::<_, _, Fp32BitPrime>(
+                            ctx.set_total_records(1),
+                            RecordId::from(0),
+                            breakdown_key_share,
+                            value_share,
+                            MAX_BREAKDOWNS + 1,
+                            false,
+                        )
+                        .await
+                        .unwrap()
+                    },
+                )
+                .await;
+        });
+    }
 }
@@ -1,4 +1,4 @@
-use std::{iter::zip, pin::pin};
+use std::{iter::zip, ops::Not, pin::pin};
 
 use futures::{stream::iter as stream_iter, TryStreamExt};
 use futures_util::{future::try_join, stream::unfold, Stream, StreamExt};
@@ -6,7 +6,7 @@ use ipa_macros::Step;
 
 use crate::{
     error::Error,
-    ff::{Field, GaloisField, Gf2, PrimeField, Serializable},
+    ff::{boolean::Boolean, ArrayAccess, CustomArray, Field, PrimeField, Serializable},
     protocol::{
         basics::{SecureMul, ShareKnownValue},
         boolean::or::or,
@@ -16,24 +16,22 @@ use crate::{
         RecordId,
     },
     secret_sharing::{
-        replicated::{
-            malicious::ExtendableField, semi_honest::AdditiveShare as Replicated,
-            ReplicatedSecretSharing,
-        },
-        BitDecomposed, Linear as LinearSecretSharing,
+        replicated::{malicious::ExtendableField, semi_honest::AdditiveShare as Replicated},
+        BitDecomposed, Linear as LinearSecretSharing, WeakSharedValue,
     },
     seq_join::seq_join,
 };
 
-pub struct PrfShardedIpaInputRow<FV: GaloisField> {
-    prf_of_match_key: u64,
-    is_trigger_bit: Replicated<Gf2>,
-    feature_vector: Replicated<FV>,
+pub struct PrfShardedIpaInputRow<FV: WeakSharedValue> {
+    pub prf_of_match_key: u64,
+    pub is_trigger_bit: Replicated<Boolean>,
+    pub feature_vector: Replicated<FV>,
 }
 
 struct InputsRequiredFromPrevRow {
-    ever_encountered_a_trigger_event: Replicated<Gf2>,
-    is_saturated: Replicated<Gf2>,
+    ever_encountered_a_trigger_event: Replicated<Boolean>,
+    attributed_source_count: Vec<Replicated<Boolean>>,
+    is_saturated: Replicated<Boolean>,
 }
 
 impl InputsRequiredFromPrevRow {
@@ -58,13 +56,13 @@ impl InputsRequiredFromPrevRow {
         ctx: C,
         record_id: RecordId,
         input_row: &PrfShardedIpaInputRow<FV>,
-    ) -> Result<BitDecomposed<Replicated<Gf2>>, Error>
+    ) -> Result<BitDecomposed<Replicated<Boolean>>, Error>
     where
-        C: UpgradedContext<Gf2, Share = Replicated<Gf2>>,
-        FV: GaloisField,
+        C: Context,
+        FV: WeakSharedValue + CustomArray<Element = Boolean>,
     {
-        let share_of_one = Replicated::share_known_value(&ctx, Gf2::ONE);
-        let is_source_event = &share_of_one - &input_row.is_trigger_bit;
+        let share_of_one = Replicated::share_known_value(&ctx, Boolean::ONE);
+        let is_source_event = input_row.is_trigger_bit.clone().not();
 
         let (ever_encountered_a_trigger_event, did_source_get_attributed) = try_join(
             or(
@@ -81,13 +79,26 @@ impl InputsRequiredFromPrevRow {
         )
         .await?;
 
-        let (updated_is_saturated, capped_label) = try_join(
-            or(
+        let update_saturation = async {
+            let (attributed_source_count, carry) = increment_count(
+                ctx.narrow(&Step::IncrementAttributedSourceCount),
+                record_id,
+                &self.attributed_source_count,
+                &did_source_get_attributed,
+            )
+            .await?;
+            let is_saturated = or(
                 ctx.narrow(&Step::ComputeSaturatingSum),
                 record_id,
                 &self.is_saturated,
-                &did_source_get_attributed,
-            ),
+                &carry,
+            )
+            .await?;
+            Ok::<_, Error>((attributed_source_count, is_saturated))
+        };
+
+        let ((attributed_source_count, updated_is_saturated), capped_label) = try_join(
+            update_saturation,
             did_source_get_attributed.multiply(
                 &(share_of_one - &self.is_saturated),
                 ctx.narrow(&Step::IsAttributedSourceAndPrevRowNotSaturated),
@@ -96,19 +107,16 @@ impl InputsRequiredFromPrevRow {
         )
         .await?;
 
-        let unbitpacked_feature_vector = BitDecomposed::decompose(FV::BITS, |i| {
-            input_row.feature_vector.map(|v| Gf2::truncate_from(v[i]))
-        });
-
         let capped_attributed_feature_vector = compute_capped_feature_vector(
             ctx.narrow(&Step::ComputedCappedFeatureVector),
             record_id,
             &capped_label,
-            &unbitpacked_feature_vector,
+            &input_row.feature_vector,
         )
         .await?;
 
         self.ever_encountered_a_trigger_event = ever_encountered_a_trigger_event;
+        self.attributed_source_count = attributed_source_count;
         self.is_saturated = updated_is_saturated;
 
         Ok(capped_attributed_feature_vector)
@@ -116,12 +124,12 @@ impl InputsRequiredFromPrevRow {
 }
 
 #[derive(Step)]
-pub enum UserNthRowStep {
+pub enum UserNthRowFeatureLabelStep {
     #[dynamic(64)]
     Row(usize),
 }
 
-impl From<usize> for UserNthRowStep {
+impl From<usize> for UserNthRowFeatureLabelStep {
     fn from(v: usize) -> Self {
         Self::Row(v)
     }
@@ -133,6 +141,7 @@ pub(crate) enum Step {
     PrimeFieldValidator,
     EverEncounteredTriggerEvent,
     DidSourceReceiveAttribution,
+    IncrementAttributedSourceCount,
     ComputeSaturatingSum,
     IsAttributedSourceAndPrevRowNotSaturated,
     ComputedCappedFeatureVector,
@@ -141,7 +150,7 @@ pub(crate) enum Step {
 
 fn set_up_contexts<C>(root_ctx: &C, histogram: &[usize]) -> Vec<C>
 where
-    C: UpgradedContext<Gf2, Share = Replicated<Gf2>>,
+    C: Context,
 {
     let mut context_per_row_depth = Vec::with_capacity(histogram.len());
     for (row_number, num_users_having_that_row_number) in histogram.iter().enumerate() {
@@ -149,7 +158,7 @@ where
             // no multiplications needed for each user's row 0. No context needed
         } else {
             let ctx_for_row_number = root_ctx
-                .narrow(&UserNthRowStep::from(row_number))
+                .narrow(&UserNthRowFeatureLabelStep::from(row_number))
                 .set_total_records(*num_users_having_that_row_number);
             context_per_row_depth.push(ctx_for_row_number);
         }
@@ -166,7 +175,7 @@ fn chunk_rows_by_user<FV, IS>(
     first_row: PrfShardedIpaInputRow<FV>,
 ) -> impl Stream<Item = Vec<PrfShardedIpaInputRow<FV>>>
 where
-    FV: GaloisField,
+    FV: WeakSharedValue,
     IS: Stream<Item = PrfShardedIpaInputRow<FV>> + Unpin,
 {
     unfold(Some((input_stream, first_row)), |state| async move {
@@ -193,7 +202,8 @@ where
 /// This circuit expects to receive records from multiple users,
 /// but with all of the records from a given user adjacent to one another, and in reverse time order (most recent event comes first).
 ///
-/// This circuit will compute attribution, and per-user capping.
+/// This circuit will compute attribution, and per-user capping. At most `per_user_credit_cap`
+/// source events of every user receive attribution.
 ///
 /// After those steps, source events to which trigger events were attributed will contribute their feature vectors to an aggregate
 ///
@@ -218,24 +228,27 @@ where
 /// # Errors
 /// Propagates errors from multiplications
 /// # Panics
-/// Propagates errors from multiplications
+/// If `per_user_credit_cap` is not a power of two.
 pub async fn compute_feature_label_dot_product<C, FV, F, S>(
     sh_ctx: C,
     input_rows: Vec<PrfShardedIpaInputRow<FV>>,
     histogram: &[usize],
+    per_user_credit_cap: u32,
 ) -> Result<Vec<S>, Error>
 where
     C: UpgradableContext,
-    C::UpgradedContext<Gf2>: UpgradedContext<Gf2, Share = Replicated<Gf2>>,
+    C::UpgradedContext<Boolean>: UpgradedContext<Boolean, Share = Replicated<Boolean>>,
     C::UpgradedContext<F>: UpgradedContext<F, Share = S>,
     S: LinearSecretSharing<F> + Serializable + SecureMul<C::UpgradedContext<F>>,
-    FV: GaloisField,
+    FV: WeakSharedValue + CustomArray<Element = Boolean> + Field,
     F: PrimeField + ExtendableField,
 {
-    assert!(FV::BITS > 0);
+    assert!(<FV as WeakSharedValue>::BITS > 0);
+    assert!(per_user_credit_cap.is_power_of_two());
+    let count_bits = per_user_credit_cap.trailing_zeros();
 
-    // Get the validator and context to use for Gf2 multiplication operations
-    let binary_validator = sh_ctx.narrow(&Step::BinaryValidator).validator::<Gf2>();
+    // Get the validator and context to use for Boolean multiplication operations
+    let binary_validator = sh_ctx.narrow(&Step::BinaryValidator).validator::<Boolean>();
     let binary_m_ctx = binary_validator.context();
 
     // Get the validator and context to use for `Z_p` operations (modulus conversion)
@@ -243,17 +256,18 @@ where
     let prime_field_ctx = prime_field_validator.context();
 
     // Tricky hacks to work around the limitations of our current infrastructure
-    let num_outputs = input_rows.len() - histogram[0];
+    let num_outputs = input_rows.len() - histogram.first().copied().unwrap_or_default();
     let mut record_id_for_row_depth = vec![0_u32; histogram.len()];
     let ctx_for_row_number = set_up_contexts(&binary_m_ctx, histogram);
 
     // Chunk the incoming stream of records into stream of vectors of records with the same PRF
     let mut input_stream = stream_iter(input_rows);
-    let first_row = input_stream.next().await;
-    if first_row.is_none() {
-        return Ok(vec![]);
-    }
-    let first_row = first_row.unwrap();
+    let Some(first_row) = input_stream.next().await else {
+        return Ok(vec![
+            S::ZERO;
+            usize::try_from(<FV as WeakSharedValue>::BITS).unwrap()
+        ]);
+    };
     let rows_chunked_by_user = chunk_rows_by_user(input_stream, first_row);
 
     // Convert to a stream of async futures that represent the result of executing the per-user circuit
@@ -268,13 +282,17 @@ where
         #[allow(clippy::async_yields_async)]
         // this is ok, because seq join wants a stream of futures
         async move {
-            evaluate_per_user_attribution_circuit(contexts, record_ids, rows_for_user)
+            evaluate_per_user_attribution_circuit(contexts, record_ids, rows_for_user, count_bits)
         }
     }));
 
-    // Execute all of the async futures (sequentially), and flatten the result
-    let flattened_stream = seq_join(sh_ctx.active_work(), stream_of_per_user_circuits)
-        .flat_map(|x| stream_iter(x.unwrap()));
+    // Execute all of the async futures (sequentially), and flatten the result.
+    // As in `attribute_cap_aggregate`, the per-user circuits need to finish before modulus
+    // conversion starts, or helpers can end up waiting on each other.
+    let per_user_outputs = seq_join(sh_ctx.active_work(), stream_of_per_user_circuits)
+        .try_collect::<Vec<_>>()
+        .await?;
+    let flattened_stream = stream_iter(per_user_outputs.into_iter().flatten());
 
     // modulus convert feature vector bits from shares in `Z_2` to shares in `Z_p`
     let converted_feature_vector_bits = convert_bits(
@@ -282,13 +300,13 @@ where
             .narrow(&Step::ModulusConvertFeatureVectorBits)
             .set_total_records(num_outputs),
         flattened_stream,
-        0..FV::BITS,
+        0..<FV as WeakSharedValue>::BITS,
     );
 
     // Sum up all the vectors
     converted_feature_vector_bits
         .try_fold(
-            vec![S::ZERO; usize::try_from(FV::BITS).unwrap()],
+            vec![S::ZERO; usize::try_from(<FV as WeakSharedValue>::BITS).unwrap()],
             |mut running_sums, row_contribution| async move {
                 for (i, contribution) in row_contribution.iter().enumerate() {
                     running_sums[i] += contribution;
@@ -303,17 +321,18 @@ async fn evaluate_per_user_attribution_circuit<C, FV>(
     ctx_for_row_number: Vec<C>,
     record_id_for_each_depth: Vec<u32>,
     rows_for_user: Vec<PrfShardedIpaInputRow<FV>>,
-) -> Result<Vec<BitDecomposed<Replicated<Gf2>>>, Error>
+    count_bits: u32,
+) -> Result<Vec<BitDecomposed<Replicated<Boolean>>>, Error>
 where
-    C: UpgradedContext<Gf2, Share = Replicated<Gf2>>,
-    FV: GaloisField,
+    C: Context,
+    FV: WeakSharedValue + CustomArray<Element = Boolean>,
 {
     assert!(!rows_for_user.is_empty());
     if rows_for_user.len() == 1 {
         return Ok(Vec::new());
     }
     let first_row = &rows_for_user[0];
-    let mut prev_row_inputs = initialize_new_device_attribution_variables(first_row);
+    let mut prev_row_inputs = initialize_new_device_attribution_variables(first_row, count_bits);
 
     let mut output = Vec::with_capacity(rows_for_user.len() - 1);
     // skip the first row as it requires no multiplications
@@ -339,30 +358,61 @@ where
 ///
 fn initialize_new_device_attribution_variables<FV>(
     input_row: &PrfShardedIpaInputRow<FV>,
+    count_bits: u32,
 ) -> InputsRequiredFromPrevRow
 where
-    FV: GaloisField,
+    FV: WeakSharedValue,
 {
     InputsRequiredFromPrevRow {
         ever_encountered_a_trigger_event: input_row.is_trigger_bit.clone(),
+        attributed_source_count: vec![Replicated::ZERO; usize::try_from(count_bits).unwrap()],
         is_saturated: Replicated::ZERO,
     }
 }
 
-async fn compute_capped_feature_vector<C>(
+///
+/// Adds `increment` to the little-endian bits of `count` with a chain of half adders. Returns the
+/// new value of `count` and the carry out of its most significant bit, which is set when the
+/// count wraps around. With no bits at all, the carry is `increment` itself.
+///
+async fn increment_count<C>(
     ctx: C,
     record_id: RecordId,
-    capped_label: &Replicated<Gf2>,
-    feature_vector: &BitDecomposed<Replicated<Gf2>>,
-) -> Result<BitDecomposed<Replicated<Gf2>>, Error>
+    count: &[Replicated<Boolean>],
+    increment: &Replicated<Boolean>,
+) -> Result<(Vec<Replicated<Boolean>>, Replicated<Boolean>), Error>
 where
-    C: UpgradedContext<Gf2, Share = Replicated<Gf2>>,
+    C: Context,
+{
+    let mut carry = increment.clone();
+    let mut updated_count = Vec::with_capacity(count.len());
+    for (i, bit) in count.iter().enumerate() {
+        updated_count.push(bit + &carry);
+        carry = bit
+            .multiply(&carry, ctx.narrow(&BitOpStep::from(i)), record_id)
+            .await?;
+    }
+    Ok((updated_count, carry))
+}
+
+async fn compute_capped_feature_vector<C, FV>(
+    ctx: C,
+    record_id: RecordId,
+    capped_label: &Replicated<Boolean>,
+    feature_vector: &Replicated<FV>,
+) -> Result<BitDecomposed<Replicated<Boolean>>, Error>
+where
+    C: Context,
+    FV: WeakSharedValue + CustomArray<Element = Boolean>,
 {
     Ok(BitDecomposed::new(
-        ctx.parallel_join(feature_vector.iter().enumerate().map(|(i, bit)| {
-            let c1 = ctx.narrow(&BitOpStep::from(i));
-            async move { capped_label.multiply(bit, c1, record_id).await }
-        }))
+        ctx.parallel_join(
+            (0..usize::try_from(<FV as WeakSharedValue>::BITS).unwrap()).map(|i| {
+                let c1 = ctx.narrow(&BitOpStep::from(i));
+                let bit = feature_vector.get(i).unwrap();
+                async move { capped_label.multiply(&bit, c1, record_id).await }
+            }),
+        )
         .await?,
     ))
 }
@@ -370,21 +420,21 @@ where
 #[cfg(all(test, unit_test))]
 pub mod tests {
     use crate::{
-        ff::{Field, Fp32BitPrime, GaloisField, Gf2, Gf32Bit},
+        ff::{boolean::Boolean, boolean_array::BA32, Field, Fp32BitPrime},
         protocol::ipa_prf::prf_sharding::feature_label_dot_product::{
             compute_feature_label_dot_product, PrfShardedIpaInputRow,
         },
         rand::Rng,
         secret_sharing::{
-            replicated::semi_honest::AdditiveShare as Replicated, IntoShares, SharedValue,
+            replicated::semi_honest::AdditiveShare as Replicated, IntoShares, WeakSharedValue,
         },
         test_executor::run,
         test_fixture::{Reconstruct, Runner, TestWorld},
     };
 
-    struct PreShardedAndSortedOPRFTestInput<FV: GaloisField> {
+    struct PreShardedAndSortedOPRFTestInput<FV: WeakSharedValue> {
         prf_of_match_key: u64,
-        is_trigger_bit: Gf2,
+        is_trigger_bit: Boolean,
         feature_vector: FV,
     }
 
@@ -392,19 +442,17 @@ pub mod tests {
         prf_of_match_key: u64,
         is_trigger: bool,
         feature_vector: u32,
-    ) -> PreShardedAndSortedOPRFTestInput<Gf32Bit> {
-        let is_trigger_bit = if is_trigger { Gf2::ONE } else { Gf2::ZERO };
-
+    ) -> PreShardedAndSortedOPRFTestInput<BA32> {
         PreShardedAndSortedOPRFTestInput {
             prf_of_match_key,
-            is_trigger_bit,
-            feature_vector: Gf32Bit::truncate_from(feature_vector),
+            is_trigger_bit: Boolean::from(is_trigger),
+            feature_vector: BA32::truncate_from(feature_vector),
         }
     }
 
     impl<FV> IntoShares<PrfShardedIpaInputRow<FV>> for PreShardedAndSortedOPRFTestInput<FV>
     where
-        FV: GaloisField + IntoShares<Replicated<FV>>,
+        FV: WeakSharedValue + IntoShares<Replicated<FV>>,
     {
         fn share_with<R: Rng>(self, rng: &mut R) -> [PrfShardedIpaInputRow<FV>; 3] {
             let PreShardedAndSortedOPRFTestInput {
@@ -443,7 +491,7 @@ pub mod tests {
         run(|| async move {
             let world = TestWorld::default();
 
-            let records: Vec<PreShardedAndSortedOPRFTestInput<Gf32Bit>> = vec![
+            let records: Vec<PreShardedAndSortedOPRFTestInput<BA32>> = vec![
                 /* First User */
                 test_input(123, true, 0b0000_0000_0000_0000_0000_0000_0000_0000), // trigger
                 test_input(123, false, 0b1101_0100_1111_0001_0111_0010_1010_1011), // this source DOES receive attribution
@@ -482,10 +530,52 @@ pub mod tests {
                     async move {
                         compute_feature_label_dot_product::<
                             _,
-                            Gf32Bit,
+                            BA32,
+                            Fp32BitPrime,
+                            Replicated<Fp32BitPrime>,
+                        >(ctx, input_rows, h, 1)
+                        .await
+                        .unwrap()
+                    }
+                })
+                .await
+                .reconstruct();
+            assert_eq!(result, &expected);
+        });
+    }
+
+    #[test]
+    fn per_user_credit_cap() {
+        run(|| async move {
+            let world = TestWorld::default();
+
+            let records: Vec<PreShardedAndSortedOPRFTestInput<BA32>> = vec![
+                /* First User */
+                test_input(123, true, 0),
+                test_input(123, false, 0b0001), // receives attribution
+                test_input(123, true, 0),
+                test_input(123, false, 0b0011), // receives attribution
+                test_input(123, false, 0b0111), // capped
+                /* Second User */
+                test_input(234, false, 0b1111), // no trigger event after it
+                test_input(234, true, 0),
+                test_input(234, false, 0b1000), // receives attribution
+            ];
+            let mut expected = [0_u128; 32];
+            expected[..4].copy_from_slice(&[2, 1, 0, 1]);
+
+            let histogram = vec![2, 2, 2, 1, 1];
+
+            let result: Vec<Fp32BitPrime> = world
+                .semi_honest(records.into_iter(), |ctx, input_rows| {
+                    let h = histogram.as_slice();
+                    async move {
+                        compute_feature_label_dot_product::<
+                            _,
+                            BA32,
                             Fp32BitPrime,
                             Replicated<Fp32BitPrime>,
-                        >(ctx, input_rows, h)
+                        >(ctx, input_rows, h, 2)
                         .await
                         .unwrap()
                     }
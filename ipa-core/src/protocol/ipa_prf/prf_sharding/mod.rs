@@ -16,6 +16,7 @@ use crate::{
         basics::{if_else, SecureMul, ShareKnownValue},
         boolean::or::or,
         context::{Context, UpgradableContext, UpgradedContext, Validator},
+        dp::{add_binomial_noise, BinomialNoise},
         ipa_prf::boolean_ops::{
             addition_sequential::integer_add,
             comparison_and_subtraction_sequential::{compare_gt, integer_sub},
@@ -34,7 +35,6 @@ use crate::{
 };
 
 pub mod bucket;
-#[cfg(feature = "descriptive-gate")]
 pub mod feature_label_dot_product;
 
 #[derive(Debug)]
@@ -305,6 +305,7 @@ pub(crate) enum Step {
     ComputedCappedAttributedTriggerValueJustSaturatedCase,
     ModulusConvertBreakdownKeyBitsAndTriggerValues,
     MoveValueToCorrectBreakdown,
+    AddNoise,
 }
 
 pub trait GroupingKey {
@@ -396,7 +397,9 @@ where
 ///
 /// This circuit will compute attribution, and per-user capping.
 ///
-/// The output of this circuit is the input to the next stage: Aggregation.
+/// The output of this circuit is the input to the next stage: Aggregation. It produces
+/// `breakdown_count` totals, one for each breakdown key below it. If `output_noise` is
+/// set, the aggregated totals are noised before being returned.
 ///
 /// # Errors
 /// Propagates errors from multiplications
@@ -407,6 +410,8 @@ pub async fn attribute_cap_aggregate<C, BK, TV, TS, SS, S, F>(
     input_rows: Vec<PrfShardedIpaInputRow<BK, TV, TS>>,
     attribution_window_seconds: Option<NonZeroU32>,
     histogram: &[usize],
+    breakdown_count: usize,
+    output_noise: Option<BinomialNoise>,
 ) -> Result<Vec<S>, Error>
 where
     C: UpgradableContext,
@@ -472,9 +477,15 @@ where
         }
     }));
 
-    // Execute all of the async futures (sequentially), and flatten the result
-    let flattenned_stream = seq_join(sh_ctx.active_work(), stream_of_per_user_circuits)
-        .flat_map(|x| stream_iter(x.unwrap()));
+    // Execute all of the async futures (sequentially), and flatten the result.
+    // The per-user circuits need to finish before modulus conversion starts. Otherwise helpers
+    // can end up waiting on each other: one that is blocked in modulus conversion stops making
+    // progress on the per-user circuits, and never flushes the partially filled send buffers
+    // that the other helpers need to reach the same point in modulus conversion.
+    let per_user_outputs = seq_join(sh_ctx.active_work(), stream_of_per_user_circuits)
+        .try_collect::<Vec<_>>()
+        .await?;
+    let flattenned_stream = stream_iter(per_user_outputs.into_iter().flatten());
 
     // modulus convert breakdown keys and trigger values
     let converted_bks_and_tvs = convert_bits(
@@ -503,7 +514,7 @@ where
                     record_id,
                     bk_bits,
                     BitDecomposed::to_additive_sharing_in_large_field_consuming(tv_bits),
-                    1 << <BK as WeakSharedValue>::BITS,
+                    breakdown_count,
                     false,
                 )
                 .await
@@ -512,9 +523,9 @@ where
 
     // aggregate all row level contributions
     let row_contributions = seq_join(prime_field_ctx.active_work(), row_contributions_stream);
-    row_contributions
+    let aggregated = row_contributions
         .try_fold(
-            vec![S::ZERO; 1 << <BK as WeakSharedValue>::BITS],
+            vec![S::ZERO; breakdown_count],
             |mut running_sums, row_contribution| async move {
                 for (i, contribution) in row_contribution.iter().enumerate() {
                     running_sums[i] += contribution;
@@ -522,7 +533,14 @@ where
                 Ok(running_sums)
             },
         )
-        .await
+        .await?;
+
+    match output_noise {
+        Some(noise) => {
+            add_binomial_noise(prime_field_ctx.narrow(&Step::AddNoise), noise, aggregated).await
+        }
+        None => Ok(aggregated),
+    }
 }
 
 async fn evaluate_per_user_attribution_circuit<C, BK, TV, TS, SS>(
@@ -1019,7 +1037,7 @@ pub mod tests {
                         BA5,
                         Replicated<Fp32BitPrime>,
                         Fp32BitPrime,
-                    >(ctx, input_rows, None, &histogram)
+                    >(ctx, input_rows, None, &histogram, expected.len(), None)
                     .await
                     .unwrap()
                 })
@@ -1078,6 +1096,8 @@ pub mod tests {
                         input_rows,
                         NonZeroU32::new(ATTRIBUTION_WINDOW_SECONDS),
                         &histogram,
+                        expected.len(),
+                        None,
                     )
                     .await
                     .unwrap()
@@ -1166,7 +1186,7 @@ pub mod tests {
                         SaturatingSumType,
                         Replicated<Fp32BitPrime>,
                         Fp32BitPrime,
-                    >(ctx, input_rows, None, &HISTOGRAM)
+                    >(ctx, input_rows, None, &HISTOGRAM, expected.len(), None)
                     .await
                     .unwrap()
                 })
@@ -1175,4 +1195,53 @@ pub mod tests {
             assert_eq!(result, &expected);
         });
     }
+
+    /// With more users sharing a row depth than the active work size, helpers used to end up
+    /// waiting on each other between the per-user circuits and modulus conversion.
+    #[test]
+    fn more_users_than_active_work() {
+        const USERS: u64 = 75;
+
+        run(|| async move {
+            let world = TestWorld::default();
+
+            // user `u` has a source event followed by `u % 5` trigger events
+            let records: Vec<PreShardedAndSortedOPRFTestInput<BA8, BA3, BA20>> = (0..USERS)
+                .flat_map(|user| {
+                    std::iter::once(oprf_test_input(user, false, 17, 0))
+                        .chain((0..user % 5).map(move |_| oprf_test_input(user, true, 0, 1)))
+                })
+                .collect();
+            // the number of users with more than `i` rows
+            let histogram = (0..5)
+                .map(|i| (0..USERS).filter(|user| user % 5 >= i).count())
+                .collect::<Vec<_>>();
+
+            let mut expected = [0_u128; 256];
+            expected[17] = (0..USERS).map(|user| u128::from(user % 5)).sum();
+
+            let result: Vec<_> = world
+                .semi_honest(records.into_iter(), |ctx, input_rows| {
+                    let histogram = histogram.clone();
+                    async move {
+                        attribute_cap_aggregate::<
+                            _,
+                            BA8,
+                            BA3,
+                            BA20,
+                            BA5,
+                            Replicated<Fp32BitPrime>,
+                            Fp32BitPrime,
+                        >(
+                            ctx, input_rows, None, &histogram, expected.len(), None
+                        )
+                        .await
+                        .unwrap()
+                    }
+                })
+                .await
+                .reconstruct();
+            assert_eq!(result, &expected);
+        });
+    }
 }
@@ -0,0 +1,305 @@
+use std::ops::Range;
+
+use ipa_macros::Step;
+
+use crate::{
+    error::Error,
+    ff::{boolean::Boolean, CustomArray, Field},
+    protocol::{
+        basics::Reveal, context::Context,
+        ipa_prf::boolean_ops::comparison_and_subtraction_sequential::compare_gt, RecordId,
+    },
+    secret_sharing::{replicated::semi_honest::AdditiveShare, WeakSharedValue},
+};
+
+/// The number of passes the sort is allowed to make. Must match the `dynamic` attribute of
+/// [`QuicksortStep::QuicksortPass`].
+const MAX_PASSES: usize = 64;
+
+#[derive(Step)]
+pub(crate) enum QuicksortStep {
+    #[dynamic(64)]
+    QuicksortPass(usize),
+    Compare,
+    Reveal,
+}
+
+/// Sorts each of `ranges_to_sort` within `list` by the secret-shared key returned by `get_key`,
+/// in ascending order (descending if `desc` is set). Ranges must not overlap. The sort is
+/// stable: elements with equal keys keep their relative order in `list`.
+///
+/// This is a quicksort in which the result of every comparison against a pivot is revealed.
+/// Every element is compared against a pivot once, with ties broken by position, so the revealed
+/// results are exactly those of sorting by key and then by position. This is only safe if
+/// `list` has been shuffled beforehand: the comparisons then reveal the permutation that sorts
+/// the shuffled elements, which is uniformly random if all keys are distinct. Elements with
+/// equal keys stay in their shuffled order, so which elements have equal keys is not revealed,
+/// but the distribution of the permutation depends a little on how many of them there are.
+/// Making sure the input is shuffled is the responsibility of the caller, hence the `insecure`
+/// suffix.
+/// # Errors
+/// Propagates errors from the comparison circuit or from revealing the comparison results.
+/// Returns [`Error::Unsupported`] if sorting takes more than 64 passes, which does not happen
+/// for shuffled input unless a range is astronomically large.
+pub async fn quicksort_ranges_by_key_insecure<C, K, F, S>(
+    ctx: C,
+    list: &mut [S],
+    desc: bool,
+    get_key: F,
+    mut ranges_to_sort: Vec<Range<usize>>,
+) -> Result<(), Error>
+where
+    C: Context,
+    S: Send + Sync,
+    F: Fn(&S) -> &AdditiveShare<K> + Sync + Send + Copy,
+    K: WeakSharedValue + Field + CustomArray<Element = Boolean>,
+    for<'a> &'a AdditiveShare<K>: IntoIterator<Item = AdditiveShare<Boolean>>,
+{
+    ranges_to_sort.retain(|r| r.len() > 1);
+
+    // where every element was in `list` before sorting, to order equal keys by
+    let mut positions = (0..list.len()).collect::<Vec<_>>();
+    let mut pass = 0;
+    while !ranges_to_sort.is_empty() {
+        if pass == MAX_PASSES {
+            return Err(Error::Unsupported(format!(
+                "quicksort did not finish in {MAX_PASSES} passes"
+            )));
+        }
+
+        // every element in a range, apart from the pivot, is compared against the pivot
+        let num_comparisons = ranges_to_sort.iter().map(|r| r.len() - 1).sum::<usize>();
+        let ctx = ctx
+            .narrow(&QuicksortStep::QuicksortPass(pass))
+            .set_total_records(num_comparisons);
+        let compare_ctx = ctx.narrow(&QuicksortStep::Compare);
+        let reveal_ctx = ctx.narrow(&QuicksortStep::Reveal);
+
+        // Partitioning keeps equal keys in position order, so taking the middle element as the
+        // pivot splits a range of equal keys in half.
+        let (elements, positions_before) = (&*list, &positions);
+        let pairs = ranges_to_sort
+            .iter()
+            .flat_map(|r| {
+                let pivot = r.start + r.len() / 2;
+                r.clone().filter(move |&i| i != pivot).map(move |i| {
+                    let (key, pivot_key) = (get_key(&elements[i]), get_key(&elements[pivot]));
+                    // If `x` comes first, it goes before the pivot unless it orders after it,
+                    // otherwise only if it orders before it.
+                    let x_first = positions_before[i] < positions_before[pivot];
+                    let (lhs, rhs) = if x_first == desc {
+                        (pivot_key, key)
+                    } else {
+                        (key, pivot_key)
+                    };
+                    (lhs.clone(), rhs.clone(), !x_first)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let before_pivot = ctx
+            .try_join(
+                pairs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (lhs, rhs, before_if_gt))| {
+                        let compare_ctx = compare_ctx.clone();
+                        let reveal_ctx = reveal_ctx.clone();
+                        async move {
+                            let record_id = RecordId::from(i);
+                            let gt = compare_gt(compare_ctx, record_id, &lhs, &rhs)
+                                .await?
+                                .reveal(reveal_ctx, record_id)
+                                .await?;
+                            Ok::<_, Error>((gt == Boolean::ONE) == before_if_gt)
+                        }
+                    }),
+            )
+            .await?;
+
+        let mut before_pivot = before_pivot.into_iter();
+        let mut next_ranges = Vec::with_capacity(2 * ranges_to_sort.len());
+        for r in ranges_to_sort {
+            let before = before_pivot.by_ref().take(r.len() - 1).collect::<Vec<_>>();
+            let pivot = stable_partition(
+                &mut list[r.clone()],
+                &mut positions[r.clone()],
+                r.len() / 2,
+                &before,
+            );
+            next_ranges.extend(
+                [r.start..r.start + pivot, r.start + pivot + 1..r.end]
+                    .into_iter()
+                    .filter(|r| r.len() > 1),
+            );
+        }
+
+        ranges_to_sort = next_ranges;
+        pass += 1;
+    }
+
+    Ok(())
+}
+
+/// Partitions `slice` around the element at `pivot`, given whether each of the other elements,
+/// in order, goes before it. Elements keep their relative order on either side of the pivot,
+/// and `positions` is rearranged along with `slice`. Returns the new index of the pivot.
+fn stable_partition<S>(
+    slice: &mut [S],
+    positions: &mut [usize],
+    pivot: usize,
+    before: &[bool],
+) -> usize {
+    debug_assert_eq!(slice.len(), before.len() + 1);
+
+    let num_before = before.iter().filter(|&&b| b).count();
+    // the index every element moves to
+    let mut dest = vec![num_before; slice.len()];
+    let (mut next_before, mut next_after) = (0, num_before + 1);
+    for (i, &b) in (0..slice.len()).filter(|&i| i != pivot).zip(before) {
+        let next = if b { &mut next_before } else { &mut next_after };
+        dest[i] = *next;
+        *next += 1;
+    }
+
+    for i in 0..slice.len() {
+        while dest[i] != i {
+            let j = dest[i];
+            slice.swap(i, j);
+            positions.swap(i, j);
+            dest.swap(i, j);
+        }
+    }
+
+    num_before
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::iter::{self, repeat_with};
+
+    use rand::Rng;
+
+    use super::quicksort_ranges_by_key_insecure;
+    use crate::{
+        ff::{boolean_array::BA20, Field},
+        rand::thread_rng,
+        test_fixture::{Reconstruct, Runner, TestWorld},
+    };
+
+    async fn sort(
+        records: Vec<BA20>,
+        desc: bool,
+        ranges: Vec<std::ops::Range<usize>>,
+    ) -> Vec<BA20> {
+        TestWorld::default()
+            .semi_honest(records.into_iter(), |ctx, mut shares| {
+                let ranges = ranges.clone();
+                async move {
+                    quicksort_ranges_by_key_insecure(ctx, &mut shares, desc, |x| x, ranges)
+                        .await
+                        .unwrap();
+                    shares
+                }
+            })
+            .await
+            .reconstruct()
+    }
+
+    #[tokio::test]
+    async fn sorts_ascending() {
+        let mut rng = thread_rng();
+        let records = repeat_with(|| rng.gen::<BA20>())
+            .take(20)
+            .collect::<Vec<_>>();
+        let mut expected = records.clone();
+        expected.sort_by_key(Field::as_u128);
+
+        assert_eq!(
+            sort(records, false, iter::once(0..20).collect()).await,
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn sorts_descending_with_duplicates() {
+        let mut rng = thread_rng();
+        let records = repeat_with(|| BA20::truncate_from(rng.gen_range(0_u128..8)))
+            .take(20)
+            .collect::<Vec<_>>();
+        let mut expected = records.clone();
+        expected.sort_by_key(|v| std::cmp::Reverse(v.as_u128()));
+
+        assert_eq!(
+            sort(records, true, iter::once(0..20).collect()).await,
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn sorts_many_equal_keys() {
+        // more equal keys than the sort is allowed passes
+        let mut rng = thread_rng();
+        let mut records = vec![BA20::truncate_from(7_u128); 200];
+        records.extend(repeat_with(|| rng.gen::<BA20>()).take(20));
+        let mut expected = records.clone();
+        expected.sort_by_key(Field::as_u128);
+        let len = records.len();
+
+        assert_eq!(
+            sort(records, false, iter::once(0..len).collect()).await,
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn sorts_only_given_ranges() {
+        let records = [5_u128, 4, 3, 9, 8, 2, 1, 0, 7, 6]
+            .into_iter()
+            .map(BA20::truncate_from)
+            .collect::<Vec<_>>();
+        let expected = [3_u128, 4, 5, 9, 8, 0, 1, 2, 7, 6]
+            .into_iter()
+            .map(BA20::truncate_from)
+            .collect::<Vec<_>>();
+
+        assert_eq!(sort(records, false, vec![0..3, 4..4, 5..8]).await, expected);
+    }
+
+    async fn sort_stable(keys: &[u128], desc: bool) -> Vec<usize> {
+        let [h1, h2, h3] = TestWorld::default()
+            .semi_honest(
+                keys.iter()
+                    .copied()
+                    .map(BA20::truncate_from)
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+                |ctx, shares| async move {
+                    let len = shares.len();
+                    let mut rows = shares.into_iter().enumerate().collect::<Vec<_>>();
+                    quicksort_ranges_by_key_insecure(
+                        ctx,
+                        &mut rows,
+                        desc,
+                        |(_, x)| x,
+                        iter::once(0..len).collect(),
+                    )
+                    .await
+                    .unwrap();
+                    rows.into_iter().map(|(i, _)| i).collect::<Vec<_>>()
+                },
+            )
+            .await;
+        assert_eq!(h1, h2);
+        assert_eq!(h1, h3);
+        h1
+    }
+
+    #[tokio::test]
+    async fn keeps_order_of_equal_keys() {
+        let keys = [4_u128, 2, 4, 1, 2, 4, 3];
+
+        assert_eq!(sort_stable(&keys, false).await, vec![3, 1, 4, 6, 0, 2, 5]);
+        assert_eq!(sort_stable(&keys, true).await, vec![0, 2, 5, 6, 1, 4, 3]);
+    }
+}
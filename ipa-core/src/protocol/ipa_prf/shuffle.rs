@@ -7,10 +7,16 @@ use rand::{distributions::Standard, prelude::Distribution, seq::SliceRandom, Rng
 use super::super::{context::Context, RecordId};
 use crate::{
     error::Error,
+    ff::{
+        boolean::Boolean,
+        boolean_array::{BA112, BA128, BA64},
+        ArrayAccess, CustomArray, Field,
+    },
     helpers::{Direction, ReceivingEnd, Role},
+    report::{FeatureLabelDotProductReport, OprfReport},
     secret_sharing::{
         replicated::{semi_honest::AdditiveShare, ReplicatedSecretSharing},
-        SharedValue,
+        SharedValue, WeakSharedValue,
     },
 };
 
@@ -25,6 +31,167 @@ pub(crate) enum OPRFShuffleStep {
     TransferY1,
 }
 
+/// Shuffles the given OPRF reports. All fields of a report are packed into a single `BA112`
+/// value, so that they all end up in the same position after the shuffle.
+/// # Errors
+/// Will propagate errors from transport and a few typecasts
+/// # Panics
+/// If the fields of `OprfReport` do not fit into `BA112`.
+pub async fn shuffle_inputs<C, BK, TV, TS>(
+    ctx: C,
+    input: Vec<OprfReport<BK, TV, TS>>,
+) -> Result<Vec<OprfReport<BK, TV, TS>>, Error>
+where
+    C: Context,
+    BK: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+    TV: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+    TS: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+{
+    assert!(
+        <BA64 as SharedValue>::BITS
+            + 1
+            + <BK as WeakSharedValue>::BITS
+            + <TV as WeakSharedValue>::BITS
+            + <TS as WeakSharedValue>::BITS
+            <= <BA112 as SharedValue>::BITS
+    );
+
+    let shuffle_input = input
+        .iter()
+        .map(oprf_report_to_shuffle_input)
+        .collect::<Vec<_>>();
+    let shuffled = shuffle(ctx, shuffle_input).await?;
+
+    Ok(shuffled.iter().map(shuffled_to_oprf_report).collect())
+}
+
+fn oprf_report_to_shuffle_input<BK, TV, TS>(input: &OprfReport<BK, TV, TS>) -> AdditiveShare<BA112>
+where
+    BK: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+    TV: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+    TS: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+{
+    let mut y = AdditiveShare::<BA112>::ZERO;
+    let mut offset = 0;
+    offset = pack_bits(&mut y, offset, &input.match_key);
+    y.set(offset, input.is_trigger.clone());
+    offset += 1;
+    offset = pack_bits(&mut y, offset, &input.breakdown_key);
+    offset = pack_bits(&mut y, offset, &input.trigger_value);
+    pack_bits(&mut y, offset, &input.timestamp);
+    y
+}
+
+fn shuffled_to_oprf_report<BK, TV, TS>(input: &AdditiveShare<BA112>) -> OprfReport<BK, TV, TS>
+where
+    BK: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+    TV: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+    TS: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+{
+    let mut offset = 0;
+    let match_key = unpack_bits(input, &mut offset);
+    let is_trigger = input.get(offset).unwrap();
+    offset += 1;
+    let breakdown_key = unpack_bits(input, &mut offset);
+    let trigger_value = unpack_bits(input, &mut offset);
+    let timestamp = unpack_bits(input, &mut offset);
+
+    OprfReport {
+        match_key,
+        is_trigger,
+        breakdown_key,
+        trigger_value,
+        timestamp,
+    }
+}
+
+/// Shuffles the given feature-label dot product reports. All fields of a report are packed into a
+/// single `BA128` value, so that they all end up in the same position after the shuffle.
+/// # Errors
+/// Will propagate errors from transport and a few typecasts
+/// # Panics
+/// If the fields of `FeatureLabelDotProductReport` do not fit into `BA128`.
+pub async fn shuffle_feature_label_reports<C, FV, TS>(
+    ctx: C,
+    input: Vec<FeatureLabelDotProductReport<FV, TS>>,
+) -> Result<Vec<FeatureLabelDotProductReport<FV, TS>>, Error>
+where
+    C: Context,
+    FV: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+    TS: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+{
+    assert!(
+        <BA64 as SharedValue>::BITS
+            + 1
+            + <FV as WeakSharedValue>::BITS
+            + <TS as WeakSharedValue>::BITS
+            <= <BA128 as SharedValue>::BITS
+    );
+
+    let shuffle_input = input
+        .iter()
+        .map(|report| {
+            let mut y = AdditiveShare::<BA128>::ZERO;
+            let mut offset = 0;
+            offset = pack_bits(&mut y, offset, &report.match_key);
+            y.set(offset, report.is_trigger.clone());
+            offset += 1;
+            offset = pack_bits(&mut y, offset, &report.feature_vector);
+            pack_bits(&mut y, offset, &report.timestamp);
+            y
+        })
+        .collect::<Vec<_>>();
+    let shuffled = shuffle(ctx, shuffle_input).await?;
+
+    Ok(shuffled
+        .iter()
+        .map(|y| {
+            let mut offset = 0;
+            let match_key = unpack_bits(y, &mut offset);
+            let is_trigger = y.get(offset).unwrap();
+            offset += 1;
+            let feature_vector = unpack_bits(y, &mut offset);
+            let timestamp = unpack_bits(y, &mut offset);
+
+            FeatureLabelDotProductReport {
+                match_key,
+                is_trigger,
+                feature_vector,
+                timestamp,
+            }
+        })
+        .collect())
+}
+
+/// Copies the bits of `value` into `dest`, starting at `offset`. Returns the offset of the first
+/// bit after `value`.
+fn pack_bits<P, V>(dest: &mut AdditiveShare<P>, offset: usize, value: &AdditiveShare<V>) -> usize
+where
+    P: WeakSharedValue + CustomArray<Element = Boolean>,
+    V: WeakSharedValue + CustomArray<Element = Boolean>,
+{
+    let bits = usize::try_from(<V as WeakSharedValue>::BITS).unwrap();
+    for i in 0..bits {
+        dest.set(offset + i, value.get(i).unwrap());
+    }
+    offset + bits
+}
+
+/// Reads `V::BITS` bits from `src` starting at `offset` and advances `offset` past them.
+fn unpack_bits<P, V>(src: &AdditiveShare<P>, offset: &mut usize) -> AdditiveShare<V>
+where
+    P: WeakSharedValue + CustomArray<Element = Boolean>,
+    V: WeakSharedValue + CustomArray<Element = Boolean>,
+{
+    let bits = usize::try_from(<V as WeakSharedValue>::BITS).unwrap();
+    let mut value = AdditiveShare::<V>::ZERO;
+    for i in 0..bits {
+        value.set(i, src.get(*offset + i).unwrap());
+    }
+    *offset += bits;
+    value
+}
+
 /// # Errors
 /// Will propagate errors from transport and a few typecasts
 pub async fn shuffle<C, I, S>(ctx: C, shares: I) -> Result<Vec<AdditiveShare<S>>, Error>
@@ -349,10 +516,16 @@ where
 
 #[cfg(all(test, unit_test))]
 pub mod tests {
-    use super::shuffle;
+    use std::iter::zip;
+
+    use super::{shuffle, shuffle_inputs};
     use crate::{
-        ff::{Field, Gf40Bit},
-        test_fixture::{Reconstruct, Runner, TestWorld, TestWorldConfig},
+        ff::{
+            boolean_array::{BA20, BA3, BA8},
+            Field, Gf40Bit,
+        },
+        report::OprfReport,
+        test_fixture::{ipa::TestRawDataRecord, Reconstruct, Runner, TestWorld, TestWorldConfig},
     };
 
     pub type MatchKey = Gf40Bit;
@@ -387,4 +560,80 @@ pub mod tests {
             "Shuffle should not change the items in the set"
         );
     }
+
+    #[tokio::test]
+    async fn shuffles_reports_as_a_whole() {
+        let records = (0..20_u64)
+            .map(|i| TestRawDataRecord {
+                timestamp: 1000 + i,
+                user_id: 12345 + i,
+                is_trigger_report: i % 2 == 0,
+                breakdown_key: u32::try_from(i % 256).unwrap(),
+                trigger_value: u32::try_from(i % 8).unwrap(),
+            })
+            .collect::<Vec<_>>();
+
+        let [s0, s1, s2] = TestWorld::new_with(TestWorldConfig::default().with_seed(123))
+            .semi_honest(
+                records.clone().into_iter(),
+                |ctx, shares: Vec<OprfReport<BA8, BA3, BA20>>| async move {
+                    shuffle_inputs(ctx, shares).await.unwrap()
+                },
+            )
+            .await;
+
+        let to_tuple = |r: &TestRawDataRecord| {
+            (
+                r.user_id,
+                r.timestamp,
+                r.is_trigger_report,
+                r.breakdown_key,
+                r.trigger_value,
+            )
+        };
+        let mut actual = zip(s0, zip(s1, s2))
+            .map(|(a, (b, c))| {
+                (
+                    u64::try_from(
+                        [&a.match_key, &b.match_key, &c.match_key]
+                            .reconstruct()
+                            .as_u128(),
+                    )
+                    .unwrap(),
+                    u64::try_from(
+                        [&a.timestamp, &b.timestamp, &c.timestamp]
+                            .reconstruct()
+                            .as_u128(),
+                    )
+                    .unwrap(),
+                    [&a.is_trigger, &b.is_trigger, &c.is_trigger].reconstruct() == Field::ONE,
+                    u32::try_from(
+                        [&a.breakdown_key, &b.breakdown_key, &c.breakdown_key]
+                            .reconstruct()
+                            .as_u128(),
+                    )
+                    .unwrap(),
+                    u32::try_from(
+                        [&a.trigger_value, &b.trigger_value, &c.trigger_value]
+                            .reconstruct()
+                            .as_u128(),
+                    )
+                    .unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let expected = records.iter().map(to_tuple).collect::<Vec<_>>();
+
+        assert_ne!(
+            actual, expected,
+            "Shuffle should produce a different order of items"
+        );
+
+        actual.sort_unstable();
+
+        assert_eq!(
+            actual, expected,
+            "Shuffle should keep the fields of each report together"
+        );
+    }
 }
@@ -22,6 +22,7 @@ pub use basics::BasicProtocols;
 use crate::{
     error::Error,
     ff::{Gf20Bit, Gf3Bit, Gf40Bit, Gf8Bit},
+    rand::{thread_rng, Rng},
 };
 
 pub type MatchKey = Gf40Bit;
@@ -29,42 +30,48 @@ pub type BreakdownKey = Gf8Bit;
 pub type TriggerValue = Gf3Bit;
 pub type Timestamp = Gf20Bit;
 
-/// Unique identifier of the MPC query requested by report collectors
-/// TODO(615): Generating this unique id may be tricky as it may involve communication between helpers and
-/// them collaborating on constructing this unique id. These details haven't been flushed out yet,
-/// so for now it is just an empty struct. Once we know more about it, we will make necessary
-/// amendments to it
+/// Unique identifier of the MPC query requested by report collectors.
+///
+/// The identifier is chosen by the coordinator helper when it receives a new query request and
+/// is sent to the followers as part of [`PrepareQuery`]. Followers reject identifiers that are
+/// already in use, so every query running on a helper party network has a distinct id. This is
+/// what allows helpers to prepare, receive inputs for and run several queries at the same time.
+///
+/// [`PrepareQuery`]: crate::helpers::query::PrepareQuery
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(
     feature = "serde",
     derive(serde::Serialize, serde::Deserialize),
-    serde(into = "&'static str", try_from = "&str")
+    serde(into = "String", try_from = "&str")
 )]
-pub struct QueryId;
+pub struct QueryId(u64);
 
-impl Display for QueryId {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        // fail when query id becomes meaningful and change the display implementation
-        let _: QueryId = QueryId;
-        write!(f, "{self:?}")
+impl QueryId {
+    /// Generates a new query identifier. Identifiers are drawn at random from a 64-bit space, so
+    /// the probability of two helpers acting as coordinators picking the same id is negligible.
+    /// Even if that happens, followers will refuse to prepare a query with an id they are
+    /// already tracking.
+    #[must_use]
+    pub fn random() -> Self {
+        Self(thread_rng().gen())
     }
 }
 
-impl QueryId {
-    fn repr() -> &'static str {
-        "0"
+impl Display for QueryId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
 
-impl AsRef<str> for QueryId {
-    fn as_ref(&self) -> &str {
-        QueryId::repr()
+impl From<u64> for QueryId {
+    fn from(value: u64) -> Self {
+        Self(value)
     }
 }
 
-impl From<QueryId> for &'static str {
-    fn from(_: QueryId) -> Self {
-        QueryId::repr()
+impl From<QueryId> for String {
+    fn from(value: QueryId) -> Self {
+        value.to_string()
     }
 }
 
@@ -72,11 +79,10 @@ impl TryFrom<&str> for QueryId {
     type Error = Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        if value == QueryId::repr() {
-            Ok(QueryId)
-        } else {
-            Err(Error::path_parse_error(value))
-        }
+        value
+            .parse::<u64>()
+            .map(QueryId)
+            .map_err(|_| Error::path_parse_error(value))
     }
 }
 
@@ -34,7 +34,7 @@ use pin_project::pin_project;
 use crate::{
     error::Error,
     exact::ExactSizeStream,
-    ff::{Field, GaloisField, Gf2, PrimeField},
+    ff::{boolean::Boolean, Field, GaloisField, Gf2, PrimeField},
     helpers::Role,
     protocol::{
         basics::{SecureMul, ZeroPositions},
@@ -188,6 +188,38 @@ impl ToBitConversionTriples for BitDecomposed<Replicated<Gf2>> {
     }
 }
 
+impl ToBitConversionTriples for BitDecomposed<Replicated<Boolean>> {
+    type Residual = ();
+
+    fn bits(&self) -> u32 {
+        u32::try_from(self.len()).unwrap()
+    }
+
+    fn triple<F: PrimeField>(&self, role: Role, i: u32) -> BitConversionTriple<Replicated<F>> {
+        let i = usize::try_from(i).unwrap();
+        BitConversionTriple::new(
+            role,
+            self[i].left() == Boolean::ONE,
+            self[i].right() == Boolean::ONE,
+        )
+    }
+
+    fn into_triples<F, I>(
+        self,
+        role: Role,
+        indices: I,
+    ) -> (
+        BitDecomposed<BitConversionTriple<Replicated<F>>>,
+        Self::Residual,
+    )
+    where
+        F: PrimeField,
+        I: IntoIterator<Item = u32>,
+    {
+        (self.triple_range(role, indices), ())
+    }
+}
+
 #[pin_project]
 pub struct LocalBitConverter<F, V, S, R>
 where
@@ -3,7 +3,10 @@ use std::fmt::{Debug, Display, Formatter};
 use ipa_macros::Gate;
 
 use super::StepNarrow;
-use crate::helpers::{prss_protocol::PrssExchangeStep, query::QueryType};
+use crate::{
+    helpers::{prss_protocol::PrssExchangeStep, query::QueryType},
+    query::InputCheckStep,
+};
 
 #[derive(Gate, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Default)]
 #[cfg_attr(
@@ -38,6 +41,7 @@ const QUERY_TYPE_SEMIHONEST_STATE: u16 = 65533;
 const QUERY_TYPE_MALICIOUS_STATE: u16 = 65532;
 const PRSS_EXCHANGE_STATE: u16 = 65531;
 const QUERY_TYPE_OPRF_STATE: u16 = 65530;
+const INPUT_CHECK_STATE: u16 = 65528;
 
 impl StepNarrow<QueryType> for Compact {
     fn narrow(&self, step: &QueryType) -> Self {
@@ -56,6 +60,12 @@ impl StepNarrow<PrssExchangeStep> for Compact {
     }
 }
 
+impl StepNarrow<InputCheckStep> for Compact {
+    fn narrow(&self, _step: &InputCheckStep) -> Self {
+        Self(INPUT_CHECK_STATE)
+    }
+}
+
 // Reverse of `static_state_map` for `Compact::as_ref()`
 fn static_reverse_state_map(state: u16) -> &'static str {
     match state {
@@ -64,6 +74,7 @@ fn static_reverse_state_map(state: u16) -> &'static str {
         QUERY_TYPE_MALICIOUS_STATE => QueryType::MALICIOUS_IPA_STR,
         QUERY_TYPE_OPRF_STATE => QueryType::OPRF_IPA_STR,
         PRSS_EXCHANGE_STATE => PrssExchangeStep.as_ref(),
+        INPUT_CHECK_STATE => InputCheckStep.as_ref(),
         _ => panic!("cannot as_ref() from the invalid state {state}"),
     }
 }
@@ -79,6 +90,8 @@ fn static_deserialize_state_map(s: &str) -> u16 {
         return QUERY_TYPE_OPRF_STATE;
     } else if s == PrssExchangeStep.as_ref() {
         return PRSS_EXCHANGE_STATE;
+    } else if s == InputCheckStep.as_ref() {
+        return INPUT_CHECK_STATE;
     }
 
     panic!("cannot deserialize from the invalid step \"{s}\"");
@@ -0,0 +1,365 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs, io,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    helpers::query::{QueryConfig, QueryType, ReportCollectorId},
+    report::Epoch,
+    sync::Mutex,
+};
+
+/// Two charges that add up to the limit must not be rejected because of rounding errors.
+const TOLERANCE: f64 = 1e-9;
+
+#[derive(thiserror::Error, Debug)]
+pub enum PrivacyBudgetError {
+    #[error("report collector {report_collector} requested epsilon={requested} on epoch {epoch}, but only {remaining} is left")]
+    Exceeded {
+        report_collector: ReportCollectorId,
+        epoch: Epoch,
+        requested: f64,
+        remaining: f64,
+    },
+    #[error("failed to access privacy budget ledger at {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+}
+
+/// Keeps track of the privacy budget that every report collector spends on every epoch, and
+/// rejects queries that would spend more than the limit.
+///
+/// If the ledger is backed by a file, every change is written to it before the query that
+/// caused it is accepted, so restarting the helper does not reset the budget.
+#[derive(Debug)]
+pub struct PrivacyBudgetLedger {
+    limit: f64,
+    path: Option<PathBuf>,
+    spent: Mutex<HashMap<(ReportCollectorId, Epoch), f64>>,
+}
+
+impl PrivacyBudgetLedger {
+    /// Creates a ledger that is not persisted anywhere.
+    #[must_use]
+    pub fn in_memory(limit: f64) -> Self {
+        Self {
+            limit,
+            path: None,
+            spent: Mutex::default(),
+        }
+    }
+
+    /// Creates a ledger backed by the file at `path`, loading the budget spent so far from it if
+    /// it exists.
+    ///
+    /// ## Errors
+    /// If the file exists but cannot be read or parsed.
+    pub fn open<P: AsRef<Path>>(path: P, limit: f64) -> Result<Self, PrivacyBudgetError> {
+        let path = path.as_ref().to_path_buf();
+        let spent = match fs::read_to_string(&path) {
+            Ok(contents) => parse(&contents).map_err(|source| PrivacyBudgetError::Io {
+                path: path.clone(),
+                source,
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::default(),
+            Err(source) => return Err(PrivacyBudgetError::Io { path, source }),
+        };
+
+        Ok(Self {
+            limit,
+            path: Some(path),
+            spent: Mutex::new(spent),
+        })
+    }
+
+    /// Records the privacy budget that the query described by `config` spends on every epoch it
+    /// accepts reports from. Either all of these epochs are charged, or none of them.
+    ///
+    /// ## Errors
+    /// If the report collector does not have enough budget left on one of the epochs the query
+    /// runs on, or if the ledger cannot be persisted.
+    /// ## Panics
+    /// If the mutex is poisoned.
+    pub fn charge(&self, config: &QueryConfig) -> Result<(), PrivacyBudgetError> {
+        let Some((epochs, epsilon)) = privacy_budget(config) else {
+            return Ok(());
+        };
+
+        let mut spent = self.spent.lock().unwrap();
+        for epoch in epochs.clone() {
+            let before = spent
+                .get(&(config.report_collector, epoch))
+                .copied()
+                .unwrap_or_default();
+            if before + epsilon > self.limit + TOLERANCE {
+                return Err(PrivacyBudgetError::Exceeded {
+                    report_collector: config.report_collector,
+                    epoch,
+                    requested: epsilon,
+                    remaining: (self.limit - before).max(0.0),
+                });
+            }
+        }
+
+        let before = spent.clone();
+        for epoch in epochs {
+            *spent.entry((config.report_collector, epoch)).or_default() += epsilon;
+        }
+        if let Err(e) = self.persist(&spent) {
+            *spent = before;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    /// Gives back the privacy budget charged for a query that never ran.
+    ///
+    /// ## Errors
+    /// If the ledger cannot be persisted.
+    /// ## Panics
+    /// If the mutex is poisoned.
+    pub fn refund(&self, config: &QueryConfig) -> Result<(), PrivacyBudgetError> {
+        let Some((epochs, epsilon)) = privacy_budget(config) else {
+            return Ok(());
+        };
+
+        let mut spent = self.spent.lock().unwrap();
+        for epoch in epochs {
+            if let Some(v) = spent.get_mut(&(config.report_collector, epoch)) {
+                *v = (*v - epsilon).max(0.0);
+            }
+        }
+        self.persist(&spent)
+    }
+
+    /// Returns the privacy budget that `report_collector` has left on `epoch`.
+    /// ## Panics
+    /// If the mutex is poisoned.
+    #[must_use]
+    pub fn remaining(&self, report_collector: ReportCollectorId, epoch: Epoch) -> f64 {
+        let spent = self.spent.lock().unwrap();
+        let spent = spent
+            .get(&(report_collector, epoch))
+            .copied()
+            .unwrap_or_default();
+        (self.limit - spent).max(0.0)
+    }
+
+    /// Writes the ledger to a temporary file first, so that a crash in the middle of writing it
+    /// leaves the previous version intact.
+    fn persist(
+        &self,
+        spent: &HashMap<(ReportCollectorId, Epoch), f64>,
+    ) -> Result<(), PrivacyBudgetError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut contents = String::new();
+        for ((report_collector, epoch), epsilon) in spent {
+            writeln!(contents, "{report_collector} {epoch} {epsilon}").unwrap();
+        }
+
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, contents)
+            .and_then(|()| fs::rename(&tmp_path, path))
+            .map_err(|source| PrivacyBudgetError::Io {
+                path: path.clone(),
+                source,
+            })
+    }
+}
+
+/// Returns the epochs that the query described by `config` accepts reports from and the privacy
+/// budget it spends on each of them, if any.
+fn privacy_budget(config: &QueryConfig) -> Option<(RangeInclusive<Epoch>, f64)> {
+    match config.query_type {
+        QueryType::SemiHonestIpa(ipa_config) | QueryType::MaliciousIpa(ipa_config) => {
+            // Sort-based IPA does not add dummy records, so it only spends budget on the noise.
+            let epsilon = ipa_config.output_noise_epsilon.map_or(0.0, f64::from);
+            (epsilon > 0.0).then(|| (ipa_config.report_epochs(), epsilon))
+        }
+        QueryType::OprfIpa(ipa_config) => {
+            let epsilon = ipa_config.epsilon();
+            (epsilon > 0.0).then(|| (ipa_config.report_epochs(), epsilon))
+        }
+        #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
+        QueryType::TestMultiply => None,
+        QueryType::SemiHonestSparseAggregate(_)
+        | QueryType::MaliciousSparseAggregate(_)
+        | QueryType::FeatureLabelDotProduct(_) => None,
+    }
+}
+
+fn parse(contents: &str) -> Result<HashMap<(ReportCollectorId, Epoch), f64>, io::Error> {
+    let invalid = |line: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("malformed ledger entry: {line}"),
+        )
+    };
+
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split_whitespace();
+            let (Some(report_collector), Some(epoch), Some(epsilon), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(invalid(line));
+            };
+            Ok((
+                (
+                    report_collector.parse().map_err(|_| invalid(line))?,
+                    epoch.parse().map_err(|_| invalid(line))?,
+                ),
+                epsilon.parse().map_err(|_| invalid(line))?,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::{PrivacyBudgetError, PrivacyBudgetLedger};
+    use crate::{
+        ff::FieldType,
+        helpers::query::{IpaQueryConfig, QueryConfig, QueryType, ReportCollectorId},
+    };
+
+    fn query(report_collector: ReportCollectorId, epoch: u16, epsilon: f64) -> QueryConfig {
+        let mut ipa_config = IpaQueryConfig::default().with_output_noise(epsilon, 1e-6);
+        ipa_config.epoch = epoch;
+        let mut config =
+            QueryConfig::new(QueryType::OprfIpa(ipa_config), FieldType::Fp32BitPrime, 1).unwrap();
+        config.report_collector = report_collector;
+        config
+    }
+
+    #[test]
+    fn rejects_queries_over_budget() {
+        let ledger = PrivacyBudgetLedger::in_memory(1.0);
+        let rc = ReportCollectorId::default();
+
+        ledger.charge(&query(rc, 0, 0.6)).unwrap();
+        ledger.charge(&query(rc, 0, 0.4)).unwrap();
+        assert!(matches!(
+            ledger.charge(&query(rc, 0, 0.1)),
+            Err(PrivacyBudgetError::Exceeded { .. })
+        ));
+        assert!(ledger.remaining(rc, 0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn budget_is_per_report_collector_and_epoch() {
+        let ledger = PrivacyBudgetLedger::in_memory(1.0);
+        let rc1 = ReportCollectorId::default();
+        let rc2 = ReportCollectorId::from_certificate(b"certificate");
+
+        ledger.charge(&query(rc1, 0, 1.0)).unwrap();
+        ledger.charge(&query(rc1, 1, 1.0)).unwrap();
+        ledger.charge(&query(rc2, 0, 1.0)).unwrap();
+        assert!(ledger.charge(&query(rc2, 0, 1.0)).is_err());
+    }
+
+    #[test]
+    fn charges_every_report_epoch() {
+        let ledger = PrivacyBudgetLedger::in_memory(1.0);
+        let rc = ReportCollectorId::default();
+        let mut config = query(rc, 0, 0.5);
+        let QueryType::OprfIpa(ipa_config) = &mut config.query_type else {
+            unreachable!()
+        };
+        *ipa_config = ipa_config.with_report_epochs(2..=4);
+
+        ledger.charge(&config).unwrap();
+        assert!((ledger.remaining(rc, 0) - 1.0).abs() < 1e-9);
+        for epoch in 2..=4 {
+            assert!((ledger.remaining(rc, epoch) - 0.5).abs() < 1e-9);
+        }
+
+        // nothing is charged if one of the epochs is over the budget
+        ledger.charge(&query(rc, 3, 0.75)).unwrap_err();
+        ledger.charge(&query(rc, 4, 0.5)).unwrap();
+        assert!(ledger.charge(&config).is_err());
+        assert!((ledger.remaining(rc, 2) - 0.5).abs() < 1e-9);
+        assert!((ledger.remaining(rc, 3) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn queries_without_noise_are_free() {
+        let ledger = PrivacyBudgetLedger::in_memory(0.0);
+        let config = QueryConfig::new(
+            QueryType::OprfIpa(IpaQueryConfig::default()),
+            FieldType::Fp32BitPrime,
+            1,
+        )
+        .unwrap();
+
+        ledger.charge(&config).unwrap();
+    }
+
+    #[test]
+    fn sort_based_ipa_is_not_charged_for_dummy_records() {
+        let ledger = PrivacyBudgetLedger::in_memory(1.0);
+        let rc = ReportCollectorId::default();
+        let ipa_config = IpaQueryConfig::default()
+            .with_dummy_records(0.5, 1e-6)
+            .with_output_noise(0.25, 1e-6);
+
+        for query_type in [
+            QueryType::SemiHonestIpa(ipa_config),
+            QueryType::MaliciousIpa(ipa_config),
+        ] {
+            let config = QueryConfig::new(query_type, FieldType::Fp32BitPrime, 1).unwrap();
+            ledger.charge(&config).unwrap();
+        }
+        assert!((ledger.remaining(rc, 0) - 0.5).abs() < 1e-9);
+
+        let config =
+            QueryConfig::new(QueryType::OprfIpa(ipa_config), FieldType::Fp32BitPrime, 1).unwrap();
+        ledger.charge(&config).unwrap_err();
+    }
+
+    #[test]
+    fn refund() {
+        let ledger = PrivacyBudgetLedger::in_memory(1.0);
+        let rc = ReportCollectorId::default();
+
+        ledger.charge(&query(rc, 0, 1.0)).unwrap();
+        ledger.refund(&query(rc, 0, 1.0)).unwrap();
+        ledger.charge(&query(rc, 0, 1.0)).unwrap();
+    }
+
+    #[test]
+    fn persists_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("budget");
+        let rc = ReportCollectorId::from_certificate(b"certificate");
+
+        let ledger = PrivacyBudgetLedger::open(&path, 1.0).unwrap();
+        ledger.charge(&query(rc, 3, 0.75)).unwrap();
+        drop(ledger);
+
+        let ledger = PrivacyBudgetLedger::open(&path, 1.0).unwrap();
+        assert!((ledger.remaining(rc, 3) - 0.25).abs() < 1e-9);
+        assert!((ledger.remaining(rc, 2) - 1.0).abs() < 1e-9);
+        assert!(ledger.charge(&query(rc, 3, 0.5)).is_err());
+    }
+
+    #[test]
+    fn rejects_corrupted_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("budget");
+        std::fs::write(&path, "not a ledger").unwrap();
+
+        assert!(matches!(
+            PrivacyBudgetLedger::open(&path, 1.0),
+            Err(PrivacyBudgetError::Io { .. })
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use bytes::Bytes;
+use futures::stream;
+
+use crate::{
+    helpers::{
+        query::{QueryInputChunk, ReceivedInputChunks},
+        BodyStream,
+    },
+    query::QueryInputError,
+};
+
+/// Query input that is being uploaded in chunks. Chunks are buffered until all the records that
+/// the query expects arrive, then they are concatenated in the order of their indices.
+///
+/// The whole input is held in memory until it is complete. Empty chunks are rejected, so there
+/// are never more chunks than expected records; the HTTP server limits the size of each of them.
+pub(super) struct ChunkedInput {
+    expected_records: u32,
+    /// The number of records in every received chunk.
+    received: BTreeMap<u32, u32>,
+    data: BTreeMap<u32, Bytes>,
+}
+
+impl ChunkedInput {
+    pub fn new(expected_records: u32) -> Self {
+        Self {
+            expected_records,
+            received: BTreeMap::new(),
+            data: BTreeMap::new(),
+        }
+    }
+
+    /// Adds `chunk` to the input. Chunks that have been received already are ignored, so that
+    /// clients can safely retry uploads.
+    ///
+    /// ## Errors
+    /// If the chunk does not match its checksum, has no records, or the input would have more
+    /// records than the query expects.
+    pub fn add(&mut self, chunk: QueryInputChunk) -> Result<(), QueryInputError> {
+        if !chunk.is_intact() {
+            return Err(QueryInputError::CorruptedChunk {
+                query_id: chunk.query_id,
+                chunk: chunk.index,
+            });
+        }
+        if chunk.records == 0 {
+            return Err(QueryInputError::EmptyChunk {
+                query_id: chunk.query_id,
+                chunk: chunk.index,
+            });
+        }
+        if self.received.contains_key(&chunk.index) {
+            return Ok(());
+        }
+
+        let received = self.records().saturating_add(chunk.records);
+        if received > self.expected_records {
+            return Err(QueryInputError::TooManyRecords {
+                query_id: chunk.query_id,
+                expected: self.expected_records,
+                received,
+            });
+        }
+        self.received.insert(chunk.index, chunk.records);
+        self.data.insert(chunk.index, chunk.data);
+
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.records() == self.expected_records
+    }
+
+    /// Returns the input received so far. Chunks stay registered as received, so retried
+    /// uploads of them are still ignored.
+    pub fn take_input(&mut self) -> BodyStream {
+        let chunks = std::mem::take(&mut self.data).into_values().map(Ok);
+        BodyStream::from_bytes_stream(stream::iter(chunks))
+    }
+
+    pub fn status(&self) -> ReceivedInputChunks {
+        ReceivedInputChunks {
+            chunks: self.received.keys().copied().collect(),
+            records: self.records(),
+            expected_records: self.expected_records,
+        }
+    }
+
+    fn records(&self) -> u32 {
+        self.received.values().sum()
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::*;
+    use crate::{helpers::BytesStream, protocol::QueryId};
+
+    fn chunk(index: u32, records: u32, data: &'static [u8]) -> QueryInputChunk {
+        QueryInputChunk::new(QueryId::from(1), index, records, Bytes::from_static(data))
+    }
+
+    #[tokio::test]
+    async fn assembles_in_order() {
+        let mut input = ChunkedInput::new(5);
+        input.add(chunk(2, 1, b"e")).unwrap();
+        input.add(chunk(0, 2, b"ab")).unwrap();
+        assert!(!input.is_complete());
+        input.add(chunk(1, 2, b"cd")).unwrap();
+        assert!(input.is_complete());
+
+        assert_eq!(
+            ReceivedInputChunks {
+                chunks: vec![0, 1, 2],
+                records: 5,
+                expected_records: 5,
+            },
+            input.status()
+        );
+        assert_eq!(b"abcde".to_vec(), input.take_input().to_vec().await);
+    }
+
+    #[test]
+    fn ignores_duplicates() {
+        let mut input = ChunkedInput::new(3);
+        input.add(chunk(0, 2, b"ab")).unwrap();
+        input.add(chunk(0, 2, b"ab")).unwrap();
+        assert_eq!(2, input.status().records);
+        assert_eq!(vec![0], input.status().chunks);
+    }
+
+    #[test]
+    fn rejects_corrupted_chunk() {
+        let mut input = ChunkedInput::new(3);
+        let mut corrupted = chunk(0, 2, b"ab");
+        corrupted.data = Bytes::from_static(b"ba");
+        assert!(matches!(
+            input.add(corrupted),
+            Err(QueryInputError::CorruptedChunk { chunk: 0, .. })
+        ));
+        assert!(input.status().chunks.is_empty());
+    }
+
+    #[test]
+    fn rejects_empty_chunk() {
+        let mut input = ChunkedInput::new(3);
+        assert!(matches!(
+            input.add(chunk(0, 0, b"")),
+            Err(QueryInputError::EmptyChunk { chunk: 0, .. })
+        ));
+        assert!(input.status().chunks.is_empty());
+    }
+
+    #[test]
+    fn rejects_extra_records() {
+        let mut input = ChunkedInput::new(3);
+        input.add(chunk(0, 2, b"ab")).unwrap();
+        assert!(matches!(
+            input.add(chunk(1, 2, b"cd")),
+            Err(QueryInputError::TooManyRecords {
+                expected: 3,
+                received: 4,
+                ..
+            })
+        ));
+        assert_eq!(vec![0], input.status().chunks);
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fmt::Debug,
     future::{ready, Future},
     pin::Pin,
@@ -6,10 +7,12 @@ use std::{
 };
 
 use ::tokio::sync::oneshot;
-use futures::FutureExt;
+use bytes::Bytes;
+use futures::{stream, FutureExt, StreamExt};
 use generic_array::GenericArray;
 use rand::rngs::StdRng;
 use rand_core::SeedableRng;
+use serde::{Deserialize, Serialize};
 #[cfg(all(feature = "shuttle", test))]
 use shuttle::future as tokio;
 use typenum::Unsigned;
@@ -21,7 +24,7 @@ use crate::{
     helpers::{
         negotiate_prss,
         query::{QueryConfig, QueryType},
-        BodyStream, Gateway,
+        BodyStream, BoxBytesStream, Gateway,
     },
     hpke::{KeyPair, KeyRegistry},
     protocol::{
@@ -30,29 +33,130 @@ use crate::{
         step::{Gate, StepNarrow},
     },
     query::{
-        runner::{IpaQuery, OprfIpaQuery, QueryResult, SparseAggregateQuery},
+        input_check,
+        runner::{
+            FeatureLabelDotProductQuery, IpaQuery, OprfIpaQuery, QueryResult, SparseAggregateQuery,
+        },
         state::RunningQuery,
     },
+    report::InvalidReportKind,
 };
 
+/// The number of records serialized at once when query results are streamed.
+const RESULT_CHUNK_RECORDS: usize = 4096;
+
 pub trait Result: Send + Debug {
     fn into_bytes(self: Box<Self>) -> Vec<u8>;
+
+    /// Describes how to decode the serialized result.
+    fn header(&self) -> ResultHeader;
+
+    /// Serializes the result incrementally. By default, the whole result is serialized at once.
+    #[must_use]
+    fn into_stream(self: Box<Self>) -> BoxBytesStream {
+        Box::pin(stream::once(ready(Ok(Bytes::from(self.into_bytes())))))
+    }
+}
+
+/// Number of input reports that the query rejected, by the kind of the problem found in them.
+pub type RejectedReports = BTreeMap<InvalidReportKind, u32>;
+
+/// Layout of the serialized query result: `records` secret shares of `record_size` bytes each.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResultHeader {
+    /// Field that the query was asked to use, if known.
+    pub field_type: Option<FieldType>,
+    pub records: usize,
+    pub record_size: usize,
+    /// Input reports that the query left out of the result. Helpers agree on these, so the counts
+    /// are the same in results from every helper.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rejected_reports: RejectedReports,
 }
 
 impl<T> Result for Vec<T>
 where
-    T: Serializable,
+    T: Serializable + Send + 'static,
     Vec<T>: Debug + Send,
 {
     fn into_bytes(self: Box<Self>) -> Vec<u8> {
-        let mut r = vec![0u8; self.len() * T::Size::USIZE];
-        for (i, row) in self.into_iter().enumerate() {
-            row.serialize(GenericArray::from_mut_slice(
-                &mut r[(i * T::Size::USIZE)..((i + 1) * T::Size::USIZE)],
-            ));
+        serialize_records(*self)
+    }
+
+    fn header(&self) -> ResultHeader {
+        ResultHeader {
+            field_type: None,
+            records: self.len(),
+            record_size: T::Size::USIZE,
+            rejected_reports: RejectedReports::new(),
         }
+    }
 
-        r
+    fn into_stream(self: Box<Self>) -> BoxBytesStream {
+        Box::pin(
+            stream::iter(*self)
+                .chunks(RESULT_CHUNK_RECORDS)
+                .map(|chunk| Ok(Bytes::from(serialize_records(chunk)))),
+        )
+    }
+}
+
+fn serialize_records<T: Serializable>(records: Vec<T>) -> Vec<u8> {
+    let mut r = vec![0u8; records.len() * T::Size::USIZE];
+    for (i, row) in records.into_iter().enumerate() {
+        row.serialize(GenericArray::from_mut_slice(
+            &mut r[(i * T::Size::USIZE)..((i + 1) * T::Size::USIZE)],
+        ));
+    }
+
+    r
+}
+
+/// Result of the query together with the input reports it rejected.
+#[derive(Debug)]
+pub struct WithRejectedReports<T> {
+    pub results: T,
+    pub rejected_reports: RejectedReports,
+}
+
+impl<T: Result> Result for WithRejectedReports<T> {
+    fn into_bytes(self: Box<Self>) -> Vec<u8> {
+        Box::new(self.results).into_bytes()
+    }
+
+    fn header(&self) -> ResultHeader {
+        ResultHeader {
+            rejected_reports: self.rejected_reports.clone(),
+            ..self.results.header()
+        }
+    }
+
+    fn into_stream(self: Box<Self>) -> BoxBytesStream {
+        Box::new(self.results).into_stream()
+    }
+}
+
+/// Result of the query that knows the field type requested in the query config.
+#[derive(Debug)]
+struct TypedResult {
+    field_type: FieldType,
+    inner: Box<dyn Result>,
+}
+
+impl Result for TypedResult {
+    fn into_bytes(self: Box<Self>) -> Vec<u8> {
+        self.inner.into_bytes()
+    }
+
+    fn header(&self) -> ResultHeader {
+        ResultHeader {
+            field_type: Some(self.field_type),
+            ..self.inner.header()
+        }
+    }
+
+    fn into_stream(self: Box<Self>) -> BoxBytesStream {
+        self.inner.into_stream()
     }
 }
 
@@ -60,6 +164,7 @@ where
 pub fn execute(
     config: QueryConfig,
     key_registry: Arc<KeyRegistry<KeyPair>>,
+    helper_origin: Arc<str>,
     gateway: Gateway,
     input: BodyStream,
 ) -> RunningQuery {
@@ -86,7 +191,7 @@ pub fn execute(
             move |prss, gateway, config, input| {
                 let ctx = SemiHonestContext::new(prss, gateway);
                 Box::pin(
-                    IpaQuery::<crate::ff::Fp31, _, _>::new(ipa_config, key_registry)
+                    IpaQuery::<crate::ff::Fp31, _, _>::new(ipa_config, key_registry, helper_origin)
                         .execute(ctx, config.size, input)
                         .then(|res| ready(res.map(|out| Box::new(out) as Box<dyn Result>))),
                 )
@@ -99,7 +204,7 @@ pub fn execute(
             move |prss, gateway, config, input| {
                 let ctx = SemiHonestContext::new(prss, gateway);
                 Box::pin(
-                    IpaQuery::<Fp32BitPrime, _, _>::new(ipa_config, key_registry)
+                    IpaQuery::<Fp32BitPrime, _, _>::new(ipa_config, key_registry, helper_origin)
                         .execute(ctx, config.size, input)
                         .then(|res| ready(res.map(|out| Box::new(out) as Box<dyn Result>))),
                 )
@@ -113,7 +218,7 @@ pub fn execute(
             move |prss, gateway, config, input| {
                 let ctx = MaliciousContext::new(prss, gateway);
                 Box::pin(
-                    IpaQuery::<crate::ff::Fp31, _, _>::new(ipa_config, key_registry)
+                    IpaQuery::<crate::ff::Fp31, _, _>::new(ipa_config, key_registry, helper_origin)
                         .execute(ctx, config.size, input)
                         .then(|res| ready(res.map(|out| Box::new(out) as Box<dyn Result>))),
                 )
@@ -126,7 +231,7 @@ pub fn execute(
             move |prss, gateway, config, input| {
                 let ctx = MaliciousContext::new(prss, gateway);
                 Box::pin(
-                    IpaQuery::<Fp32BitPrime, _, _>::new(ipa_config, key_registry)
+                    IpaQuery::<Fp32BitPrime, _, _>::new(ipa_config, key_registry, helper_origin)
                         .execute(ctx, config.size, input)
                         .then(|res| ready(res.map(|out| Box::new(out) as Box<dyn Result>))),
                 )
@@ -209,7 +314,7 @@ pub fn execute(
             move |prss, gateway, config, input| {
                 let ctx = SemiHonestContext::new(prss, gateway);
                 Box::pin(
-                    OprfIpaQuery::<_, Fp32BitPrime>::new(ipa_config)
+                    OprfIpaQuery::<_, Fp32BitPrime>::new(ipa_config, key_registry, helper_origin)
                         .execute(ctx, config.size, input)
                         .then(|res| ready(res.map(|out| Box::new(out) as Box<dyn Result>))),
                 )
@@ -223,7 +328,38 @@ pub fn execute(
             move |prss, gateway, config, input| {
                 let ctx = SemiHonestContext::new(prss, gateway);
                 Box::pin(
-                    OprfIpaQuery::<_, crate::ff::Fp31>::new(ipa_config)
+                    OprfIpaQuery::<_, crate::ff::Fp31>::new(
+                        ipa_config,
+                        key_registry,
+                        helper_origin,
+                    )
+                    .execute(ctx, config.size, input)
+                    .then(|res| ready(res.map(|out| Box::new(out) as Box<dyn Result>))),
+                )
+            },
+        ),
+        (QueryType::FeatureLabelDotProduct(fldp_config), FieldType::Fp32BitPrime) => do_query(
+            config,
+            gateway,
+            input,
+            move |prss, gateway, config, input| {
+                let ctx = SemiHonestContext::new(prss, gateway);
+                Box::pin(
+                    FeatureLabelDotProductQuery::<_, Fp32BitPrime>::new(fldp_config)
+                        .execute(ctx, config.size, input)
+                        .then(|res| ready(res.map(|out| Box::new(out) as Box<dyn Result>))),
+                )
+            },
+        ),
+        #[cfg(any(test, feature = "weak-field"))]
+        (QueryType::FeatureLabelDotProduct(fldp_config), FieldType::Fp31) => do_query(
+            config,
+            gateway,
+            input,
+            move |prss, gateway, config, input| {
+                let ctx = SemiHonestContext::new(prss, gateway);
+                Box::pin(
+                    FeatureLabelDotProductQuery::<_, crate::ff::Fp31>::new(fldp_config)
                         .execute(ctx, config.size, input)
                         .then(|res| ready(res.map(|out| Box::new(out) as Box<dyn Result>))),
                 )
@@ -249,32 +385,71 @@ where
         + 'static,
 {
     let (tx, rx) = oneshot::channel();
+    let progress = gateway.progress();
 
     let join_handle = tokio::spawn(async move {
         // TODO: make it a generic argument for this function
         let mut rng = StdRng::from_entropy();
+
         // Negotiate PRSS first
         let step = Gate::default().narrow(&config.query_type);
         let prss = negotiate_prss(&gateway, &step, &mut rng).await.unwrap();
 
-        tx.send(query_impl(&prss, &gateway, &config, input_stream).await)
-            .unwrap();
+        let result = input_check::verify(&gateway, &config, input_stream, |input_stream| {
+            query_impl(&prss, &gateway, &config, input_stream)
+        })
+        .await
+        .map(|inner| {
+            Box::new(TypedResult {
+                field_type: config.field_type,
+                inner,
+            }) as Box<dyn Result>
+        });
+        tx.send(result).unwrap();
     });
 
     RunningQuery {
         result: rx,
         join_handle,
+        progress,
     }
 }
 
 #[cfg(all(test, unit_test))]
 mod tests {
+    use futures::StreamExt;
+
+    use super::{RejectedReports, ResultHeader};
     use crate::{
         ff::{Field, Fp31},
         query::ProtocolResult,
         secret_sharing::{replicated::semi_honest::AdditiveShare, IntoShares},
     };
 
+    #[tokio::test]
+    async fn stream_result() {
+        let [input, ..] = (0u128..10_000).map(Fp31::truncate_from).share();
+        let expected = Box::new(input.clone()).into_bytes();
+        let result: Box<dyn ProtocolResult> = Box::new(input);
+        assert_eq!(
+            ResultHeader {
+                field_type: None,
+                records: 10_000,
+                record_size: 2,
+                rejected_reports: RejectedReports::new(),
+            },
+            result.header()
+        );
+
+        let chunks = result
+            .into_stream()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(3, chunks.len());
+        assert_eq!(expected, chunks.concat());
+    }
+
     #[test]
     fn serialize_result() {
         let [input, ..] = (0u128..=3).map(Fp31::truncate_from).share();
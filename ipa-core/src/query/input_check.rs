@@ -0,0 +1,592 @@
+use std::{future::Future, pin::pin};
+
+use bytes::{Buf, Bytes};
+use futures::{
+    future::{select, Either},
+    TryStreamExt,
+};
+use futures_util::future::try_join4;
+use generic_array::GenericArray;
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use typenum::U44;
+
+#[cfg(any(test, feature = "weak-field"))]
+use crate::ff::Fp31;
+use crate::{
+    error::{BoxError, Error},
+    ff::{FieldType, Fp32BitPrime, PrimeField, Serializable},
+    helpers::{
+        query::{IpaQueryConfig, QueryConfig, QueryType},
+        BodyStream, ChannelId, Direction, Gateway, Message, Role, TotalRecords,
+    },
+    protocol::{
+        step::{Gate, Step, StepNarrow},
+        QueryId, RecordId,
+    },
+    query::QueryInputError,
+    report::{
+        with_oprf_report_types, EncryptedOprfReport, EncryptedReport, Epoch, EventType,
+        InvalidReportError, KeyIdentifier, ReportVersion,
+    },
+    secret_sharing::{replicated::semi_honest::AdditiveShare as Replicated, WeakSharedValue},
+};
+
+pub struct InputCheckStep;
+
+impl AsRef<str> for InputCheckStep {
+    fn as_ref(&self) -> &'static str {
+        "input_check"
+    }
+}
+
+impl Step for InputCheckStep {}
+
+/// What helpers know about their inputs that must be the same on all of them. Every helper
+/// receives different shares of the same reports, so the summary only covers the shape of the
+/// input and what is public in it: its length and, for inputs made of length-delimited encrypted
+/// reports, the number of reports and the digest of their lengths and unencrypted fields.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InputSummary {
+    bytes: u64,
+    /// The number of length-delimited reports, zero if the input consists of fixed-size records.
+    rows: u32,
+    digest: [u8; 32],
+}
+
+impl Serializable for InputSummary {
+    type Size = U44;
+
+    fn serialize(&self, buf: &mut GenericArray<u8, Self::Size>) {
+        buf[..8].copy_from_slice(&self.bytes.to_le_bytes());
+        buf[8..12].copy_from_slice(&self.rows.to_le_bytes());
+        buf[12..].copy_from_slice(&self.digest);
+    }
+
+    fn deserialize(buf: &GenericArray<u8, Self::Size>) -> Self {
+        Self {
+            bytes: u64::from_le_bytes(buf[..8].try_into().unwrap()),
+            rows: u32::from_le_bytes(buf[8..12].try_into().unwrap()),
+            digest: buf[12..].try_into().unwrap(),
+        }
+    }
+}
+
+impl Message for InputSummary {}
+
+/// Computes the [`InputSummary`] of an input as it is read, one chunk at a time.
+struct InputDigest {
+    hasher: Sha256,
+    bytes: u64,
+    rows: u32,
+    /// Adds the unencrypted fields of every report to the digest, if the input is made of
+    /// length-delimited reports.
+    hash_report: Option<Box<HashReport>>,
+    /// The part of the length prefix of the next report that has been read.
+    len: Vec<u8>,
+    /// The part of the current report that has been read.
+    report: Vec<u8>,
+    /// The number of bytes of the current report that have not been read yet.
+    remaining: usize,
+}
+
+impl InputDigest {
+    fn new(hash_report: Option<Box<HashReport>>) -> Self {
+        Self {
+            hasher: Sha256::new(),
+            bytes: 0,
+            rows: 0,
+            hash_report,
+            len: Vec::with_capacity(2),
+            report: Vec::new(),
+            remaining: 0,
+        }
+    }
+
+    fn update(&mut self, mut chunk: &[u8]) {
+        self.bytes += u64::try_from(chunk.len()).unwrap();
+        let Some(hash_report) = &self.hash_report else {
+            return;
+        };
+        // a truncated last report still counts, it is rejected when the input is parsed
+        while !chunk.is_empty() {
+            if self.remaining > 0 {
+                let n = self.remaining.min(chunk.len());
+                self.report.extend_from_slice(&chunk[..n]);
+                chunk = &chunk[n..];
+                self.remaining -= n;
+            } else {
+                self.len.push(chunk.get_u8());
+                if self.len.len() < 2 {
+                    continue;
+                }
+                self.hasher.update(&self.len);
+                self.remaining = usize::from(u16::from_le_bytes([self.len[0], self.len[1]]));
+                self.rows += 1;
+                self.len.clear();
+            }
+            if self.remaining == 0 {
+                hash_report(&mut self.hasher, &self.report);
+                self.report.clear();
+            }
+        }
+    }
+
+    fn finish(self) -> InputSummary {
+        InputSummary {
+            bytes: self.bytes,
+            rows: self.rows,
+            digest: self.hasher.finalize().into(),
+        }
+    }
+}
+
+/// Adds the fields of an encrypted report that are the same on all helpers to the digest.
+type HashReport = dyn Fn(&mut Sha256, &[u8]) + Send + Sync;
+
+/// Returns the function that hashes the unencrypted fields of input reports, if the input of the
+/// query is a sequence of encrypted reports, each prefixed by its length. Otherwise, the input
+/// consists of fixed-size records.
+fn report_hasher(config: &QueryConfig) -> Option<Box<HashReport>> {
+    match config.query_type {
+        QueryType::SemiHonestIpa(ipa_config) | QueryType::MaliciousIpa(ipa_config)
+            if !ipa_config.plaintext_match_keys =>
+        {
+            Some(match config.field_type {
+                #[cfg(any(test, feature = "weak-field"))]
+                FieldType::Fp31 => ipa_report_hasher::<Fp31>(ipa_config.report_version),
+                FieldType::Fp32BitPrime => {
+                    ipa_report_hasher::<Fp32BitPrime>(ipa_config.report_version)
+                }
+            })
+        }
+        QueryType::OprfIpa(ipa_config) if !ipa_config.plaintext_match_keys => {
+            Some(oprf_report_hasher(&ipa_config))
+        }
+        _ => None,
+    }
+}
+
+fn ipa_report_hasher<F>(version: ReportVersion) -> Box<HashReport>
+where
+    F: PrimeField,
+    Replicated<F>: Serializable,
+{
+    Box::new(move |hasher, report| {
+        match EncryptedReport::<F, _, _, _>::from_bytes_with_version(report, version) {
+            Ok(report) => hash_public_fields(
+                hasher,
+                report.key_id(),
+                report.epoch(),
+                report.event_type(),
+                report.site_domain(),
+            ),
+            Err(e) => hash_invalid_report(hasher, &e),
+        }
+    })
+}
+
+fn oprf_report_hasher(config: &IpaQueryConfig) -> Box<HashReport> {
+    with_oprf_report_types!(
+        config,
+        |BK, TV, TS| Box::new(hash_oprf_report::<BK, TV, TS>),
+        // the query rejects unsupported report field widths before it reads the reports
+        _ => Box::new(|_, _| {}),
+    )
+}
+
+fn hash_oprf_report<BK, TV, TS>(hasher: &mut Sha256, report: &[u8])
+where
+    BK: WeakSharedValue,
+    TV: WeakSharedValue,
+    TS: WeakSharedValue,
+    Replicated<BK>: Serializable,
+    Replicated<TV>: Serializable,
+    Replicated<TS>: Serializable,
+{
+    match EncryptedOprfReport::<BK, TV, TS, _>::from_bytes(report) {
+        Ok(report) => hash_public_fields(
+            hasher,
+            report.key_id(),
+            report.epoch(),
+            Some(report.event_type()),
+            report.site_domain(),
+        ),
+        Err(e) => hash_invalid_report(hasher, &e),
+    }
+}
+
+fn hash_public_fields(
+    hasher: &mut Sha256,
+    key_id: KeyIdentifier,
+    epoch: Epoch,
+    event_type: Option<EventType>,
+    site_domain: &str,
+) {
+    hasher.update([0, key_id]);
+    hasher.update(epoch.to_le_bytes());
+    if let Some(event_type) = event_type {
+        hasher.update([u8::from(&event_type)]);
+    }
+    hasher.update(u64::try_from(site_domain.len()).unwrap().to_le_bytes());
+    hasher.update(site_domain);
+}
+
+/// Reports that cannot be parsed are dealt with later, according to the
+/// [`InvalidReportPolicy`](crate::helpers::query::InvalidReportPolicy) of the query. Parsing
+/// only looks at unencrypted fields, so all helpers find the same reports malformed.
+fn hash_invalid_report(hasher: &mut Sha256, error: &InvalidReportError) {
+    hasher.update([1, u8::from(error.kind())]);
+}
+
+/// Chunks of the input that are buffered between the input check and the query.
+const INPUT_CHUNKS: usize = 16;
+
+/// Runs `query` on `input`, while making sure that all helpers received inputs of the same
+/// shape, consistent with the query size. The input is streamed to the query as it arrives, and
+/// once all of it is read, helpers exchange [`InputSummary`] of their inputs with each other.
+///
+/// If the inputs do not match, the query is dropped, as it may never finish. Otherwise, this
+/// waits for the query to finish. The whole input is read even if the query stops reading it
+/// early, because other helpers wait for the summary of this input.
+///
+/// ## Errors
+/// If reading the input or communicating with other helpers fails, or inputs do not match. In
+/// the latter case, the error is [`Error::QueryInput`]. Otherwise, propagates the error of
+/// `query`.
+pub async fn verify<F, Fut, T>(
+    gateway: &Gateway,
+    config: &QueryConfig,
+    input: BodyStream,
+    query: F,
+) -> Result<T, Error>
+where
+    F: FnOnce(BodyStream) -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let (tx, rx) = mpsc::channel(INPUT_CHUNKS);
+    let mut check = pin!(check(gateway, config, input, tx));
+    let query = pin!(query(BodyStream::from_bytes_stream(ReceiverStream::new(
+        rx
+    ))));
+
+    match select(check.as_mut(), query).await {
+        Either::Left((Err(e), _)) => {
+            tracing::error!("{:?} query input is rejected: {e}", gateway.query_id());
+            Err(e)
+        }
+        Either::Left((Ok(()), query)) => query.await,
+        Either::Right((result, _)) => check.await.and(result),
+    }
+}
+
+/// Reads `input`, passing it on to `tx`, and compares its summary with the summaries of other
+/// helpers.
+async fn check(
+    gateway: &Gateway,
+    config: &QueryConfig,
+    mut input: BodyStream,
+    tx: mpsc::Sender<Result<Bytes, BoxError>>,
+) -> Result<(), Error> {
+    let query_id = gateway.query_id();
+    let hash_report = report_hasher(config);
+    let length_delimited = hash_report.is_some();
+    let mut digest = InputDigest::new(hash_report);
+    while let Some(chunk) = input.try_next().await.map_err(Error::ParseError)? {
+        digest.update(&chunk);
+        // the query does not need to read all of its input
+        let _ = tx.send(Ok(chunk)).await;
+    }
+    drop(tx);
+    let summary = digest.finish();
+
+    let step = Gate::default()
+        .narrow(&config.query_type)
+        .narrow(&InputCheckStep);
+    let peer = |direction| gateway.role().peer(direction);
+    let left_channel = ChannelId::new(peer(Direction::Left), step.clone());
+    let right_channel = ChannelId::new(peer(Direction::Right), step);
+    let total_records = TotalRecords::from(1);
+    let ((), (), left, right) = try_join4(
+        gateway
+            .get_sender::<InputSummary>(&left_channel, total_records)
+            .send(RecordId::FIRST, summary),
+        gateway
+            .get_sender::<InputSummary>(&right_channel, total_records)
+            .send(RecordId::FIRST, summary),
+        gateway
+            .get_receiver::<InputSummary>(&left_channel)
+            .receive(RecordId::FIRST),
+        gateway
+            .get_receiver::<InputSummary>(&right_channel)
+            .receive(RecordId::FIRST),
+    )
+    .await?;
+
+    // every helper sends its summary before checking anything, so that other helpers do not
+    // wait for it if this helper's input is wrong
+    let expected = u32::from(config.size);
+    if length_delimited && summary.rows != expected {
+        return Err(QueryInputError::WrongRecordCount {
+            query_id,
+            expected,
+            actual: summary.rows,
+        }
+        .into());
+    }
+    if !length_delimited && (summary.bytes == 0 || summary.bytes % u64::from(expected) != 0) {
+        return Err(QueryInputError::MisalignedInput {
+            query_id,
+            expected,
+            bytes: summary.bytes,
+        }
+        .into());
+    }
+    for (peer, peer_summary) in [
+        (peer(Direction::Left), left),
+        (peer(Direction::Right), right),
+    ] {
+        if peer_summary != summary {
+            return Err(mismatch(query_id, peer, &summary, &peer_summary).into());
+        }
+    }
+
+    Ok(())
+}
+
+fn mismatch(
+    query_id: QueryId,
+    peer: Role,
+    summary: &InputSummary,
+    peer_summary: &InputSummary,
+) -> QueryInputError {
+    QueryInputError::InputMismatch {
+        query_id,
+        peer,
+        reason: if summary.bytes != peer_summary.bytes {
+            format!(
+                "{} bytes, {peer:?} has {}",
+                summary.bytes, peer_summary.bytes
+            )
+        } else if summary.rows != peer_summary.rows {
+            format!(
+                "{} reports, {peer:?} has {}",
+                summary.rows, peer_summary.rows
+            )
+        } else {
+            "report lengths or unencrypted report fields differ".to_string()
+        },
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use futures::future::join_all;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        ff::{FieldType, Gf40Bit, Gf8Bit},
+        helpers::{
+            query::{IpaQueryConfig, QueryType::TestMultiply},
+            BytesStream,
+        },
+        hpke::KeyRegistry,
+        report::{Report, DEFAULT_HELPER_ORIGIN},
+        test_fixture::TestWorld,
+    };
+
+    fn oprf_ipa_config() -> QueryConfig {
+        QueryConfig::new(
+            QueryType::OprfIpa(IpaQueryConfig::default()),
+            FieldType::Fp32BitPrime,
+            2,
+        )
+        .unwrap()
+    }
+
+    fn summary(config: &QueryConfig, input: &[Bytes]) -> InputSummary {
+        let mut digest = InputDigest::new(report_hasher(config));
+        for chunk in input {
+            digest.update(chunk);
+        }
+        digest.finish()
+    }
+
+    fn report(len: u16) -> Vec<u8> {
+        let mut report = len.to_le_bytes().to_vec();
+        report.resize(2 + usize::from(len), 0);
+        report
+    }
+
+    async fn verify_all(config: &QueryConfig, inputs: [Vec<u8>; 3]) -> Vec<Result<Vec<u8>, Error>> {
+        let world = TestWorld::default();
+        let world = &world;
+        join_all(zip_roles(inputs).map(|(role, input)| async move {
+            verify(
+                world.gateway(role),
+                config,
+                BodyStream::from(input),
+                |input| async move { Ok(input.to_vec().await) },
+            )
+            .await
+        }))
+        .await
+    }
+
+    fn zip_roles(inputs: [Vec<u8>; 3]) -> impl Iterator<Item = (Role, Vec<u8>)> {
+        Role::all().iter().copied().zip(inputs)
+    }
+
+    #[test]
+    fn summary_of_split_reports() {
+        let config = oprf_ipa_config();
+        let input = [report(3), report(300), report(0)].concat();
+        let whole = summary(&config, &[Bytes::from(input.clone())]);
+        assert_eq!(3, whole.rows);
+        assert_eq!(309, whole.bytes);
+
+        // chunk boundaries fall inside length prefixes and report bodies
+        let split = [0, 1, 4, 6, 306, input.len()]
+            .windows(2)
+            .map(|w| Bytes::copy_from_slice(&input[w[0]..w[1]]))
+            .collect::<Vec<_>>();
+        assert_eq!(whole, summary(&config, &split));
+
+        let other = [report(300), report(3), report(0)].concat();
+        assert_ne!(whole.digest, summary(&config, &[Bytes::from(other)]).digest);
+    }
+
+    #[test]
+    fn summary_of_unencrypted_report_fields() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let key_registry = KeyRegistry::random(1, &mut rng);
+        let config = QueryConfig::new(
+            QueryType::SemiHonestIpa(IpaQueryConfig::default()),
+            FieldType::Fp32BitPrime,
+            1,
+        )
+        .unwrap();
+        let report = Report::<Fp32BitPrime, Gf40Bit, Gf8Bit> {
+            timestamp: rng.gen(),
+            mk_shares: (rng.gen(), rng.gen()).into(),
+            event_type: EventType::Trigger,
+            breakdown_key: rng.gen(),
+            trigger_value: (rng.gen(), rng.gen()).into(),
+            epoch: 1,
+            site_domain: "www.foo.example".to_owned(),
+        };
+        let mut encrypt = |report: &Report<_, _, _>| {
+            let mut buf = Vec::new();
+            report
+                .delimited_encrypt_to(
+                    ReportVersion::MatchKeyOnly,
+                    0,
+                    &key_registry,
+                    DEFAULT_HELPER_ORIGIN,
+                    &mut rng,
+                    &mut buf,
+                )
+                .unwrap();
+            summary(&config, &[Bytes::from(buf)])
+        };
+
+        // ciphertexts differ, but helpers see the same unencrypted fields
+        let expected = encrypt(&report);
+        assert_eq!(expected, encrypt(&report));
+
+        for other in [
+            Report {
+                epoch: 2,
+                ..report.clone()
+            },
+            Report {
+                site_domain: "www.bar.example".to_owned(),
+                ..report.clone()
+            },
+            Report {
+                event_type: EventType::Source,
+                ..report.clone()
+            },
+        ] {
+            let actual = encrypt(&other);
+            assert_eq!(expected.bytes, actual.bytes);
+            assert_ne!(expected.digest, actual.digest);
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_matching_inputs() {
+        let config = QueryConfig::new(TestMultiply, FieldType::Fp31, 2).unwrap();
+        for result in verify_all(&config, [vec![1; 4], vec![2; 4], vec![3; 4]]).await {
+            assert_eq!(4, result.unwrap().len());
+        }
+    }
+
+    #[tokio::test]
+    async fn checks_input_the_query_does_not_read() {
+        let config = QueryConfig::new(TestMultiply, FieldType::Fp31, 2).unwrap();
+        let world = TestWorld::default();
+        let world = &world;
+        let inputs = [vec![1; 4 << 10], vec![2; 4 << 10], vec![3; 6 << 10]];
+        let results = join_all(zip_roles(inputs).map(|(role, input)| async move {
+            verify(
+                world.gateway(role),
+                &config,
+                BodyStream::from(input),
+                |_| async { Ok(()) },
+            )
+            .await
+        }))
+        .await;
+        assert!(results.iter().all(|r| matches!(
+            r,
+            Err(Error::QueryInput(QueryInputError::InputMismatch { .. }))
+        )));
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_inputs() {
+        let config = QueryConfig::new(TestMultiply, FieldType::Fp31, 2).unwrap();
+        let results = verify_all(&config, [vec![1; 4], vec![2; 4], vec![3; 6]]).await;
+        for (&role, result) in Role::all().iter().zip(results) {
+            let err = result.unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    Error::QueryInput(QueryInputError::InputMismatch { peer, .. })
+                        if peer == Role::H3 || role == Role::H3
+                ),
+                "{role:?}: {err:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_record_count() {
+        let config = oprf_ipa_config();
+        let input = [report(5), report(5), report(5)].concat();
+        for result in verify_all(&config, [input.clone(), input.clone(), input]).await {
+            assert!(matches!(
+                result.unwrap_err(),
+                Error::QueryInput(QueryInputError::WrongRecordCount {
+                    expected: 2,
+                    actual: 3,
+                    ..
+                })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_misaligned_input() {
+        let config = QueryConfig::new(TestMultiply, FieldType::Fp31, 2).unwrap();
+        for result in verify_all(&config, [vec![1; 3], vec![2; 3], vec![3; 3]]).await {
+            assert!(matches!(
+                result.unwrap_err(),
+                Error::QueryInput(QueryInputError::MisalignedInput { bytes: 3, .. })
+            ));
+        }
+    }
+}
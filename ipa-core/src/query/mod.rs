@@ -1,13 +1,22 @@
+mod budget;
+mod chunked_input;
 mod completion;
 mod executor;
+mod input_check;
 mod processor;
+mod result_store;
 mod runner;
 mod state;
 
+pub use budget::{PrivacyBudgetError, PrivacyBudgetLedger};
 use completion::Handle as CompletionHandle;
-pub use executor::Result as ProtocolResult;
+pub use executor::{RejectedReports, Result as ProtocolResult, ResultHeader};
+#[cfg(feature = "compact-gate")]
+pub(crate) use input_check::InputCheckStep;
 pub use processor::{
-    NewQueryError, PrepareQueryError, Processor as QueryProcessor, QueryCompletionError,
-    QueryInputError, QueryStatusError,
+    KeyReloadError, NewQueryError, PrepareQueryError, Processor as QueryProcessor,
+    QueryAccessError, QueryCompletionError, QueryInputError, QueryKillError, QueryStatusError,
+    QueryTimeouts,
 };
-pub use state::QueryStatus;
+pub use result_store::{ResultStore, ResultStoreError};
+pub use state::{QueryStatus, QueryStatusReport};
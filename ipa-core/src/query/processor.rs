@@ -1,24 +1,39 @@
 use std::{
-    collections::hash_map::Entry,
+    collections::{hash_map::Entry, HashMap},
     fmt::{Debug, Formatter},
-    sync::Arc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
 };
 
-use futures::{future::try_join, stream};
+use futures::{
+    future::{join, try_join},
+    stream,
+};
 
 use crate::{
     error::Error as ProtocolError,
     helpers::{
-        query::{PrepareQuery, QueryConfig, QueryInput},
-        Gateway, GatewayConfig, Role, RoleAssignment, Transport, TransportError, TransportImpl,
+        query::{
+            PrepareQuery, QueryConfig, QueryInput, QueryInputChunk, ReceivedInputChunks,
+            ReportCollectorId,
+        },
+        Gateway, GatewayConfig, Role, RoleAssignment, RouteId, Transport, TransportError,
+        TransportImpl,
     },
-    hpke::{KeyPair, KeyRegistry},
+    hpke::{KeyDirectory, KeyDirectoryError, KeyPair, KeyRegistry, PublicKeyOnly},
     protocol::QueryId,
     query::{
+        budget::{PrivacyBudgetError, PrivacyBudgetLedger},
+        chunked_input::ChunkedInput,
         executor,
-        state::{QueryState, QueryStatus, RemoveQuery, RunningQueries, StateError},
+        result_store::{ResultStore, ResultStoreError},
+        state::{
+            QueryState, QueryStatus, QueryStatusReport, RemoveQuery, RunningQueries, StateError,
+        },
         CompletionHandle, ProtocolResult,
     },
+    report::{KeyIdentifier, DEFAULT_HELPER_ORIGIN},
+    sync::Weak,
 };
 
 /// `Processor` accepts and tracks requests to initiate new queries on this helper party
@@ -36,19 +51,65 @@ use crate::{
 /// IPA protocol.
 /// - When helper party is done, it holds onto the results of the computation until the external party
 /// that initiated this request asks for them.
+/// - If the query does not make progress within the deadlines set by [`QueryTimeouts`], it is
+///   timed out and, eventually, forgotten.
 ///
 /// [`AdditiveShare`]: crate::secret_sharing::replicated::semi_honest::AdditiveShare
 pub struct Processor {
     queries: RunningQueries,
-    key_registry: Arc<KeyRegistry<KeyPair>>,
+    /// Keys used to decrypt reports. Queries take a snapshot of the registry when they start, so
+    /// reloading keys does not affect queries that are already running.
+    key_registry: Mutex<Arc<KeyRegistry<KeyPair>>>,
+    key_directory: Option<KeyDirectory>,
+    /// Origin that reports must be encrypted for to be accepted by this helper.
+    helper_origin: Arc<str>,
+    privacy_budget: Option<PrivacyBudgetLedger>,
+    timeouts: QueryTimeouts,
+    result_store: Option<ResultStore>,
+    /// The status of every query the last time [`Processor::reap`] looked at it, and the time
+    /// when it was first seen in that status.
+    observed: Mutex<HashMap<QueryId, (QueryStatus, Instant)>>,
+    /// Inputs of the queries that are being uploaded in chunks.
+    uploads: Mutex<HashMap<QueryId, ChunkedInput>>,
+    /// Report collectors that requested the queries known to this helper. Owners of the results
+    /// kept in the result store are kept there as well.
+    owners: Mutex<HashMap<QueryId, ReportCollectorId>>,
 }
 
 impl Default for Processor {
     fn default() -> Self {
-        Self {
-            queries: RunningQueries::default(),
-            key_registry: Arc::new(KeyRegistry::<KeyPair>::empty()),
-        }
+        Self::new(KeyRegistry::<KeyPair>::empty())
+    }
+}
+
+/// Deadlines for the queries that are not making progress. `None` means the query can stay in
+/// the corresponding state forever.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryTimeouts {
+    /// How long the coordinator waits for other helpers to accept a new query.
+    pub prepare: Option<Duration>,
+    /// How long a query waits for its input after all helpers accepted it.
+    pub inputs: Option<Duration>,
+    /// How long the results of a completed query are kept until the report collector asks for
+    /// them. Results in the result store are deleted after this period whether they were collected
+    /// or not. Queries that did not produce results are forgotten after the same period, or after
+    /// [`QueryTimeouts::DEFAULT_TERMINAL_RETENTION`] if it is not set.
+    pub result_retention: Option<Duration>,
+}
+
+impl QueryTimeouts {
+    /// How often the background reaper checks queries against these deadlines.
+    pub const REAPER_PERIOD: Duration = Duration::from_secs(1);
+
+    /// How long queries that did not produce results are remembered if `result_retention` is not
+    /// set, so that report collectors can still find out what happened to them.
+    pub const DEFAULT_TERMINAL_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+    /// How long queries that did not produce results are remembered.
+    #[must_use]
+    pub fn terminal_retention(&self) -> Duration {
+        self.result_retention
+            .unwrap_or(Self::DEFAULT_TERMINAL_RETENTION)
     }
 }
 
@@ -58,6 +119,8 @@ pub enum NewQueryError {
     State(#[from] StateError),
     #[error(transparent)]
     Transport(#[from] TransportError),
+    #[error(transparent)]
+    PrivacyBudget(#[from] PrivacyBudgetError),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -71,6 +134,8 @@ pub enum PrepareQueryError {
         #[from]
         source: StateError,
     },
+    #[error(transparent)]
+    PrivacyBudget(#[from] PrivacyBudgetError),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -82,6 +147,42 @@ pub enum QueryInputError {
         #[from]
         source: StateError,
     },
+    #[error("Chunk {chunk} of the {query_id:?} query input does not match its checksum")]
+    CorruptedChunk { query_id: QueryId, chunk: u32 },
+    #[error("Chunk {chunk} of the {query_id:?} query input has no records")]
+    EmptyChunk { query_id: QueryId, chunk: u32 },
+    #[error(
+        "The query with id {query_id:?} expects {expected} input records, got at least {received}"
+    )]
+    TooManyRecords {
+        query_id: QueryId,
+        expected: u32,
+        received: u32,
+    },
+    #[error("The query with id {query_id:?} expects {expected} input records, got {actual}")]
+    WrongRecordCount {
+        query_id: QueryId,
+        expected: u32,
+        actual: u32,
+    },
+    #[error(
+        "The input of the query with id {query_id:?} is {bytes} bytes long, it cannot have \
+         {expected} records of the same size"
+    )]
+    MisalignedInput {
+        query_id: QueryId,
+        expected: u32,
+        bytes: u64,
+    },
+    #[error(
+        "The input of the query with id {query_id:?} does not match the input of {peer:?}: \
+         this helper has {reason}"
+    )]
+    InputMismatch {
+        query_id: QueryId,
+        peer: Role,
+        reason: String,
+    },
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -101,6 +202,41 @@ pub enum QueryCompletionError {
     },
     #[error("query execution failed: {0}")]
     ExecutionError(#[from] ProtocolError),
+    #[error("The query with id {0:?} was cancelled")]
+    Cancelled(QueryId),
+    #[error(transparent)]
+    ResultStore(#[from] ResultStoreError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum QueryKillError {
+    #[error("The query with id {0:?} does not exist")]
+    NoSuchQuery(QueryId),
+    #[error(transparent)]
+    StateError {
+        #[from]
+        source: StateError,
+    },
+    #[error(transparent)]
+    Transport(#[from] TransportError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeyReloadError {
+    #[error("This helper does not load keys from a key directory")]
+    NotConfigured,
+    #[error(transparent)]
+    Directory(#[from] KeyDirectoryError),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum QueryAccessError {
+    #[error("The query with id {0:?} does not exist")]
+    NoSuchQuery(QueryId),
+    #[error("The query with id {0:?} was requested by another report collector")]
+    Forbidden(QueryId),
+    #[error(transparent)]
+    ResultStore(#[from] ResultStoreError),
 }
 
 impl Debug for Processor {
@@ -114,10 +250,65 @@ impl Processor {
     pub fn new(key_registry: KeyRegistry<KeyPair>) -> Self {
         Self {
             queries: RunningQueries::default(),
-            key_registry: Arc::new(key_registry),
+            key_registry: Mutex::new(Arc::new(key_registry)),
+            key_directory: None,
+            helper_origin: Arc::from(DEFAULT_HELPER_ORIGIN),
+            privacy_budget: None,
+            timeouts: QueryTimeouts::default(),
+            result_store: None,
+            observed: Mutex::default(),
+            uploads: Mutex::default(),
+            owners: Mutex::default(),
         }
     }
 
+    /// Makes this processor charge the privacy budget that queries spend to `ledger`, and reject
+    /// queries once a report collector runs out of it.
+    #[must_use]
+    pub fn with_privacy_budget(mut self, ledger: PrivacyBudgetLedger) -> Self {
+        self.privacy_budget = Some(ledger);
+        self
+    }
+
+    /// Makes this processor time out queries that do not make progress and discard results
+    /// that nobody collected, according to `timeouts`. Deadlines are enforced by
+    /// [`Self::reap`], which must be called periodically, see [`Self::run_reaper`].
+    #[must_use]
+    pub fn with_timeouts(mut self, timeouts: QueryTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Makes this processor write query results to `store` as soon as queries complete. Stored
+    /// results can be collected any number of times, including after the helper restarts, until
+    /// they expire.
+    #[must_use]
+    pub fn with_result_store(mut self, store: ResultStore) -> Self {
+        self.result_store = Some(store);
+        self
+    }
+
+    /// Makes this processor reload its keys from `directory` on [`Self::reload_keys`]. The
+    /// directory is not read until then, so the initial keys must be provided to [`Self::new`].
+    #[must_use]
+    pub fn with_key_directory(mut self, directory: KeyDirectory) -> Self {
+        self.key_directory = Some(directory);
+        self
+    }
+
+    /// Makes this processor accept reports encrypted for `helper_origin` instead of
+    /// [`DEFAULT_HELPER_ORIGIN`]. All helpers must use the same origin as report collectors do.
+    #[must_use]
+    pub fn with_helper_origin(mut self, helper_origin: &str) -> Self {
+        self.helper_origin = Arc::from(helper_origin);
+        self
+    }
+
+    #[must_use]
+    pub fn timeouts(&self) -> &QueryTimeouts {
+        &self.timeouts
+    }
+
     /// Upon receiving a new query request:
     /// * processor generates new query id
     /// * assigns roles to helpers in the ring. Helper that received new query request becomes `Role::H1` (aka coordinator).
@@ -127,15 +318,19 @@ impl Processor {
     /// * records newly created query id internally and sets query state to awaiting data
     /// * returns query configuration
     ///
+    /// If this helper keeps track of the privacy budget, the budget the query spends is charged
+    /// to the report collector that requested it before other helpers learn about it.
+    ///
     /// ## Errors
-    /// When other peers failed to acknowledge this query
+    /// When other peers failed to acknowledge this query, or the report collector does not have
+    /// enough privacy budget left
     #[allow(clippy::missing_panics_doc)]
     pub async fn new_query(
         &self,
         transport: TransportImpl,
         req: QueryConfig,
     ) -> Result<PrepareQuery, NewQueryError> {
-        let query_id = QueryId;
+        let query_id = QueryId::random();
         let handle = self.queries.handle(query_id);
         handle.set_state(QueryState::Preparing(req))?;
         let guard = handle.remove_query_on_drop();
@@ -152,15 +347,40 @@ impl Processor {
             roles: roles.clone(),
         };
 
+        if let Some(ledger) = &self.privacy_budget {
+            ledger.charge(&req)?;
+        }
+        self.owners
+            .lock()
+            .unwrap()
+            .insert(query_id, req.report_collector);
+
         // Inform other parties about new query. If any of them rejects it, this join will fail
-        try_join(
+        if let Err(e) = try_join(
             transport.send(left, &prepare_request, stream::empty()),
             transport.send(right, &prepare_request, stream::empty()),
         )
         .await
-        .map_err(NewQueryError::Transport)?;
+        {
+            self.refund_privacy_budget(query_id, &req);
+            // The other helper may have accepted the query and charged its budget for it.
+            // Aborting the query there gives the budget back. If it did not accept the query,
+            // there is nothing to abort.
+            let _ = join(
+                transport.send(left, (RouteId::AbortQuery, query_id), stream::empty()),
+                transport.send(right, (RouteId::AbortQuery, query_id), stream::empty()),
+            )
+            .await;
+            return Err(NewQueryError::Transport(e));
+        }
 
-        handle.set_state(QueryState::AwaitingInputs(query_id, req, roles))?;
+        // The query may have timed out or been cancelled while other helpers were accepting it.
+        // Keep it around, so the report collector can see why it failed. Its budget has already
+        // been refunded when it was stopped.
+        if let Err(e) = handle.set_state(QueryState::AwaitingInputs(query_id, req, roles)) {
+            guard.restore();
+            return Err(e.into());
+        }
 
         guard.restore();
         Ok(prepare_request)
@@ -168,12 +388,17 @@ impl Processor {
 
     /// On prepare, each follower:
     /// * ensures that it is not the leader on this query
-    /// * query is not registered yet
+    /// * query is not registered yet, unless it is the same request received again
+    /// * charges the privacy budget the query spends, if this helper keeps track of it
     /// * creates gateway and network
     /// * registers query
     ///
     /// ## Errors
-    /// if query is already running or this helper cannot be a follower in it
+    /// if query is already running, this helper cannot be a follower in it, or the report
+    /// collector does not have enough privacy budget left
+    ///
+    /// ## Panics
+    /// If failed to obtain an exclusive access to the query collection.
     pub fn prepare(
         &self,
         transport: &TransportImpl,
@@ -186,14 +411,34 @@ impl Processor {
         }
         let handle = self.queries.handle(req.query_id);
         if handle.status().is_some() {
-            return Err(PrepareQueryError::AlreadyRunning);
+            // The leader retries this request if the response to it is lost, so the same
+            // request may arrive again.
+            let queries = self.queries.inner.lock().unwrap();
+            return match queries.get(&req.query_id) {
+                Some(QueryState::AwaitingInputs(_, config, roles))
+                    if *config == req.config && *roles == req.roles =>
+                {
+                    Ok(())
+                }
+                _ => Err(PrepareQueryError::AlreadyRunning),
+            };
         }
 
-        handle.set_state(QueryState::AwaitingInputs(
+        if let Some(ledger) = &self.privacy_budget {
+            ledger.charge(&req.config)?;
+        }
+        if let Err(e) = handle.set_state(QueryState::AwaitingInputs(
             req.query_id,
             req.config,
             req.roles,
-        ))?;
+        )) {
+            self.refund_privacy_budget(req.query_id, &req.config);
+            return Err(e.into());
+        }
+        self.owners
+            .lock()
+            .unwrap()
+            .insert(req.query_id, req.config.report_collector);
 
         Ok(())
     }
@@ -225,15 +470,20 @@ impl Processor {
                         role_assignment,
                         transport,
                     );
-                    queries.insert(
-                        input.query_id,
-                        QueryState::Running(executor::execute(
-                            config,
-                            Arc::clone(&self.key_registry),
-                            gateway,
-                            input.input_stream,
-                        )),
+                    let running = executor::execute(
+                        config,
+                        Arc::clone(&self.key_registry.lock().unwrap()),
+                        Arc::clone(&self.helper_origin),
+                        gateway,
+                        input.input_stream,
                     );
+                    let running = match &self.result_store {
+                        Some(store) => {
+                            store.persist_on_completion(query_id, config.report_collector, running)
+                        }
+                        None => running,
+                    };
+                    queries.insert(input.query_id, QueryState::Running(running));
                     Ok(())
                 } else {
                     let error = StateError::InvalidState {
@@ -248,17 +498,185 @@ impl Processor {
         }
     }
 
+    /// Receives one chunk of the query input. Once all the records that the query expects
+    /// arrive, the chunks are concatenated and the query starts running, as if the input was
+    /// sent to [`Self::receive_inputs`] in one piece. Chunks that have been received already are
+    /// ignored.
+    ///
+    /// Until then, the chunks are held in memory. Every chunk has at least one record, so a query
+    /// holds at most as many chunks as it expects records, each no larger than the chunk size
+    /// limit of the HTTP server.
+    ///
+    /// ## Errors
+    /// If query is not registered on this helper, it is not waiting for inputs, or the chunk is
+    /// corrupted, empty or has more records than the query expects.
+    ///
+    /// ## Panics
+    /// If the query collection mutex is poisoned.
+    pub fn receive_input_chunk(
+        &self,
+        transport: TransportImpl,
+        chunk: QueryInputChunk,
+    ) -> Result<ReceivedInputChunks, QueryInputError> {
+        let query_id = chunk.query_id;
+        let (status, input) = {
+            let queries = self.queries.inner.lock().unwrap();
+            let mut uploads = self.uploads.lock().unwrap();
+            let upload = match queries.get(&query_id) {
+                Some(QueryState::AwaitingInputs(_, config, _)) => uploads
+                    .entry(query_id)
+                    .or_insert_with(|| ChunkedInput::new(u32::from(config.size))),
+                Some(state) => {
+                    // the client may be retrying the chunk that completed the input
+                    return match uploads.get(&query_id).map(ChunkedInput::status) {
+                        Some(status) if status.contains(chunk.index) => Ok(status),
+                        _ => Err(QueryInputError::StateError {
+                            source: StateError::InvalidState {
+                                from: QueryStatus::from(state),
+                                to: QueryStatus::Running,
+                            },
+                        }),
+                    };
+                }
+                None => return Err(QueryInputError::NoSuchQuery(query_id)),
+            };
+
+            let was_complete = upload.is_complete();
+            upload.add(chunk)?;
+            let input = (!was_complete && upload.is_complete()).then(|| upload.take_input());
+            let status = upload.status();
+
+            // forget the uploads of the queries that are gone
+            uploads.retain(|query_id, _| queries.contains_key(query_id));
+            (status, input)
+        }; // release mutexes before starting the query
+
+        if let Some(input_stream) = input {
+            self.receive_inputs(
+                transport,
+                QueryInput {
+                    query_id,
+                    input_stream,
+                },
+            )?;
+        }
+
+        Ok(status)
+    }
+
+    /// Returns the chunks of the query input that this helper has received so far.
+    ///
+    /// ## Errors
+    /// If query is not registered on this helper, or its input was not uploaded in chunks and
+    /// the query is no longer waiting for it.
+    ///
+    /// ## Panics
+    /// If the query collection mutex is poisoned.
+    pub fn received_input_chunks(
+        &self,
+        query_id: QueryId,
+    ) -> Result<ReceivedInputChunks, QueryInputError> {
+        let queries = self.queries.inner.lock().unwrap();
+        let uploads = self.uploads.lock().unwrap();
+        match (queries.get(&query_id), uploads.get(&query_id)) {
+            (Some(_), Some(upload)) => Ok(upload.status()),
+            (Some(QueryState::AwaitingInputs(_, config, _)), None) => Ok(ReceivedInputChunks {
+                expected_records: u32::from(config.size),
+                ..ReceivedInputChunks::default()
+            }),
+            (Some(state), None) => Err(QueryInputError::StateError {
+                source: StateError::InvalidState {
+                    from: QueryStatus::from(state),
+                    to: QueryStatus::Running,
+                },
+            }),
+            (None, _) => Err(QueryInputError::NoSuchQuery(query_id)),
+        }
+    }
+
+    /// Makes sure that `report_collector` is the one that requested the query, so that nobody
+    /// else can send its input, see its status, cancel it or collect its results.
+    ///
+    /// ## Errors
+    /// If query is not registered on this helper, or another report collector requested it.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    pub fn authorize(
+        &self,
+        query_id: QueryId,
+        report_collector: ReportCollectorId,
+    ) -> Result<(), QueryAccessError> {
+        let owner = self.owners.lock().unwrap().get(&query_id).copied();
+        let owner = match (owner, &self.result_store) {
+            (Some(owner), _) => Some(owner),
+            (None, Some(store)) => store.owner(query_id)?,
+            (None, None) => None,
+        };
+        match owner {
+            Some(owner) if owner == report_collector => Ok(()),
+            Some(_) => Err(QueryAccessError::Forbidden(query_id)),
+            None => Err(QueryAccessError::NoSuchQuery(query_id)),
+        }
+    }
+
+    /// Returns the public keys of the key pairs this helper currently uses to decrypt reports.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    pub fn public_keys(&self) -> KeyRegistry<PublicKeyOnly> {
+        self.key_registry.lock().unwrap().public_keys()
+    }
+
+    /// Replaces the keys used to decrypt reports with the ones currently in the key directory
+    /// and returns their identifiers. Reports encrypted with keys that were removed from the
+    /// directory are rejected by the queries that start after this call.
+    ///
+    /// ## Errors
+    /// If this processor has no key directory or the keys in it cannot be loaded. Keys in use
+    /// are not changed in that case.
+    ///
+    /// ## Panics
+    /// If the mutex is poisoned.
+    pub fn reload_keys(&self) -> Result<Vec<KeyIdentifier>, KeyReloadError> {
+        let directory = self
+            .key_directory
+            .as_ref()
+            .ok_or(KeyReloadError::NotConfigured)?;
+        let registry = directory.load()?;
+        let key_ids = registry.key_ids().collect();
+        *self.key_registry.lock().unwrap() = Arc::new(registry);
+
+        Ok(key_ids)
+    }
+
     /// Returns the query status.
     ///
     /// ## Errors
     /// If query is not registered on this helper.
+    pub fn query_status(&self, query_id: QueryId) -> Result<QueryStatus, QueryStatusError> {
+        self.query_status_report(query_id)
+            .map(|report| report.status)
+    }
+
+    /// Returns the query status along with the amount of data exchanged with other helpers so
+    /// far, if the query is running.
+    ///
+    /// ## Errors
+    /// If query is not registered on this helper.
     ///
     /// ## Panics
     /// If the query collection mutex is poisoned.
-    pub fn query_status(&self, query_id: QueryId) -> Result<QueryStatus, QueryStatusError> {
+    pub fn query_status_report(
+        &self,
+        query_id: QueryId,
+    ) -> Result<QueryStatusReport, QueryStatusError> {
         let mut queries = self.queries.inner.lock().unwrap();
         let Some(mut state) = queries.remove(&query_id) else {
-            return Err(QueryStatusError::NoSuchQuery(query_id));
+            return match &self.result_store {
+                Some(store) if store.contains(query_id) => Ok(QueryStatus::Completed.into()),
+                _ => Err(QueryStatusError::NoSuchQuery(query_id)),
+            };
         };
 
         if let QueryState::Running(ref mut running) = state {
@@ -267,9 +685,9 @@ impl Processor {
             }
         }
 
-        let status = QueryStatus::from(&state);
+        let report = QueryStatusReport::from(&state);
         queries.insert(query_id, state);
-        Ok(status)
+        Ok(report)
     }
 
     /// Awaits the query completion
@@ -288,8 +706,14 @@ impl Processor {
 
             match queries.remove(&query_id) {
                 Some(QueryState::Completed(result)) => return result.map_err(Into::into),
+                Some(QueryState::Cancelled) => {
+                    return Err(QueryCompletionError::Cancelled(query_id))
+                }
                 Some(QueryState::Running(handle)) => {
-                    queries.insert(query_id, QueryState::AwaitingCompletion);
+                    queries.insert(
+                        query_id,
+                        QueryState::AwaitingCompletion(crate::sync::Arc::clone(&handle.progress)),
+                    );
                     CompletionHandle::new(RemoveQuery::new(query_id, &self.queries), handle)
                 }
                 Some(state) => {
@@ -302,12 +726,255 @@ impl Processor {
                         source: state_error,
                     });
                 }
-                None => return Err(QueryCompletionError::NoSuchQuery(query_id)),
+                None => {
+                    return match self.result_store.as_ref().map(|s| s.load(query_id)) {
+                        Some(Ok(Some(result))) => Ok(Box::new(result)),
+                        Some(Err(e)) => Err(e.into()),
+                        Some(Ok(None)) | None => Err(QueryCompletionError::NoSuchQuery(query_id)),
+                    }
+                }
             }
         }; // release mutex before await
 
         Ok(handle.await?)
     }
+
+    /// Cancels the query on this helper and asks other helpers to do the same. The query task is
+    /// aborted, which closes all of its gateway channels, and any results it has already produced
+    /// are discarded. The query stays in the [`QueryStatus::Cancelled`] state until the report
+    /// collector calls [`Self::complete`] on it.
+    ///
+    /// ## Errors
+    /// If query is not registered on this helper, its results are being collected, or other
+    /// helpers failed to cancel it. In the latter case, the query is still cancelled on this
+    /// helper.
+    pub async fn kill(
+        &self,
+        transport: TransportImpl,
+        query_id: QueryId,
+    ) -> Result<(), QueryKillError> {
+        self.abort(query_id)?;
+
+        let [right, left] = transport.identity().others();
+        let (left_res, right_res) = join(
+            transport.send(left, (RouteId::AbortQuery, query_id), stream::empty()),
+            transport.send(right, (RouteId::AbortQuery, query_id), stream::empty()),
+        )
+        .await;
+        left_res?;
+        right_res?;
+
+        Ok(())
+    }
+
+    /// Cancels the query on this helper only. Called when another helper received a request to
+    /// kill the query.
+    ///
+    /// ## Errors
+    /// If query is not registered on this helper or its results are being collected.
+    ///
+    /// ## Panics
+    /// If the query collection mutex is poisoned.
+    pub fn abort(&self, query_id: QueryId) -> Result<(), QueryKillError> {
+        let mut queries = self.queries.inner.lock().unwrap();
+        match queries.remove(&query_id) {
+            Some(state @ QueryState::AwaitingCompletion(_)) => {
+                queries.insert(query_id, state);
+                Err(QueryKillError::StateError {
+                    source: StateError::InvalidState {
+                        from: QueryStatus::AwaitingCompletion,
+                        to: QueryStatus::Cancelled,
+                    },
+                })
+            }
+            Some(state @ (QueryState::Cancelled | QueryState::TimedOut | QueryState::Expired)) => {
+                // query has already been stopped for another reason
+                queries.insert(query_id, state);
+                Ok(())
+            }
+            Some(state) => {
+                match state {
+                    QueryState::Running(running) => running.join_handle.abort(),
+                    // the query never saw any reports, so it did not spend any budget
+                    QueryState::Preparing(config) | QueryState::AwaitingInputs(_, config, _) => {
+                        self.refund_privacy_budget(query_id, &config);
+                    }
+                    _ => {}
+                }
+                self.discard_stored_result(query_id);
+                self.uploads.lock().unwrap().remove(&query_id);
+                tracing::info!("{query_id} query is cancelled");
+                queries.insert(query_id, QueryState::Cancelled);
+                Ok(())
+            }
+            None => Err(QueryKillError::NoSuchQuery(query_id)),
+        }
+    }
+
+    /// Enforces [`QueryTimeouts`] as of `now`:
+    /// * queries that stayed in [`QueryStatus::Preparing`] or [`QueryStatus::AwaitingInputs`] for
+    ///   too long are moved to [`QueryStatus::TimedOut`].
+    /// * results that nobody collected within the retention period are discarded and the query
+    ///   is moved to [`QueryStatus::Expired`].
+    /// * queries that did not produce results ([`QueryStatus::Cancelled`],
+    ///   [`QueryStatus::TimedOut`] or [`QueryStatus::Expired`]) are forgotten after
+    ///   [`QueryTimeouts::terminal_retention`].
+    ///
+    /// The time a query spent in its current state is measured from the first call that observed
+    /// it in that state, or moved it there.
+    ///
+    /// Returns the queries that timed out, other helpers need to be informed about them.
+    ///
+    /// ## Panics
+    /// If the query collection mutex is poisoned.
+    pub fn reap(&self, now: Instant) -> Vec<QueryId> {
+        let timeouts = self.timeouts;
+        let mut timed_out = Vec::new();
+        let mut queries = self.queries.inner.lock().unwrap();
+        let mut observed = self.observed.lock().unwrap();
+        observed.retain(|query_id, _| queries.contains_key(query_id));
+
+        queries.retain(|query_id, state| {
+            if let QueryState::Running(running) = state {
+                if let Some(result) = running.try_complete() {
+                    *state = QueryState::Completed(result);
+                }
+            }
+
+            let status = QueryStatus::from(&*state);
+            let since = match observed.entry(*query_id) {
+                Entry::Occupied(entry) if entry.get().0 == status => entry.get().1,
+                entry => {
+                    *entry.or_insert((status, now)) = (status, now);
+                    now
+                }
+            };
+            let elapsed = now.saturating_duration_since(since);
+            let expired = |timeout: Option<Duration>| timeout.map_or(false, |t| elapsed >= t);
+
+            match status {
+                QueryStatus::Preparing if expired(timeouts.prepare) => {
+                    tracing::warn!("{query_id} query timed out waiting for other helpers");
+                    self.time_out(*query_id, state);
+                    timed_out.push(*query_id);
+                }
+                QueryStatus::AwaitingInputs if expired(timeouts.inputs) => {
+                    tracing::warn!("{query_id} query timed out waiting for inputs");
+                    self.time_out(*query_id, state);
+                    timed_out.push(*query_id);
+                }
+                QueryStatus::Completed if expired(timeouts.result_retention) => {
+                    tracing::info!("{query_id} query results expired");
+                    self.discard_stored_result(*query_id);
+                    *state = QueryState::Expired;
+                }
+                QueryStatus::Cancelled | QueryStatus::TimedOut | QueryStatus::Expired
+                    if expired(Some(timeouts.terminal_retention())) =>
+                {
+                    return false;
+                }
+                _ => return true,
+            }
+
+            // the clock for the new state starts now
+            observed.insert(*query_id, (QueryStatus::from(&*state), now));
+            true
+        });
+        self.owners
+            .lock()
+            .unwrap()
+            .retain(|query_id, _| queries.contains_key(query_id));
+        self.uploads.lock().unwrap().retain(|query_id, _| {
+            matches!(
+                queries.get(query_id),
+                Some(
+                    QueryState::AwaitingInputs(..)
+                        | QueryState::Running(_)
+                        | QueryState::AwaitingCompletion(_)
+                        | QueryState::Completed(_)
+                )
+            )
+        });
+
+        timed_out
+    }
+
+    /// Deletes the results in the result store that were written at least
+    /// [`QueryTimeouts::result_retention`] before `now`. Unlike [`Self::reap`], this also covers
+    /// results of queries that this helper no longer tracks, because they have been collected or
+    /// the helper restarted.
+    pub fn expire_stored_results(&self, now: SystemTime) {
+        let (Some(store), Some(retention)) = (&self.result_store, self.timeouts.result_retention)
+        else {
+            return;
+        };
+        match store.remove_expired(retention, now) {
+            Ok(expired) => {
+                for query_id in expired {
+                    tracing::info!("{query_id} query stored results expired");
+                }
+            }
+            Err(e) => tracing::warn!("failed to discard expired query results: {e}"),
+        }
+    }
+
+    /// Moves a query that never saw any reports to [`QueryState::TimedOut`] and gives back the
+    /// privacy budget charged for it.
+    fn time_out(&self, query_id: QueryId, state: &mut QueryState) {
+        if let QueryState::Preparing(config) | QueryState::AwaitingInputs(_, config, _) = state {
+            self.refund_privacy_budget(query_id, config);
+        }
+        *state = QueryState::TimedOut;
+    }
+
+    /// Gives back the privacy budget charged for a query that did not run. Failing to do so does
+    /// not fail the caller, the report collector just loses that budget.
+    fn refund_privacy_budget(&self, query_id: QueryId, config: &QueryConfig) {
+        if let Some(Err(e)) = self.privacy_budget.as_ref().map(|l| l.refund(config)) {
+            tracing::warn!("{query_id} failed to refund privacy budget: {e}");
+        }
+    }
+
+    fn discard_stored_result(&self, query_id: QueryId) {
+        if let Some(store) = &self.result_store {
+            if let Err(e) = store.remove(query_id) {
+                tracing::warn!("failed to discard the result of {query_id} query: {e}");
+            }
+        }
+    }
+
+    /// Calls [`Self::reap`] and [`Self::expire_stored_results`] every `period` until `processor` is dropped and aborts the queries
+    /// that timed out on other helpers. Other helpers that already accepted these queries will
+    /// see them as [`QueryStatus::Cancelled`].
+    pub async fn run_reaper(processor: Weak<Self>, transport: TransportImpl, period: Duration) {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let Some(processor) = processor.upgrade() else {
+                break;
+            };
+            let timed_out = processor.reap(Instant::now());
+            processor.expire_stored_results(SystemTime::now());
+            drop(processor);
+
+            let [right, left] = transport.identity().others();
+            for query_id in timed_out {
+                let (left_res, right_res) = join(
+                    transport.send(left, (RouteId::AbortQuery, query_id), stream::empty()),
+                    transport.send(right, (RouteId::AbortQuery, query_id), stream::empty()),
+                )
+                .await;
+                for (peer, res) in [(left, left_res), (right, right_res)] {
+                    if let Err(e) = res {
+                        tracing::warn!(
+                            "failed to abort timed out query {query_id} on {peer:?}: {e:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[cfg(all(test, unit_test))]
@@ -322,9 +989,13 @@ mod tests {
     use crate::{
         ff::FieldType,
         helpers::{
-            query::{QueryType, QueryType::TestMultiply},
+            query::{
+                DpParameter, InvalidReportPolicy, IpaQueryConfig, QueryType,
+                QueryType::TestMultiply, ReportCollectorId,
+            },
             HelperIdentity, InMemoryNetwork, PrepareQueryCallback, TransportCallbacks,
         },
+        report::ReportVersion,
     };
 
     fn prepare_query_callback<T, F, Fut>(cb: F) -> Box<dyn PrepareQueryCallback<T>>
@@ -375,7 +1046,13 @@ mod tests {
         // poll future once to trigger query status change
         let _qc = poll_immediate(&mut qc_future).await;
 
-        assert_eq!(QueryStatus::Preparing, p0.query_status(QueryId).unwrap());
+        let (query_id, status) = {
+            let queries = p0.queries.inner.lock().unwrap();
+            assert_eq!(1, queries.len());
+            let (query_id, state) = queries.iter().next().unwrap();
+            (*query_id, QueryStatus::from(state))
+        };
+        assert_eq!(QueryStatus::Preparing, status);
         // unblock sends
         barrier.wait().await;
 
@@ -384,7 +1061,7 @@ mod tests {
 
         assert_eq!(
             PrepareQuery {
-                query_id: QueryId,
+                query_id,
                 config: request,
                 roles: expected_assignment,
             },
@@ -392,12 +1069,12 @@ mod tests {
         );
         assert_eq!(
             QueryStatus::AwaitingInputs,
-            p0.query_status(QueryId).unwrap()
+            p0.query_status(query_id).unwrap()
         );
     }
 
     #[tokio::test]
-    async fn rejects_duplicate_query_id() {
+    async fn concurrent_queries() {
         let cb = array::from_fn(|_| TransportCallbacks {
             prepare_query: prepare_query_callback(|_, _| async { Ok(()) }),
             ..Default::default()
@@ -407,14 +1084,19 @@ mod tests {
         let p0 = Processor::default();
         let request = test_multiply_config();
 
-        let _qc = p0
+        let qc1 = p0
             .new_query(Transport::clone_ref(&t0), request)
             .await
             .unwrap();
-        assert!(matches!(
-            p0.new_query(t0, request).await,
-            Err(NewQueryError::State(StateError::AlreadyRunning)),
-        ));
+        let qc2 = p0.new_query(t0, request).await.unwrap();
+
+        assert_ne!(qc1.query_id, qc2.query_id);
+        for query_id in [qc1.query_id, qc2.query_id] {
+            assert_eq!(
+                QueryStatus::AwaitingInputs,
+                p0.query_status(query_id).unwrap()
+            );
+        }
     }
 
     #[tokio::test]
@@ -464,12 +1146,142 @@ mod tests {
         ));
     }
 
+    fn noisy_ipa_config(epsilon: f64) -> QueryConfig {
+        QueryConfig::new(
+            QueryType::OprfIpa(IpaQueryConfig::default().with_output_noise(epsilon, 1e-6)),
+            FieldType::Fp32BitPrime,
+            1,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn rejects_query_over_privacy_budget() {
+        let cb = array::from_fn(|_| TransportCallbacks {
+            prepare_query: prepare_query_callback(|_, _| async { Ok(()) }),
+            ..Default::default()
+        });
+        let network = InMemoryNetwork::new(cb);
+        let [t0, _, _] = network.transports();
+        let p0 = Processor::default().with_privacy_budget(PrivacyBudgetLedger::in_memory(1.0));
+
+        p0.new_query(t0.clone_ref(), noisy_ipa_config(0.75))
+            .await
+            .unwrap();
+        assert!(matches!(
+            p0.new_query(t0.clone_ref(), noisy_ipa_config(0.5))
+                .await
+                .unwrap_err(),
+            NewQueryError::PrivacyBudget(PrivacyBudgetError::Exceeded { .. })
+        ));
+        // the rejected query is not registered to the report collector
+        assert_eq!(p0.owners.lock().unwrap().len(), 1);
+        p0.new_query(t0, noisy_ipa_config(0.25)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn refunds_privacy_budget_on_prepare_error() {
+        let cb2 = TransportCallbacks {
+            prepare_query: prepare_query_callback(|_, _| async { Ok(()) }),
+            ..Default::default()
+        };
+        let cb3 = TransportCallbacks {
+            prepare_query: prepare_query_callback(|_, _| async {
+                Err(PrepareQueryError::WrongTarget)
+            }),
+            ..Default::default()
+        };
+        let network = InMemoryNetwork::new([TransportCallbacks::default(), cb2, cb3]);
+        let [t0, _, _] = network.transports();
+        let p0 = Processor::default().with_privacy_budget(PrivacyBudgetLedger::in_memory(1.0));
+
+        p0.new_query(t0, noisy_ipa_config(1.0)).await.unwrap_err();
+        let remaining = p0
+            .privacy_budget
+            .as_ref()
+            .unwrap()
+            .remaining(ReportCollectorId::default(), 0);
+        assert!((remaining - 1.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn kill_unknown_query() {
+        let network = InMemoryNetwork::default();
+        let [t0, _, _] = network.transports();
+        let p0 = Processor::default();
+
+        assert!(matches!(
+            p0.kill(t0, QueryId::from(1)).await.unwrap_err(),
+            QueryKillError::NoSuchQuery(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn authorize() {
+        let cb = || TransportCallbacks {
+            prepare_query: prepare_query_callback(|_, _| async { Ok(()) }),
+            ..Default::default()
+        };
+        let network = InMemoryNetwork::new([TransportCallbacks::default(), cb(), cb()]);
+        let [t0, _, _] = network.transports();
+        let p0 = Processor::default();
+        let owner = ReportCollectorId::from_token("owner");
+        let request = QueryConfig {
+            report_collector: owner,
+            ..test_multiply_config()
+        };
+        let query_id = p0.new_query(t0, request).await.unwrap().query_id;
+
+        p0.authorize(query_id, owner).unwrap();
+        assert!(matches!(
+            p0.authorize(query_id, ReportCollectorId::from_token("other")),
+            Err(QueryAccessError::Forbidden(_))
+        ));
+        assert!(matches!(
+            p0.authorize(QueryId::from(42), owner),
+            Err(QueryAccessError::NoSuchQuery(_))
+        ));
+    }
+
+    #[test]
+    fn reload_keys() {
+        use rand::rngs::StdRng;
+        use rand_core::SeedableRng;
+
+        assert!(matches!(
+            Processor::default().reload_keys(),
+            Err(KeyReloadError::NotConfigured)
+        ));
+
+        let dir = tempfile::tempdir().unwrap();
+        let keys = KeyDirectory::open(dir.path()).unwrap();
+        let processor = Processor::default().with_key_directory(keys.clone());
+        assert_eq!(
+            Vec::<KeyIdentifier>::new(),
+            processor.reload_keys().unwrap()
+        );
+
+        let mut rng = StdRng::seed_from_u64(42);
+        keys.add(3, &KeyPair::gen(&mut rng)).unwrap();
+        keys.add(4, &KeyPair::gen(&mut rng)).unwrap();
+        assert_eq!(vec![3, 4], processor.reload_keys().unwrap());
+        assert_eq!(
+            vec![3, 4],
+            processor
+                .key_registry
+                .lock()
+                .unwrap()
+                .key_ids()
+                .collect::<Vec<_>>()
+        );
+    }
+
     mod prepare {
         use super::*;
 
         fn prepare_query(identities: [HelperIdentity; 3]) -> PrepareQuery {
             PrepareQuery {
-                query_id: QueryId,
+                query_id: QueryId::from(1),
                 config: test_multiply_config(),
                 roles: RoleAssignment::new(identities),
             }
@@ -480,17 +1292,18 @@ mod tests {
             let network = InMemoryNetwork::default();
             let identities = HelperIdentity::make_three();
             let req = prepare_query(identities);
+            let query_id = req.query_id;
             let transport = network.transport(identities[1]);
             let processor = Processor::default();
 
             assert!(matches!(
-                processor.query_status(QueryId).unwrap_err(),
+                processor.query_status(query_id).unwrap_err(),
                 QueryStatusError::NoSuchQuery(_)
             ));
             processor.prepare(&transport, req).unwrap();
             assert_eq!(
                 QueryStatus::AwaitingInputs,
-                processor.query_status(QueryId).unwrap()
+                processor.query_status(query_id).unwrap()
             );
         }
 
@@ -516,27 +1329,309 @@ mod tests {
             let transport = network.transport(identities[1]);
             let processor = Processor::default();
             processor.prepare(&transport, req.clone()).unwrap();
+            let other_req = PrepareQuery {
+                config: QueryConfig::new(TestMultiply, FieldType::Fp31, 2).unwrap(),
+                ..req
+            };
             assert!(matches!(
-                processor.prepare(&transport, req),
+                processor.prepare(&transport, other_req),
                 Err(PrepareQueryError::AlreadyRunning)
             ));
         }
+
+        #[tokio::test]
+        async fn accepts_repeated_request() {
+            let network = InMemoryNetwork::default();
+            let identities = HelperIdentity::make_three();
+            let req = prepare_query(identities);
+            let transport = network.transport(identities[1]);
+            let processor = Processor::default();
+            processor.prepare(&transport, req.clone()).unwrap();
+            processor.prepare(&transport, req.clone()).unwrap();
+            assert_eq!(
+                QueryStatus::AwaitingInputs,
+                processor.query_status(req.query_id).unwrap()
+            );
+        }
+
+        #[tokio::test]
+        async fn rejects_query_over_privacy_budget() {
+            let network = InMemoryNetwork::default();
+            let identities = HelperIdentity::make_three();
+            let transport = network.transport(identities[1]);
+            let processor =
+                Processor::default().with_privacy_budget(PrivacyBudgetLedger::in_memory(1.0));
+            let req = PrepareQuery {
+                config: noisy_ipa_config(0.75),
+                ..prepare_query(identities)
+            };
+
+            processor.prepare(&transport, req.clone()).unwrap();
+            // repeated request is not charged again
+            processor.prepare(&transport, req.clone()).unwrap();
+            assert!(matches!(
+                processor.prepare(
+                    &transport,
+                    PrepareQuery {
+                        query_id: QueryId::from(2),
+                        ..req
+                    }
+                ),
+                Err(PrepareQueryError::PrivacyBudget(
+                    PrivacyBudgetError::Exceeded { .. }
+                ))
+            ));
+            assert!(matches!(
+                processor.query_status(QueryId::from(2)).unwrap_err(),
+                QueryStatusError::NoSuchQuery(_)
+            ));
+        }
+
+        #[tokio::test]
+        async fn abort_refunds_privacy_budget() {
+            let network = InMemoryNetwork::default();
+            let identities = HelperIdentity::make_three();
+            let transport = network.transport(identities[1]);
+            let processor =
+                Processor::default().with_privacy_budget(PrivacyBudgetLedger::in_memory(1.0));
+            let req = PrepareQuery {
+                config: noisy_ipa_config(1.0),
+                ..prepare_query(identities)
+            };
+
+            processor.prepare(&transport, req.clone()).unwrap();
+            processor.abort(req.query_id).unwrap();
+            processor
+                .prepare(
+                    &transport,
+                    PrepareQuery {
+                        query_id: QueryId::from(2),
+                        ..req
+                    },
+                )
+                .unwrap();
+        }
+    }
+
+    mod timeouts {
+        use super::*;
+        use crate::ff::{Field, Fp31};
+
+        const TIMEOUTS: QueryTimeouts = QueryTimeouts {
+            prepare: Some(Duration::from_secs(5)),
+            inputs: Some(Duration::from_secs(10)),
+            result_retention: Some(Duration::from_secs(60)),
+        };
+
+        fn prepare_query() -> PrepareQuery {
+            PrepareQuery {
+                query_id: QueryId::from(1),
+                config: test_multiply_config(),
+                roles: RoleAssignment::new(HelperIdentity::make_three()),
+            }
+        }
+
+        #[tokio::test]
+        async fn times_out_awaiting_inputs() {
+            let network = InMemoryNetwork::default();
+            let transport = network.transport(HelperIdentity::TWO);
+            let processor = Processor::default().with_timeouts(TIMEOUTS);
+            let query_id = prepare_query().query_id;
+            processor.prepare(&transport, prepare_query()).unwrap();
+
+            let now = Instant::now();
+            assert!(processor.reap(now).is_empty());
+            assert!(processor.reap(now + Duration::from_secs(9)).is_empty());
+            assert_eq!(
+                QueryStatus::AwaitingInputs,
+                processor.query_status(query_id).unwrap()
+            );
+
+            assert_eq!(
+                vec![query_id],
+                processor.reap(now + Duration::from_secs(10))
+            );
+            assert_eq!(
+                QueryStatus::TimedOut,
+                processor.query_status(query_id).unwrap()
+            );
+            assert!(matches!(
+                processor.receive_inputs(
+                    transport,
+                    QueryInput {
+                        query_id,
+                        input_stream: Vec::new().into(),
+                    }
+                ),
+                Err(QueryInputError::StateError { .. })
+            ));
+
+            // timed out queries are forgotten after the retention period
+            assert!(processor.reap(now + Duration::from_secs(69)).is_empty());
+            assert_eq!(
+                QueryStatus::TimedOut,
+                processor.query_status(query_id).unwrap()
+            );
+            processor.reap(now + Duration::from_secs(70));
+            assert!(matches!(
+                processor.query_status(query_id),
+                Err(QueryStatusError::NoSuchQuery(_))
+            ));
+        }
+
+        #[tokio::test]
+        async fn time_out_refunds_privacy_budget() {
+            let network = InMemoryNetwork::default();
+            let transport = network.transport(HelperIdentity::TWO);
+            let processor = Processor::default()
+                .with_timeouts(TIMEOUTS)
+                .with_privacy_budget(PrivacyBudgetLedger::in_memory(1.0));
+            let req = PrepareQuery {
+                config: noisy_ipa_config(1.0),
+                ..prepare_query()
+            };
+
+            processor.prepare(&transport, req.clone()).unwrap();
+            let now = Instant::now();
+            processor.reap(now);
+            assert_eq!(
+                vec![req.query_id],
+                processor.reap(now + Duration::from_secs(10))
+            );
+            processor
+                .prepare(
+                    &transport,
+                    PrepareQuery {
+                        query_id: QueryId::from(2),
+                        ..req
+                    },
+                )
+                .unwrap();
+        }
+
+        #[tokio::test]
+        async fn times_out_preparing() {
+            let cb = array::from_fn(|_| TransportCallbacks {
+                prepare_query: prepare_query_callback(|_, _| std::future::pending()),
+                ..Default::default()
+            });
+            let network = InMemoryNetwork::new(cb);
+            let [t0, _, _] = network.transports();
+            let processor = Processor::default().with_timeouts(TIMEOUTS);
+
+            let qc_future = processor.new_query(t0, test_multiply_config());
+            pin_mut!(qc_future);
+            assert!(poll_immediate(&mut qc_future).await.is_none());
+
+            let now = Instant::now();
+            assert!(processor.reap(now).is_empty());
+            let timed_out = processor.reap(now + Duration::from_secs(5));
+            assert_eq!(1, timed_out.len());
+            assert_eq!(
+                QueryStatus::TimedOut,
+                processor.query_status(timed_out[0]).unwrap()
+            );
+        }
+
+        #[tokio::test]
+        async fn expires_completed_results() {
+            let processor = Processor::default().with_timeouts(TIMEOUTS);
+            let query_id = QueryId::from(1);
+            processor.queries.inner.lock().unwrap().insert(
+                query_id,
+                QueryState::Completed(Ok(Box::new(vec![Fp31::ONE]))),
+            );
+
+            let now = Instant::now();
+            processor.reap(now);
+            processor.reap(now + Duration::from_secs(59));
+            assert_eq!(
+                QueryStatus::Completed,
+                processor.query_status(query_id).unwrap()
+            );
+
+            processor.reap(now + Duration::from_secs(60));
+            assert_eq!(
+                QueryStatus::Expired,
+                processor.query_status(query_id).unwrap()
+            );
+            assert!(matches!(
+                processor.complete(query_id).await,
+                Err(QueryCompletionError::StateError { .. })
+            ));
+
+            processor.reap(now + Duration::from_secs(120));
+            assert!(matches!(
+                processor.query_status(query_id),
+                Err(QueryStatusError::NoSuchQuery(_))
+            ));
+        }
+
+        #[tokio::test]
+        async fn no_timeouts_by_default() {
+            let network = InMemoryNetwork::default();
+            let transport = network.transport(HelperIdentity::TWO);
+            let processor = Processor::default();
+            let query_id = prepare_query().query_id;
+            processor.prepare(&transport, prepare_query()).unwrap();
+
+            let now = Instant::now();
+            processor.reap(now);
+            assert!(processor
+                .reap(now + Duration::from_secs(24 * 60 * 60))
+                .is_empty());
+            assert_eq!(
+                QueryStatus::AwaitingInputs,
+                processor.query_status(query_id).unwrap()
+            );
+        }
+
+        #[tokio::test]
+        async fn forgets_cancelled_queries_by_default() {
+            let processor = Processor::default();
+            let query_id = QueryId::from(1);
+            processor
+                .queries
+                .inner
+                .lock()
+                .unwrap()
+                .insert(query_id, QueryState::Cancelled);
+
+            let now = Instant::now();
+            processor.reap(now);
+            processor.reap(
+                (now + QueryTimeouts::DEFAULT_TERMINAL_RETENTION)
+                    .checked_sub(Duration::from_secs(1))
+                    .unwrap(),
+            );
+            assert_eq!(
+                QueryStatus::Cancelled,
+                processor.query_status(query_id).unwrap()
+            );
+
+            processor.reap(now + QueryTimeouts::DEFAULT_TERMINAL_RETENTION);
+            assert!(matches!(
+                processor.query_status(query_id),
+                Err(QueryStatusError::NoSuchQuery(_))
+            ));
+        }
     }
 
     mod e2e {
-        use std::time::Duration;
+        use std::{iter::zip, time::Duration};
 
         use tokio::time::sleep;
 
         use super::*;
         use crate::{
+            app::Error as AppError,
             error::BoxError,
             ff::{Field, Fp31},
-            helpers::query::IpaQueryConfig,
             ipa_test_input,
             protocol::{ipa::IPAInputRow, BreakdownKey, MatchKey},
-            secret_sharing::replicated::semi_honest,
-            test_fixture::{input::GenericReportTestInput, Reconstruct, TestApp},
+            secret_sharing::{replicated::semi_honest, IntoShares},
+            test_fixture::{input::GenericReportTestInput, IntoBuf, Reconstruct, TestApp},
+            AppSetup,
         };
 
         #[tokio::test]
@@ -558,6 +1653,29 @@ mod tests {
             ))
         }
 
+        #[tokio::test]
+        async fn complete_query_in_chunks() -> Result<(), BoxError> {
+            let app = TestApp::default();
+            let input = [4u128, 5, 3, 7].map(Fp31::truncate_from).to_vec();
+            let query_id = app
+                .start_query_in_chunks(
+                    input.into_iter(),
+                    QueryConfig::new(TestMultiply, FieldType::Fp31, 2).unwrap(),
+                    1,
+                )
+                .await?;
+
+            let results = app.complete_query(query_id).await?.map(|bytes| {
+                semi_honest::AdditiveShare::<Fp31>::from_byte_slice(&bytes).collect::<Vec<_>>()
+            });
+            assert_eq!(
+                [20u128, 21].map(Fp31::truncate_from).to_vec(),
+                results.reconstruct()
+            );
+
+            Ok(())
+        }
+
         #[tokio::test]
         async fn complete_query_status_poll() -> Result<(), BoxError> {
             let app = TestApp::default();
@@ -585,6 +1703,176 @@ mod tests {
             ))
         }
 
+        #[tokio::test]
+        async fn complete_concurrent_queries() -> Result<(), BoxError> {
+            let app = TestApp::default();
+            let inputs = [(4u128, 5u128), (3u128, 7u128)]
+                .map(|(a, b)| vec![Fp31::truncate_from(a), Fp31::truncate_from(b)]);
+            let mut query_ids = Vec::with_capacity(inputs.len());
+            for input in inputs {
+                query_ids.push(
+                    app.start_query(input.into_iter(), test_multiply_config())
+                        .await?,
+                );
+            }
+            assert_ne!(query_ids[0], query_ids[1]);
+
+            // complete queries in the reverse order to make sure they do not depend on each other
+            for (query_id, expected) in query_ids.into_iter().zip([20u128, 21]).rev() {
+                let results = app.complete_query(query_id).await?.map(|bytes| {
+                    semi_honest::AdditiveShare::<Fp31>::from_byte_slice(&bytes).collect::<Vec<_>>()
+                });
+                assert_eq!(vec![Fp31::truncate_from(expected)], results.reconstruct());
+            }
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn kill_query() -> Result<(), BoxError> {
+            let app = TestApp::default();
+            let input = vec![Fp31::truncate_from(4u128), Fp31::truncate_from(5u128)];
+            let query_id = app
+                .start_query(input.clone().into_iter(), test_multiply_config())
+                .await?;
+
+            app.kill_query(query_id).await?;
+            assert_eq!([QueryStatus::Cancelled; 3], app.query_status(query_id)?);
+            assert!(matches!(
+                app.complete_query(query_id).await.unwrap_err(),
+                AppError::QueryCompletion(QueryCompletionError::Cancelled(_))
+            ));
+
+            // helpers can still run queries after one got cancelled
+            let results = app
+                .execute_query(input.into_iter(), test_multiply_config())
+                .await?
+                .map(|bytes| {
+                    semi_honest::AdditiveShare::<Fp31>::from_byte_slice(&bytes).collect::<Vec<_>>()
+                });
+            assert_eq!(vec![Fp31::truncate_from(20u128)], results.reconstruct());
+
+            Ok(())
+        }
+
+        #[tokio::test]
+        async fn kill_running_query() {
+            let (setup, callbacks): (Vec<_>, Vec<_>) = (0..3).map(|_| AppSetup::new()).unzip();
+            let network = InMemoryNetwork::new(callbacks.try_into().ok().unwrap());
+            let apps = zip(network.transports(), setup)
+                .map(|(transport, setup)| setup.connect(transport))
+                .collect::<Vec<_>>();
+
+            let query_id = apps[0].start_query(test_multiply_config()).await.unwrap();
+            let inputs: [Vec<semi_honest::AdditiveShare<Fp31>>; 3] =
+                vec![Fp31::truncate_from(4u128), Fp31::truncate_from(5u128)]
+                    .into_iter()
+                    .share();
+
+            // the last helper never receives its input, so others can't finish the query
+            for (app, input) in zip(&apps, inputs.map(IntoBuf::into_buf)).take(2) {
+                app.execute_query(QueryInput {
+                    query_id,
+                    input_stream: input.into(),
+                })
+                .unwrap();
+            }
+            assert_eq!(
+                QueryStatus::Running,
+                apps[1].query_status(query_id).unwrap()
+            );
+            assert_eq!(
+                QueryStatus::AwaitingInputs,
+                apps[2].query_status(query_id).unwrap()
+            );
+
+            // any helper can be asked to kill the query
+            apps[1].kill_query(query_id).await.unwrap();
+            for app in &apps {
+                assert_eq!(QueryStatus::Cancelled, app.query_status(query_id).unwrap());
+            }
+        }
+
+        #[tokio::test]
+        async fn stored_results_survive_restart() {
+            let dirs = [(); 3].map(|()| tempfile::tempdir().unwrap());
+            let (setup, callbacks): (Vec<_>, Vec<_>) = dirs
+                .iter()
+                .map(|dir| {
+                    AppSetup::with_query_processor(
+                        Processor::default()
+                            .with_result_store(ResultStore::open(dir.path()).unwrap()),
+                    )
+                })
+                .unzip();
+            let network = InMemoryNetwork::new(callbacks.try_into().ok().unwrap());
+            let apps = zip(network.transports(), setup)
+                .map(|(transport, setup)| setup.connect(transport))
+                .collect::<Vec<_>>();
+
+            let query_id = apps[0].start_query(test_multiply_config()).await.unwrap();
+            let inputs: [Vec<semi_honest::AdditiveShare<Fp31>>; 3] =
+                vec![Fp31::truncate_from(4u128), Fp31::truncate_from(5u128)]
+                    .into_iter()
+                    .share();
+            for (app, input) in zip(&apps, inputs.map(IntoBuf::into_buf)) {
+                app.execute_query(QueryInput {
+                    query_id,
+                    input_stream: input.into(),
+                })
+                .unwrap();
+            }
+
+            let mut results = Vec::new();
+            for app in &apps {
+                results.push(app.complete_query(query_id).await.unwrap());
+            }
+            let reconstructed: Vec<Fp31> = <[_; 3]>::try_from(results.clone())
+                .unwrap()
+                .map(|bytes| {
+                    semi_honest::AdditiveShare::<Fp31>::from_byte_slice(&bytes).collect::<Vec<_>>()
+                })
+                .reconstruct();
+            assert_eq!(vec![Fp31::truncate_from(20u128)], reconstructed);
+
+            // results can be collected again
+            for (app, expected) in zip(&apps, &results) {
+                assert_eq!(QueryStatus::Completed, app.query_status(query_id).unwrap());
+                assert_eq!(expected, &app.complete_query(query_id).await.unwrap());
+            }
+
+            // and after the helper restarts
+            for (dir, expected) in zip(&dirs, &results) {
+                let processor =
+                    Processor::default().with_result_store(ResultStore::open(dir.path()).unwrap());
+                assert_eq!(
+                    QueryStatus::Completed,
+                    processor.query_status(query_id).unwrap()
+                );
+                assert_eq!(
+                    expected,
+                    &processor.complete(query_id).await.unwrap().into_bytes()
+                );
+            }
+
+            // until they expire
+            let retention = Duration::from_secs(60);
+            for dir in &dirs {
+                let processor = Processor::default()
+                    .with_timeouts(QueryTimeouts {
+                        result_retention: Some(retention),
+                        ..Default::default()
+                    })
+                    .with_result_store(ResultStore::open(dir.path()).unwrap());
+                processor.expire_stored_results(SystemTime::now() + retention);
+                assert!(matches!(
+                    processor.query_status(query_id),
+                    Err(QueryStatusError::NoSuchQuery(_))
+                ));
+                assert_eq!(0, std::fs::read_dir(dir.path()).unwrap().count());
+            }
+        }
+
         #[tokio::test]
         async fn complete_query_ipa() -> Result<(), BoxError> {
             let app = TestApp::default();
@@ -623,7 +1911,20 @@ mod tests {
                             attribution_window_seconds: None,
                             num_multi_bits: 3,
                             plaintext_match_keys: true,
+                            dummy_records_epsilon: None,
+                            dummy_records_delta: DpParameter::DEFAULT_DELTA,
+                            output_noise_epsilon: None,
+                            output_noise_delta: DpParameter::DEFAULT_DELTA,
+                            epoch: 0,
+                            min_report_epoch: None,
+                            max_report_epoch: None,
+                            breakdown_key_bits: 8,
+                            trigger_value_bits: 3,
+                            timestamp_bits: 20,
+                            invalid_report_policy: InvalidReportPolicy::Fail,
+                            report_version: ReportVersion::MatchKeyOnly,
                         }),
+                        report_collector: ReportCollectorId::default(),
                     },
                 )
                 .await?;
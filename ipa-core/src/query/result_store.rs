@@ -0,0 +1,411 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use ::tokio::sync::oneshot;
+use futures::FutureExt;
+#[cfg(all(feature = "shuttle", test))]
+use shuttle::future as tokio;
+
+use crate::{
+    helpers::query::ReportCollectorId,
+    protocol::QueryId,
+    query::{runner::QueryResult, state::RunningQuery, ProtocolResult, ResultHeader},
+    sync::Arc,
+    task::JoinHandle,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ResultStoreError {
+    #[error("failed to access query result store at {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("query result metadata at {path} is corrupted: {source}")]
+    Corrupted {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+/// Keeps the results of completed queries on disk, so they can be fetched more than once and are
+/// not lost when the helper restarts. Every query has a file with the serialized result, a
+/// file with its [`ResultHeader`] and a file with the report collector that may fetch it.
+#[derive(Clone, Debug)]
+pub struct ResultStore {
+    dir: PathBuf,
+}
+
+/// Query result that has already been serialized.
+#[derive(Debug)]
+pub struct StoredResult {
+    header: ResultHeader,
+    bytes: Vec<u8>,
+}
+
+impl StoredResult {
+    #[must_use]
+    pub fn new(header: ResultHeader, bytes: Vec<u8>) -> Self {
+        Self { header, bytes }
+    }
+}
+
+impl ProtocolResult for StoredResult {
+    fn into_bytes(self: Box<Self>) -> Vec<u8> {
+        self.bytes
+    }
+
+    fn header(&self) -> ResultHeader {
+        self.header.clone()
+    }
+}
+
+/// Aborts the query task if the task that persists its result is aborted.
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl ResultStore {
+    /// Opens the store in `dir`, creating the directory if it does not exist. Results stored by
+    /// a previous run of the helper remain available.
+    ///
+    /// ## Errors
+    /// If the directory cannot be created.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, ResultStoreError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|source| ResultStoreError::Io {
+            path: dir.clone(),
+            source,
+        })?;
+
+        Ok(Self { dir })
+    }
+
+    /// Writes the result of the given query, along with the report collector that owns it. The
+    /// owner and the header are written before the result and every file is written to a
+    /// temporary location first, so that a crash in the middle of writing does not leave a
+    /// truncated result behind.
+    ///
+    /// ## Errors
+    /// If the result cannot be written.
+    pub fn save(
+        &self,
+        query_id: QueryId,
+        owner: ReportCollectorId,
+        header: &ResultHeader,
+        result: &[u8],
+    ) -> Result<(), ResultStoreError> {
+        let owner_path = self.owner_path(query_id);
+        write_atomically(&owner_path, &to_json(&owner_path, &owner)?)?;
+        let header_path = self.header_path(query_id);
+        write_atomically(&header_path, &to_json(&header_path, header)?)?;
+        write_atomically(&self.path(query_id), result)
+    }
+
+    /// Returns the result of the given query, or `None` if it is not stored.
+    ///
+    /// ## Errors
+    /// If the result exists but cannot be read.
+    pub fn load(&self, query_id: QueryId) -> Result<Option<StoredResult>, ResultStoreError> {
+        let path = self.path(query_id);
+        let bytes = match fs::read(&path) {
+            Ok(result) => result,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(ResultStoreError::Io { path, source }),
+        };
+        let path = self.header_path(query_id);
+        let header = fs::read(&path).map_err(|source| ResultStoreError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let header = serde_json::from_slice(&header)
+            .map_err(|source| ResultStoreError::Corrupted { path, source })?;
+
+        Ok(Some(StoredResult::new(header, bytes)))
+    }
+
+    /// Returns the report collector that owns the result of the given query, or `None` if the
+    /// result is not stored.
+    ///
+    /// ## Errors
+    /// If the owner exists but cannot be read.
+    pub fn owner(&self, query_id: QueryId) -> Result<Option<ReportCollectorId>, ResultStoreError> {
+        let path = self.owner_path(query_id);
+        let owner = match fs::read(&path) {
+            Ok(owner) => owner,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(ResultStoreError::Io { path, source }),
+        };
+        serde_json::from_slice(&owner)
+            .map(Some)
+            .map_err(|source| ResultStoreError::Corrupted { path, source })
+    }
+
+    #[must_use]
+    pub fn contains(&self, query_id: QueryId) -> bool {
+        self.path(query_id).is_file()
+    }
+
+    /// Deletes the result of the given query, if it is stored.
+    ///
+    /// ## Errors
+    /// If the result exists but cannot be deleted.
+    pub fn remove(&self, query_id: QueryId) -> Result<(), ResultStoreError> {
+        for path in [
+            self.path(query_id),
+            self.header_path(query_id),
+            self.owner_path(query_id),
+        ] {
+            match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    return Err(ResultStoreError::Io { path, source: e })
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the results that were written at least `retention` before `now`, along with the
+    /// files left behind by writes that did not complete. Results are deleted even if the query
+    /// is no longer tracked by the helper, for example because the helper restarted or the result
+    /// has already been collected. Returns the queries whose results were deleted.
+    ///
+    /// ## Errors
+    /// If the store cannot be listed or an expired file cannot be deleted.
+    pub fn remove_expired(
+        &self,
+        retention: Duration,
+        now: SystemTime,
+    ) -> Result<Vec<QueryId>, ResultStoreError> {
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| ResultStoreError::Io { path, source }
+        };
+        let mut removed = Vec::new();
+        for entry in fs::read_dir(&self.dir).map_err(io_error(&self.dir))? {
+            let path = entry.map_err(io_error(&self.dir))?.path();
+            let modified = match fs::metadata(&path).and_then(|m| m.modified()) {
+                Ok(modified) => modified,
+                // removed together with the result it belongs to
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(source) => return Err(ResultStoreError::Io { path, source }),
+            };
+            if now
+                .duration_since(modified)
+                .map_or(true, |age| age < retention)
+            {
+                continue;
+            }
+
+            let query_id = path
+                .file_stem()
+                .and_then(|stem| QueryId::try_from(stem.to_str()?).ok());
+            match (query_id, path.extension().and_then(|ext| ext.to_str())) {
+                (Some(query_id), Some("result")) => {
+                    self.remove(query_id)?;
+                    removed.push(query_id);
+                }
+                // owners and headers go away with their results, unless the result was never
+                // written
+                (Some(query_id), Some("owner" | "header")) if self.contains(query_id) => {}
+                (_, Some("owner" | "header" | "tmp")) => match fs::remove_file(&path) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                        return Err(ResultStoreError::Io { path, source: e })
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Makes the result of `query` to be written to this store as soon as it is available,
+    /// without waiting for the report collector to ask for it. Failing to persist the result
+    /// does not fail the query, it is still kept in memory.
+    ///
+    /// ## Panics
+    /// If the query task terminates without producing a result.
+    #[must_use]
+    pub fn persist_on_completion(
+        &self,
+        query_id: QueryId,
+        owner: ReportCollectorId,
+        query: RunningQuery,
+    ) -> RunningQuery {
+        let (tx, rx) = oneshot::channel();
+        let store = self.clone();
+        let progress = Arc::clone(&query.progress);
+        let join_handle = tokio::spawn(async move {
+            let _query_task = AbortOnDrop(query.join_handle);
+            let result: QueryResult = query
+                .result
+                .map(|r| r.expect("query completed without returning a result"))
+                .await
+                .map(|result| {
+                    let header = result.header();
+                    let bytes = result.into_bytes();
+                    if let Err(e) = store.save(query_id, owner, &header, &bytes) {
+                        tracing::error!("failed to persist the result of {query_id} query: {e}");
+                    }
+                    Box::new(StoredResult::new(header, bytes)) as Box<dyn ProtocolResult>
+                });
+            // the receiver is gone if the query has been cancelled
+            let _ = tx.send(result);
+        });
+
+        RunningQuery {
+            result: rx,
+            join_handle,
+            progress,
+        }
+    }
+
+    fn path(&self, query_id: QueryId) -> PathBuf {
+        self.dir.join(format!("{query_id}.result"))
+    }
+
+    fn header_path(&self, query_id: QueryId) -> PathBuf {
+        self.dir.join(format!("{query_id}.header"))
+    }
+
+    fn owner_path(&self, query_id: QueryId) -> PathBuf {
+        self.dir.join(format!("{query_id}.owner"))
+    }
+}
+
+fn to_json<T: serde::Serialize>(path: &Path, value: &T) -> Result<Vec<u8>, ResultStoreError> {
+    serde_json::to_vec(value).map_err(|e| ResultStoreError::Io {
+        path: path.to_path_buf(),
+        source: e.into(),
+    })
+}
+
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), ResultStoreError> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, contents)
+        .and_then(|()| fs::rename(&tmp_path, path))
+        .map_err(|source| ResultStoreError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use super::*;
+    use crate::{ff::FieldType, query::RejectedReports};
+
+    fn header() -> ResultHeader {
+        ResultHeader {
+            field_type: Some(FieldType::Fp31),
+            records: 3,
+            record_size: 1,
+            rejected_reports: RejectedReports::new(),
+        }
+    }
+
+    fn owner() -> ReportCollectorId {
+        ReportCollectorId::from_token("token")
+    }
+
+    fn load(store: &ResultStore, query_id: QueryId) -> Option<(ResultHeader, Vec<u8>)> {
+        store.load(query_id).unwrap().map(|result| {
+            let result = Box::new(result);
+            (result.header(), result.into_bytes())
+        })
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ResultStore::open(dir.path()).unwrap();
+        let query_id = QueryId::from(7);
+
+        assert!(!store.contains(query_id));
+        assert_eq!(None, load(&store, query_id));
+
+        store
+            .save(query_id, owner(), &header(), &[1, 2, 3])
+            .unwrap();
+        assert!(store.contains(query_id));
+        assert_eq!(Some((header(), vec![1, 2, 3])), load(&store, query_id));
+        assert_eq!(None, load(&store, QueryId::from(8)));
+    }
+
+    #[test]
+    fn persists_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let query_id = QueryId::from(7);
+        ResultStore::open(dir.path().join("results"))
+            .unwrap()
+            .save(query_id, owner(), &header(), &[4, 5, 6])
+            .unwrap();
+
+        let store = ResultStore::open(dir.path().join("results")).unwrap();
+        assert_eq!(Some((header(), vec![4, 5, 6])), load(&store, query_id));
+        assert_eq!(Some(owner()), store.owner(query_id).unwrap());
+    }
+
+    #[test]
+    fn remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ResultStore::open(dir.path()).unwrap();
+        let query_id = QueryId::from(7);
+
+        store
+            .save(query_id, owner(), &header(), &[1, 2, 3])
+            .unwrap();
+        store.remove(query_id).unwrap();
+        assert!(!store.contains(query_id));
+        assert_eq!(None, store.owner(query_id).unwrap());
+        store.remove(query_id).unwrap();
+    }
+
+    #[test]
+    fn remove_expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ResultStore::open(dir.path()).unwrap();
+        let query_id = QueryId::from(7);
+        let retention = Duration::from_secs(60);
+
+        store
+            .save(query_id, owner(), &header(), &[1, 2, 3])
+            .unwrap();
+        fs::write(dir.path().join("8.tmp"), "").unwrap();
+        let now = SystemTime::now();
+        assert!(store.remove_expired(retention, now).unwrap().is_empty());
+        assert!(store.contains(query_id));
+
+        assert_eq!(
+            vec![query_id],
+            store.remove_expired(retention, now + retention).unwrap()
+        );
+        assert!(!store.contains(query_id));
+        assert_eq!(None, store.owner(query_id).unwrap());
+        assert_eq!(0, fs::read_dir(dir.path()).unwrap().count());
+    }
+
+    #[test]
+    fn rejects_corrupted_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ResultStore::open(dir.path()).unwrap();
+        let query_id = QueryId::from(7);
+
+        store
+            .save(query_id, owner(), &header(), &[1, 2, 3])
+            .unwrap();
+        fs::write(dir.path().join("7.header"), "not a header").unwrap();
+        assert!(matches!(
+            store.load(query_id),
+            Err(ResultStoreError::Corrupted { .. })
+        ));
+    }
+}
@@ -0,0 +1,281 @@
+use std::marker::PhantomData;
+
+use futures::TryStreamExt;
+
+use crate::{
+    error::Error,
+    ff::{
+        boolean::Boolean,
+        boolean_array::{BA16, BA20, BA32, BA8},
+        CustomArray, Field, PrimeField, Serializable,
+    },
+    helpers::{
+        query::{FeatureLabelDotProductQueryConfig, QuerySize},
+        BodyStream, RecordsStream,
+    },
+    protocol::{
+        context::{UpgradableContext, UpgradedContext},
+        ipa_prf::feature_label_dot_product,
+    },
+    report::FeatureLabelDotProductReport,
+    secret_sharing::{
+        replicated::{malicious::ExtendableField, semi_honest::AdditiveShare as Replicated},
+        WeakSharedValue,
+    },
+};
+
+pub struct FeatureLabelDotProductQuery<C, F> {
+    config: FeatureLabelDotProductQueryConfig,
+    phantom_data: PhantomData<(C, F)>,
+}
+
+impl<C, F> FeatureLabelDotProductQuery<C, F> {
+    pub fn new(config: FeatureLabelDotProductQueryConfig) -> Self {
+        Self {
+            config,
+            phantom_data: PhantomData,
+        }
+    }
+}
+
+/// Widths of the feature vector that the feature-label dot product supports.
+const SUPPORTED_FEATURE_VECTOR_BITS: [u32; 3] = [8, 16, 32];
+
+/// Per-user credit caps that the feature-label dot product supports. The number of labelled
+/// source events of every user is counted with `log2(cap)` bits.
+const MAX_PER_USER_CREDIT_CAP: u32 = 1 << 8;
+
+fn validate_config(config: FeatureLabelDotProductQueryConfig) -> Result<(), Error> {
+    let invalid = |msg: String| Err(Error::InvalidQueryParameter(msg.into()));
+
+    if !SUPPORTED_FEATURE_VECTOR_BITS.contains(&config.feature_vector_bits) {
+        return invalid(format!(
+            "feature vector must be one of {SUPPORTED_FEATURE_VECTOR_BITS:?} bits long, got {}",
+            config.feature_vector_bits
+        ));
+    }
+    if !config.per_user_credit_cap.is_power_of_two()
+        || config.per_user_credit_cap > MAX_PER_USER_CREDIT_CAP
+    {
+        return invalid(format!(
+            "per-user credit cap must be a power of two no greater than \
+             {MAX_PER_USER_CREDIT_CAP}, got {}",
+            config.per_user_credit_cap
+        ));
+    }
+
+    Ok(())
+}
+
+impl<C, F> FeatureLabelDotProductQuery<C, F>
+where
+    C: UpgradableContext,
+    C::UpgradedContext<F>: UpgradedContext<F, Share = Replicated<F>>,
+    C::UpgradedContext<Boolean>: UpgradedContext<Boolean, Share = Replicated<Boolean>>,
+    F: PrimeField + ExtendableField,
+    Replicated<F>: Serializable,
+{
+    #[tracing::instrument("feature_label_dot_product_query", skip_all, fields(sz=%query_size))]
+    pub async fn execute(
+        self,
+        ctx: C,
+        query_size: QuerySize,
+        input_stream: BodyStream,
+    ) -> Result<Vec<Replicated<F>>, Error> {
+        tracing::info!("New query: {:?}", self.config);
+        validate_config(self.config)?;
+        let sz = usize::from(query_size);
+
+        match self.config.feature_vector_bits {
+            8 => self.execute_with_type::<BA8>(ctx, sz, input_stream).await,
+            16 => self.execute_with_type::<BA16>(ctx, sz, input_stream).await,
+            32 => self.execute_with_type::<BA32>(ctx, sz, input_stream).await,
+            _ => unreachable!("feature vector width is validated above"),
+        }
+    }
+
+    async fn execute_with_type<FV>(
+        self,
+        ctx: C,
+        sz: usize,
+        input_stream: BodyStream,
+    ) -> Result<Vec<Replicated<F>>, Error>
+    where
+        FV: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+        FeatureLabelDotProductReport<FV, BA20>: Serializable,
+    {
+        let mut input =
+            RecordsStream::<FeatureLabelDotProductReport<FV, BA20>, _>::new(input_stream)
+                .try_concat()
+                .await?;
+        if input.len() < sz {
+            return Err(Error::InvalidQueryParameter(
+                format!("expected {sz} input records, got {}", input.len()).into(),
+            ));
+        }
+        input.truncate(sz);
+
+        feature_label_dot_product::<C, FV, BA20, F>(ctx, input, self.config).await
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::iter::zip;
+
+    use generic_array::GenericArray;
+    use typenum::Unsigned;
+
+    use super::*;
+    use crate::{
+        ff::{Fp31, Fp32BitPrime},
+        secret_sharing::IntoShares,
+        test_fixture::{
+            feature_label_dot_product::{
+                feature_label_dot_product_in_the_clear, TestFeatureLabelRecord,
+            },
+            join3v, Reconstruct, TestWorld,
+        },
+    };
+
+    fn test_input() -> Vec<TestFeatureLabelRecord> {
+        vec![
+            TestFeatureLabelRecord {
+                timestamp: 0,
+                user_id: 12345,
+                is_trigger_report: false,
+                feature_vector: 0b0000_0011,
+            },
+            TestFeatureLabelRecord {
+                timestamp: 5,
+                user_id: 12345,
+                is_trigger_report: false,
+                feature_vector: 0b0000_0110,
+            },
+            TestFeatureLabelRecord {
+                timestamp: 10,
+                user_id: 12345,
+                is_trigger_report: true,
+                feature_vector: 0,
+            },
+            TestFeatureLabelRecord {
+                timestamp: 0,
+                user_id: 68362,
+                is_trigger_report: false,
+                feature_vector: 0b1000_0100,
+            },
+            TestFeatureLabelRecord {
+                timestamp: 20,
+                user_id: 68362,
+                is_trigger_report: true,
+                feature_vector: 0,
+            },
+            TestFeatureLabelRecord {
+                timestamp: 30,
+                user_id: 68362,
+                is_trigger_report: false,
+                feature_vector: 0b0001_0000,
+            },
+            // everything below this line will be ignored by the runner
+            TestFeatureLabelRecord {
+                timestamp: 40,
+                user_id: 68362,
+                is_trigger_report: true,
+                feature_vector: 0,
+            },
+        ]
+    }
+
+    async fn run_query<F>(
+        records: Vec<TestFeatureLabelRecord>,
+        query_size: usize,
+        config: FeatureLabelDotProductQueryConfig,
+    ) -> Vec<F>
+    where
+        F: PrimeField + ExtendableField,
+        Replicated<F>: Serializable,
+    {
+        let sz = <FeatureLabelDotProductReport<BA8, BA20> as Serializable>::Size::USIZE;
+        let mut buffers: [_; 3] = std::array::from_fn(|_| vec![0u8; records.len() * sz]);
+
+        let shares: [Vec<FeatureLabelDotProductReport<BA8, BA20>>; 3] = records.into_iter().share();
+        for (buf, shares) in zip(&mut buffers, shares) {
+            for (share, chunk) in zip(shares, buf.chunks_mut(sz)) {
+                share.serialize(GenericArray::from_mut_slice(chunk));
+            }
+        }
+
+        let world = TestWorld::default();
+        let contexts = world.contexts();
+        let query_size = QuerySize::try_from(query_size).unwrap();
+        #[allow(clippy::large_futures)]
+        let results = join3v(buffers.into_iter().zip(contexts).map(|(buffer, ctx)| {
+            FeatureLabelDotProductQuery::<_, F>::new(config).execute(
+                ctx,
+                query_size,
+                BodyStream::from(buffer),
+            )
+        }))
+        .await;
+
+        results.reconstruct()
+    }
+
+    #[tokio::test]
+    async fn semi_honest() {
+        let records = test_input();
+        let query_size = records.len() - 1;
+        let config = FeatureLabelDotProductQueryConfig {
+            feature_vector_bits: 8,
+            per_user_credit_cap: 1,
+        };
+        let expected = feature_label_dot_product_in_the_clear(&records[..query_size], 8, 1);
+        // only the most recent labelled source event of every user counts
+        assert_eq!(expected, vec![0, 1, 2, 0, 0, 0, 0, 1]);
+
+        let results = Box::pin(run_query::<Fp31>(records, query_size, config)).await;
+        assert_eq!(
+            results,
+            expected
+                .into_iter()
+                .map(|v| Fp31::try_from(u128::from(v)).unwrap())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn per_user_credit_cap() {
+        let records = test_input();
+        let query_size = records.len() - 1;
+        let config = FeatureLabelDotProductQueryConfig {
+            feature_vector_bits: 8,
+            per_user_credit_cap: 2,
+        };
+        let expected = feature_label_dot_product_in_the_clear(&records[..query_size], 8, 2);
+        assert_eq!(expected, vec![1, 2, 2, 0, 0, 0, 0, 1]);
+
+        let results = Box::pin(run_query::<Fp32BitPrime>(records, query_size, config)).await;
+        assert_eq!(
+            results,
+            expected
+                .into_iter()
+                .map(|v| Fp32BitPrime::try_from(u128::from(v)).unwrap())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn validates_config() {
+        for (feature_vector_bits, per_user_credit_cap) in
+            [(7, 1), (64, 1), (8, 3), (8, 0), (8, 512)]
+        {
+            assert!(matches!(
+                validate_config(FeatureLabelDotProductQueryConfig {
+                    feature_vector_bits,
+                    per_user_credit_cap,
+                }),
+                Err(Error::InvalidQueryParameter(_))
+            ));
+        }
+    }
+}
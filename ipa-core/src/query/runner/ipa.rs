@@ -1,27 +1,30 @@
-use std::marker::PhantomData;
+use std::{iter::zip, marker::PhantomData};
 
-use futures::{
-    stream::{iter, repeat},
-    StreamExt, TryStreamExt,
-};
+use bytes::Bytes;
+use futures::{future::try_join4, stream::iter, StreamExt, TryStreamExt};
+use ipa_macros::Step;
 
 use crate::{
     error::Error,
-    ff::{Gf2, PrimeField, Serializable},
+    ff::{Field, Gf2, Gf8Bit, PrimeField, Serializable},
     helpers::{
-        query::{IpaQueryConfig, QuerySize},
-        BodyStream, LengthDelimitedStream, RecordsStream,
+        query::{InvalidReportPolicy, IpaQueryConfig, QuerySize},
+        BodyStream, Direction, LengthDelimitedStream, RecordsStream,
     },
     hpke::{KeyPair, KeyRegistry},
     protocol::{
         basics::{Reshare, ShareKnownValue},
-        context::{UpgradableContext, UpgradeContext, UpgradeToMalicious, UpgradedContext},
+        context::{
+            Context, UpgradableContext, UpgradeContext, UpgradeToMalicious, UpgradedContext,
+        },
         ipa::{ipa, ArithmeticallySharedIPAInputs, IPAInputRow},
         modulus_conversion::BitConversionTriple,
+        prss::SharedRandomness,
         sort::generate_permutation::ShuffledPermutationWrapper,
         BasicProtocols, BreakdownKey, MatchKey, RecordId,
     },
-    report::{EncryptedReport, EventType, InvalidReportError},
+    query::{executor::WithRejectedReports, RejectedReports},
+    report::{EncryptedReport, EventType, InvalidReportError, InvalidReportKind, Report},
     secret_sharing::{
         replicated::{malicious::DowngradeMalicious, semi_honest::AdditiveShare as Replicated},
         Linear as LinearSecretSharing, LinearRefOps,
@@ -29,17 +32,30 @@ use crate::{
     sync::Arc,
 };
 
+/// Steps of dealing with the reports that helpers reject, see [`InvalidReportPolicy`].
+#[derive(Step)]
+pub(crate) enum InvalidReportsStep {
+    ExchangeRejected,
+    NullEvents,
+}
+
 pub struct IpaQuery<F, C, S> {
     config: IpaQueryConfig,
     key_registry: Arc<KeyRegistry<KeyPair>>,
+    helper_origin: Arc<str>,
     phantom_data: PhantomData<(F, C, S)>,
 }
 
 impl<F, C, S> IpaQuery<F, C, S> {
-    pub fn new(config: IpaQueryConfig, key_registry: Arc<KeyRegistry<KeyPair>>) -> Self {
+    pub fn new(
+        config: IpaQueryConfig,
+        key_registry: Arc<KeyRegistry<KeyPair>>,
+        helper_origin: Arc<str>,
+    ) -> Self {
         Self {
             config,
             key_registry,
+            helper_origin,
             phantom_data: PhantomData,
         }
     }
@@ -79,68 +95,190 @@ where
         ctx: C,
         query_size: QuerySize,
         input_stream: BodyStream,
-    ) -> Result<Vec<Replicated<F>>, Error> {
+    ) -> Result<WithRejectedReports<Vec<Replicated<F>>>, Error> {
         let Self {
             config,
             key_registry,
+            helper_origin,
             phantom_data: _,
         } = self;
+        let epochs = config.report_epochs();
         tracing::info!("New query: {config:?}");
         let sz = usize::from(query_size);
 
-        let input = if config.plaintext_match_keys {
+        let (input, rejected_reports) = if config.plaintext_match_keys {
             let mut v =
                 RecordsStream::<IPAInputRow<F, MatchKey, BreakdownKey>, _>::new(input_stream)
                     .try_concat()
                     .await?;
             v.truncate(sz);
-            v
+            (v, RejectedReports::new())
         } else {
-            LengthDelimitedStream::<EncryptedReport<F, MatchKey, BreakdownKey, _>, _>::new(
-                input_stream,
-            )
-            .map_err(Into::<Error>::into)
-            .map_ok(|enc_reports| {
-                iter(enc_reports.into_iter().map(|enc_report| {
-                    enc_report
-                        .decrypt(key_registry.as_ref())
-                        .map_err(Into::<Error>::into)
-                }))
-            })
-            .try_flatten()
-            .take(sz)
-            .zip(repeat(ctx.clone()))
-            .map(|(res, ctx)| {
-                res.and_then(|report| {
-                    let timestamp = Replicated::<F>::share_known_value(
-                        &ctx,
-                        F::try_from(report.timestamp.into())
-                            .map_err(|_| InvalidReportError::Timestamp(report.timestamp))?,
-                    );
-                    let breakdown_key =
-                        Replicated::<BreakdownKey>::share_known_value(&ctx, report.breakdown_key);
-                    let is_trigger_bit = Replicated::<F>::share_known_value(
-                        &ctx,
-                        match report.event_type {
-                            EventType::Source => F::ZERO,
-                            EventType::Trigger => F::ONE,
-                        },
-                    );
-
-                    Ok(IPAInputRow {
-                        timestamp,
-                        mk_shares: report.mk_shares,
-                        is_trigger_bit,
-                        breakdown_key,
-                        trigger_value: report.trigger_value,
+            let policy = config.invalid_report_policy;
+            let rows = LengthDelimitedStream::<Bytes, _>::new(input_stream)
+                .map_err(Into::<Error>::into)
+                .map_ok(|reports| iter(reports.into_iter().map(Ok::<_, Error>)))
+                .try_flatten()
+                .take(sz)
+                .map_ok(|report| {
+                    EncryptedReport::<F, MatchKey, BreakdownKey, _>::from_bytes_with_version(
+                        report,
+                        config.report_version,
+                    )
+                    .and_then(|report| {
+                        report.decrypt(key_registry.as_ref(), &helper_origin, &epochs)
                     })
+                    .and_then(|report| input_row(&ctx, report))
+                })
+                .map(|res| match res {
+                    Ok(Err(e)) if policy == InvalidReportPolicy::Fail => Err(e.into()),
+                    res => res,
                 })
-            })
-            .try_collect::<Vec<_>>()
-            .await?
+                .try_collect::<Vec<_>>()
+                .await?;
+
+            reject_invalid_reports(ctx.clone(), policy, rows).await?
         };
 
-        ipa(ctx, input.as_slice(), config).await
+        if !rejected_reports.is_empty() {
+            tracing::warn!("Rejected invalid reports: {rejected_reports:?}");
+        }
+
+        Ok(WithRejectedReports {
+            results: ipa(ctx, input.as_slice(), config).await?,
+            rejected_reports,
+        })
+    }
+}
+
+fn input_row<C, F>(
+    ctx: &C,
+    report: Report<F, MatchKey, BreakdownKey>,
+) -> Result<IPAInputRow<F, MatchKey, BreakdownKey>, InvalidReportError>
+where
+    C: Context,
+    F: PrimeField,
+    Replicated<F>: Serializable,
+{
+    let timestamp = Replicated::<F>::share_known_value(
+        ctx,
+        F::try_from(report.timestamp.into())
+            .map_err(|_| InvalidReportError::Timestamp(report.timestamp))?,
+    );
+    let breakdown_key = Replicated::<BreakdownKey>::share_known_value(ctx, report.breakdown_key);
+    let is_trigger_bit = Replicated::<F>::share_known_value(
+        ctx,
+        match report.event_type {
+            EventType::Source => F::ZERO,
+            EventType::Trigger => F::ONE,
+        },
+    );
+
+    Ok(IPAInputRow {
+        timestamp,
+        mk_shares: report.mk_shares,
+        is_trigger_bit,
+        breakdown_key,
+        trigger_value: report.trigger_value,
+    })
+}
+
+/// Makes helpers agree on the reports that at least one of them could not turn into an input row
+/// and deals with those reports according to `policy`, keeping the rows of all helpers aligned.
+/// Returns the input rows for IPA and the number of rejected reports of every kind.
+///
+/// ## Errors
+/// If `policy` is [`InvalidReportPolicy::Fail`] and some of the reports are invalid, or if
+/// communicating with other helpers fails.
+async fn reject_invalid_reports<C, F>(
+    ctx: C,
+    policy: InvalidReportPolicy,
+    rows: Vec<Result<IPAInputRow<F, MatchKey, BreakdownKey>, InvalidReportError>>,
+) -> Result<(Vec<IPAInputRow<F, MatchKey, BreakdownKey>>, RejectedReports), Error>
+where
+    C: Context,
+    F: PrimeField,
+    Replicated<F>: Serializable,
+{
+    if policy == InvalidReportPolicy::Fail {
+        let rows = rows.into_iter().collect::<Result<Vec<_>, _>>()?;
+        return Ok((rows, RejectedReports::new()));
+    }
+
+    let exchange_ctx = ctx
+        .narrow(&InvalidReportsStep::ExchangeRejected)
+        .set_total_records(rows.len());
+    let rejected = exchange_ctx
+        .try_join(rows.iter().enumerate().map(|(i, row)| {
+            exchange_rejected(
+                exchange_ctx.clone(),
+                RecordId::from(i),
+                row.as_ref().err().map(InvalidReportError::kind),
+            )
+        }))
+        .await?;
+
+    // Null events are trigger events with zero value, so they contribute nothing even if their
+    // random match key happens to be the match key of a real user.
+    let null_ctx = ctx.narrow(&InvalidReportsStep::NullEvents);
+    let null_event = |i: usize| IPAInputRow {
+        timestamp: Replicated::ZERO,
+        mk_shares: null_ctx.prss().generate_replicated(RecordId::from(i)),
+        is_trigger_bit: Replicated::share_known_value(&null_ctx, F::ONE),
+        breakdown_key: Replicated::ZERO,
+        trigger_value: Replicated::ZERO,
+    };
+
+    let total = rows.len();
+    let mut input = Vec::with_capacity(total);
+    let mut rejected_reports = RejectedReports::new();
+    for (i, (row, kind)) in zip(rows, rejected).enumerate() {
+        match kind {
+            // a helper that rejected the report itself always knows its kind
+            None => input.push(row?),
+            Some(kind) => {
+                *rejected_reports.entry(kind).or_default() += 1;
+                if policy == InvalidReportPolicy::ReplaceWithNullEvent {
+                    input.push(null_event(i));
+                }
+            }
+        }
+    }
+    let kept = input.len();
+    input.extend((kept..total).map(null_event));
+
+    Ok((input, rejected_reports))
+}
+
+/// Sends the kind of the problem that this helper found in the report to both other helpers, if
+/// any, and returns the kind that all helpers settle on.
+async fn exchange_rejected<C: Context>(
+    ctx: C,
+    record_id: RecordId,
+    kind: Option<InvalidReportKind>,
+) -> Result<Option<InvalidReportKind>, Error> {
+    let kind = Gf8Bit::truncate_from(kind.map_or(0, u8::from));
+    let left = ctx.role().peer(Direction::Left);
+    let right = ctx.role().peer(Direction::Right);
+    let ((), (), from_left, from_right) = try_join4(
+        ctx.send_channel(left).send(record_id, kind),
+        ctx.send_channel(right).send(record_id, kind),
+        ctx.recv_channel::<Gf8Bit>(left).receive(record_id),
+        ctx.recv_channel::<Gf8Bit>(right).receive(record_id),
+    )
+    .await?;
+
+    // If more than one helper rejected the report, they all pick the same kind.
+    let kind = [kind, from_left, from_right]
+        .into_iter()
+        .map(|kind| u8::try_from(kind.as_u128()).unwrap())
+        .max()
+        .unwrap();
+    match kind {
+        0 => Ok(None),
+        kind => InvalidReportKind::try_from(kind).map(Some).map_err(|kind| {
+            Error::ParseError(format!("unknown kind {kind} of a rejected report").into())
+        }),
     }
 }
 
@@ -149,6 +287,7 @@ where
 mod tests {
     use std::iter::zip;
 
+    use futures::future::join_all;
     use generic_array::GenericArray;
     use rand::rngs::StdRng;
     use rand_core::SeedableRng;
@@ -157,8 +296,9 @@ mod tests {
     use super::*;
     use crate::{
         ff::Fp31,
+        helpers::query::DpParameter,
         ipa_test_input,
-        report::{Report, DEFAULT_KEY_ID},
+        report::{Report, ReportVersion, DEFAULT_HELPER_ORIGIN, DEFAULT_KEY_ID},
         secret_sharing::IntoShares,
         test_fixture::{input::GenericReportTestInput, join3v, Reconstruct, TestWorld},
     };
@@ -212,16 +352,32 @@ mod tests {
                 attribution_window_seconds: None,
                 max_breakdown_key: 3,
                 plaintext_match_keys: true,
+                dummy_records_epsilon: None,
+                dummy_records_delta: DpParameter::DEFAULT_DELTA,
+                output_noise_epsilon: None,
+                output_noise_delta: DpParameter::DEFAULT_DELTA,
+                epoch: 0,
+                min_report_epoch: None,
+                max_report_epoch: None,
+                breakdown_key_bits: 8,
+                trigger_value_bits: 3,
+                timestamp_bits: 20,
+                invalid_report_policy: InvalidReportPolicy::Fail,
+                report_version: ReportVersion::MatchKeyOnly,
             };
             let input = BodyStream::from(shares);
             // Note that we ignore the last 2 records to test that runner follows the rule
             // to take up to `record_count` reports. Everything else outside that will
             // be ignored
-            IpaQuery::<Fp31, _, _>::new(query_config, Arc::new(KeyRegistry::empty()))
-                .execute(ctx, query_size, input)
+            IpaQuery::<Fp31, _, _>::new(
+                query_config,
+                Arc::new(KeyRegistry::empty()),
+                DEFAULT_HELPER_ORIGIN.into(),
+            )
+            .execute(ctx, query_size, input)
         }))
         .await;
-        assert_eq!(results.reconstruct(), EXPECTED);
+        assert_eq!(results.map(|r| r.results).reconstruct(), EXPECTED);
     }
 
     #[tokio::test]
@@ -271,20 +427,32 @@ mod tests {
                 attribution_window_seconds: None,
                 max_breakdown_key: 3,
                 plaintext_match_keys: true,
+                dummy_records_epsilon: None,
+                dummy_records_delta: DpParameter::DEFAULT_DELTA,
+                output_noise_epsilon: None,
+                output_noise_delta: DpParameter::DEFAULT_DELTA,
+                epoch: 0,
+                min_report_epoch: None,
+                max_report_epoch: None,
+                breakdown_key_bits: 8,
+                trigger_value_bits: 3,
+                timestamp_bits: 20,
+                invalid_report_policy: InvalidReportPolicy::Fail,
+                report_version: ReportVersion::MatchKeyOnly,
             };
-            IpaQuery::<Fp31, _, _>::new(query_config, Arc::new(KeyRegistry::empty())).execute(
-                ctx,
-                query_size,
-                shares.into(),
+            IpaQuery::<Fp31, _, _>::new(
+                query_config,
+                Arc::new(KeyRegistry::empty()),
+                DEFAULT_HELPER_ORIGIN.into(),
             )
+            .execute(ctx, query_size, shares.into())
         }))
         .await;
 
-        assert_eq!(results.reconstruct(), EXPECTED);
+        assert_eq!(results.map(|r| r.results).reconstruct(), EXPECTED);
     }
 
-    #[tokio::test]
-    async fn encrypted_match_keys() {
+    async fn encrypted_reports(version: ReportVersion) {
         const EXPECTED: &[u128] = &[0, 2, 3];
 
         let records: Vec<GenericReportTestInput<Fp31, MatchKey, BreakdownKey>> = ipa_test_input!(
@@ -312,7 +480,14 @@ mod tests {
         for (buf, shares) in zip(&mut buffers, shares) {
             for share in shares {
                 share
-                    .delimited_encrypt_to(key_id, key_registry.as_ref(), &mut rng, buf)
+                    .delimited_encrypt_to(
+                        version,
+                        key_id,
+                        key_registry.as_ref(),
+                        DEFAULT_HELPER_ORIGIN,
+                        &mut rng,
+                        buf,
+                    )
                     .unwrap();
             }
         }
@@ -327,13 +502,150 @@ mod tests {
                 attribution_window_seconds: None,
                 max_breakdown_key: 3,
                 plaintext_match_keys: false,
+                dummy_records_epsilon: None,
+                dummy_records_delta: DpParameter::DEFAULT_DELTA,
+                output_noise_epsilon: None,
+                output_noise_delta: DpParameter::DEFAULT_DELTA,
+                epoch: 0,
+                min_report_epoch: None,
+                max_report_epoch: None,
+                breakdown_key_bits: 8,
+                trigger_value_bits: 3,
+                timestamp_bits: 20,
+                invalid_report_policy: InvalidReportPolicy::Fail,
+                report_version: version,
             };
             let input = BodyStream::from(buffer);
-            IpaQuery::<Fp31, _, _>::new(query_config, Arc::clone(&key_registry))
-                .execute(ctx, query_size, input)
+            IpaQuery::<Fp31, _, _>::new(
+                query_config,
+                Arc::clone(&key_registry),
+                DEFAULT_HELPER_ORIGIN.into(),
+            )
+            .execute(ctx, query_size, input)
         }))
         .await;
 
-        assert_eq!(results.reconstruct(), EXPECTED);
+        assert_eq!(results.map(|r| r.results).reconstruct(), EXPECTED);
+    }
+
+    #[tokio::test]
+    async fn encrypted_match_keys() {
+        #[allow(clippy::large_futures)]
+        encrypted_reports(ReportVersion::MatchKeyOnly).await;
+    }
+
+    #[tokio::test]
+    async fn encrypted_all_fields() {
+        #[allow(clippy::large_futures)]
+        encrypted_reports(ReportVersion::AllFields).await;
+    }
+
+    /// Encrypts the shares of test records that helpers receive. On helpers with indices in
+    /// `invalid_on`, the last report is encrypted for another helper origin, so they cannot
+    /// decrypt it. If it was valid, the report would add 1 to the second breakdown.
+    fn encrypt_with_invalid_report(
+        key_registry: &KeyRegistry<KeyPair>,
+        invalid_on: &[usize],
+    ) -> [Vec<u8>; 3] {
+        let records: Vec<GenericReportTestInput<Fp31, MatchKey, BreakdownKey>> = ipa_test_input!(
+            [
+                { timestamp: 0, match_key: 12345, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                { timestamp: 0, match_key: 12345, is_trigger_report: 0, breakdown_key: 2, trigger_value: 0 },
+                { timestamp: 0, match_key: 68362, is_trigger_report: 0, breakdown_key: 1, trigger_value: 0 },
+                { timestamp: 0, match_key: 12345, is_trigger_report: 1, breakdown_key: 0, trigger_value: 5 },
+                { timestamp: 0, match_key: 68362, is_trigger_report: 1, breakdown_key: 0, trigger_value: 2 },
+                { timestamp: 0, match_key: 68362, is_trigger_report: 1, breakdown_key: 0, trigger_value: 1 },
+            ];
+            (Fp31, MatchKey, BreakdownKey)
+        );
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut buffers: [_; 3] = std::array::from_fn(|_| Vec::new());
+        let shares: [Vec<Report<_, _, _>>; 3] = records.into_iter().share();
+        for (i, (buf, shares)) in zip(&mut buffers, shares).enumerate() {
+            let last = shares.len() - 1;
+            for (j, share) in shares.into_iter().enumerate() {
+                let helper_origin = if j == last && invalid_on.contains(&i) {
+                    "example.com"
+                } else {
+                    DEFAULT_HELPER_ORIGIN
+                };
+                share
+                    .delimited_encrypt_to(
+                        ReportVersion::MatchKeyOnly,
+                        DEFAULT_KEY_ID,
+                        key_registry,
+                        helper_origin,
+                        &mut rng,
+                        buf,
+                    )
+                    .unwrap();
+            }
+        }
+
+        buffers
+    }
+
+    async fn ipa_with_invalid_report(
+        policy: InvalidReportPolicy,
+        invalid_on: &[usize],
+    ) -> Vec<Result<WithRejectedReports<Vec<Replicated<Fp31>>>, Error>> {
+        let mut rng = StdRng::seed_from_u64(42);
+        let key_registry = Arc::new(KeyRegistry::random(1, &mut rng));
+        let buffers = encrypt_with_invalid_report(&key_registry, invalid_on);
+        let query_size = QuerySize::try_from(6).unwrap();
+
+        let world = TestWorld::default();
+        #[allow(clippy::large_futures)]
+        join_all(zip(buffers, world.contexts()).map(|(buffer, ctx)| {
+            IpaQuery::<Fp31, _, _>::new(
+                IpaQueryConfig::no_window(3, 3, 3).with_invalid_report_policy(policy),
+                Arc::clone(&key_registry),
+                DEFAULT_HELPER_ORIGIN.into(),
+            )
+            .execute(ctx, query_size, BodyStream::from(buffer))
+        }))
+        .await
+    }
+
+    fn assert_rejected_one(
+        results: Vec<Result<WithRejectedReports<Vec<Replicated<Fp31>>>, Error>>,
+    ) {
+        const EXPECTED: &[u128] = &[0, 2, 3];
+
+        let results: [_; 3] = results
+            .into_iter()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        for r in &results {
+            assert_eq!(
+                RejectedReports::from([(InvalidReportKind::Crypt, 1)]),
+                r.rejected_reports
+            );
+        }
+        assert_eq!(results.map(|r| r.results).reconstruct(), EXPECTED);
+    }
+
+    #[tokio::test]
+    async fn invalid_report_fails_query() {
+        let results = ipa_with_invalid_report(InvalidReportPolicy::Fail, &[0, 1, 2]).await;
+
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, Err(Error::InvalidReport(InvalidReportError::Crypt(_))))));
+    }
+
+    #[tokio::test]
+    async fn drop_invalid_report() {
+        assert_rejected_one(ipa_with_invalid_report(InvalidReportPolicy::Drop, &[1]).await);
+    }
+
+    #[tokio::test]
+    async fn replace_invalid_report_with_null_event() {
+        assert_rejected_one(
+            ipa_with_invalid_report(InvalidReportPolicy::ReplaceWithNullEvent, &[0, 2]).await,
+        );
     }
 }
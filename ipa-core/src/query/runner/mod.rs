@@ -1,4 +1,5 @@
 mod aggregate;
+mod feature_label_dot_product;
 mod ipa;
 mod oprf_ipa;
 #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
@@ -7,7 +8,10 @@ mod test_multiply;
 #[cfg(any(test, feature = "cli", feature = "test-fixture"))]
 pub(super) use test_multiply::execute_test_multiply;
 
-pub(super) use self::{aggregate::SparseAggregateQuery, ipa::IpaQuery, oprf_ipa::OprfIpaQuery};
+pub(super) use self::{
+    aggregate::SparseAggregateQuery, feature_label_dot_product::FeatureLabelDotProductQuery,
+    ipa::IpaQuery, oprf_ipa::OprfIpaQuery,
+};
 use crate::{error::Error, query::ProtocolResult};
 
 pub(super) type QueryResult = Result<Box<dyn ProtocolResult>, Error>;
@@ -1,44 +1,111 @@
 use std::marker::PhantomData;
 
-use futures::TryStreamExt;
+use futures::{
+    stream::{iter, repeat},
+    StreamExt, TryStreamExt,
+};
 
 use crate::{
     error::Error,
     ff::{
         boolean::Boolean,
-        boolean_array::{BA20, BA3, BA4, BA5, BA6, BA7, BA8},
-        PrimeField, Serializable,
+        boolean_array::{BA3, BA4, BA5, BA6, BA7, BA8},
+        CustomArray, Field, PrimeField, Serializable,
     },
     helpers::{
-        query::{IpaQueryConfig, QuerySize},
-        BodyStream, RecordsStream,
+        query::{InvalidReportPolicy, IpaQueryConfig, QuerySize},
+        BodyStream, LengthDelimitedStream, RecordsStream,
     },
+    hpke::{KeyPair, KeyRegistry},
     protocol::{
         basics::ShareKnownValue,
         context::{UpgradableContext, UpgradedContext},
-        ipa_prf::oprf_ipa,
+        ipa_prf::{oprf_ipa, prf_sharding::bucket::MAX_BREAKDOWNS},
     },
-    report::OprfReport,
-    secret_sharing::replicated::{
-        malicious::ExtendableField, semi_honest::AdditiveShare as Replicated,
+    report::{with_oprf_report_types, EncryptedOprfReport, EventType, OprfReport, ReportVersion},
+    secret_sharing::{
+        replicated::{malicious::ExtendableField, semi_honest::AdditiveShare as Replicated},
+        WeakSharedValue,
     },
+    sync::Arc,
 };
 
 pub struct OprfIpaQuery<C, F> {
     config: IpaQueryConfig,
+    key_registry: Arc<KeyRegistry<KeyPair>>,
+    helper_origin: Arc<str>,
     phantom_data: PhantomData<(C, F)>,
 }
 
 impl<C, F> OprfIpaQuery<C, F> {
-    pub fn new(config: IpaQueryConfig) -> Self {
+    pub fn new(
+        config: IpaQueryConfig,
+        key_registry: Arc<KeyRegistry<KeyPair>>,
+        helper_origin: Arc<str>,
+    ) -> Self {
         Self {
             config,
+            key_registry,
+            helper_origin,
             phantom_data: PhantomData,
         }
     }
 }
 
-#[allow(clippy::too_many_lines)]
+/// Per-user credit caps that OPRF IPA supports. Trigger values are capped using a saturating sum
+/// of `log2(cap)` bits.
+const SUPPORTED_CAPS: [u32; 6] = [8, 16, 32, 64, 128, 256];
+
+/// Checks that OPRF IPA can run with the report field widths, the per-user credit cap, the
+/// invalid report policy and the report version requested by `config`.
+fn validate_config(config: &IpaQueryConfig) -> Result<(), Error> {
+    let invalid = |msg: String| Err(Error::InvalidQueryParameter(msg.into()));
+
+    // OPRF IPA fails the query on the first report it cannot decrypt
+    if config.invalid_report_policy != InvalidReportPolicy::Fail {
+        return invalid(format!(
+            "invalid report policy {} is not supported by OPRF IPA",
+            config.invalid_report_policy
+        ));
+    }
+    if config.report_version != ReportVersion::MatchKeyOnly {
+        return invalid(format!(
+            "report version {} is not supported by OPRF IPA",
+            config.report_version
+        ));
+    }
+
+    if !SUPPORTED_CAPS.contains(&config.per_user_credit_cap) {
+        return invalid(format!(
+            "per-user credit cap must be one of {SUPPORTED_CAPS:?}, got {}",
+            config.per_user_credit_cap
+        ));
+    }
+    if with_oprf_report_types!(config, |BK, TV, TS| false, _ => true) {
+        return invalid(format!(
+            "{}-bit breakdown keys, {}-bit trigger values and {}-bit timestamps are not \
+             supported by OPRF IPA",
+            config.breakdown_key_bits, config.trigger_value_bits, config.timestamp_bits
+        ));
+    }
+
+    let max_breakdowns = MAX_BREAKDOWNS.min(1 << config.breakdown_key_bits);
+    if !(1..=max_breakdowns).contains(&usize::try_from(config.max_breakdown_key).unwrap()) {
+        return invalid(format!(
+            "{}-bit breakdown keys allow between 1 and {max_breakdowns} breakdowns, got {}",
+            config.breakdown_key_bits, config.max_breakdown_key
+        ));
+    }
+    if config.trigger_value_bits > config.per_user_credit_cap.trailing_zeros() {
+        return invalid(format!(
+            "{}-bit trigger values cannot be capped at {}",
+            config.trigger_value_bits, config.per_user_credit_cap
+        ));
+    }
+
+    Ok(())
+}
+
 impl<C, F> OprfIpaQuery<C, F>
 where
     C: UpgradableContext,
@@ -55,34 +122,545 @@ where
         query_size: QuerySize,
         input_stream: BodyStream,
     ) -> Result<Vec<Replicated<F>>, Error> {
+        tracing::info!("New query: {:?}", self.config);
+        validate_config(&self.config)?;
+        let sz = usize::from(query_size);
+
+        with_oprf_report_types!(
+            self.config,
+            |BK, TV, TS| self
+                .execute_with_fields::<BK, TV, TS>(ctx, sz, input_stream)
+                .await,
+            _ => unreachable!("report field widths are validated above"),
+        )
+    }
+
+    /// Picks the type of the saturating sum used to cap trigger values.
+    async fn execute_with_fields<BK, TV, TS>(
+        self,
+        ctx: C,
+        sz: usize,
+        input_stream: BodyStream,
+    ) -> Result<Vec<Replicated<F>>, Error>
+    where
+        BK: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+        TV: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+        TS: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+        Replicated<BK>: Serializable,
+        Replicated<TV>: Serializable,
+        Replicated<TS>: Serializable,
+        OprfReport<BK, TV, TS>: Serializable,
+        for<'a> &'a Replicated<TS>: IntoIterator<Item = Replicated<Boolean>>,
+        for<'a> &'a Replicated<TV>: IntoIterator<Item = Replicated<Boolean>>,
+        for<'a> &'a Replicated<BK>: IntoIterator<Item = Replicated<Boolean>>,
+        for<'a> <&'a Replicated<TV> as IntoIterator>::IntoIter: Send,
+        for<'a> <&'a Replicated<TS> as IntoIterator>::IntoIter: Send,
+    {
+        match self.config.per_user_credit_cap {
+            8 => {
+                self.execute_with_types::<BK, TV, TS, BA3>(ctx, sz, input_stream)
+                    .await
+            }
+            16 => {
+                self.execute_with_types::<BK, TV, TS, BA4>(ctx, sz, input_stream)
+                    .await
+            }
+            32 => {
+                self.execute_with_types::<BK, TV, TS, BA5>(ctx, sz, input_stream)
+                    .await
+            }
+            64 => {
+                self.execute_with_types::<BK, TV, TS, BA6>(ctx, sz, input_stream)
+                    .await
+            }
+            128 => {
+                self.execute_with_types::<BK, TV, TS, BA7>(ctx, sz, input_stream)
+                    .await
+            }
+            256 => {
+                self.execute_with_types::<BK, TV, TS, BA8>(ctx, sz, input_stream)
+                    .await
+            }
+            _ => unreachable!("per-user credit cap is validated"),
+        }
+    }
+
+    async fn execute_with_types<BK, TV, TS, SS>(
+        self,
+        ctx: C,
+        sz: usize,
+        input_stream: BodyStream,
+    ) -> Result<Vec<Replicated<F>>, Error>
+    where
+        BK: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+        TV: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+        TS: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+        SS: WeakSharedValue + CustomArray<Element = Boolean> + Field,
+        Replicated<BK>: Serializable,
+        Replicated<TV>: Serializable,
+        Replicated<TS>: Serializable,
+        OprfReport<BK, TV, TS>: Serializable,
+        for<'a> &'a Replicated<SS>: IntoIterator<Item = Replicated<Boolean>>,
+        for<'a> &'a Replicated<TS>: IntoIterator<Item = Replicated<Boolean>>,
+        for<'a> &'a Replicated<TV>: IntoIterator<Item = Replicated<Boolean>>,
+        for<'a> &'a Replicated<BK>: IntoIterator<Item = Replicated<Boolean>>,
+        for<'a> <&'a Replicated<SS> as IntoIterator>::IntoIter: Send,
+        for<'a> <&'a Replicated<TV> as IntoIterator>::IntoIter: Send,
+        for<'a> <&'a Replicated<TS> as IntoIterator>::IntoIter: Send,
+    {
         let Self {
             config,
+            key_registry,
+            helper_origin,
             phantom_data: _,
         } = self;
-        tracing::info!("New query: {config:?}");
-        let sz = usize::from(query_size);
+        let epochs = config.report_epochs();
 
         let input = if config.plaintext_match_keys {
-            let mut v = RecordsStream::<OprfReport<BA8, BA3, BA20>, _>::new(input_stream)
+            let mut v = RecordsStream::<OprfReport<BK, TV, TS>, _>::new(input_stream)
                 .try_concat()
                 .await?;
             v.truncate(sz);
             v
         } else {
-            panic!("Encrypted match key handling is not handled for OPRF flow as yet");
+            LengthDelimitedStream::<EncryptedOprfReport<BK, TV, TS, _>, _>::new(input_stream)
+                .map_err(Into::<Error>::into)
+                .map_ok(|enc_reports| {
+                    iter(enc_reports.into_iter().map(|enc_report| {
+                        enc_report
+                            .decrypt(key_registry.as_ref(), &helper_origin, &epochs)
+                            .map_err(Into::<Error>::into)
+                    }))
+                })
+                .try_flatten()
+                .take(sz)
+                .zip(repeat(ctx.clone()))
+                .map(|(res, ctx)| {
+                    res.map(|report| {
+                        let is_trigger = Replicated::<Boolean>::share_known_value(
+                            &ctx,
+                            match report.event_type {
+                                EventType::Source => Boolean::from(false),
+                                EventType::Trigger => Boolean::from(true),
+                            },
+                        );
+
+                        OprfReport {
+                            match_key: report.match_key,
+                            is_trigger,
+                            breakdown_key: report.breakdown_key,
+                            trigger_value: report.trigger_value,
+                            timestamp: report.timestamp,
+                        }
+                    })
+                })
+                .try_collect::<Vec<_>>()
+                .await?
+        };
+
+        if input.len() < sz {
+            return Err(Error::InvalidQueryParameter(
+                format!("expected {sz} input records, got {}", input.len()).into(),
+            ));
+        }
+
+        oprf_ipa::<C, BK, TV, TS, SS, F>(ctx, input, config).await
+    }
+}
+
+#[cfg(all(test, unit_test))]
+mod tests {
+    use std::iter::zip;
+
+    use futures::future::join_all;
+    use generic_array::GenericArray;
+    use rand::rngs::StdRng;
+    use rand_core::SeedableRng;
+    use typenum::Unsigned;
+
+    use super::*;
+    use crate::{
+        ff::{
+            boolean_array::{BA20, BA9},
+            Fp31, Fp32BitPrime,
+        },
+        helpers::query::DpParameter,
+        report::{DecryptedOprfReport, InvalidReportError, DEFAULT_HELPER_ORIGIN, DEFAULT_KEY_ID},
+        secret_sharing::IntoShares,
+        test_fixture::{ipa::TestRawDataRecord, join3v, Reconstruct, TestWorld},
+    };
+
+    const EXPECTED: &[u128] = &[0, 2, 5];
+
+    fn test_input() -> Vec<TestRawDataRecord> {
+        vec![
+            TestRawDataRecord {
+                timestamp: 0,
+                user_id: 12345,
+                is_trigger_report: false,
+                breakdown_key: 1,
+                trigger_value: 0,
+            },
+            TestRawDataRecord {
+                timestamp: 5,
+                user_id: 12345,
+                is_trigger_report: false,
+                breakdown_key: 2,
+                trigger_value: 0,
+            },
+            TestRawDataRecord {
+                timestamp: 10,
+                user_id: 12345,
+                is_trigger_report: true,
+                breakdown_key: 0,
+                trigger_value: 5,
+            },
+            TestRawDataRecord {
+                timestamp: 0,
+                user_id: 68362,
+                is_trigger_report: false,
+                breakdown_key: 1,
+                trigger_value: 0,
+            },
+            TestRawDataRecord {
+                timestamp: 20,
+                user_id: 68362,
+                is_trigger_report: true,
+                breakdown_key: 0,
+                trigger_value: 2,
+            },
+            // everything below this line will be ignored by the runner
+            TestRawDataRecord {
+                timestamp: 30,
+                user_id: 68362,
+                is_trigger_report: true,
+                breakdown_key: 0,
+                trigger_value: 7,
+            },
+        ]
+    }
+
+    fn query_config(plaintext_match_keys: bool) -> IpaQueryConfig {
+        IpaQueryConfig {
+            num_multi_bits: 3,
+            per_user_credit_cap: 8,
+            attribution_window_seconds: None,
+            max_breakdown_key: 3,
+            plaintext_match_keys,
+            dummy_records_epsilon: None,
+            dummy_records_delta: DpParameter::DEFAULT_DELTA,
+            output_noise_epsilon: None,
+            output_noise_delta: DpParameter::DEFAULT_DELTA,
+            epoch: 0,
+            min_report_epoch: None,
+            max_report_epoch: None,
+            breakdown_key_bits: 8,
+            trigger_value_bits: 3,
+            timestamp_bits: 20,
+            invalid_report_policy: InvalidReportPolicy::Fail,
+            report_version: ReportVersion::MatchKeyOnly,
+        }
+    }
+
+    #[tokio::test]
+    async fn plaintext_match_keys() {
+        let records = test_input();
+        let query_size = QuerySize::try_from(records.len() - 1).unwrap();
+
+        let sz = <OprfReport<BA8, BA3, BA20> as Serializable>::Size::USIZE;
+        let mut buffers: [_; 3] = std::array::from_fn(|_| vec![0u8; records.len() * sz]);
+
+        let shares: [Vec<OprfReport<BA8, BA3, BA20>>; 3] = records.into_iter().share();
+        for (buf, shares) in zip(&mut buffers, shares) {
+            for (share, chunk) in zip(shares, buf.chunks_mut(sz)) {
+                share.serialize(GenericArray::from_mut_slice(chunk));
+            }
+        }
+
+        let world = TestWorld::default();
+        let contexts = world.contexts();
+        #[allow(clippy::large_futures)]
+        let results = join3v(buffers.into_iter().zip(contexts).map(|(buffer, ctx)| {
+            let input = BodyStream::from(buffer);
+            OprfIpaQuery::<_, Fp31>::new(
+                query_config(true),
+                Arc::new(KeyRegistry::empty()),
+                DEFAULT_HELPER_ORIGIN.into(),
+            )
+            .execute(ctx, query_size, input)
+        }))
+        .await;
+
+        let results: Vec<Fp31> = results.reconstruct();
+        assert_eq!(
+            results,
+            EXPECTED
+                .iter()
+                .map(|&v| Fp31::try_from(v).unwrap())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn encrypted_match_keys() {
+        let records = test_input();
+        let query_size = QuerySize::try_from(records.len() - 1).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let key_id = DEFAULT_KEY_ID;
+        let key_registry = Arc::new(KeyRegistry::random(1, &mut rng));
+
+        let mut buffers: [_; 3] = std::array::from_fn(|_| Vec::new());
+
+        let shares: [Vec<DecryptedOprfReport<BA8, BA3, BA20>>; 3] = records.into_iter().share();
+        for (buf, shares) in zip(&mut buffers, shares) {
+            for share in shares {
+                share
+                    .delimited_encrypt_to(
+                        key_id,
+                        key_registry.as_ref(),
+                        DEFAULT_HELPER_ORIGIN,
+                        &mut rng,
+                        buf,
+                    )
+                    .unwrap();
+            }
+        }
+
+        let world = TestWorld::default();
+        let contexts = world.contexts();
+        #[allow(clippy::large_futures)]
+        let results = join3v(buffers.into_iter().zip(contexts).map(|(buffer, ctx)| {
+            let input = BodyStream::from(buffer);
+            OprfIpaQuery::<_, Fp31>::new(
+                query_config(false),
+                Arc::clone(&key_registry),
+                DEFAULT_HELPER_ORIGIN.into(),
+            )
+            .execute(ctx, query_size, input)
+        }))
+        .await;
+
+        let results: Vec<Fp31> = results.reconstruct();
+        assert_eq!(
+            results,
+            EXPECTED
+                .iter()
+                .map(|&v| Fp31::try_from(v).unwrap())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_reports_from_other_epochs() {
+        let records = test_input();
+        let query_size = QuerySize::try_from(records.len()).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let key_registry = Arc::new(KeyRegistry::random(1, &mut rng));
+
+        let mut buffers: [_; 3] = std::array::from_fn(|_| Vec::new());
+
+        // test records are from epoch 0
+        let shares: [Vec<DecryptedOprfReport<BA8, BA3, BA20>>; 3] = records.into_iter().share();
+        for (buf, shares) in zip(&mut buffers, shares) {
+            for share in shares {
+                share
+                    .delimited_encrypt_to(
+                        DEFAULT_KEY_ID,
+                        key_registry.as_ref(),
+                        DEFAULT_HELPER_ORIGIN,
+                        &mut rng,
+                        buf,
+                    )
+                    .unwrap();
+            }
+        }
+
+        // queries accept reports from their own epoch only, unless told otherwise
+        let mut other_epoch = query_config(false);
+        other_epoch.epoch = 1;
+        for config in [query_config(false).with_report_epochs(2..=3), other_epoch] {
+            let world = TestWorld::default();
+            let contexts = world.contexts();
+            let results = join_all(buffers.clone().into_iter().zip(contexts).map(
+                |(buffer, ctx)| {
+                    OprfIpaQuery::<_, Fp31>::new(
+                        config,
+                        Arc::clone(&key_registry),
+                        DEFAULT_HELPER_ORIGIN.into(),
+                    )
+                    .execute(ctx, query_size, BodyStream::from(buffer))
+                },
+            ))
+            .await;
+
+            assert!(results.iter().all(|r| matches!(
+                r,
+                Err(Error::InvalidReport(InvalidReportError::Epoch {
+                    epoch: 0,
+                    ..
+                }))
+            )));
+        }
+    }
+
+    #[tokio::test]
+    async fn wide_report_fields() {
+        type Shares = Vec<OprfReport<BA9, BA8, BA20>>;
+
+        let records = vec![
+            TestRawDataRecord {
+                timestamp: 0,
+                user_id: 12345,
+                is_trigger_report: false,
+                breakdown_key: 300,
+                trigger_value: 0,
+            },
+            TestRawDataRecord {
+                timestamp: 10,
+                user_id: 12345,
+                is_trigger_report: true,
+                breakdown_key: 0,
+                trigger_value: 200,
+            },
+            TestRawDataRecord {
+                timestamp: 0,
+                user_id: 68362,
+                is_trigger_report: false,
+                breakdown_key: 2,
+                trigger_value: 0,
+            },
+            TestRawDataRecord {
+                timestamp: 20,
+                user_id: 68362,
+                is_trigger_report: true,
+                breakdown_key: 0,
+                trigger_value: 100,
+            },
+        ];
+        let query_size = QuerySize::try_from(records.len()).unwrap();
+        let config = IpaQueryConfig {
+            per_user_credit_cap: 256,
+            max_breakdown_key: 301,
+            ..query_config(true)
+        }
+        .with_report_field_bits(9, 8, 20);
+
+        let sz = <OprfReport<BA9, BA8, BA20> as Serializable>::Size::USIZE;
+        let mut buffers: [_; 3] = std::array::from_fn(|_| vec![0u8; records.len() * sz]);
+
+        let shares: [Shares; 3] = records.into_iter().share();
+        for (buf, shares) in zip(&mut buffers, shares) {
+            for (share, chunk) in zip(shares, buf.chunks_mut(sz)) {
+                share.serialize(GenericArray::from_mut_slice(chunk));
+            }
+        }
+
+        let world = TestWorld::default();
+        let contexts = world.contexts();
+        #[allow(clippy::large_futures)]
+        let results = join3v(buffers.into_iter().zip(contexts).map(|(buffer, ctx)| {
+            let input = BodyStream::from(buffer);
+            OprfIpaQuery::<_, Fp32BitPrime>::new(
+                config,
+                Arc::new(KeyRegistry::empty()),
+                DEFAULT_HELPER_ORIGIN.into(),
+            )
+            .execute(ctx, query_size, input)
+        }))
+        .await;
+
+        let results: Vec<Fp32BitPrime> = results.reconstruct();
+        let mut expected = vec![0_u128; 301];
+        expected[2] = 100;
+        expected[300] = 200;
+        assert_eq!(
+            results,
+            expected
+                .into_iter()
+                .map(Fp32BitPrime::truncate_from)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_short_input() {
+        let records = test_input();
+        let query_size = QuerySize::try_from(records.len() + 1).unwrap();
+
+        let sz = <OprfReport<BA8, BA3, BA20> as Serializable>::Size::USIZE;
+        let mut buffers: [_; 3] = std::array::from_fn(|_| vec![0u8; records.len() * sz]);
+
+        let shares: [Vec<OprfReport<BA8, BA3, BA20>>; 3] = records.into_iter().share();
+        for (buf, shares) in zip(&mut buffers, shares) {
+            for (share, chunk) in zip(shares, buf.chunks_mut(sz)) {
+                share.serialize(GenericArray::from_mut_slice(chunk));
+            }
+        }
+
+        let world = TestWorld::default();
+        let contexts = world.contexts();
+        let results = join_all(buffers.into_iter().zip(contexts).map(|(buffer, ctx)| {
+            let input = BodyStream::from(buffer);
+            OprfIpaQuery::<_, Fp31>::new(
+                query_config(true),
+                Arc::new(KeyRegistry::empty()),
+                DEFAULT_HELPER_ORIGIN.into(),
+            )
+            .execute(ctx, query_size, input)
+        }))
+        .await;
+
+        assert!(results
+            .iter()
+            .all(|r| matches!(r, Err(Error::InvalidQueryParameter(_)))));
+    }
+
+    #[test]
+    fn validates_config() {
+        let config = |cap, max_breakdown_key, bk, tv, ts| IpaQueryConfig {
+            per_user_credit_cap: cap,
+            max_breakdown_key,
+            ..query_config(true).with_report_field_bits(bk, tv, ts)
         };
 
-        let aws = config.attribution_window_seconds;
-        match config.per_user_credit_cap {
-            8 => oprf_ipa::<C, BA8, BA3, BA20, BA3, F>(ctx, input, aws).await,
-            16 => oprf_ipa::<C, BA8, BA3, BA20, BA4, F>(ctx, input, aws).await,
-            32 => oprf_ipa::<C, BA8, BA3, BA20, BA5, F>(ctx, input, aws).await,
-            64 => oprf_ipa::<C, BA8, BA3, BA20, BA6, F>(ctx, input, aws).await,
-            128 => oprf_ipa::<C, BA8, BA3, BA20, BA7, F>(ctx, input, aws).await,
-            _ => panic!(
-                "Invalid value specified for per-user cap: {:?}. Must be one of 8, 16, 32, 64, or 128.",
-                config.per_user_credit_cap
-            ),
+        assert!(validate_config(&config(8, 256, 8, 3, 20)).is_ok());
+        assert!(validate_config(&config(256, 512, 9, 8, 20)).is_ok());
+        assert!(validate_config(&config(128, 512, 9, 3, 32)).is_ok());
+        assert!(validate_config(&config(8, 2048, 12, 3, 32)).is_ok());
+        assert!(validate_config(&config(256, 1000, 16, 8, 20)).is_ok());
+
+        for invalid in [
+            // unsupported cap
+            config(5, 256, 8, 3, 20),
+            // unsupported widths
+            config(8, 256, 7, 3, 20),
+            config(8, 256, 8, 4, 20),
+            config(8, 256, 8, 3, 24),
+            // too many breakdowns for the breakdown key
+            config(8, 257, 8, 3, 20),
+            // too many breakdowns to move values to
+            config(8, 2049, 16, 3, 20),
+            // no breakdowns
+            config(8, 0, 8, 3, 20),
+            // trigger values cannot be capped
+            config(128, 256, 8, 8, 20),
+            // does not fit into the shuffle input
+            config(256, 256, 8, 8, 32),
+            config(8, 256, 16, 3, 32),
+            // invalid reports are not dropped
+            config(8, 256, 8, 3, 20).with_invalid_report_policy(InvalidReportPolicy::Drop),
+            // OPRF reports have a single version
+            config(8, 256, 8, 3, 20).with_report_version(ReportVersion::AllFields),
+        ] {
+            assert!(
+                matches!(
+                    validate_config(&invalid),
+                    Err(Error::InvalidQueryParameter(_))
+                ),
+                "{invalid:?}"
+            );
         }
     }
 }
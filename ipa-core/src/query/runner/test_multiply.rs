@@ -41,10 +41,10 @@ where
 
     let mut input = Box::pin(RecordsStream::<Replicated<F>, _>::new(input_stream));
     let mut results = Vec::new();
+    let mut record_id = 0_u32;
     while let Some(v) = input.next().await {
         // multiply pairs
         let mut a = None;
-        let mut record_id = 0_u32;
         for share in v.unwrap() {
             match a {
                 None => a = Some(share),
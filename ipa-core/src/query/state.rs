@@ -10,10 +10,10 @@ use futures::{ready, FutureExt};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    helpers::{query::QueryConfig, RoleAssignment},
+    helpers::{query::QueryConfig, GatewayProgress, QueryProgress, RoleAssignment},
     protocol::QueryId,
     query::runner::QueryResult,
-    sync::Mutex,
+    sync::{Arc, Mutex},
     task::JoinHandle,
 };
 
@@ -34,6 +34,45 @@ pub enum QueryStatus {
     AwaitingCompletion,
     /// Query has finished and results are available.
     Completed,
+    /// Query was cancelled by request. It will not produce any results.
+    Cancelled,
+    /// Query did not receive a response from other helpers or its inputs in time. It will not
+    /// produce any results.
+    TimedOut,
+    /// Query has finished, but nobody collected its results in time, so they were discarded.
+    Expired,
+}
+
+/// The status of query processing along with its progress, if the query is running.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryStatusReport {
+    pub status: QueryStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<QueryProgress>,
+}
+
+impl From<QueryStatus> for QueryStatusReport {
+    fn from(status: QueryStatus) -> Self {
+        Self {
+            status,
+            progress: None,
+        }
+    }
+}
+
+impl From<&QueryState> for QueryStatusReport {
+    fn from(source: &QueryState) -> Self {
+        let progress = match source {
+            QueryState::Running(running) => Some(running.progress.snapshot()),
+            QueryState::AwaitingCompletion(progress) => Some(progress.snapshot()),
+            _ => None,
+        };
+
+        Self {
+            status: source.into(),
+            progress,
+        }
+    }
 }
 
 impl From<&QueryState> for QueryStatus {
@@ -43,8 +82,11 @@ impl From<&QueryState> for QueryStatus {
             QueryState::Preparing(_) => QueryStatus::Preparing,
             QueryState::AwaitingInputs(_, _, _) => QueryStatus::AwaitingInputs,
             QueryState::Running(_) => QueryStatus::Running,
-            QueryState::AwaitingCompletion => QueryStatus::AwaitingCompletion,
+            QueryState::AwaitingCompletion(_) => QueryStatus::AwaitingCompletion,
             QueryState::Completed(_) => QueryStatus::Completed,
+            QueryState::Cancelled => QueryStatus::Cancelled,
+            QueryState::TimedOut => QueryStatus::TimedOut,
+            QueryState::Expired => QueryStatus::Expired,
         }
     }
 }
@@ -55,8 +97,11 @@ pub enum QueryState {
     Preparing(QueryConfig),
     AwaitingInputs(QueryId, QueryConfig, RoleAssignment),
     Running(RunningQuery),
-    AwaitingCompletion,
+    AwaitingCompletion(Arc<GatewayProgress>),
     Completed(QueryResult),
+    Cancelled,
+    TimedOut,
+    Expired,
 }
 
 impl QueryState {
@@ -88,6 +133,9 @@ pub struct RunningQuery {
     /// We could return the result via the JoinHandle, except that we want to check the status
     /// of the task, and shuttle doesn't implement `JoinHandle::is_finished`.
     pub join_handle: JoinHandle<()>,
+
+    /// Data exchanged with other helpers so far.
+    pub progress: Arc<GatewayProgress>,
 }
 
 impl RunningQuery {
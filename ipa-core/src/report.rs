@@ -2,14 +2,14 @@ use std::{
     fmt::{Display, Formatter},
     marker::PhantomData,
     mem::size_of,
-    ops::{Add, Deref},
+    ops::{Add, Deref, RangeInclusive},
 };
 
 use bytes::{BufMut, Bytes};
 use generic_array::{ArrayLength, GenericArray};
 use hpke::Serializable as _;
 use rand_core::{CryptoRng, RngCore};
-use typenum::{Unsigned, U1, U18, U8};
+use typenum::{Sum, Unsigned, U1, U18, U8};
 
 use crate::{
     ff::{
@@ -17,18 +17,24 @@ use crate::{
         Serializable,
     },
     hpke::{
-        open_in_place, seal_in_place, CryptError, FieldShareCrypt, Info, KeyPair, KeyRegistry,
-        PublicKeyRegistry,
+        open_in_place, seal_in_place, CryptError, FieldShareCrypt, Info, IpaEncappedKey, KeyPair,
+        KeyRegistry, PublicKeyRegistry, TagSize,
     },
     secret_sharing::{replicated::semi_honest::AdditiveShare as Replicated, WeakSharedValue},
 };
 
-// TODO(679): This needs to come from configuration.
-static HELPER_ORIGIN: &str = "github.com/private-attribution";
+/// Helper origin that reports are encrypted for, unless the network configuration names a
+/// different one. It is bound to every report, so helpers and report collectors must agree on it.
+pub const DEFAULT_HELPER_ORIGIN: &str = "github.com/private-attribution";
 
 pub type KeyIdentifier = u8;
 pub const DEFAULT_KEY_ID: KeyIdentifier = 0;
 
+/// Expected size of a length-delimited encrypted report, used to size buffers for many of them.
+/// Reports of every version, with the default field types, take about 80 bytes along with their
+/// length prefix, plus the length of their site domain.
+pub const ESTIMATED_AVERAGE_REPORT_SIZE: usize = 100;
+
 pub type Timestamp = u32;
 
 /// Event epoch as described [`ipa-spec`]
@@ -154,9 +160,139 @@ pub enum InvalidReportError {
     Timestamp(Timestamp),
     #[error("en/decryption failure: {0}")]
     Crypt(#[from] CryptError),
+    #[error("report is {actual} bytes long, but must be at least {min} bytes long")]
+    TooShort { min: usize, actual: usize },
+    #[error("unknown report version {0}")]
+    UnknownVersion(u8),
+    #[error("epoch {epoch} is outside of the epochs {allowed:?} accepted by the query")]
+    Epoch {
+        epoch: Epoch,
+        allowed: RangeInclusive<Epoch>,
+    },
+}
+
+impl InvalidReportError {
+    #[must_use]
+    pub fn kind(&self) -> InvalidReportKind {
+        match self {
+            Self::BadEventType(_) => InvalidReportKind::BadEventType,
+            Self::NonAsciiString(_) => InvalidReportKind::NonAsciiString,
+            Self::Timestamp(_) => InvalidReportKind::Timestamp,
+            Self::Crypt(_) => InvalidReportKind::Crypt,
+            Self::TooShort { .. } => InvalidReportKind::TooShort,
+            Self::UnknownVersion(_) => InvalidReportKind::UnknownVersion,
+            Self::Epoch { .. } => InvalidReportKind::Epoch,
+        }
+    }
+}
+
+/// Kind of [`InvalidReportError`], without the details of a particular report. Queries count the
+/// reports they reject by kind.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(
+    feature = "enable-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[repr(u8)]
+pub enum InvalidReportKind {
+    BadEventType = 1,
+    NonAsciiString,
+    Timestamp,
+    Crypt,
+    TooShort,
+    UnknownVersion,
+    Epoch,
+}
+
+impl From<InvalidReportKind> for u8 {
+    fn from(value: InvalidReportKind) -> Self {
+        value as u8
+    }
+}
+
+impl TryFrom<u8> for InvalidReportKind {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            1 => Self::BadEventType,
+            2 => Self::NonAsciiString,
+            3 => Self::Timestamp,
+            4 => Self::Crypt,
+            5 => Self::TooShort,
+            6 => Self::UnknownVersion,
+            7 => Self::Epoch,
+            _ => return Err(value),
+        })
+    }
+}
+
+/// Rejects reports from epochs outside of `allowed`, before spending any effort on decrypting them.
+fn check_epoch(epoch: Epoch, allowed: &RangeInclusive<Epoch>) -> Result<(), InvalidReportError> {
+    if allowed.contains(&epoch) {
+        Ok(())
+    } else {
+        Err(InvalidReportError::Epoch {
+            epoch,
+            allowed: allowed.clone(),
+        })
+    }
+}
+
+/// Version of the [`EncryptedReport`] wire format. Queries accept reports of a single version,
+/// chosen by the report collector with
+/// [`IpaQueryConfig::report_version`](crate::helpers::query::IpaQueryConfig::report_version).
+///
+/// Reports of the original version are laid out the way they always were. Every later version
+/// starts with a version byte, so that helpers detect reports of a version other than the one
+/// the query expects.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "enable-serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum ReportVersion {
+    /// Only the match key shares are encrypted. Timestamp, breakdown key, trigger value and event
+    /// type travel in the clear.
+    #[default]
+    MatchKeyOnly,
+    /// The whole share payload is encrypted: match key shares, timestamp, breakdown key, trigger
+    /// value and event type.
+    AllFields,
+}
+
+impl ReportVersion {
+    /// The first byte of the reports of this version, `None` for the original layout that does
+    /// not have one.
+    #[must_use]
+    pub fn version_byte(self) -> Option<u8> {
+        match self {
+            Self::MatchKeyOnly => None,
+            Self::AllFields => Some(2),
+        }
+    }
+}
+
+impl AsRef<str> for ReportVersion {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::MatchKeyOnly => "match-key-only",
+            Self::AllFields => "all-fields",
+        }
+    }
+}
+
+impl Display for ReportVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
+    }
 }
 
-/// A binary report as submitted by a report collector, containing encrypted match key shares.
+/// A binary report as submitted by a report collector, containing encrypted match key shares and,
+/// depending on the [`ReportVersion`], the rest of the share fields.
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub struct EncryptedReport<F, MK, BK, B>
 where
@@ -167,12 +303,13 @@ where
     BK: GaloisField,
 {
     data: B,
+    version: ReportVersion,
     phantom_data: PhantomData<(F, MK, BK)>,
 }
 
 // TODO: If we are parsing reports from CSV files, we may also want an owned version of EncryptedReport.
 
-// Report structure:
+// Report structure, version 1 (`ReportVersion::MatchKeyOnly`), without a version byte:
 //  * 0..4: `timestamp`
 //  * 4: `breakdown_key`
 //  * 5..a: `trigger_value`
@@ -182,6 +319,15 @@ where
 //  * c+1: `key_id`
 //  * c+2..c+4: `epoch`
 //  * c+4..: `site_domain`
+//
+// Report structure, version 2 (`ReportVersion::AllFields`):
+//  * 0: `version`
+//  * 1..a: `encap_key`
+//  * a..b: `ciphertext` of `mk_shares`, `timestamp`, `breakdown_key`, `trigger_value` and
+//    `event_type`, in this order
+//  * b: `key_id`
+//  * b+1..b+3: `epoch`
+//  * b+3..: `site_domain`
 impl<F, B> EncryptedReport<F, Gf40Bit, Gf8Bit, B>
 where
     F: PrimeField,
@@ -192,108 +338,200 @@ where
     //  1. Offsets that are calculated from typenum values
     //  2. Offsets that appear in the code in more places than two successive accessors. (Some
     //     offsets are used by validations in the `from_bytes` constructor.)
-    const ENCAP_KEY_OFFSET: usize = 5 + 2 * <F as Serializable>::Size::USIZE;
-    const CIPHERTEXT_OFFSET: usize =
-        Self::ENCAP_KEY_OFFSET + <Gf40Bit as FieldShareCrypt>::EncapKeySize::USIZE;
-    const EVENT_TYPE_OFFSET: usize =
-        Self::CIPHERTEXT_OFFSET + <Gf40Bit as FieldShareCrypt>::CiphertextSize::USIZE;
-    const SITE_DOMAIN_OFFSET: usize = Self::EVENT_TYPE_OFFSET + 4;
-
-    /// ## Panics
-    /// Never.
-    pub fn timestamp(&self) -> u32 {
-        u32::from_le_bytes(self.data[0..4].try_into().unwrap()) // infallible slice-to-array conversion
+    const ENCAP_KEY_SIZE: usize = <Gf40Bit as FieldShareCrypt>::EncapKeySize::USIZE;
+    const MK_SIZE: usize =
+        <<Gf40Bit as FieldShareCrypt>::SemiHonestShares as Serializable>::Size::USIZE;
+    const MK_CIPHERTEXT_SIZE: usize = <Gf40Bit as FieldShareCrypt>::CiphertextSize::USIZE;
+    // Timestamp, breakdown key and trigger value, laid out the same way in both versions.
+    const FIELDS_SIZE: usize = 5 + <Replicated<F> as Serializable>::Size::USIZE;
+
+    const V1_ENCAP_KEY_OFFSET: usize = Self::FIELDS_SIZE;
+    const V1_CIPHERTEXT_OFFSET: usize = Self::V1_ENCAP_KEY_OFFSET + Self::ENCAP_KEY_SIZE;
+    const V1_EVENT_TYPE_OFFSET: usize = Self::V1_CIPHERTEXT_OFFSET + Self::MK_CIPHERTEXT_SIZE;
+    const V2_CIPHERTEXT_OFFSET: usize = 1 + Self::ENCAP_KEY_SIZE;
+    // The event type is the last byte of the sealed payload, which is followed by the tag.
+    #[cfg(all(test, unit_test))]
+    const V2_SEALED_EVENT_TYPE_OFFSET: usize =
+        Self::V2_CIPHERTEXT_OFFSET + Self::MK_SIZE + Self::FIELDS_SIZE;
+    const V2_KEY_ID_OFFSET: usize =
+        Self::V2_CIPHERTEXT_OFFSET + Self::MK_CIPHERTEXT_SIZE + Self::FIELDS_SIZE + 1;
+
+    fn key_id_offset(version: ReportVersion) -> usize {
+        match version {
+            ReportVersion::MatchKeyOnly => Self::V1_EVENT_TYPE_OFFSET + 1,
+            ReportVersion::AllFields => Self::V2_KEY_ID_OFFSET,
+        }
     }
 
-    pub fn breakdown_key(&self) -> Gf8Bit {
-        Gf8Bit::deserialize(GenericArray::from_slice(&[self.data[4]]))
+    fn site_domain_offset(version: ReportVersion) -> usize {
+        Self::key_id_offset(version) + 3
     }
 
-    pub fn trigger_value(&self) -> Replicated<F> {
-        Replicated::<F>::deserialize(GenericArray::from_slice(
-            &self.data[5..Self::ENCAP_KEY_OFFSET],
-        ))
+    pub fn version(&self) -> ReportVersion {
+        self.version
     }
 
     pub fn encap_key(&self) -> &[u8] {
-        &self.data[Self::ENCAP_KEY_OFFSET..Self::CIPHERTEXT_OFFSET]
+        let offset = match self.version() {
+            ReportVersion::MatchKeyOnly => Self::V1_ENCAP_KEY_OFFSET,
+            ReportVersion::AllFields => 1,
+        };
+        &self.data[offset..offset + Self::ENCAP_KEY_SIZE]
     }
 
-    pub fn match_key_ciphertext(&self) -> &[u8] {
-        &self.data[Self::CIPHERTEXT_OFFSET..Self::EVENT_TYPE_OFFSET]
+    /// Encrypted match key shares, followed by the rest of the share fields if the report version
+    /// encrypts them.
+    pub fn ciphertext(&self) -> &[u8] {
+        match self.version() {
+            ReportVersion::MatchKeyOnly => {
+                &self.data[Self::V1_CIPHERTEXT_OFFSET..Self::V1_EVENT_TYPE_OFFSET]
+            }
+            ReportVersion::AllFields => {
+                &self.data[Self::V2_CIPHERTEXT_OFFSET..Self::V2_KEY_ID_OFFSET]
+            }
+        }
     }
 
+    /// The event type of the report, `None` if the report version encrypts it.
+    ///
     /// ## Panics
     /// Only if a `Report` constructor failed to validate the contents properly, which would be a bug.
-    pub fn event_type(&self) -> EventType {
-        EventType::try_from(self.data[Self::EVENT_TYPE_OFFSET]).unwrap() // validated on construction
+    pub fn event_type(&self) -> Option<EventType> {
+        match self.version() {
+            ReportVersion::MatchKeyOnly => Some(
+                EventType::try_from(self.data[Self::V1_EVENT_TYPE_OFFSET]).unwrap(), // validated on construction
+            ),
+            ReportVersion::AllFields => None,
+        }
     }
 
     pub fn key_id(&self) -> KeyIdentifier {
-        self.data[Self::EVENT_TYPE_OFFSET + 1]
+        self.data[Self::key_id_offset(self.version())]
     }
 
     /// ## Panics
     /// Never.
     pub fn epoch(&self) -> Epoch {
+        let offset = Self::key_id_offset(self.version()) + 1;
         u16::from_le_bytes(
-            self.data[Self::EVENT_TYPE_OFFSET + 2..Self::SITE_DOMAIN_OFFSET]
-                .try_into()
-                .unwrap(), // infallible slice-to-array conversion
+            self.data[offset..offset + 2].try_into().unwrap(), // infallible slice-to-array conversion
         )
     }
 
     /// ## Panics
     /// Only if a `Report` constructor failed to validate the contents properly, which would be a bug.
     pub fn site_domain(&self) -> &str {
-        std::str::from_utf8(&self.data[Self::SITE_DOMAIN_OFFSET..]).unwrap() // validated on construction
+        std::str::from_utf8(&self.data[Self::site_domain_offset(self.version())..]).unwrap()
+        // validated on construction
     }
 
+    /// Parses a report of the original version, [`ReportVersion::MatchKeyOnly`].
+    ///
     /// ## Errors
     /// If the report contents are invalid.
     pub fn from_bytes(bytes: B) -> Result<Self, InvalidReportError> {
-        EventType::try_from(bytes[Self::EVENT_TYPE_OFFSET])?;
-        let site_domain = &bytes[Self::SITE_DOMAIN_OFFSET..];
+        Self::from_bytes_with_version(bytes, ReportVersion::MatchKeyOnly)
+    }
+
+    /// Parses a report of the given `version`.
+    ///
+    /// ## Errors
+    /// If the report contents are invalid, or the report starts with the version byte of a
+    /// different version.
+    pub fn from_bytes_with_version(
+        bytes: B,
+        version: ReportVersion,
+    ) -> Result<Self, InvalidReportError> {
+        if let Some(expected) = version.version_byte() {
+            match bytes.first() {
+                None => return Err(InvalidReportError::TooShort { min: 1, actual: 0 }),
+                Some(&actual) if actual != expected => {
+                    return Err(InvalidReportError::UnknownVersion(actual))
+                }
+                Some(_) => {}
+            }
+        }
+        let site_domain_offset = Self::site_domain_offset(version);
+        if bytes.len() < site_domain_offset {
+            return Err(InvalidReportError::TooShort {
+                min: site_domain_offset,
+                actual: bytes.len(),
+            });
+        }
+        if version == ReportVersion::MatchKeyOnly {
+            EventType::try_from(bytes[Self::V1_EVENT_TYPE_OFFSET])?;
+        }
+        let site_domain = &bytes[site_domain_offset..];
         if !site_domain.is_ascii() {
             return Err(NonAsciiStringError::from(site_domain).into());
         }
         Ok(Self {
             data: bytes,
+            version,
             phantom_data: PhantomData,
         })
     }
 
+    /// Decrypts the report, which must have been encrypted for `helper_origin` and come from one
+    /// of the `epochs`.
+    ///
     /// ## Errors
-    /// If the match key shares in the report cannot be decrypted (e.g. due to a
-    /// failure of the authenticated encryption).
+    /// If the report is from an epoch outside of `epochs`, cannot be decrypted (e.g. due to a
+    /// failure of the authenticated encryption), or if the decrypted event type is invalid.
     /// ## Panics
     /// Should not panic. Only panics if a `Report` constructor failed to validate the
     /// contents properly, which would be a bug.
     pub fn decrypt(
         &self,
         key_registry: &KeyRegistry<KeyPair>,
+        helper_origin: &str,
+        epochs: &RangeInclusive<Epoch>,
     ) -> Result<Report<F, Gf40Bit, Gf8Bit>, InvalidReportError> {
-        let info = Info::new(
-            self.key_id(),
-            self.epoch(),
-            self.event_type(),
-            HELPER_ORIGIN,
-            self.site_domain(),
-        )
+        check_epoch(self.epoch(), epochs)?;
+        let version = self.version();
+        let info = match version {
+            ReportVersion::MatchKeyOnly => Info::new(
+                self.key_id(),
+                self.epoch(),
+                EventType::try_from(self.data[Self::V1_EVENT_TYPE_OFFSET]).unwrap(),
+                helper_origin,
+                self.site_domain(),
+            ),
+            ReportVersion::AllFields => Info::without_event_type(
+                self.key_id(),
+                self.epoch(),
+                helper_origin,
+                self.site_domain(),
+            ),
+        }
         .unwrap(); // validated on construction
 
-        let mut ciphertext: GenericArray<u8, <Gf40Bit as FieldShareCrypt>::CiphertextSize> =
-            *GenericArray::from_slice(self.match_key_ciphertext());
+        let mut ciphertext = self.ciphertext().to_vec();
         let plaintext = open_in_place(key_registry, self.encap_key(), &mut ciphertext, &info)?;
+        let (mk_shares, fields, event_type) = match version {
+            ReportVersion::MatchKeyOnly => (
+                plaintext,
+                &self.data[..Self::V1_ENCAP_KEY_OFFSET],
+                self.data[Self::V1_EVENT_TYPE_OFFSET],
+            ),
+            ReportVersion::AllFields => {
+                let (mk_shares, fields) = plaintext.split_at(Self::MK_SIZE);
+                (
+                    mk_shares,
+                    &fields[..Self::FIELDS_SIZE],
+                    fields[Self::FIELDS_SIZE],
+                )
+            }
+        };
 
         Ok(Report {
-            timestamp: self.timestamp(),
+            timestamp: u32::from_le_bytes(fields[0..4].try_into().unwrap()), // infallible slice-to-array conversion
             mk_shares: <Gf40Bit as FieldShareCrypt>::SemiHonestShares::deserialize(
-                GenericArray::from_slice(plaintext),
+                GenericArray::from_slice(mk_shares),
             ),
-            event_type: self.event_type(),
-            breakdown_key: self.breakdown_key(),
-            trigger_value: self.trigger_value(),
+            event_type: EventType::try_from(event_type)?,
+            breakdown_key: Gf8Bit::deserialize(GenericArray::from_slice(&fields[4..5])),
+            trigger_value: Replicated::<F>::deserialize(GenericArray::from_slice(&fields[5..])),
             epoch: self.epoch(),
             site_domain: self.site_domain().to_owned(),
         })
@@ -336,8 +574,8 @@ where
 {
     /// # Panics
     /// If report length does not fit in u16.
-    pub fn encrypted_len(&self) -> u16 {
-        let len = EncryptedReport::<F, Gf40Bit, Gf8Bit, &[u8]>::SITE_DOMAIN_OFFSET
+    pub fn encrypted_len(&self, version: ReportVersion) -> u16 {
+        let len = EncryptedReport::<F, Gf40Bit, Gf8Bit, &[u8]>::site_domain_offset(version)
             + self.site_domain.as_bytes().len();
         len.try_into().unwrap()
     }
@@ -346,26 +584,30 @@ where
     /// If there is a problem encrypting the report.
     pub fn delimited_encrypt_to<R: CryptoRng + RngCore, B: BufMut>(
         &self,
+        version: ReportVersion,
         key_id: KeyIdentifier,
         key_registry: &impl PublicKeyRegistry,
+        helper_origin: &str,
         rng: &mut R,
         out: &mut B,
     ) -> Result<(), InvalidReportError> {
-        out.put_u16_le(self.encrypted_len());
-        self.encrypt_to(key_id, key_registry, rng, out)
+        out.put_u16_le(self.encrypted_len(version));
+        self.encrypt_to(version, key_id, key_registry, helper_origin, rng, out)
     }
 
     /// # Errors
     /// If there is a problem encrypting the report.
     pub fn encrypt<R: CryptoRng + RngCore>(
         &self,
+        version: ReportVersion,
         key_id: KeyIdentifier,
         key_registry: &impl PublicKeyRegistry,
+        helper_origin: &str,
         rng: &mut R,
     ) -> Result<Vec<u8>, InvalidReportError> {
         let mut out = Vec::new();
-        self.encrypt_to(key_id, key_registry, rng, &mut out)?;
-        debug_assert_eq!(out.len(), usize::from(self.encrypted_len()));
+        self.encrypt_to(version, key_id, key_registry, helper_origin, rng, &mut out)?;
+        debug_assert_eq!(out.len(), usize::from(self.encrypted_len(version)));
         Ok(out)
     }
 
@@ -373,38 +615,60 @@ where
     /// If there is a problem encrypting the report.
     pub fn encrypt_to<R: CryptoRng + RngCore, B: BufMut>(
         &self,
+        version: ReportVersion,
         key_id: KeyIdentifier,
         key_registry: &impl PublicKeyRegistry,
+        helper_origin: &str,
         rng: &mut R,
         out: &mut B,
     ) -> Result<(), InvalidReportError> {
-        let info = Info::new(
-            key_id,
-            self.epoch,
-            self.event_type,
-            HELPER_ORIGIN,
-            self.site_domain.as_ref(),
-        )?;
-
-        let mut plaintext = GenericArray::default();
-        self.mk_shares.serialize(&mut plaintext);
-
-        let (encap_key, ciphertext, tag) =
-            seal_in_place(key_registry, plaintext.as_mut(), &info, rng)?;
-
-        out.put_slice(&self.timestamp.to_le_bytes());
+        let info = match version {
+            ReportVersion::MatchKeyOnly => Info::new(
+                key_id,
+                self.epoch,
+                self.event_type,
+                helper_origin,
+                self.site_domain.as_ref(),
+            )?,
+            ReportVersion::AllFields => Info::without_event_type(
+                key_id,
+                self.epoch,
+                helper_origin,
+                self.site_domain.as_ref(),
+            )?,
+        };
 
+        let mut fields = Vec::new();
+        fields.put_slice(&self.timestamp.to_le_bytes());
         let mut bk = GenericArray::default();
         self.breakdown_key.serialize(&mut bk);
-        out.put_slice(bk.as_slice());
-
+        fields.put_slice(bk.as_slice());
         let mut trigger_value = GenericArray::default();
         self.trigger_value.serialize(&mut trigger_value);
-        out.put_slice(trigger_value.as_slice());
+        fields.put_slice(trigger_value.as_slice());
+
+        let mut mk_shares = GenericArray::default();
+        self.mk_shares.serialize(&mut mk_shares);
+        let mut plaintext = mk_shares.to_vec();
+        if version == ReportVersion::AllFields {
+            plaintext.put_slice(&fields);
+            plaintext.put_slice(&[u8::from(&self.event_type)]);
+        }
+
+        let (encap_key, ciphertext, tag) = seal_in_place(key_registry, &mut plaintext, &info, rng)?;
+
+        if let Some(version_byte) = version.version_byte() {
+            out.put_slice(&[version_byte]);
+        }
+        if version == ReportVersion::MatchKeyOnly {
+            out.put_slice(&fields);
+        }
         out.put_slice(&encap_key.to_bytes());
         out.put_slice(ciphertext);
         out.put_slice(&tag.to_bytes());
-        out.put_slice(&[u8::from(&self.event_type)]);
+        if version == ReportVersion::MatchKeyOnly {
+            out.put_slice(&[u8::from(&self.event_type)]);
+        }
         out.put_slice(&[key_id]);
         out.put_slice(&self.epoch.to_le_bytes());
         out.put_slice(self.site_domain.as_bytes());
@@ -427,6 +691,56 @@ where
     pub timestamp: Replicated<TS>,
 }
 
+/// Evaluates `$body` with `$bk`, `$tv` and `$ts` naming the breakdown key, trigger value and
+/// timestamp types of the [`OprfReport`]s described by the `breakdown_key_bits`,
+/// `trigger_value_bits` and `timestamp_bits` of `$config`. Evaluates `$unsupported` instead if
+/// OPRF IPA cannot run with these widths.
+///
+/// All fields of a report, along with its 64-bit match key and the trigger bit, are packed into a
+/// `BA112` to be shuffled, so the supported widths add up to 47 bits at most.
+macro_rules! with_oprf_report_types {
+    ($config:expr, |$bk:ident, $tv:ident, $ts:ident| $body:expr, _ => $unsupported:expr $(,)?) => {
+        $crate::report::with_oprf_report_types!(
+            @match $config, $bk, $tv, $ts, $body, $unsupported,
+            (8, BA8, 3, BA3, 20, BA20),
+            (8, BA8, 3, BA3, 32, BA32),
+            (8, BA8, 8, BA8, 20, BA20),
+            (9, BA9, 3, BA3, 20, BA20),
+            (9, BA9, 3, BA3, 32, BA32),
+            (9, BA9, 8, BA8, 20, BA20),
+            (12, BA12, 3, BA3, 20, BA20),
+            (12, BA12, 3, BA3, 32, BA32),
+            (12, BA12, 8, BA8, 20, BA20),
+            (16, BA16, 3, BA3, 20, BA20),
+            (16, BA16, 8, BA8, 20, BA20),
+        )
+    };
+    (
+        @match $config:expr, $bk:ident, $tv:ident, $ts:ident, $body:expr, $unsupported:expr,
+        $(($bk_bits:literal, $bk_ty:ident, $tv_bits:literal, $tv_ty:ident, $ts_bits:literal, $ts_ty:ident),)*
+    ) => {{
+        let config = &$config;
+        match (
+            config.breakdown_key_bits,
+            config.trigger_value_bits,
+            config.timestamp_bits,
+        ) {
+            $(($bk_bits, $tv_bits, $ts_bits) => {
+                #[allow(dead_code)]
+                type $bk = $crate::ff::boolean_array::$bk_ty;
+                #[allow(dead_code)]
+                type $tv = $crate::ff::boolean_array::$tv_ty;
+                #[allow(dead_code)]
+                type $ts = $crate::ff::boolean_array::$ts_ty;
+                $body
+            })*
+            _ => $unsupported,
+        }
+    }};
+}
+
+pub(crate) use with_oprf_report_types;
+
 impl Serializable for u64 {
     type Size = U8;
 
@@ -529,13 +843,386 @@ where
     }
 }
 
+/// Input report of the feature-label dot product query, with all fields secret-shared.
+///
+/// Source reports carry a vector of binary features. Trigger reports label the source reports of
+/// the same user that precede them, and their own feature vector is ignored.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeatureLabelDotProductReport<FV, TS>
+where
+    FV: WeakSharedValue,
+    TS: WeakSharedValue,
+{
+    pub match_key: Replicated<BA64>,
+    pub is_trigger: Replicated<Boolean>,
+    pub feature_vector: Replicated<FV>,
+    pub timestamp: Replicated<TS>,
+}
+
+impl<FV: WeakSharedValue, TS: WeakSharedValue> Serializable for FeatureLabelDotProductReport<FV, TS>
+where
+    Replicated<FV>: Serializable,
+    Replicated<TS>: Serializable,
+    <Replicated<TS> as Serializable>::Size: Add<U18>,
+    <Replicated<FV> as Serializable>::Size: Add<Sum<<Replicated<TS> as Serializable>::Size, U18>>,
+    Sum<<Replicated<FV> as Serializable>::Size, Sum<<Replicated<TS> as Serializable>::Size, U18>>:
+        ArrayLength,
+{
+    type Size = Sum<
+        <Replicated<FV> as Serializable>::Size,
+        Sum<<Replicated<TS> as Serializable>::Size, U18>,
+    >;
+
+    fn serialize(&self, buf: &mut GenericArray<u8, Self::Size>) {
+        let sizeof_matchkey = size_of::<u64>() * 2;
+        let sizeof_eventtype = size_of::<Boolean>() * 2;
+        let ts_sz = <Replicated<TS> as Serializable>::Size::USIZE;
+        let fv_sz = <Replicated<FV> as Serializable>::Size::USIZE;
+
+        self.match_key
+            .serialize(GenericArray::from_mut_slice(&mut buf[..sizeof_matchkey]));
+
+        self.timestamp.serialize(GenericArray::from_mut_slice(
+            &mut buf[sizeof_matchkey..sizeof_matchkey + ts_sz],
+        ));
+
+        self.feature_vector.serialize(GenericArray::from_mut_slice(
+            &mut buf[sizeof_matchkey + ts_sz..sizeof_matchkey + ts_sz + fv_sz],
+        ));
+
+        self.is_trigger.serialize(GenericArray::from_mut_slice(
+            &mut buf[sizeof_matchkey + ts_sz + fv_sz
+                ..sizeof_matchkey + ts_sz + fv_sz + sizeof_eventtype],
+        ));
+    }
+
+    fn deserialize(buf: &GenericArray<u8, Self::Size>) -> Self {
+        let sizeof_matchkey = size_of::<u64>() * 2;
+        let sizeof_eventtype = size_of::<Boolean>() * 2;
+        let ts_sz = <Replicated<TS> as Serializable>::Size::USIZE;
+        let fv_sz = <Replicated<FV> as Serializable>::Size::USIZE;
+
+        let match_key =
+            Replicated::<BA64>::deserialize(GenericArray::from_slice(&buf[..sizeof_matchkey]));
+        let timestamp = Replicated::<TS>::deserialize(GenericArray::from_slice(
+            &buf[sizeof_matchkey..sizeof_matchkey + ts_sz],
+        ));
+        let feature_vector = Replicated::<FV>::deserialize(GenericArray::from_slice(
+            &buf[sizeof_matchkey + ts_sz..sizeof_matchkey + ts_sz + fv_sz],
+        ));
+        let is_trigger = Replicated::<Boolean>::deserialize(GenericArray::from_slice(
+            &buf[sizeof_matchkey + ts_sz + fv_sz
+                ..sizeof_matchkey + ts_sz + fv_sz + sizeof_eventtype],
+        ));
+        Self {
+            match_key,
+            is_trigger,
+            feature_vector,
+            timestamp,
+        }
+    }
+}
+
+/// Size of the match key ciphertext in an [`EncryptedOprfReport`], including the authentication tag.
+type OprfMatchKeyCiphertextSize = Sum<<Replicated<BA64> as Serializable>::Size, TagSize>;
+
+/// A binary report for the OPRF IPA protocol as submitted by a report collector, containing
+/// encrypted match key shares.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct EncryptedOprfReport<BK, TV, TS, B>
+where
+    B: Deref<Target = [u8]>,
+    BK: WeakSharedValue,
+    TV: WeakSharedValue,
+    TS: WeakSharedValue,
+{
+    data: B,
+    phantom_data: PhantomData<(BK, TV, TS)>,
+}
+
+// Report structure:
+//  * 0..a: `timestamp`
+//  * a..b: `breakdown_key`
+//  * b..c: `trigger_value`
+//  * c..d: `encap_key`
+//  * d..e: `mk_ciphertext`
+//  * e: `event_type`
+//  * e+1: `key_id`
+//  * e+2..e+4: `epoch`
+//  * e+4..: `site_domain`
+impl<BK, TV, TS, B> EncryptedOprfReport<BK, TV, TS, B>
+where
+    BK: WeakSharedValue,
+    TV: WeakSharedValue,
+    TS: WeakSharedValue,
+    Replicated<BK>: Serializable,
+    Replicated<TV>: Serializable,
+    Replicated<TS>: Serializable,
+    B: Deref<Target = [u8]>,
+{
+    const BREAKDOWN_KEY_OFFSET: usize = <Replicated<TS> as Serializable>::Size::USIZE;
+    const TRIGGER_VALUE_OFFSET: usize =
+        Self::BREAKDOWN_KEY_OFFSET + <Replicated<BK> as Serializable>::Size::USIZE;
+    const ENCAP_KEY_OFFSET: usize =
+        Self::TRIGGER_VALUE_OFFSET + <Replicated<TV> as Serializable>::Size::USIZE;
+    const CIPHERTEXT_OFFSET: usize =
+        Self::ENCAP_KEY_OFFSET + <IpaEncappedKey as hpke::Serializable>::OutputSize::USIZE;
+    const EVENT_TYPE_OFFSET: usize =
+        Self::CIPHERTEXT_OFFSET + <OprfMatchKeyCiphertextSize as Unsigned>::USIZE;
+    const SITE_DOMAIN_OFFSET: usize = Self::EVENT_TYPE_OFFSET + 4;
+
+    pub fn timestamp(&self) -> Replicated<TS> {
+        Replicated::<TS>::deserialize(GenericArray::from_slice(
+            &self.data[..Self::BREAKDOWN_KEY_OFFSET],
+        ))
+    }
+
+    pub fn breakdown_key(&self) -> Replicated<BK> {
+        Replicated::<BK>::deserialize(GenericArray::from_slice(
+            &self.data[Self::BREAKDOWN_KEY_OFFSET..Self::TRIGGER_VALUE_OFFSET],
+        ))
+    }
+
+    pub fn trigger_value(&self) -> Replicated<TV> {
+        Replicated::<TV>::deserialize(GenericArray::from_slice(
+            &self.data[Self::TRIGGER_VALUE_OFFSET..Self::ENCAP_KEY_OFFSET],
+        ))
+    }
+
+    pub fn encap_key(&self) -> &[u8] {
+        &self.data[Self::ENCAP_KEY_OFFSET..Self::CIPHERTEXT_OFFSET]
+    }
+
+    pub fn match_key_ciphertext(&self) -> &[u8] {
+        &self.data[Self::CIPHERTEXT_OFFSET..Self::EVENT_TYPE_OFFSET]
+    }
+
+    /// ## Panics
+    /// Only if a `Report` constructor failed to validate the contents properly, which would be a bug.
+    pub fn event_type(&self) -> EventType {
+        EventType::try_from(self.data[Self::EVENT_TYPE_OFFSET]).unwrap() // validated on construction
+    }
+
+    pub fn key_id(&self) -> KeyIdentifier {
+        self.data[Self::EVENT_TYPE_OFFSET + 1]
+    }
+
+    /// ## Panics
+    /// Never.
+    pub fn epoch(&self) -> Epoch {
+        u16::from_le_bytes(
+            self.data[Self::EVENT_TYPE_OFFSET + 2..Self::SITE_DOMAIN_OFFSET]
+                .try_into()
+                .unwrap(), // infallible slice-to-array conversion
+        )
+    }
+
+    /// ## Panics
+    /// Only if a `Report` constructor failed to validate the contents properly, which would be a bug.
+    pub fn site_domain(&self) -> &str {
+        std::str::from_utf8(&self.data[Self::SITE_DOMAIN_OFFSET..]).unwrap() // validated on construction
+    }
+
+    /// ## Errors
+    /// If the report contents are invalid.
+    pub fn from_bytes(bytes: B) -> Result<Self, InvalidReportError> {
+        if bytes.len() < Self::SITE_DOMAIN_OFFSET {
+            return Err(InvalidReportError::TooShort {
+                min: Self::SITE_DOMAIN_OFFSET,
+                actual: bytes.len(),
+            });
+        }
+        EventType::try_from(bytes[Self::EVENT_TYPE_OFFSET])?;
+        let site_domain = &bytes[Self::SITE_DOMAIN_OFFSET..];
+        if !site_domain.is_ascii() {
+            return Err(NonAsciiStringError::from(site_domain).into());
+        }
+        Ok(Self {
+            data: bytes,
+            phantom_data: PhantomData,
+        })
+    }
+
+    /// Decrypts the match key shares of the report, which must have been encrypted for
+    /// `helper_origin` and come from one of the `epochs`.
+    ///
+    /// ## Errors
+    /// If the report is from an epoch outside of `epochs`, or the match key shares in the report
+    /// cannot be decrypted (e.g. due to a failure of the authenticated encryption).
+    /// ## Panics
+    /// Should not panic. Only panics if a `Report` constructor failed to validate the
+    /// contents properly, which would be a bug.
+    pub fn decrypt(
+        &self,
+        key_registry: &KeyRegistry<KeyPair>,
+        helper_origin: &str,
+        epochs: &RangeInclusive<Epoch>,
+    ) -> Result<DecryptedOprfReport<BK, TV, TS>, InvalidReportError> {
+        check_epoch(self.epoch(), epochs)?;
+        let info = Info::new(
+            self.key_id(),
+            self.epoch(),
+            self.event_type(),
+            helper_origin,
+            self.site_domain(),
+        )
+        .unwrap(); // validated on construction
+
+        let mut ciphertext: GenericArray<u8, OprfMatchKeyCiphertextSize> =
+            *GenericArray::from_slice(self.match_key_ciphertext());
+        let plaintext = open_in_place(key_registry, self.encap_key(), &mut ciphertext, &info)?;
+
+        Ok(DecryptedOprfReport {
+            match_key: Replicated::<BA64>::deserialize(GenericArray::from_slice(plaintext)),
+            event_type: self.event_type(),
+            breakdown_key: self.breakdown_key(),
+            trigger_value: self.trigger_value(),
+            timestamp: self.timestamp(),
+            epoch: self.epoch(),
+            site_domain: self.site_domain().to_owned(),
+        })
+    }
+}
+
+impl<BK, TV, TS> TryFrom<Bytes> for EncryptedOprfReport<BK, TV, TS, Bytes>
+where
+    BK: WeakSharedValue,
+    TV: WeakSharedValue,
+    TS: WeakSharedValue,
+    Replicated<BK>: Serializable,
+    Replicated<TV>: Serializable,
+    Replicated<TS>: Serializable,
+{
+    type Error = InvalidReportError;
+
+    fn try_from(bytes: Bytes) -> Result<Self, InvalidReportError> {
+        EncryptedOprfReport::from_bytes(bytes)
+    }
+}
+
+/// Contents of an [`EncryptedOprfReport`] with the match key shares in the clear. Report
+/// collectors encrypt these before submitting them, helpers get them back after decryption.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecryptedOprfReport<BK, TV, TS>
+where
+    BK: WeakSharedValue,
+    TV: WeakSharedValue,
+    TS: WeakSharedValue,
+{
+    pub match_key: Replicated<BA64>,
+    pub event_type: EventType,
+    pub breakdown_key: Replicated<BK>,
+    pub trigger_value: Replicated<TV>,
+    pub timestamp: Replicated<TS>,
+    pub epoch: Epoch,
+    pub site_domain: String,
+}
+
+impl<BK, TV, TS> DecryptedOprfReport<BK, TV, TS>
+where
+    BK: WeakSharedValue,
+    TV: WeakSharedValue,
+    TS: WeakSharedValue,
+    Replicated<BK>: Serializable,
+    Replicated<TV>: Serializable,
+    Replicated<TS>: Serializable,
+{
+    /// # Panics
+    /// If report length does not fit in u16.
+    pub fn encrypted_len(&self) -> u16 {
+        let len =
+            EncryptedOprfReport::<BK, TV, TS, &[u8]>::SITE_DOMAIN_OFFSET + self.site_domain.len();
+        len.try_into().unwrap()
+    }
+
+    /// # Errors
+    /// If there is a problem encrypting the report.
+    pub fn delimited_encrypt_to<R: CryptoRng + RngCore, B: BufMut>(
+        &self,
+        key_id: KeyIdentifier,
+        key_registry: &impl PublicKeyRegistry,
+        helper_origin: &str,
+        rng: &mut R,
+        out: &mut B,
+    ) -> Result<(), InvalidReportError> {
+        out.put_u16_le(self.encrypted_len());
+        self.encrypt_to(key_id, key_registry, helper_origin, rng, out)
+    }
+
+    /// # Errors
+    /// If there is a problem encrypting the report.
+    pub fn encrypt<R: CryptoRng + RngCore>(
+        &self,
+        key_id: KeyIdentifier,
+        key_registry: &impl PublicKeyRegistry,
+        helper_origin: &str,
+        rng: &mut R,
+    ) -> Result<Vec<u8>, InvalidReportError> {
+        let mut out = Vec::new();
+        self.encrypt_to(key_id, key_registry, helper_origin, rng, &mut out)?;
+        debug_assert_eq!(out.len(), usize::from(self.encrypted_len()));
+        Ok(out)
+    }
+
+    /// # Errors
+    /// If there is a problem encrypting the report.
+    pub fn encrypt_to<R: CryptoRng + RngCore, B: BufMut>(
+        &self,
+        key_id: KeyIdentifier,
+        key_registry: &impl PublicKeyRegistry,
+        helper_origin: &str,
+        rng: &mut R,
+        out: &mut B,
+    ) -> Result<(), InvalidReportError> {
+        let info = Info::new(
+            key_id,
+            self.epoch,
+            self.event_type,
+            helper_origin,
+            self.site_domain.as_ref(),
+        )?;
+
+        let mut plaintext = GenericArray::default();
+        self.match_key.serialize(&mut plaintext);
+
+        let (encap_key, ciphertext, tag) =
+            seal_in_place(key_registry, plaintext.as_mut(), &info, rng)?;
+
+        let mut timestamp = GenericArray::default();
+        self.timestamp.serialize(&mut timestamp);
+        out.put_slice(timestamp.as_slice());
+
+        let mut breakdown_key = GenericArray::default();
+        self.breakdown_key.serialize(&mut breakdown_key);
+        out.put_slice(breakdown_key.as_slice());
+
+        let mut trigger_value = GenericArray::default();
+        self.trigger_value.serialize(&mut trigger_value);
+        out.put_slice(trigger_value.as_slice());
+        out.put_slice(&encap_key.to_bytes());
+        out.put_slice(ciphertext);
+        out.put_slice(&tag.to_bytes());
+        out.put_slice(&[u8::from(&self.event_type)]);
+        out.put_slice(&[key_id]);
+        out.put_slice(&self.epoch.to_le_bytes());
+        out.put_slice(self.site_domain.as_bytes());
+
+        Ok(())
+    }
+}
+
 #[cfg(all(test, unit_test))]
 mod test {
     use rand::{distributions::Alphanumeric, rngs::StdRng, Rng};
     use rand_core::SeedableRng;
 
     use super::*;
-    use crate::ff::{Fp32BitPrime, Gf40Bit, Gf8Bit};
+    use crate::ff::{
+        boolean_array::{BA20, BA3, BA8, BA9},
+        Fp32BitPrime, Gf40Bit, Gf8Bit,
+    };
+
+    const ALL_EPOCHS: RangeInclusive<Epoch> = 0..=Epoch::MAX;
 
     #[test]
     fn enc_dec_roundtrip() {
@@ -558,9 +1245,216 @@ mod test {
         let key_registry = KeyRegistry::random(1, &mut rng);
         let key_id = 0;
 
-        let enc_report_bytes = report.encrypt(key_id, &key_registry, &mut rng).unwrap();
+        for version in [ReportVersion::MatchKeyOnly, ReportVersion::AllFields] {
+            let enc_report_bytes = report
+                .encrypt(
+                    version,
+                    key_id,
+                    &key_registry,
+                    DEFAULT_HELPER_ORIGIN,
+                    &mut rng,
+                )
+                .unwrap();
+            let enc_report =
+                EncryptedReport::from_bytes_with_version(enc_report_bytes.as_slice(), version)
+                    .unwrap();
+            assert_eq!(enc_report.version(), version);
+            let dec_report = enc_report
+                .decrypt(&key_registry, DEFAULT_HELPER_ORIGIN, &ALL_EPOCHS)
+                .unwrap();
+
+            assert_eq!(dec_report, report);
+        }
+    }
+
+    #[test]
+    fn all_fields_are_encrypted() {
+        let mut rng = StdRng::from_seed([1_u8; 32]);
+
+        let report = Report::<Fp32BitPrime, Gf40Bit, Gf8Bit> {
+            timestamp: 0x0102_0304,
+            mk_shares: (rng.gen(), rng.gen()).into(),
+            event_type: EventType::Trigger,
+            breakdown_key: rng.gen(),
+            trigger_value: (rng.gen(), rng.gen()).into(),
+            epoch: rng.gen(),
+            site_domain: "foo.example".to_owned(),
+        };
+        let key_registry = KeyRegistry::random(1, &mut rng);
+
+        let enc_report_bytes = report
+            .encrypt(
+                ReportVersion::AllFields,
+                0,
+                &key_registry,
+                DEFAULT_HELPER_ORIGIN,
+                &mut rng,
+            )
+            .unwrap();
+        assert!(!enc_report_bytes
+            .windows(4)
+            .any(|w| w == report.timestamp.to_le_bytes()));
+
+        // the event type is only authenticated as part of the sealed payload
+        let event_type_offset =
+            EncryptedReport::<Fp32BitPrime, Gf40Bit, Gf8Bit, &[u8]>::V2_SEALED_EVENT_TYPE_OFFSET;
+        let mut tampered = enc_report_bytes.clone();
+        tampered[event_type_offset] ^= 1;
+        let err = EncryptedReport::<Fp32BitPrime, Gf40Bit, Gf8Bit, _>::from_bytes_with_version(
+            tampered.as_slice(),
+            ReportVersion::AllFields,
+        )
+        .unwrap()
+        .decrypt(&key_registry, DEFAULT_HELPER_ORIGIN, &ALL_EPOCHS)
+        .unwrap_err();
+        assert!(matches!(err, InvalidReportError::Crypt(_)));
+    }
+
+    #[test]
+    fn wrong_helper_origin() {
+        let mut rng = StdRng::from_seed([1_u8; 32]);
+
+        let report = Report::<Fp32BitPrime, Gf40Bit, Gf8Bit> {
+            timestamp: rng.gen(),
+            mk_shares: (rng.gen(), rng.gen()).into(),
+            event_type: EventType::Source,
+            breakdown_key: rng.gen(),
+            trigger_value: (rng.gen(), rng.gen()).into(),
+            epoch: rng.gen(),
+            site_domain: "foo.example".to_owned(),
+        };
+        let key_registry = KeyRegistry::random(1, &mut rng);
+
+        let enc_report_bytes = report
+            .encrypt(
+                ReportVersion::default(),
+                0,
+                &key_registry,
+                "helpers.example",
+                &mut rng,
+            )
+            .unwrap();
         let enc_report = EncryptedReport::from_bytes(enc_report_bytes.as_slice()).unwrap();
-        let dec_report = enc_report.decrypt(&key_registry).unwrap();
+        assert!(matches!(
+            enc_report.decrypt(&key_registry, DEFAULT_HELPER_ORIGIN, &ALL_EPOCHS),
+            Err(InvalidReportError::Crypt(_))
+        ));
+        assert_eq!(
+            report,
+            enc_report
+                .decrypt(&key_registry, "helpers.example", &ALL_EPOCHS)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn epoch_out_of_range() {
+        let mut rng = StdRng::from_seed([1_u8; 32]);
+
+        let report = DecryptedOprfReport::<BA8, BA3, BA20> {
+            match_key: (rng.gen(), rng.gen()).into(),
+            event_type: EventType::Trigger,
+            breakdown_key: (rng.gen(), rng.gen()).into(),
+            trigger_value: (rng.gen(), rng.gen()).into(),
+            timestamp: (rng.gen(), rng.gen()).into(),
+            epoch: 5,
+            site_domain: "foo.example".to_owned(),
+        };
+        let key_registry = KeyRegistry::random(1, &mut rng);
+
+        let enc_report_bytes = report
+            .encrypt(0, &key_registry, DEFAULT_HELPER_ORIGIN, &mut rng)
+            .unwrap();
+        let enc_report =
+            EncryptedOprfReport::<BA8, BA3, BA20, _>::from_bytes(enc_report_bytes.as_slice())
+                .unwrap();
+        for epochs in [0..=4, 6..=Epoch::MAX] {
+            assert!(matches!(
+                enc_report.decrypt(&key_registry, DEFAULT_HELPER_ORIGIN, &epochs),
+                Err(InvalidReportError::Epoch { epoch: 5, .. })
+            ));
+        }
+        assert_eq!(
+            report,
+            enc_report
+                .decrypt(&key_registry, DEFAULT_HELPER_ORIGIN, &(5..=5))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_version() {
+        let err = EncryptedReport::<Fp32BitPrime, Gf40Bit, Gf8Bit, _>::from_bytes_with_version(
+            [3_u8; 100].as_slice(),
+            ReportVersion::AllFields,
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, InvalidReportError::UnknownVersion(3)));
+    }
+
+    #[test]
+    fn detects_original_version() {
+        let mut rng = StdRng::from_seed([1_u8; 32]);
+
+        let report = Report::<Fp32BitPrime, Gf40Bit, Gf8Bit> {
+            // the first byte of the report would be taken for the version byte
+            timestamp: 0x0102_0300,
+            mk_shares: (rng.gen(), rng.gen()).into(),
+            event_type: EventType::Source,
+            breakdown_key: rng.gen(),
+            trigger_value: (rng.gen(), rng.gen()).into(),
+            epoch: rng.gen(),
+            site_domain: "foo.example".to_owned(),
+        };
+        let key_registry = KeyRegistry::random(1, &mut rng);
+
+        let enc_report_bytes = report
+            .encrypt(
+                ReportVersion::MatchKeyOnly,
+                0,
+                &key_registry,
+                DEFAULT_HELPER_ORIGIN,
+                &mut rng,
+            )
+            .unwrap();
+        let err = EncryptedReport::<Fp32BitPrime, Gf40Bit, Gf8Bit, _>::from_bytes_with_version(
+            enc_report_bytes.as_slice(),
+            ReportVersion::AllFields,
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, InvalidReportError::UnknownVersion(0)));
+    }
+
+    #[test]
+    fn enc_dec_roundtrip_oprf() {
+        let mut rng = StdRng::from_seed([1_u8; 32]);
+
+        let report = DecryptedOprfReport::<BA8, BA3, BA20> {
+            match_key: (rng.gen(), rng.gen()).into(),
+            event_type: EventType::Trigger,
+            breakdown_key: (rng.gen(), rng.gen()).into(),
+            trigger_value: (rng.gen(), rng.gen()).into(),
+            timestamp: (rng.gen(), rng.gen()).into(),
+            epoch: rng.gen(),
+            site_domain: (&mut rng)
+                .sample_iter(Alphanumeric)
+                .map(char::from)
+                .take(10)
+                .collect(),
+        };
+
+        let key_registry = KeyRegistry::random(1, &mut rng);
+        let key_id = 0;
+
+        let enc_report_bytes = report
+            .encrypt(key_id, &key_registry, DEFAULT_HELPER_ORIGIN, &mut rng)
+            .unwrap();
+        let enc_report = EncryptedOprfReport::from_bytes(enc_report_bytes.as_slice()).unwrap();
+        let dec_report = enc_report
+            .decrypt(&key_registry, DEFAULT_HELPER_ORIGIN, &ALL_EPOCHS)
+            .unwrap();
 
         assert_eq!(dec_report, report);
     }
@@ -595,7 +1489,9 @@ mod test {
         .unwrap();
 
         let enc_report = EncryptedReport::from_bytes(enc_report_bytes.as_slice()).unwrap();
-        let report = enc_report.decrypt(&key_registry).unwrap();
+        let report = enc_report
+            .decrypt(&key_registry, DEFAULT_HELPER_ORIGIN, &ALL_EPOCHS)
+            .unwrap();
 
         assert_eq!(report, expected);
     }
@@ -633,4 +1529,57 @@ mod test {
             .unwrap();
         assert!(matches!(err, InvalidReportError::NonAsciiString(_)));
     }
+
+    #[test]
+    fn too_short() {
+        let mut rng = StdRng::from_seed([1_u8; 32]);
+
+        let report = DecryptedOprfReport::<BA8, BA3, BA20> {
+            match_key: (rng.gen(), rng.gen()).into(),
+            event_type: EventType::Source,
+            breakdown_key: (rng.gen(), rng.gen()).into(),
+            trigger_value: (rng.gen(), rng.gen()).into(),
+            timestamp: (rng.gen(), rng.gen()).into(),
+            epoch: rng.gen(),
+            site_domain: String::new(),
+        };
+
+        let key_registry = KeyRegistry::random(1, &mut rng);
+        let enc_report_bytes = report
+            .encrypt(0, &key_registry, DEFAULT_HELPER_ORIGIN, &mut rng)
+            .unwrap();
+
+        let err = EncryptedOprfReport::<BA8, BA3, BA20, _>::from_bytes(
+            &enc_report_bytes[..enc_report_bytes.len() - 1],
+        )
+        .err()
+        .unwrap();
+        assert!(matches!(err, InvalidReportError::TooShort { .. }));
+    }
+
+    #[test]
+    fn too_short_for_breakdown_key() {
+        let mut rng = StdRng::from_seed([1_u8; 32]);
+
+        let report = DecryptedOprfReport::<BA8, BA3, BA20> {
+            match_key: (rng.gen(), rng.gen()).into(),
+            event_type: EventType::Source,
+            breakdown_key: (rng.gen(), rng.gen()).into(),
+            trigger_value: (rng.gen(), rng.gen()).into(),
+            timestamp: (rng.gen(), rng.gen()).into(),
+            epoch: rng.gen(),
+            site_domain: String::new(),
+        };
+
+        let key_registry = KeyRegistry::random(1, &mut rng);
+        let enc_report_bytes = report
+            .encrypt(0, &key_registry, DEFAULT_HELPER_ORIGIN, &mut rng)
+            .unwrap();
+
+        // a report that is too short for the expected breakdown key size
+        let err = EncryptedOprfReport::<BA9, BA3, BA20, _>::from_bytes(enc_report_bytes.as_slice())
+            .err()
+            .unwrap();
+        assert!(matches!(err, InvalidReportError::TooShort { .. }));
+    }
 }
@@ -14,6 +14,8 @@ pub mod uri {
 }
 
 pub mod duration {
+    #[cfg(feature = "web-app")]
+    use std::collections::BTreeMap;
     use std::time::Duration;
 
     pub fn to_secs<'dur, I, S>(d: I, s: S) -> Result<S::Ok, S::Error>
@@ -41,4 +43,26 @@ pub mod duration {
         let secs: Option<f64> = serde::Deserialize::deserialize(d)?;
         Ok(secs.map(Duration::from_secs_f64))
     }
+
+    #[cfg(feature = "web-app")]
+    pub fn to_secs_map<K, S>(m: &BTreeMap<K, Duration>, s: S) -> Result<S::Ok, S::Error>
+    where
+        K: serde::Serialize,
+        S: serde::Serializer,
+    {
+        s.collect_map(m.iter().map(|(k, v)| (k, v.as_secs_f64())))
+    }
+
+    #[cfg(feature = "web-app")]
+    pub fn from_secs_map<'de, K, D>(d: D) -> Result<BTreeMap<K, Duration>, D::Error>
+    where
+        K: serde::Deserialize<'de> + Ord,
+        D: serde::Deserializer<'de>,
+    {
+        let secs: BTreeMap<K, f64> = serde::Deserialize::deserialize(d)?;
+        Ok(secs
+            .into_iter()
+            .map(|(k, v)| (k, Duration::from_secs_f64(v)))
+            .collect())
+    }
 }
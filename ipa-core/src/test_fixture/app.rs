@@ -1,5 +1,6 @@
 use std::iter::zip;
 
+use bytes::Bytes;
 use generic_array::GenericArray;
 use typenum::Unsigned;
 
@@ -7,7 +8,7 @@ use crate::{
     app::Error,
     ff::Serializable,
     helpers::{
-        query::{QueryConfig, QueryInput},
+        query::{QueryConfig, QueryInput, QueryInputChunk},
         InMemoryNetwork, InMemoryTransport,
     },
     protocol::QueryId,
@@ -113,6 +114,45 @@ impl TestApp {
         Ok(query_id)
     }
 
+    /// Same as [`Self::start_query`], but the input is sent in chunks of `records_per_chunk`
+    /// records, last chunk first.
+    ///
+    /// ## Errors
+    /// Returns an error if it can't start a query or send query input.
+    #[allow(clippy::missing_panics_doc)]
+    pub async fn start_query_in_chunks<I, A>(
+        &self,
+        input: I,
+        query_config: QueryConfig,
+        records_per_chunk: u32,
+    ) -> Result<QueryId, Error>
+    where
+        I: IntoShares<A>,
+        A: IntoBuf,
+    {
+        let helpers_input = input.share().map(IntoBuf::into_buf);
+        let records = u32::from(query_config.size);
+        let query_id = self.drivers[0].start_query(query_config).await?;
+
+        for (driver, input) in zip(&self.drivers, helpers_input) {
+            let record_size = input.len() / usize::try_from(records).unwrap();
+            let chunk_size = record_size * usize::try_from(records_per_chunk).unwrap();
+            let input = Bytes::from(input);
+            for (index, offset) in (0..input.len()).step_by(chunk_size).enumerate().rev() {
+                let data = input.slice(offset..input.len().min(offset + chunk_size));
+                let chunk_records = u32::try_from(data.len() / record_size).unwrap();
+                driver.execute_query_chunk(QueryInputChunk::new(
+                    query_id,
+                    u32::try_from(index).unwrap(),
+                    chunk_records,
+                    data,
+                ))?;
+            }
+        }
+
+        Ok(query_id)
+    }
+
     /// ## Errors
     /// Propagates errors retrieving the query status.
     /// ## Panics
@@ -132,10 +172,21 @@ impl TestApp {
     pub async fn complete_query(&self, query_id: QueryId) -> Result<[Vec<u8>; 3], Error> {
         let results =
             try_join3_array([0, 1, 2].map(|i| self.drivers[i].complete_query(query_id))).await;
-        self.network.reset();
+        self.network.reset_query(query_id);
         results
     }
 
+    /// Cancels the query on all helpers. Helper 1 receives the request and forwards it to the
+    /// other helpers.
+    ///
+    /// ## Errors
+    /// Returns an error if one or more helpers can't cancel the query.
+    pub async fn kill_query(&self, query_id: QueryId) -> Result<(), Error> {
+        let result = self.drivers[0].kill_query(query_id).await;
+        self.network.reset_query(query_id);
+        result
+    }
+
     /// Initiates a new query on all helpers and drives it to completion.
     ///
     /// ## Errors
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "in-memory-infra")]
+use crate::{
+    ff::{PrimeField, Serializable},
+    helpers::query::FeatureLabelDotProductQueryConfig,
+    secret_sharing::{
+        replicated::{malicious::ExtendableField, semi_honest::AdditiveShare as Replicated},
+        IntoShares,
+    },
+    test_fixture::Reconstruct,
+};
+
+#[derive(Debug, Clone)]
+pub struct TestFeatureLabelRecord {
+    pub timestamp: u64,
+    pub user_id: u64,
+    pub is_trigger_report: bool,
+    pub feature_vector: u32,
+}
+
+/// Executes the feature-label dot product in the clear, that is without any MPC helpers involved
+/// in the computation. Useful to validate the output of the MPC version.
+///
+/// Every source event that is followed by a trigger event of the same user receives a label,
+/// starting with the most recent source event of that user, until `per_user_cap` of them have
+/// one. Returns, for every one of the `feature_vector_bits` features, the number of labelled
+/// source events that have it.
+///
+/// The input can be in any order. Events of the same user with the same timestamp may be ordered
+/// differently by the MPC version.
+///
+/// ## Panics
+/// If `feature_vector_bits` is greater than 32.
+#[must_use]
+pub fn feature_label_dot_product_in_the_clear(
+    input: &[TestFeatureLabelRecord],
+    feature_vector_bits: u32,
+    per_user_cap: u32,
+) -> Vec<u32> {
+    assert!(feature_vector_bits <= u32::BITS);
+
+    let mut user_events = HashMap::new();
+    for row in input {
+        user_events
+            .entry(row.user_id)
+            .or_insert_with(Vec::new)
+            .push(row);
+    }
+
+    let mut sums = vec![0_u32; usize::try_from(feature_vector_bits).unwrap()];
+    for records_per_user in user_events.values_mut() {
+        records_per_user.sort_by_key(|r| std::cmp::Reverse(r.timestamp));
+
+        let mut seen_trigger = false;
+        let mut labelled = 0;
+        for record in records_per_user.iter() {
+            if record.is_trigger_report {
+                seen_trigger = true;
+            } else if seen_trigger && labelled < per_user_cap {
+                labelled += 1;
+                for (i, sum) in sums.iter_mut().enumerate() {
+                    *sum += (record.feature_vector >> i) & 1;
+                }
+            }
+        }
+    }
+
+    sums
+}
+
+/// Runs the feature-label dot product in `world` and checks its output against
+/// `expected_results`.
+///
+/// # Panics
+/// If the protocol fails, its output does not match `expected_results`, or
+/// `config.feature_vector_bits` is not one of 8, 16 or 32.
+#[cfg(feature = "in-memory-infra")]
+pub async fn test_feature_label_dot_product<F>(
+    world: &super::TestWorld,
+    records: Vec<TestFeatureLabelRecord>,
+    expected_results: &[u32],
+    config: FeatureLabelDotProductQueryConfig,
+) where
+    F: PrimeField + ExtendableField + IntoShares<Replicated<F>>,
+    rand::distributions::Standard: rand::distributions::Distribution<F>,
+    Replicated<F>: Serializable,
+{
+    use crate::{
+        ff::boolean_array::{BA16, BA20, BA32, BA8},
+        protocol::ipa_prf::feature_label_dot_product,
+        report::FeatureLabelDotProductReport,
+        test_fixture::Runner,
+    };
+
+    let result: Vec<F> = match config.feature_vector_bits {
+        8 => world
+            .semi_honest(
+                records.into_iter(),
+                |ctx, input_rows: Vec<FeatureLabelDotProductReport<BA8, BA20>>| async move {
+                    feature_label_dot_product::<_, BA8, BA20, F>(ctx, input_rows, config)
+                        .await
+                        .unwrap()
+                },
+            )
+            .await
+            .reconstruct(),
+        16 => world
+            .semi_honest(
+                records.into_iter(),
+                |ctx, input_rows: Vec<FeatureLabelDotProductReport<BA16, BA20>>| async move {
+                    feature_label_dot_product::<_, BA16, BA20, F>(ctx, input_rows, config)
+                        .await
+                        .unwrap()
+                },
+            )
+            .await
+            .reconstruct(),
+        32 => world
+            .semi_honest(
+                records.into_iter(),
+                |ctx, input_rows: Vec<FeatureLabelDotProductReport<BA32, BA20>>| async move {
+                    feature_label_dot_product::<_, BA32, BA20, F>(ctx, input_rows, config)
+                        .await
+                        .unwrap()
+                },
+            )
+            .await
+            .reconstruct(),
+        bits => panic!("Invalid feature vector width: {bits}. Must be one of 8, 16 or 32."),
+    };
+
+    let result = result
+        .into_iter()
+        .map(|v| u32::try_from(v.as_u128()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(result, expected_results);
+}
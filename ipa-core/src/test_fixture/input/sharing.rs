@@ -14,12 +14,13 @@ use crate::{
         BreakdownKey, MatchKey,
     },
     rand::Rng,
-    report::{EventType, OprfReport, Report},
+    report::{DecryptedOprfReport, EventType, FeatureLabelDotProductReport, OprfReport, Report},
     secret_sharing::{
         replicated::{semi_honest::AdditiveShare as Replicated, ReplicatedSecretSharing},
         IntoShares, WeakSharedValue,
     },
     test_fixture::{
+        feature_label_dot_product::TestFeatureLabelRecord,
         input::{GenericReportShare, GenericReportTestInput},
         ipa::TestRawDataRecord,
         Reconstruct,
@@ -261,7 +262,7 @@ where
         let trigger_value = F::try_from(u128::from(self.trigger_value))
             .unwrap()
             .share_with(rng);
-        let epoch = 1;
+        let epoch = 0;
         let site_domain = DOMAINS[rng.gen_range(0..DOMAINS.len())].to_owned();
 
         zip(mk_shares, trigger_value)
@@ -295,7 +296,7 @@ where
             EventType::Source
         };
         let trigger_value = self.trigger_value.share_with(rng);
-        let epoch = 1;
+        let epoch = 0;
         let site_domain = DOMAINS[rng.gen_range(0..DOMAINS.len())].to_owned();
 
         zip(mk_shares, trigger_value)
@@ -402,3 +403,86 @@ where
         .unwrap()
     }
 }
+
+impl<FV, TS> IntoShares<FeatureLabelDotProductReport<FV, TS>> for TestFeatureLabelRecord
+where
+    FV: WeakSharedValue + Field + IntoShares<Replicated<FV>>,
+    TS: WeakSharedValue + Field + IntoShares<Replicated<TS>>,
+{
+    fn share_with<R: Rng>(self, rng: &mut R) -> [FeatureLabelDotProductReport<FV, TS>; 3] {
+        let is_trigger = Replicated::new(
+            Boolean::from(self.is_trigger_report),
+            Boolean::from(self.is_trigger_report),
+        );
+        let match_key = BA64::try_from(u128::from(self.user_id))
+            .unwrap()
+            .share_with(rng);
+        let timestamp: [Replicated<TS>; 3] = TS::try_from(u128::from(self.timestamp))
+            .unwrap()
+            .share_with(rng);
+        let feature_vector = FV::try_from(self.feature_vector.into())
+            .unwrap()
+            .share_with(rng);
+
+        zip(
+            zip(match_key, zip(timestamp, feature_vector)),
+            repeat(is_trigger),
+        )
+        .map(|((match_key, (timestamp, feature_vector)), is_trigger)| {
+            FeatureLabelDotProductReport {
+                match_key,
+                is_trigger,
+                feature_vector,
+                timestamp,
+            }
+        })
+        .collect::<Vec<_>>()
+        .try_into()
+        .unwrap()
+    }
+}
+
+impl<BK, TV, TS> IntoShares<DecryptedOprfReport<BK, TV, TS>> for TestRawDataRecord
+where
+    BK: WeakSharedValue + Field + IntoShares<Replicated<BK>>,
+    TV: WeakSharedValue + Field + IntoShares<Replicated<TV>>,
+    TS: WeakSharedValue + Field + IntoShares<Replicated<TS>>,
+{
+    fn share_with<R: Rng>(self, rng: &mut R) -> [DecryptedOprfReport<BK, TV, TS>; 3] {
+        let match_key = BA64::try_from(u128::from(self.user_id))
+            .unwrap()
+            .share_with(rng);
+        let event_type = if self.is_trigger_report {
+            EventType::Trigger
+        } else {
+            EventType::Source
+        };
+        let timestamp: [Replicated<TS>; 3] = TS::try_from(u128::from(self.timestamp))
+            .unwrap()
+            .share_with(rng);
+        let breakdown_key = BK::try_from(self.breakdown_key.into())
+            .unwrap()
+            .share_with(rng);
+        let trigger_value = TV::try_from(self.trigger_value.into())
+            .unwrap()
+            .share_with(rng);
+        let epoch = 0;
+        let site_domain = DOMAINS[rng.gen_range(0..DOMAINS.len())].to_owned();
+
+        zip(zip(match_key, timestamp), zip(breakdown_key, trigger_value))
+            .map(
+                |((match_key, timestamp), (breakdown_key, trigger_value))| DecryptedOprfReport {
+                    match_key,
+                    event_type,
+                    breakdown_key,
+                    trigger_value,
+                    timestamp,
+                    epoch,
+                    site_domain: site_domain.clone(),
+                },
+            )
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     ff::{PrimeField, Serializable},
     helpers::query::IpaQueryConfig,
     ipa_test_input,
-    protocol::{ipa::ipa, BreakdownKey, MatchKey},
+    protocol::{dp::BinomialNoise, ipa::ipa, BreakdownKey, MatchKey},
     secret_sharing::{
         replicated::{
             malicious, malicious::ExtendableField, semi_honest,
@@ -49,8 +49,8 @@ impl GroupingKey for TestRawDataRecord {
 /// with this function's results. Note that MPC version of IPA may apply DP noise to the aggregates,
 /// so strict equality may not work.
 ///
-/// This function requires input to be sorted by the timestamp and returns a vector of contributions
-/// sorted by the breakdown key.
+/// The input can be in any order; events of each user are ordered by timestamp before attribution,
+/// with ties kept in input order. Returns a vector of contributions sorted by the breakdown key.
 ///
 /// ## Panics
 /// Will panic if you run in on Intel 80286 or any other 16 bit hardware.
@@ -65,18 +65,7 @@ pub fn ipa_in_the_clear(
     // that is more memory intensive, but should be faster to compute. We can always opt-out and
     // execute IPA in place
     let mut user_events = HashMap::new();
-    let mut last_ts = 0;
     for row in input {
-        if cfg!(debug_assertions) {
-            assert!(
-                last_ts <= row.timestamp,
-                "Input is not sorted: last row had timestamp {last_ts} that is greater than \
-                  {this_ts} timestamp of the current row",
-                this_ts = row.timestamp
-            );
-            last_ts = row.timestamp;
-        }
-
         user_events
             .entry(row.user_id)
             .or_insert_with(Vec::new)
@@ -84,9 +73,10 @@ pub fn ipa_in_the_clear(
     }
 
     let mut breakdowns = vec![0u32; usize::try_from(max_breakdown).unwrap()];
-    for records_per_user in user_events.values() {
-        // it works because input is sorted and vectors preserve the insertion order
-        // so records in `rev` are returned in reverse chronological order
+    for records_per_user in user_events.values_mut() {
+        // The input does not have to be sorted. This is a stable sort, so events with the same
+        // timestamp keep the order in which they appear in the input.
+        records_per_user.sort_by_key(|r| r.timestamp);
         let rev_records = records_per_user.iter().rev().map(Deref::deref);
         update_expected_output_for_user(
             rev_records,
@@ -225,11 +215,7 @@ pub async fn test_ipa<F>(
             .await
             .reconstruct(),
     };
-    let result = result
-        .into_iter()
-        .map(|v| u32::try_from(v.as_u128()).unwrap())
-        .collect::<Vec<_>>();
-    assert_eq!(result, expected_results);
+    assert_results(&result, expected_results, &config);
 }
 
 /// # Panics
@@ -238,7 +224,7 @@ pub async fn test_ipa<F>(
 #[cfg(feature = "in-memory-infra")]
 pub async fn test_oprf_ipa<F>(
     world: &super::TestWorld,
-    mut records: Vec<TestRawDataRecord>,
+    records: Vec<TestRawDataRecord>,
     expected_results: &[u32],
     config: IpaQueryConfig,
 ) where
@@ -254,30 +240,25 @@ pub async fn test_oprf_ipa<F>(
         test_fixture::Runner,
     };
 
-    //TODO(richaj) This manual sorting will be removed once we have the PRF sharding in place
-    records.sort_by(|a, b| b.user_id.cmp(&a.user_id));
-
-    let aws = config.attribution_window_seconds;
-
     let result: Vec<_> = world
         .semi_honest(
             records.into_iter(),
             |ctx, input_rows: Vec<OprfReport<BA8, BA3, BA20>>| async move {
 
                 match config.per_user_credit_cap {
-                    8 => oprf_ipa::<_, BA8, BA3, BA20, BA3, F>(ctx, input_rows, aws)
+                    8 => oprf_ipa::<_, BA8, BA3, BA20, BA3, F>(ctx, input_rows, config)
                     .await
                     .unwrap(),
-                    16 => oprf_ipa::<_, BA8, BA3, BA20, BA4, F>(ctx, input_rows, aws)
+                    16 => oprf_ipa::<_, BA8, BA3, BA20, BA4, F>(ctx, input_rows, config)
                     .await
                     .unwrap(),
-                    32 => oprf_ipa::<_, BA8, BA3, BA20, BA5, F>(ctx, input_rows, aws)
+                    32 => oprf_ipa::<_, BA8, BA3, BA20, BA5, F>(ctx, input_rows, config)
                     .await
                     .unwrap(),
-                    64 => oprf_ipa::<_, BA8, BA3, BA20, BA6, F>(ctx, input_rows, aws)
+                    64 => oprf_ipa::<_, BA8, BA3, BA20, BA6, F>(ctx, input_rows, config)
                     .await
                     .unwrap(),
-                    128 => oprf_ipa::<_, BA8, BA3, BA20, BA7, F>(ctx, input_rows, aws)
+                    128 => oprf_ipa::<_, BA8, BA3, BA20, BA7, F>(ctx, input_rows, config)
                     .await
                     .unwrap(),
                     _ =>
@@ -291,12 +272,34 @@ pub async fn test_oprf_ipa<F>(
         .await
         .reconstruct();
 
-    let mut result = result
-        .into_iter()
-        .map(|v| u32::try_from(v.as_u128()).unwrap())
-        .collect::<Vec<_>>();
+    assert_results(&result, expected_results, &config);
+}
+
+/// Checks the outputs of IPA against `expected_results`. If `config` asks for output noise, every
+/// output may be off by up to the number of random bits its noise is made of.
+#[cfg(feature = "in-memory-infra")]
+fn assert_results<F: PrimeField>(result: &[F], expected_results: &[u32], config: &IpaQueryConfig) {
+    let Some(noise) = BinomialNoise::for_query(config).unwrap() else {
+        let result = result
+            .iter()
+            .map(|v| u32::try_from(v.as_u128()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(result, expected_results);
+        return;
+    };
 
-    //TODO(richaj): To be removed once the function supports non power of 2 breakdowns
-    let _ = result.split_off(expected_results.len());
-    assert_eq!(result, expected_results);
+    assert_eq!(result.len(), expected_results.len());
+    let bound = i128::try_from(noise.bits_per_side()).unwrap();
+    let prime = i128::try_from(F::PRIME.into()).unwrap();
+    for (actual, &expected) in result.iter().zip(expected_results) {
+        // the upper half of the field holds negative values
+        let actual = i128::try_from(actual.as_u128()).unwrap();
+        let actual = if actual > prime / 2 {
+            actual - prime
+        } else {
+            actual
+        };
+        let noise = actual - i128::from(expected);
+        assert!((-bound..=bound).contains(&noise), "noise is {noise}");
+    }
 }
@@ -11,6 +11,7 @@ mod app;
 #[cfg(feature = "in-memory-infra")]
 pub mod circuit;
 mod event_gen;
+pub mod feature_label_dot_product;
 pub mod ipa;
 pub mod logging;
 pub mod metrics;
@@ -18,7 +19,7 @@ pub mod metrics;
 use std::fmt::Debug;
 
 #[cfg(feature = "in-memory-infra")]
-pub use app::TestApp;
+pub use app::{IntoBuf, TestApp};
 pub use event_gen::{Config as EventGeneratorConfig, EventGenerator};
 use futures::TryFuture;
 use rand::{distributions::Standard, prelude::Distribution, rngs::mock::StepRng};
@@ -110,7 +110,7 @@ impl TestWorld {
             let transport = &network.transports[i];
             let role_assignment = role_assignment.clone();
             let gateway = Gateway::new(
-                QueryId,
+                QueryId::from(0),
                 config.gateway_config,
                 role_assignment,
                 Arc::downgrade(transport),
@@ -29,6 +29,9 @@ ATTRIBUTION_WINDOW = [0, 86400]
 # As of July 2023, we are limiting the number of breakdown keys to 32.
 BREAKDOWN_KEYS = [32]
 SECURITY_MODEL = ["malicious", "semi-honest"]
+# Output noise does not change which other steps run, so every IPA and OPRF run adds it. A large
+# budget keeps the number of random bits small.
+OUTPUT_NOISE_EPSILON = 100.0
 ROOT_STEP_PREFIX = "protocol/alloc::string::String::run-0"
 
 # TODO(taikiy): #771 allows us to remove this synthetic step generation code
@@ -52,6 +55,21 @@ DEPTH_DYNAMIC_STEPS = [
 ]
 MAXIMUM_DEPTH = 32
 
+# The number of passes the quicksort in OPRF IPA makes depends on the input data, so
+# these steps are generated synthetically, the same way as the depth steps above.
+QUICKSORT_DYNAMIC_STEPS = [
+    "ipa_prf::quicksort::QuicksortStep",
+]
+MAXIMUM_QUICKSORT_PASSES = 64
+
+# The IPA query runner narrows to these steps before running IPA, to deal with the reports that
+# helpers reject under the `drop` and `replace-with-null-event` policies. The oneshot bench runs
+# the protocol without the query runner, so these steps are added here.
+INVALID_REPORTS_STEPS = [
+    "ipa_core::query::runner::ipa::InvalidReportsStep::exchange_rejected",
+    "ipa_core::query::runner::ipa::InvalidReportsStep::null_events",
+]
+
 
 def set_env():
     env = os.environ.copy()
@@ -67,6 +85,7 @@ def remove_root_step_name_from_line(l):
 def collect_steps(args):
     output = set()
     depth_dynamic_steps = set()
+    quicksort_dynamic_steps = set()
 
     proc = subprocess.Popen(
         args=args,
@@ -95,6 +114,11 @@ def collect_steps(args):
             # continue without adding to the `output`. we'll generate the dynamic steps later
             continue
 
+        if any(s in line for s in QUICKSORT_DYNAMIC_STEPS):
+            line = re.sub(r"quicksort_pass\d+", "quicksort_passX", line)
+            quicksort_dynamic_steps.add(remove_root_step_name_from_line(line))
+            continue
+
         output.update([remove_root_step_name_from_line(line)])
 
     # safeguard against empty output
@@ -107,6 +131,10 @@ def collect_steps(args):
         for s in depth_dynamic_steps:
             line = re.sub(r"depthX", "depth" + str(i), s)
             output.add(line)
+    for i in range(MAXIMUM_QUICKSORT_PASSES):
+        for s in quicksort_dynamic_steps:
+            line = re.sub(r"quicksort_passX", "quicksort_pass" + str(i), s)
+            output.add(line)
 
     return output
 
@@ -166,6 +194,8 @@ def ipa_steps():
                         str(b),
                         "-m",
                         m,
+                        "--output-noise-epsilon",
+                        str(OUTPUT_NOISE_EPSILON),
                     ]
                     print(" ".join(args), file=sys.stderr)
                     output.update(collect_steps(args))
@@ -175,6 +205,9 @@ OPRF_BREAKDOWN_KEY = 256
 OPRF_USER_CAP = [8, 16, 32, 64, 128]
 OPRF_SECURITY_MODEL = "semi-honest"
 OPRF_TRIGGER_VALUE = [6, 7]
+# Dummy records do not change the result, so every OPRF run adds them. A large budget keeps
+# their number small.
+OPRF_DUMMY_RECORDS_EPSILON = 100.0
 
 def oprf_steps():
     output = set()
@@ -194,16 +227,41 @@ def oprf_steps():
                     OPRF_SECURITY_MODEL,
                     "-t",
                     str(tv),
-                    "-o"
+                    "-o",
+                    "--dummy-records-epsilon",
+                    str(OPRF_DUMMY_RECORDS_EPSILON),
+                    "--output-noise-epsilon",
+                    str(OUTPUT_NOISE_EPSILON),
             ]
             print(" ".join(args), file=sys.stderr)
             output.update(collect_steps(args))
     return output
 
+# The feature-label dot product narrows to a step for every bit of the per-user counter and
+# every bit of the feature vector, so the largest cap and the widest feature vector cover all
+# the other configurations.
+FLDP_USER_CAP = 256
+FLDP_FEATURE_VECTOR_BITS = 32
+
+def feature_label_dot_product_steps():
+    args = ARGS + [
+        "-n",
+        str(QUERY_SIZE),
+        "-c",
+        str(FLDP_USER_CAP),
+        "-f",
+        "--feature-vector-bits",
+        str(FLDP_FEATURE_VECTOR_BITS),
+    ]
+    print(" ".join(args), file=sys.stderr)
+    return collect_steps(args)
+
 if __name__ == "__main__":
     steps = set()
     steps.update(ipa_steps())
     steps.update(oprf_steps())
+    steps.update(feature_label_dot_product_steps())
+    steps.update(INVALID_REPORTS_STEPS)
 
     full_steps = extract_intermediate_steps(steps)
     sorted_steps = sorted(full_steps)